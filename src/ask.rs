@@ -0,0 +1,195 @@
+//! Context merging for `manx ask`
+//!
+//! `manx ask` queries Context7, the local RAG index and web search concurrently.
+//! This module merges their results into a single, deduplicated context list where
+//! every entry is labeled with the backend it came from, so citations stay traceable.
+
+use crate::rag::RagSearchResult;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Retrieval backend that produced a piece of context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ContextSource {
+    Context7,
+    Local,
+    Web,
+}
+
+impl ContextSource {
+    /// Short label used in citations and output
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContextSource::Context7 => "Context7",
+            ContextSource::Local => "Local",
+            ContextSource::Web => "Web",
+        }
+    }
+}
+
+/// A context entry tagged with its retrieval backend
+#[derive(Debug, Clone, Serialize)]
+pub struct LabeledContext {
+    pub source: ContextSource,
+    pub result: RagSearchResult,
+}
+
+/// Merge per-backend results into one ranked, deduplicated context list
+///
+/// Backends are interleaved by rank (best of each first) so no single source crowds
+/// out the others. Entries pointing at the same location or carrying the same leading
+/// text are kept only once. Titles are prefixed with the backend label (e.g. `[Web]`)
+/// so LLM citations identify where each source came from.
+pub fn merge_contexts(
+    batches: Vec<(ContextSource, Vec<RagSearchResult>)>,
+    max_entries: usize,
+) -> Vec<LabeledContext> {
+    let mut iters: Vec<_> = batches
+        .into_iter()
+        .map(|(source, results)| (source, results.into_iter()))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+
+    loop {
+        let mut progressed = false;
+        for (source, results) in iters.iter_mut() {
+            let Some(mut result) = results.next() else {
+                continue;
+            };
+            progressed = true;
+
+            let location_key = format!(
+                "{}#{}",
+                result.source_path.to_string_lossy(),
+                result.chunk_index
+            );
+            let content_key = content_fingerprint(&result.content);
+            if !seen.insert(location_key) || !seen.insert(content_key) {
+                continue;
+            }
+
+            let title = result
+                .title
+                .take()
+                .unwrap_or_else(|| "Untitled".to_string());
+            result.title = Some(format!("[{}] {}", source.label(), title));
+            merged.push(LabeledContext {
+                source: *source,
+                result,
+            });
+
+            if merged.len() >= max_entries {
+                return merged;
+            }
+        }
+        if !progressed {
+            break;
+        }
+    }
+
+    merged
+}
+
+/// Normalize the leading text of a result so near-identical snippets collapse together
+fn content_fingerprint(content: &str) -> String {
+    let normalized: String = content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    format!(
+        "content:{}",
+        normalized.chars().take(200).collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::{DocumentMetadata, SourceType};
+    use std::path::PathBuf;
+
+    fn result(path: &str, content: &str) -> RagSearchResult {
+        RagSearchResult {
+            id: path.to_string(),
+            content: content.to_string(),
+            source_path: PathBuf::from(path),
+            source_type: SourceType::Remote,
+            title: Some(path.to_string()),
+            section: None,
+            score: 0.9,
+            chunk_index: 0,
+            metadata: DocumentMetadata {
+                file_type: "web".to_string(),
+                size: content.len() as u64,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+            score_breakdown: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_interleaves_and_labels_sources() {
+        let merged = merge_contexts(
+            vec![
+                (
+                    ContextSource::Context7,
+                    vec![result("react/a", "alpha"), result("react/b", "beta")],
+                ),
+                (ContextSource::Local, vec![result("/notes/c.md", "gamma")]),
+                (ContextSource::Web, vec![result("https://x.dev", "delta")]),
+            ],
+            10,
+        );
+
+        let sources: Vec<_> = merged.iter().map(|c| c.source).collect();
+        assert_eq!(
+            sources,
+            vec![
+                ContextSource::Context7,
+                ContextSource::Local,
+                ContextSource::Web,
+                ContextSource::Context7
+            ]
+        );
+        assert_eq!(
+            merged[1].result.title.as_deref(),
+            Some("[Local] /notes/c.md")
+        );
+    }
+
+    #[test]
+    fn test_merge_dedupes_and_caps() {
+        let merged = merge_contexts(
+            vec![
+                (
+                    ContextSource::Web,
+                    vec![
+                        result("https://x.dev", "Same   snippet"),
+                        result("https://y.dev", "other"),
+                    ],
+                ),
+                (
+                    ContextSource::Local,
+                    vec![result("/notes/a.md", "same snippet")],
+                ),
+            ],
+            10,
+        );
+        assert_eq!(merged.len(), 2);
+
+        let capped = merge_contexts(
+            vec![(
+                ContextSource::Web,
+                vec![result("a", "1"), result("b", "2"), result("c", "3")],
+            )],
+            2,
+        );
+        assert_eq!(capped.len(), 2);
+    }
+}
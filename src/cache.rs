@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -9,6 +10,26 @@ const CACHE_VERSION: u32 = 1;
 const DEFAULT_TTL_HOURS: u64 = 24;
 const MAX_CACHE_SIZE_MB: u64 = 100;
 
+/// Resolved Context7 library IDs rarely change, so they outlive the configured TTL
+const LIBRARY_TTL_HOURS: u64 = 7 * 24;
+
+/// Categories the CLI stores entries under
+pub const CATEGORIES: &[&str] = &[
+    "search",
+    "docs",
+    "snippets",
+    "doc_sections",
+    "llm",
+    "libraries",
+    "explain",
+];
+
+/// Sidecar mapping the short IDs shown to users (`doc-3`) to the libraries holding them
+const ITEM_INDEX_FILE: &str = "item_index.json";
+
+/// Serializes read-modify-write cycles on the item index within this process
+static ITEM_INDEX_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
 #[derive(Debug, Serialize, Deserialize)]
 struct CacheEntry<T> {
     version: u32,
@@ -17,9 +38,28 @@ struct CacheEntry<T> {
     ttl_hours: u64,
 }
 
+/// Bookkeeping fields of a cache entry, read without deserializing its data
+#[derive(Deserialize)]
+struct EntryHeader {
+    version: u32,
+    timestamp: u64,
+}
+
+/// A cache entry file; its modification time is refreshed whenever the entry is read
+struct EntryFile {
+    path: PathBuf,
+    category: String,
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// File-based cache with a TTL per entry and a size limit enforced by evicting the
+/// least recently used entries
+#[derive(Debug, Clone)]
 pub struct CacheManager {
     cache_dir: PathBuf,
     ttl: Duration,
+    max_size_bytes: u64,
 }
 
 impl CacheManager {
@@ -30,6 +70,7 @@ impl CacheManager {
         Ok(Self {
             cache_dir,
             ttl: Duration::from_secs(DEFAULT_TTL_HOURS * 3600),
+            max_size_bytes: MAX_CACHE_SIZE_MB * 1_048_576,
         })
     }
 
@@ -38,9 +79,33 @@ impl CacheManager {
         Ok(Self {
             cache_dir: dir,
             ttl: Duration::from_secs(DEFAULT_TTL_HOURS * 3600),
+            max_size_bytes: MAX_CACHE_SIZE_MB * 1_048_576,
         })
     }
 
+    /// Cache at the configured directory with the configured TTL and size limit
+    pub fn from_config(config: &crate::config::Config) -> Result<Self> {
+        let cache = match &config.cache_dir {
+            Some(dir) => Self::with_custom_dir(crate::config::expand_path(dir))?,
+            None => Self::new()?,
+        };
+        Ok(cache
+            .with_ttl_hours(config.cache_ttl_hours)
+            .with_max_size_mb(config.max_cache_size_mb))
+    }
+
+    /// Expire entries after `hours` instead of the default 24
+    pub fn with_ttl_hours(mut self, hours: u64) -> Self {
+        self.ttl = Duration::from_secs(hours * 3600);
+        self
+    }
+
+    /// Evict least recently used entries once the cache grows past `mb` megabytes
+    pub fn with_max_size_mb(mut self, mb: u64) -> Self {
+        self.max_size_bytes = mb * 1_048_576;
+        self
+    }
+
     fn get_cache_dir() -> Result<PathBuf> {
         Ok(ProjectDirs::from("", "", "manx")
             .context("Failed to determine cache directory")?
@@ -49,11 +114,34 @@ impl CacheManager {
     }
 
     pub fn cache_key(&self, category: &str, key: &str) -> PathBuf {
-        let safe_key = key.replace('/', "_").replace('@', "_v_").replace(' ', "_");
-
         self.cache_dir
             .join(category)
-            .join(format!("{}.json", safe_key))
+            .join(format!("{}.json", safe_key(key)))
+    }
+
+    /// The most recently written live entry in `category` whose key starts with `prefix`
+    ///
+    /// Returns the key to pass to [`CacheManager::get`] and when the entry was written.
+    pub fn newest_entry(&self, category: &str, prefix: &str) -> Option<(String, DateTime<Utc>)> {
+        let prefix = safe_key(prefix);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let (key, timestamp) = fs::read_dir(self.cache_dir.join(category))
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let key = entry
+                    .file_name()
+                    .to_str()?
+                    .strip_suffix(".json")?
+                    .to_string();
+                if !key.starts_with(&prefix) {
+                    return None;
+                }
+                let header = read_header(&entry.path())?;
+                (!self.is_expired(category, &header, now)).then_some((key, header.timestamp))
+            })
+            .max_by_key(|(_, timestamp)| *timestamp)?;
+        Some((key, DateTime::<Utc>::from_timestamp(timestamp as i64, 0)?))
     }
 
     pub async fn get<T>(&self, category: &str, key: &str) -> Result<Option<T>>
@@ -81,13 +169,18 @@ impl CacheManager {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
         let age = now.saturating_sub(entry.timestamp);
-        let ttl_secs = self.ttl.as_secs();
+        let ttl_secs = self.ttl_secs(category);
 
         if age > ttl_secs {
             fs::remove_file(&path).ok();
             return Ok(None);
         }
 
+        // Mark the entry as recently used for LRU eviction
+        if let Ok(file) = fs::File::options().write(true).open(&path) {
+            file.set_modified(SystemTime::now()).ok();
+        }
+
         Ok(Some(entry.data))
     }
 
@@ -106,18 +199,131 @@ impl CacheManager {
             version: CACHE_VERSION,
             data,
             timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
-            ttl_hours: DEFAULT_TTL_HOURS,
+            ttl_hours: self.ttl_secs(category) / 3600,
         };
 
         let json = serde_json::to_string_pretty(&entry)?;
         fs::write(&path, json)?;
 
-        // Check cache size and clean if needed
-        self.clean_if_needed().await?;
+        // Check cache size and evict if needed
+        self.evict_if_over_size()?;
+
+        Ok(())
+    }
+
+    /// Raw entry stored under a key, with its file size and modification time
+    ///
+    /// Unlike `get`, this ignores the TTL and doesn't count as a use for LRU eviction.
+    pub fn inspect(&self, category: &str, key: &str) -> Result<CacheInspection> {
+        let path = self.cache_key(category, key);
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => anyhow::bail!(
+                "No cache entry '{}' in category '{}'. Run 'manx cache list' to see cached keys.",
+                key,
+                category
+            ),
+            Err(e) => return Err(e.into()),
+        };
+
+        let data = fs::read_to_string(&path).context("Failed to read cache file")?;
+        let entry = serde_json::from_str(&data).context("Failed to parse cache entry")?;
+        Ok(CacheInspection {
+            path,
+            size_bytes: metadata.len(),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            entry,
+        })
+    }
 
+    /// Record which library each cached item belongs to, so short IDs resolve without
+    /// guessing from file names
+    ///
+    /// Replaces earlier records for the same library and ID, and drops records whose
+    /// entry has since been evicted.
+    pub fn record_items(&self, category: &str, items: &[ItemRef]) -> Result<()> {
+        let _guard = ITEM_INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.load_item_index();
+        let refs = index.entry(category.to_string()).or_default();
+        refs.retain(|r| {
+            !items
+                .iter()
+                .any(|item| item.library == r.library && item.id == r.id)
+                && self.cache_key(category, &r.key).exists()
+        });
+        refs.extend(items.iter().cloned());
+
+        let path = self.cache_dir.join(ITEM_INDEX_FILE);
+        fs::write(&path, serde_json::to_string(&index)?)
+            .context("Failed to write cache item index")?;
         Ok(())
     }
 
+    /// Cached items in `category` matching `id`, most recently recorded first
+    ///
+    /// A bare ID (`doc-1`) matches that item in every library; a library-qualified ID
+    /// (`react-doc-1`) matches only that library's item. Entries cached before the item
+    /// index existed are found by their file names when the index has no match.
+    pub fn find_items(&self, category: &str, id: &str) -> Vec<ItemRef> {
+        let index = self.load_item_index();
+        let Some(refs) = index.get(category) else {
+            return self.find_unindexed_items(category, id);
+        };
+
+        let existing = |r: &&ItemRef| self.cache_key(category, &r.key).exists();
+        let qualified: Vec<ItemRef> = refs
+            .iter()
+            .rev()
+            .filter(|r| format!("{}-{}", r.library, r.id) == id)
+            .filter(existing)
+            .cloned()
+            .collect();
+        if !qualified.is_empty() {
+            return qualified;
+        }
+
+        let bare: Vec<ItemRef> = refs
+            .iter()
+            .rev()
+            .filter(|r| r.id == id)
+            .filter(existing)
+            .cloned()
+            .collect();
+        if !bare.is_empty() {
+            return bare;
+        }
+        self.find_unindexed_items(category, id)
+    }
+
+    /// Items stored under `{library}_{id}` keys without an index record, newest first
+    fn find_unindexed_items(&self, category: &str, id: &str) -> Vec<ItemRef> {
+        let Ok(entries) = fs::read_dir(self.cache_dir.join(category)) else {
+            return Vec::new();
+        };
+        let mut found: Vec<(ItemRef, SystemTime)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let key = name.to_str()?.strip_suffix(".json")?;
+                let (library, item_id) = key.rsplit_once('_')?;
+                if item_id != id && format!("{}-{}", library, item_id) != id {
+                    return None;
+                }
+                let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                Some((ItemRef::new(library, item_id, key), modified))
+            })
+            .collect();
+        found.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+        found.into_iter().map(|(item, _)| item).collect()
+    }
+
+    fn load_item_index(&self) -> std::collections::BTreeMap<String, Vec<ItemRef>> {
+        fs::read_to_string(self.cache_dir.join(ITEM_INDEX_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
     pub async fn clear(&self) -> Result<()> {
         if self.cache_dir.exists() {
             fs::remove_dir_all(&self.cache_dir)?;
@@ -126,44 +332,108 @@ impl CacheManager {
         Ok(())
     }
 
+    /// Size, file count, age range and expired entries, in total and per category
+    ///
+    /// Ages come from the time each entry was written. Other files in the cache directory
+    /// (such as the RAG index) count towards sizes only.
     pub async fn stats(&self) -> Result<CacheStats> {
         let mut total_size = 0u64;
         let mut file_count = 0u32;
+        let mut expired_count = 0u32;
         let mut categories = Vec::new();
 
         if !self.cache_dir.exists() {
             return Ok(CacheStats {
                 total_size_mb: 0.0,
                 file_count: 0,
+                expired_count: 0,
                 categories,
             });
         }
 
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
         for entry in fs::read_dir(&self.cache_dir)? {
             let entry = entry?;
             let path = entry.path();
 
             if path.is_dir() {
-                categories.push(entry.file_name().to_string_lossy().to_string());
+                let mut category = CategoryStats {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    size_mb: 0.0,
+                    file_count: 0,
+                    expired_count: 0,
+                    oldest: None,
+                    newest: None,
+                };
+                let mut category_size = 0u64;
 
                 for file in fs::read_dir(&path)? {
                     let file = file?;
-                    if file.path().is_file() {
-                        let metadata = file.metadata()?;
-                        total_size += metadata.len();
-                        file_count += 1;
+                    let file_path = file.path();
+                    if file_path.is_file() {
+                        category_size += file.metadata()?.len();
+                        category.file_count += 1;
+
+                        if let Some(header) = read_header(&file_path) {
+                            if self.is_expired(&category.name, &header, now) {
+                                category.expired_count += 1;
+                            }
+                            if let Some(written) =
+                                DateTime::<Utc>::from_timestamp(header.timestamp as i64, 0)
+                            {
+                                category.oldest =
+                                    Some(category.oldest.map_or(written, |t| t.min(written)));
+                                category.newest =
+                                    Some(category.newest.map_or(written, |t| t.max(written)));
+                            }
+                        }
                     }
                 }
+
+                category.size_mb = category_size as f64 / 1_048_576.0;
+                total_size += category_size;
+                file_count += category.file_count;
+                expired_count += category.expired_count;
+                categories.push(category);
             }
         }
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
 
         Ok(CacheStats {
             total_size_mb: total_size as f64 / 1_048_576.0,
             file_count,
+            expired_count,
             categories,
         })
     }
 
+    /// Delete expired entries, then evict least recently used ones while over the size limit
+    pub async fn prune(&self) -> Result<PruneReport> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut report = PruneReport::default();
+        let mut live = Vec::new();
+
+        for file in self.entry_files()? {
+            match read_header(&file.path) {
+                Some(header)
+                    if self.is_expired(&file.category, &header, now)
+                        && fs::remove_file(&file.path).is_ok() =>
+                {
+                    report.expired += 1;
+                    report.freed_bytes += file.size;
+                }
+                Some(_) => live.push(file),
+                // Not a cache entry (e.g. RAG index metadata)
+                None => {}
+            }
+        }
+
+        let (evicted, freed_bytes) = self.evict_lru(live);
+        report.evicted = evicted;
+        report.freed_bytes += freed_bytes;
+        Ok(report)
+    }
+
     pub async fn list_cached(&self) -> Result<Vec<CachedItem>> {
         let mut items = Vec::new();
 
@@ -208,56 +478,174 @@ impl CacheManager {
         Ok(items)
     }
 
-    async fn clean_if_needed(&self) -> Result<()> {
-        let stats = self.stats().await?;
+    /// Evict least recently used entries when a write pushes the cache over its size limit
+    fn evict_if_over_size(&self) -> Result<()> {
+        let files = self.entry_files()?;
+        if files.iter().map(|f| f.size).sum::<u64>() <= self.max_size_bytes {
+            return Ok(());
+        }
+
+        let entries = files
+            .into_iter()
+            .filter(|file| read_header(&file.path).is_some())
+            .collect();
+        let (evicted, freed_bytes) = self.evict_lru(entries);
+        log::debug!(
+            "Evicted {} cache entries ({} bytes) to stay under the size limit",
+            evicted,
+            freed_bytes
+        );
+        Ok(())
+    }
 
-        if stats.total_size_mb > MAX_CACHE_SIZE_MB as f64 {
-            // Remove oldest files until under limit
-            let mut files: Vec<(PathBuf, SystemTime)> = Vec::new();
+    /// Remove least recently used entries until they fit in 80% of the size limit
+    ///
+    /// Returns the number of entries removed and the bytes freed.
+    fn evict_lru(&self, mut entries: Vec<EntryFile>) -> (usize, u64) {
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        if total <= self.max_size_bytes {
+            return (0, 0);
+        }
 
-            for entry in fs::read_dir(&self.cache_dir)? {
-                let entry = entry?;
-                let path = entry.path();
+        let target = self.max_size_bytes / 10 * 8;
+        entries.sort_by_key(|e| e.last_used);
 
-                if path.is_dir() {
-                    for file in fs::read_dir(&path)? {
-                        let file = file?;
-                        let file_path = file.path();
-                        if file_path.is_file() {
-                            let modified = file.metadata()?.modified()?;
-                            files.push((file_path, modified));
-                        }
-                    }
-                }
+        let mut evicted = 0;
+        let mut freed = 0;
+        for entry in entries {
+            if total <= target {
+                break;
             }
+            if fs::remove_file(&entry.path).is_ok() {
+                total -= entry.size;
+                freed += entry.size;
+                evicted += 1;
+            }
+        }
+        (evicted, freed)
+    }
 
-            // Sort by modification time (oldest first)
-            files.sort_by_key(|(_, time)| *time);
-
-            // Remove oldest files
-            let mut current_size = stats.total_size_mb;
-            for (file_path, _) in files {
-                if current_size <= MAX_CACHE_SIZE_MB as f64 * 0.8 {
-                    break;
-                }
+    /// Every `.json` file directly inside a category directory
+    fn entry_files(&self) -> Result<Vec<EntryFile>> {
+        let mut files = Vec::new();
+        if !self.cache_dir.exists() {
+            return Ok(files);
+        }
 
-                if let Ok(metadata) = fs::metadata(&file_path) {
-                    let file_size_mb = metadata.len() as f64 / 1_048_576.0;
-                    fs::remove_file(&file_path).ok();
-                    current_size -= file_size_mb;
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let category = entry.file_name().to_string_lossy().to_string();
+            for file in fs::read_dir(&path)? {
+                let file = file?;
+                let file_path = file.path();
+                if !file_path.is_file()
+                    || file_path.extension() != Some(std::ffi::OsStr::new("json"))
+                {
+                    continue;
                 }
+                let metadata = file.metadata()?;
+                files.push(EntryFile {
+                    path: file_path,
+                    category: category.clone(),
+                    size: metadata.len(),
+                    last_used: metadata.modified().unwrap_or(UNIX_EPOCH),
+                });
             }
         }
+        Ok(files)
+    }
 
-        Ok(())
+    fn is_expired(&self, category: &str, header: &EntryHeader, now: u64) -> bool {
+        header.version != CACHE_VERSION
+            || now.saturating_sub(header.timestamp) > self.ttl_secs(category)
+    }
+
+    /// Lifetime of entries in `category`; library IDs keep at least [`LIBRARY_TTL_HOURS`]
+    fn ttl_secs(&self, category: &str) -> u64 {
+        if category == "libraries" {
+            self.ttl.as_secs().max(LIBRARY_TTL_HOURS * 3600)
+        } else {
+            self.ttl.as_secs()
+        }
     }
 }
 
+/// File name form of a cache key
+fn safe_key(key: &str) -> String {
+    key.replace('/', "_").replace('@', "_v_").replace(' ', "_")
+}
+
+/// Bookkeeping fields of a cache entry file, or `None` if it isn't one
+fn read_header(path: &std::path::Path) -> Option<EntryHeader> {
+    let file = fs::File::open(path).ok()?;
+    serde_json::from_reader(std::io::BufReader::new(file)).ok()
+}
+
 #[derive(Debug, Serialize)]
 pub struct CacheStats {
     pub total_size_mb: f64,
     pub file_count: u32,
-    pub categories: Vec<String>,
+    /// Entries past the TTL, removed the next time they are read
+    pub expired_count: u32,
+    pub categories: Vec<CategoryStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryStats {
+    pub name: String,
+    pub size_mb: f64,
+    pub file_count: u32,
+    pub expired_count: u32,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+/// A cache entry as stored on disk, from `CacheManager::inspect`
+#[derive(Debug)]
+pub struct CacheInspection {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// The whole entry: version, write timestamp, TTL and data
+    pub entry: serde_json::Value,
+}
+
+/// Outcome of `CacheManager::prune`
+#[derive(Debug, Default, Serialize)]
+pub struct PruneReport {
+    /// Entries removed for being past the TTL
+    pub expired: usize,
+    /// Entries removed, least recently used first, to get under the size limit
+    pub evicted: usize,
+    pub freed_bytes: u64,
+}
+
+/// Where an item listed under a short ID is cached, from `CacheManager::find_items`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemRef {
+    pub library: String,
+    /// ID without the library prefix, e.g. `doc-1` for both `doc-1` and `react-doc-1`
+    pub id: String,
+    /// Key the entry is stored under
+    pub key: String,
+}
+
+impl ItemRef {
+    pub fn new(library: &str, id: &str, key: &str) -> Self {
+        let id = id
+            .strip_prefix(&format!("{}-", library))
+            .unwrap_or(id)
+            .to_string();
+        Self {
+            library: library.to_string(),
+            id,
+            key: key.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -266,3 +654,240 @@ pub struct CachedItem {
     pub name: String,
     pub size_kb: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stats_per_category() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        cache.set("docs", "react", "hooks").await.unwrap();
+        cache.set("docs", "vue", "refs").await.unwrap();
+        cache.set("llm", "answer", "42").await.unwrap();
+
+        let stats = cache.stats().await.unwrap();
+        assert_eq!(stats.file_count, 3);
+        assert_eq!(stats.expired_count, 0);
+        let names: Vec<_> = stats.categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "llm"]);
+        let docs = &stats.categories[0];
+        assert_eq!(docs.file_count, 2);
+        assert!(docs.oldest.unwrap() <= docs.newest.unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_items_falls_back_to_unindexed_entries() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        // Written by an older version, before items were recorded in the index
+        cache
+            .set("snippets", "react_doc-1", "old react")
+            .await
+            .unwrap();
+        cache.set("snippets", "vue_doc-2", "old vue").await.unwrap();
+        cache
+            .set("snippets", "svelte_doc-3", "indexed")
+            .await
+            .unwrap();
+        cache
+            .record_items(
+                "snippets",
+                &[ItemRef::new("svelte", "doc-3", "svelte_doc-3")],
+            )
+            .unwrap();
+
+        let items = cache.find_items("snippets", "doc-1");
+        assert_eq!(items, vec![ItemRef::new("react", "doc-1", "react_doc-1")]);
+        assert_eq!(
+            cache
+                .get::<String>("snippets", &items[0].key)
+                .await
+                .unwrap(),
+            Some("old react".to_string())
+        );
+        assert_eq!(cache.find_items("snippets", "vue-doc-2").len(), 1);
+        assert!(cache.find_items("snippets", "react-doc-2").is_empty());
+        assert_eq!(cache.find_items("snippets", "doc-3")[0].library, "svelte");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_newest_entry_by_prefix() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        cache.set("docs", "react_hooks", "new").await.unwrap();
+        cache.set("docs", "vue_refs", "other").await.unwrap();
+        let entry = CacheEntry {
+            version: CACHE_VERSION,
+            data: "old",
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                - 60,
+            ttl_hours: 24,
+        };
+        fs::write(
+            cache.cache_key("docs", "react_state"),
+            serde_json::to_string(&entry).unwrap(),
+        )
+        .unwrap();
+
+        let (key, _) = cache.newest_entry("docs", "react_").unwrap();
+        assert_eq!(key, "react_hooks");
+        assert_eq!(
+            cache.get::<String>("docs", &key).await.unwrap(),
+            Some("new".to_string())
+        );
+        assert!(cache.newest_entry("docs", "svelte_").is_none());
+        assert!(cache.newest_entry("search", "react_").is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_inspect_entry() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        cache.set("docs", "react@18", "hooks").await.unwrap();
+
+        let inspection = cache.inspect("docs", "react_v_18").unwrap();
+        assert_eq!(inspection.entry["data"], "hooks");
+        assert!(inspection.size_bytes > 0);
+        assert!(inspection.modified.is_some());
+
+        let err = cache.inspect("docs", "vue").unwrap_err().to_string();
+        assert!(err.contains("manx cache list"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_item_index_resolves_library_collisions() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        for (library, id) in [
+            ("react", "react-doc-1"),
+            ("vue", "doc-1"),
+            ("my_lib", "doc-1"),
+        ] {
+            let key = format!("{}_{}", library, id);
+            cache.set("snippets", &key, library).await.unwrap();
+            cache
+                .record_items("snippets", &[ItemRef::new(library, id, &key)])
+                .unwrap();
+        }
+
+        let libraries = |id: &str| -> Vec<String> {
+            cache
+                .find_items("snippets", id)
+                .into_iter()
+                .map(|r| r.library)
+                .collect()
+        };
+        assert_eq!(libraries("doc-1"), vec!["my_lib", "vue", "react"]);
+        assert_eq!(libraries("react-doc-1"), vec!["react"]);
+        assert_eq!(libraries("my_lib-doc-1"), vec!["my_lib"]);
+        assert!(libraries("doc-2").is_empty());
+
+        // Re-recording an item replaces its old record, and evicted entries drop out
+        let key = "vue_doc-1";
+        cache
+            .record_items("snippets", &[ItemRef::new("vue", "doc-1", key)])
+            .unwrap();
+        fs::remove_file(cache.cache_key("snippets", "react_react-doc-1")).unwrap();
+        assert_eq!(libraries("doc-1"), vec!["vue", "my_lib"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn backdate(path: &std::path::Path, secs: u64) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_misses_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone())
+            .unwrap()
+            .with_ttl_hours(1);
+        cache.set("docs", "fresh", "new").await.unwrap();
+        cache.set("docs", "stale", "old").await.unwrap();
+        cache.set("docs", "unread", "old").await.unwrap();
+
+        // Entries written two hours ago, with matching mtimes; library IDs live longer
+        for (category, key) in [
+            ("docs", "stale"),
+            ("docs", "unread"),
+            ("libraries", "react"),
+        ] {
+            fs::create_dir_all(dir.join(category)).unwrap();
+            let path = cache.cache_key(category, key);
+            let entry = CacheEntry {
+                version: CACHE_VERSION,
+                data: "old",
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs()
+                    - 7200,
+                ttl_hours: 1,
+            };
+            fs::write(&path, serde_json::to_string(&entry).unwrap()).unwrap();
+            backdate(&path, 7200);
+        }
+        // Files that aren't cache entries are never pruned
+        fs::write(dir.join("docs").join("index_meta.json"), "{}").unwrap();
+
+        assert_eq!(cache.stats().await.unwrap().expired_count, 2);
+        assert_eq!(cache.get::<String>("docs", "stale").await.unwrap(), None);
+        assert!(!cache.cache_key("docs", "stale").exists());
+
+        let report = cache.prune().await.unwrap();
+        assert_eq!((report.expired, report.evicted), (1, 0));
+        assert!(!cache.cache_key("docs", "unread").exists());
+        assert!(dir.join("docs").join("index_meta.json").exists());
+        assert_eq!(
+            cache.get::<String>("docs", "fresh").await.unwrap(),
+            Some("new".to_string())
+        );
+        assert_eq!(
+            cache.get::<String>("libraries", "react").await.unwrap(),
+            Some("old".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_evicts_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("manx_cache_{}", uuid::Uuid::new_v4()));
+        let mut cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        let payload = "x".repeat(1000);
+        for (key, age) in [("a", 300), ("b", 200), ("c", 100)] {
+            cache.set("docs", key, &payload).await.unwrap();
+            backdate(&cache.cache_key("docs", key), age);
+        }
+        // Reading "a" makes it the most recently used entry
+        assert!(cache.get::<String>("docs", "a").await.unwrap().is_some());
+
+        // Room for three entries: the fourth write evicts down to 80%, oldest use first
+        let entry_size = fs::metadata(cache.cache_key("docs", "a")).unwrap().len();
+        cache.max_size_bytes = entry_size * 3 + entry_size / 2;
+        cache.set("docs", "d", &payload).await.unwrap();
+
+        assert!(!cache.cache_key("docs", "b").exists());
+        assert!(!cache.cache_key("docs", "c").exists());
+        assert!(cache.cache_key("docs", "a").exists());
+        assert!(cache.cache_key("docs", "d").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,6 @@
+use crate::render::OutputFormat;
+use crate::web_search::official_sources::SourceTier;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -17,6 +20,9 @@ LOCAL RAG COMMANDS:
   index <path>                   Index your documents for semantic search
   sources list                   View indexed document sources
   sources clear                  Clear all indexed documents
+  rag export <file>              Back up the index to a portable JSONL file
+  rag import <file>              Restore an exported index
+  rag reindex                    Re-embed the index after switching embedding models
 
 EMBEDDING SYSTEM - Smart semantic search:
   embedding status               View current embedding configuration
@@ -63,10 +69,52 @@ pub struct Cli {
     #[arg(long, help_heading = "DEBUG OPTIONS")]
     pub debug: bool,
 
+    /// Print tokens used and estimated cost after AI synthesis (implied by --debug)
+    #[arg(long, global = true, help_heading = "DEBUG OPTIONS")]
+    pub show_usage: bool,
+
     /// Output JSON format (useful for scripts and automation)
     #[arg(short = 'q', long, help_heading = "OUTPUT OPTIONS")]
     pub quiet: bool,
 
+    /// Append exported results to FILE under a timestamped heading instead of overwriting
+    #[arg(
+        long,
+        value_name = "FILE",
+        global = true,
+        help_heading = "OUTPUT OPTIONS"
+    )]
+    pub output_append: Option<PathBuf>,
+
+    /// When to use colors: auto (default; terminals only, honoring NO_COLOR), always or never
+    #[arg(
+        long,
+        value_name = "WHEN",
+        global = true,
+        help_heading = "OUTPUT OPTIONS"
+    )]
+    pub color: Option<ColorChoice>,
+
+    /// Disable colors for this run (same as --color never)
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "color",
+        help_heading = "OUTPUT OPTIONS"
+    )]
+    pub no_color: bool,
+
+    /// Report a failure as a JSON object {"error", "context"} on stderr (or set MANX_JSON=1)
+    #[arg(
+        long,
+        global = true,
+        env = "MANX_JSON",
+        hide_env = true,
+        value_parser = clap::builder::FalseyValueParser::new(),
+        help_heading = "OUTPUT OPTIONS"
+    )]
+    pub json_errors: bool,
+
     /// Clear all cached documentation and start fresh
     #[arg(long, help_heading = "CACHE OPTIONS")]
     pub clear_cache: bool,
@@ -79,6 +127,10 @@ pub struct Cli {
     #[arg(long, help_heading = "CACHE OPTIONS")]
     pub auto_cache_off: bool,
 
+    /// Skip cached AI answers and don't store new ones for this run
+    #[arg(long, global = true, help_heading = "CACHE OPTIONS")]
+    pub no_cache: bool,
+
     /// Override API key for this session
     #[arg(long, help_heading = "GLOBAL OPTIONS")]
     pub api_key: Option<String>,
@@ -90,6 +142,23 @@ pub struct Cli {
     /// Work offline using only cached results
     #[arg(long, help_heading = "GLOBAL OPTIONS")]
     pub offline: bool,
+
+    /// Override LLM, web search and embedding timeouts for this run
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help_heading = "GLOBAL OPTIONS"
+    )]
+    pub timeout: Option<u64>,
+}
+
+/// `--color` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
 
 #[derive(Subcommand)]
@@ -109,12 +178,36 @@ pub enum Commands {
         /// Limit number of sections shown (default: 10, use 0 for unlimited)
         #[arg(short = 'l', long, value_name = "NUMBER")]
         limit: Option<usize>,
+        /// Show only section N (the [N] label in the full listing)
+        #[arg(long, value_name = "N", conflicts_with_all = ["limit", "rag"])]
+        section: Option<usize>,
+        /// Re-resolve the library ID instead of using the one cached for a week
+        #[arg(long, conflicts_with = "rag")]
+        refresh: bool,
         /// Force retrieval-only mode (disable LLM synthesis even if API key configured)
         #[arg(long)]
         no_llm: bool,
+        /// LLM model for this run only, overriding the configured one (e.g. 'gpt-4o')
+        #[arg(long, value_name = "NAME", conflicts_with = "no_llm")]
+        model: Option<String>,
         /// Search locally indexed documents instead of Context7 API
         #[arg(long)]
         rag: bool,
+        /// With --rag, hide results scoring below this relevance (0 to 1) for this run
+        #[arg(long, value_name = "SCORE", value_parser = parse_unit_interval, requires = "rag")]
+        min_score: Option<f32>,
+        /// With --rag, blend keyword (BM25) matching into the semantic ranking for this run
+        #[arg(long, requires = "rag")]
+        hybrid: bool,
+        /// With --rag, share of the score from keyword matching (0 to 1; implies --hybrid)
+        #[arg(long, value_name = "WEIGHT", value_parser = parse_unit_interval, requires = "rag")]
+        keyword_weight: Option<f32>,
+        /// Write sections to stdout as json, yaml, text or markdown (no colors or spinners)
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        format: Option<OutputFormat>,
+        /// Print only the AI answer as plain text, without results or decorations
+        #[arg(long, conflicts_with_all = ["format", "no_llm"])]
+        raw: bool,
     },
 
     /// Search code snippets and examples with AI-powered understanding
@@ -140,6 +233,7 @@ pub enum Commands {
     ///   manx snippet "database pooling" --llm-api        # Get AI answer with citations  
     ///   manx snippet fastapi middleware --no-llm         # Raw results only
     ///   manx snippet python "async functions" --rag      # Search your indexed code files
+    ///   manx snippet db "connection pool" --rag --lang rust  # Only Rust files from the index
     Snippet {
         /// Library name (examples: 'fastapi', 'react@18', 'vue@3')
         #[arg(value_name = "LIBRARY")]
@@ -168,9 +262,53 @@ pub enum Commands {
         /// Force retrieval-only mode (disable LLM synthesis even if API key configured)
         #[arg(long)]
         no_llm: bool,
+        /// LLM model for this run only, overriding the configured one (e.g. 'gpt-4o')
+        #[arg(long, value_name = "NAME", conflicts_with = "no_llm")]
+        model: Option<String>,
         /// Search locally indexed documents instead of Context7 API (requires: manx config --rag-enabled)
         #[arg(long)]
         rag: bool,
+        /// With --rag, hide results scoring below this relevance (0 to 1) for this run
+        #[arg(long, value_name = "SCORE", value_parser = parse_unit_interval, requires = "rag")]
+        min_score: Option<f32>,
+        /// With --rag, blend keyword (BM25) matching into the semantic ranking for this run
+        #[arg(long, requires = "rag")]
+        hybrid: bool,
+        /// With --rag, share of the score from keyword matching (0 to 1; implies --hybrid)
+        #[arg(long, value_name = "WEIGHT", value_parser = parse_unit_interval, requires = "rag")]
+        keyword_weight: Option<f32>,
+        /// With --rag, only show results from these languages (repeat or comma-separate: --lang rust,python)
+        #[arg(
+            long = "lang",
+            value_name = "LANG",
+            value_delimiter = ',',
+            requires = "rag"
+        )]
+        languages: Vec<String>,
+        /// With --rag, only show results carrying every one of these tags (repeatable; see 'manx sources tags')
+        #[arg(long = "tag", value_name = "TAG", requires = "rag")]
+        tags: Vec<String>,
+        /// With --rag, have the configured LLM reorder the top results by relevance
+        #[arg(long, requires = "rag")]
+        rerank: bool,
+        /// Pin documentation to a library version (same as 'library@version')
+        #[arg(long, value_name = "VERSION")]
+        context7_version: Option<String>,
+        /// Detect the version from a nearby package.json, Cargo.toml or requirements.txt
+        #[arg(long, conflicts_with = "context7_version")]
+        auto_version: bool,
+        /// Re-resolve the library ID instead of using the one cached for a week
+        #[arg(long, conflicts_with = "rag")]
+        refresh: bool,
+        /// Write results to stdout as json, yaml, text or markdown (no colors or spinners)
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        format: Option<OutputFormat>,
+        /// Print only the AI answer as plain text, without results or decorations
+        #[arg(long, conflicts_with_all = ["format", "no_llm", "interactive"])]
+        raw: bool,
+        /// After listing results, pick one to read in full and optionally save
+        #[arg(short = 'i', long, conflicts_with = "format")]
+        interactive: bool,
     },
 
     /// Search official documentation across the web
@@ -191,22 +329,104 @@ pub enum Commands {
     ///   manx search "react hooks best practices"        # Uses LLM if API key configured
     ///   manx search "python async await" --no-llm       # Force embeddings-only mode
     ///   manx search "authentication" --rag              # Search your indexed documents
+    ///   manx search "tokio select timeout" --prefer-community  # Rank Stack Overflow etc. equally
+    ///   cat question.txt | manx search -                # Read the query from stdin
+    ///   manx search "tokio channels" -i                 # Pick a result to read or save
     Search {
-        /// Search query for official documentation
-        #[arg(value_name = "QUERY")]
-        query: String,
+        /// Search query for official documentation ('-' reads it from stdin)
+        #[arg(value_name = "QUERY", required_unless_present = "stdin")]
+        query: Option<String>,
+        /// Read the query from stdin, trimmed; multi-line input is kept as one query
+        #[arg(long, conflicts_with = "query")]
+        stdin: bool,
         /// Disable LLM verification (use embeddings-only mode even if API key is configured)
         #[arg(long)]
         no_llm: bool,
+        /// LLM model for this run only, overriding the configured one (e.g. 'gpt-4o')
+        #[arg(long, value_name = "NAME", conflicts_with = "no_llm")]
+        model: Option<String>,
         /// Export results to file (format auto-detected by extension: .md, .json)
         #[arg(short = 'o', long, value_name = "FILE")]
         output: Option<PathBuf>,
         /// Limit number of results shown (default: 8)
         #[arg(short = 'l', long, value_name = "NUMBER")]
         limit: Option<usize>,
+        /// Skip the first N results, for paging with --limit (--limit 5 --offset 5 shows 6-10)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        offset: usize,
         /// Search locally indexed documents instead of web search (requires: manx config --rag-enabled)
         #[arg(long)]
         rag: bool,
+        /// With --rag, never supplement weak local matches with web results
+        #[arg(long, requires = "rag")]
+        no_web_fallback: bool,
+        /// With --rag, only show results from these languages (repeat or comma-separate: --lang rust,python)
+        #[arg(
+            long = "lang",
+            value_name = "LANG",
+            value_delimiter = ',',
+            requires = "rag"
+        )]
+        languages: Vec<String>,
+        /// With --rag, only show results carrying every one of these tags (repeatable; see 'manx sources tags')
+        #[arg(long = "tag", value_name = "TAG", requires = "rag")]
+        tags: Vec<String>,
+        /// With --rag, have the configured LLM reorder the top results by relevance
+        #[arg(long, requires = "rag")]
+        rerank: bool,
+        /// Hide results scoring below this relevance (0 to 1) for this run
+        #[arg(long, value_name = "SCORE", value_parser = parse_unit_interval)]
+        min_score: Option<f32>,
+        /// With --rag, blend keyword (BM25) matching into the semantic ranking for this run
+        #[arg(long, requires = "rag")]
+        hybrid: bool,
+        /// With --rag, share of the score from keyword matching (0 to 1; implies --hybrid)
+        #[arg(long, value_name = "WEIGHT", value_parser = parse_unit_interval, requires = "rag")]
+        keyword_weight: Option<f32>,
+        /// Rank community sources purely by relevance, ignoring the official-source boost
+        #[arg(long, conflicts_with = "rag")]
+        prefer_community: bool,
+        /// Only show web results published after this: a duration (30d, 6w, 3mo, 1y) or a date (2024-01-31)
+        #[arg(long, value_name = "WHEN", value_parser = parse_since, conflicts_with = "rag")]
+        since: Option<DateTime<Utc>>,
+        /// Write results to stdout as json, yaml, text or markdown (no colors or spinners)
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        format: Option<OutputFormat>,
+        /// Print only the AI answer as plain text, without results or decorations
+        #[arg(long, conflicts_with_all = ["format", "no_llm", "interactive"])]
+        raw: bool,
+        /// After listing results, pick one to read in full and optionally save
+        #[arg(short = 'i', long, conflicts_with = "format")]
+        interactive: bool,
+    },
+
+    /// Ask a question across every source and get one cited answer
+    ///
+    /// COMBINED RETRIEVAL:
+    ///   • Context7 docs for the library detected in the question
+    ///   • Your locally indexed documents (when RAG is enabled)
+    ///   • Official documentation from the web
+    ///
+    /// Results from all three are merged, deduplicated and synthesized into a single
+    /// answer. Citations are labeled [Context7], [Local] or [Web].
+    ///
+    /// EXAMPLES:
+    ///   manx ask "how do I memoize a component in react"
+    ///   manx ask "tokio spawn vs spawn_blocking" --limit 3
+    ///   manx ask "fastapi dependency injection" --no-llm   # Show merged context only
+    Ask {
+        /// Question to answer
+        #[arg(value_name = "QUESTION")]
+        question: String,
+        /// Show the merged context without LLM synthesis
+        #[arg(long)]
+        no_llm: bool,
+        /// Export answer and context to a JSON file
+        #[arg(short = 'o', long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Results to take from each source (default: 5)
+        #[arg(short = 'l', long, value_name = "NUMBER")]
+        limit: Option<usize>,
     },
 
     /// Get specific item by ID (doc-3, section-5, etc.)
@@ -219,6 +439,21 @@ pub enum Commands {
         output: Option<PathBuf>,
     },
 
+    /// List recent search, snippet and doc queries, or re-run one
+    ///
+    /// EXAMPLES:
+    ///   manx history            # Numbered list, most recent last
+    ///   manx history --run 3    # Re-run entry 3 with its original arguments
+    ///   manx history clear      # Forget all recorded queries
+    #[command(args_conflicts_with_subcommands = true)]
+    History {
+        /// Re-run the numbered entry from the list
+        #[arg(long, value_name = "N")]
+        run: Option<usize>,
+        #[command(subcommand)]
+        command: Option<HistoryCommands>,
+    },
+
     /// Manage local documentation cache
     Cache {
         #[command(subcommand)]
@@ -226,7 +461,16 @@ pub enum Commands {
     },
 
     /// Configure Manx settings, API keys, and AI integration
+    ///
+    /// EXAMPLES:
+    ///   manx config --show                        # Current settings
+    ///   manx config export -o team.toml           # Share settings, API keys left out
+    ///   manx config import team.toml              # Review and apply a shared config
+    ///   manx config test                          # Verify API keys with live requests
+    #[command(args_conflicts_with_subcommands = true)]
     Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
         /// Display current configuration settings
         #[arg(long)]
         show: bool,
@@ -245,6 +489,15 @@ pub enum Commands {
         /// Set maximum cache size in MB (default: 100)
         #[arg(long, value_name = "SIZE")]
         max_cache_size: Option<u64>,
+        /// Set how many recent queries 'manx history' keeps (default: 500, 0 disables)
+        #[arg(long, value_name = "ENTRIES")]
+        history_limit: Option<usize>,
+        /// Send all requests through this proxy, e.g. http://proxy.corp:8080 (empty to clear)
+        #[arg(long, value_name = "URL")]
+        proxy: Option<String>,
+        /// User agent for crawling, web search and API calls (empty for the default)
+        #[arg(long, value_name = "UA")]
+        user_agent: Option<String>,
         /// Set OpenAI API key for GPT models
         #[arg(long, value_name = "API_KEY")]
         openai_api: Option<String>,
@@ -260,10 +513,30 @@ pub enum Commands {
         /// Set HuggingFace API key for open-source models
         #[arg(long, value_name = "API_KEY")]
         huggingface_api: Option<String>,
+        /// Set Azure OpenAI API key (also needs --azure-resource and --azure-deployment)
+        #[arg(long, value_name = "API_KEY")]
+        azure_api: Option<String>,
+        /// Set Azure OpenAI resource name, or its endpoint URL ("" to clear)
+        #[arg(long, value_name = "NAME")]
+        azure_resource: Option<String>,
+        /// Set Azure OpenAI chat model deployment name ("" to clear)
+        #[arg(long, value_name = "NAME")]
+        azure_deployment: Option<String>,
+        /// Set Azure OpenAI API version (default: 2024-10-21, "" to reset)
+        #[arg(long, value_name = "VERSION")]
+        azure_api_version: Option<String>,
         /// Set custom endpoint URL for self-hosted models
         #[arg(long, value_name = "URL")]
         custom_endpoint: Option<String>,
-        /// Set preferred LLM provider (openai, anthropic, groq, openrouter, huggingface, zai, custom, auto)
+        /// Use a local Ollama server for answers (default: http://localhost:11434, "" to clear)
+        #[arg(
+            long,
+            value_name = "URL",
+            num_args = 0..=1,
+            default_missing_value = crate::rag::llm::DEFAULT_OLLAMA_ENDPOINT
+        )]
+        ollama_endpoint: Option<String>,
+        /// Set preferred LLM provider (openai, azure, anthropic, groq, openrouter, huggingface, zai, ollama, custom, auto)
         #[arg(long, value_name = "PROVIDER")]
         llm_provider: Option<String>,
         /// Set specific model name (overrides provider defaults)
@@ -275,21 +548,63 @@ pub enum Commands {
         /// Enable/disable local RAG system (values: on, off)
         #[arg(long, value_name = "MODE")]
         rag: Option<String>,
-        /// Add custom official documentation domain (format: domain.com)
+        /// Set web search backend (duckduckgo, searxng:<url>, brave)
+        #[arg(long, value_name = "PROVIDER")]
+        search_provider: Option<String>,
+        /// Set API key for the web search backend (Brave Search)
+        #[arg(long, value_name = "KEY")]
+        search_api_key: Option<String>,
+        /// Scale the web search boost for official sources (0 = rank by relevance only, default: 1)
+        #[arg(long, value_name = "FACTOR")]
+        official_boost: Option<f32>,
+        /// Add custom official documentation domain (format: domain.com), saved across sessions
         #[arg(long, value_name = "DOMAIN")]
         add_official_domain: Option<String>,
-        /// Set embedding provider for RAG system (hash, onnx:model, ollama:model, openai:model, huggingface:model, custom:url)
+        /// Set embedding provider for RAG system (hash, onnx:model, ollama:model, openai:model, huggingface:model, azure:resource/deployment[@api-version], custom:url)
         #[arg(long, value_name = "PROVIDER")]
         embedding_provider: Option<String>,
         /// Set embedding API key for API-based providers
         #[arg(long, value_name = "API_KEY")]
         embedding_api_key: Option<String>,
+        /// Set the model requested from a custom:url embedding endpoint (empty clears it)
+        #[arg(long, value_name = "NAME")]
+        embedding_model: Option<String>,
         /// Set embedding model path for local models
         #[arg(long, value_name = "PATH")]
         embedding_model_path: Option<PathBuf>,
         /// Set embedding dimension (default: 384)
         #[arg(long, value_name = "DIMENSION")]
         embedding_dimension: Option<usize>,
+        /// Cap OpenAI/HuggingFace embedding requests per minute while indexing (0 = no limit)
+        #[arg(long, value_name = "N")]
+        embedding_rpm: Option<u32>,
+        /// Cap estimated embedding input tokens per minute while indexing (0 = no limit)
+        #[arg(long, value_name = "N")]
+        embedding_tpm: Option<u32>,
+        /// Prefer neural embeddings over hash when available (values: on, off)
+        #[arg(long, value_name = "MODE", value_parser = parse_switch)]
+        smart_search_prefer_semantic: Option<bool>,
+        /// Expand RAG queries into variations with the LLM (values: on, off)
+        #[arg(long, value_name = "MODE", value_parser = parse_switch)]
+        smart_search_query_enhancement: Option<bool>,
+        /// Re-score RAG results with the LLM (values: on, off)
+        #[arg(long, value_name = "MODE", value_parser = parse_switch)]
+        smart_search_result_verification: Option<bool>,
+        /// Drop RAG results scoring below this confidence (0 to 1, default: 0.7)
+        #[arg(long, value_name = "SCORE", value_parser = parse_unit_interval)]
+        smart_search_min_confidence: Option<f32>,
+        /// Number of query variations to search (default: 3)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..=10))]
+        smart_search_query_variations: Option<u64>,
+        /// Run keyword and semantic stages instead of a single search (values: on, off)
+        #[arg(long, value_name = "MODE", value_parser = parse_switch)]
+        smart_search_multi_stage: Option<bool>,
+        /// Split code and markdown on structure when indexing (values: on, off)
+        #[arg(long, value_name = "MODE", value_parser = parse_switch)]
+        smart_search_adaptive_chunking: Option<bool>,
+        /// Share of RAG scores from BM25 keyword matching (0 to 1, default: 0 = semantic only)
+        #[arg(long, value_name = "WEIGHT", value_parser = parse_unit_interval)]
+        smart_search_keyword_weight: Option<f32>,
     },
 
     /// Index local documents or web URLs for RAG search
@@ -324,8 +639,8 @@ pub enum Commands {
     ///   manx index api.pdf --alias "API Reference"        # Index with custom alias
     Index {
         /// Path to document/directory or URL to index
-        #[arg(value_name = "PATH_OR_URL")]
-        path: String,
+        #[arg(value_name = "PATH_OR_URL", required_unless_present = "retry_failed")]
+        path: Option<String>,
         /// Optional alias for the indexed source
         #[arg(long, value_name = "ALIAS")]
         id: Option<String>,
@@ -341,12 +656,51 @@ pub enum Commands {
         /// Stream indexing while crawling (overlaps crawl + embedding for speed)
         #[arg(long)]
         live_index: bool,
-        /// Number of parallel embedding workers (defaults to CPU cores)
+        /// Parallel embedding workers (crawls default to CPU cores; directories to rag.embedding.max_in_flight)
         #[arg(long, value_name = "N")]
         embed_concurrency: Option<usize>,
-        /// Maximum number of pages to process during crawl (soft cap)
-        #[arg(long, value_name = "N")]
+        /// Continue an interrupted crawl of the URL, skipping pages already stored
+        #[arg(long)]
+        resume: bool,
+        /// Stop crawling after about N pages (in-flight requests may add a few)
+        #[arg(long, value_name = "N", visible_alias = "max-pages")]
         crawl_max_pages: Option<usize>,
+        /// Emit newline-delimited JSON progress events to stderr instead of progress bars
+        #[arg(long)]
+        json_progress: bool,
+        /// Treat the URL as a sitemap and index exactly the pages it lists (no link crawl)
+        #[arg(long)]
+        sitemap: bool,
+        /// Glob to include sitemap URLs; prefix with '!' to exclude (repeatable)
+        #[arg(long, value_name = "GLOB", requires = "sitemap")]
+        sitemap_filter: Vec<String>,
+        /// Re-index every file, ignoring the unchanged-file manifest
+        #[arg(long)]
+        force: bool,
+        /// Chunk size in approximate tokens (default: 500; smaller suits code, larger prose)
+        #[arg(long, value_name = "TOKENS")]
+        chunk_size: Option<usize>,
+        /// Overlap between consecutive chunks in tokens, smaller than the chunk size (default: 50)
+        #[arg(long, value_name = "TOKENS")]
+        chunk_overlap: Option<usize>,
+        /// Also index the text outputs of Jupyter notebook code cells
+        #[arg(long)]
+        notebook_outputs: bool,
+        /// List what would be indexed (files, sizes, skipped files or page URLs) without embedding
+        #[arg(long)]
+        dry_run: bool,
+        /// Only index files matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Skip files or directories matching this glob, e.g. 'vendor/' (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Crawl pages robots.txt disallows and skip its crawl delay (for sites you own)
+        #[arg(long)]
+        ignore_robots: bool,
+        /// Re-embed only chunks that failed in earlier runs (of PATH_OR_URL if given)
+        #[arg(long, conflicts_with_all = ["dry_run", "force", "sitemap"])]
+        retry_failed: bool,
     },
 
     /// Manage indexed document sources
@@ -365,6 +719,22 @@ pub enum Commands {
         output: Option<PathBuf>,
     },
 
+    /// Show how a result from the last search was scored
+    ///
+    /// Prints the raw similarity, keyword-match contribution, official-source boost and
+    /// every later adjustment behind the final score, to tune similarity_threshold,
+    /// source weights and keyword_weight with evidence.
+    ///
+    /// EXAMPLES:
+    ///   manx search "react hooks" && manx explain 3
+    ///   manx search "parse_args" --rag --hybrid && manx explain 1
+    ///   manx explain https://react.dev/reference/react/useState
+    Explain {
+        /// Result number from the last search output, or its URL or chunk ID
+        #[arg(value_name = "RESULT")]
+        id: String,
+    },
+
     /// Update Manx to the latest version from GitHub
     Update {
         /// Check for updates without installing
@@ -373,6 +743,38 @@ pub enum Commands {
         /// Force update even if already on latest version
         #[arg(long)]
         force: bool,
+        /// Release channel to check and install from; saved for later updates
+        #[arg(long, value_enum, value_name = "CHANNEL")]
+        channel: Option<crate::config::UpdateChannel>,
+        /// Restore the binary that the last update replaced
+        #[arg(long, conflicts_with_all = ["check", "force", "channel"])]
+        rollback: bool,
+    },
+
+    /// Run a local HTTP/JSON API for editor plugins and scripts
+    ///
+    /// Each endpoint takes a POST with a JSON body and returns the same results as
+    /// `--format json`. Models and clients stay loaded between requests.
+    ///
+    /// ENDPOINTS:
+    ///   POST /search       {"query": "...", "limit": 10, "offset": 0, "prefer_community": false}
+    ///   POST /snippet      {"library": "react", "query": "hooks", "limit": 10}
+    ///   POST /doc          {"library": "react", "query": "hooks", "limit": 10}
+    ///   POST /rag/search   {"query": "...", "lang": ["rust"], "web_fallback": true}
+    ///
+    /// Every endpoint also accepts "min_score". Errors come back as {"error": ..., "context": [...]}.
+    ///
+    /// EXAMPLES:
+    ///   manx serve                               # Listen on 127.0.0.1:8080
+    ///   manx serve --port 9000
+    ///   curl -s localhost:8080/snippet -d '{"library": "react", "query": "useEffect"}'
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+        /// Address to bind; the API has no authentication, so keep it local
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
     },
 
     /// Manage embedding models and providers for semantic search
@@ -383,13 +785,14 @@ pub enum Commands {
     ///   • ollama:model: Ollama API (requires Ollama server)
     ///   • openai:model: OpenAI embeddings API (requires API key)
     ///   • huggingface:model: HuggingFace embeddings API (requires API key)
-    ///   • custom:url: Custom endpoint API
+    ///   • custom:url: OpenAI-compatible /v1/embeddings server (text-embeddings-inference, vLLM, ...)
     ///
     /// EXAMPLES:
     ///   manx embedding status                     # Show current provider and models
     ///   manx embedding set hash                   # Use hash-based (default)
     ///   manx embedding set onnx:all-MiniLM-L6-v2 # Use local ONNX model
     ///   manx embedding set ollama:nomic-embed-text # Use Ollama model
+    ///   manx embedding set custom:http://localhost:8080 --model BAAI/bge-small-en-v1.5
     ///   manx embedding download all-MiniLM-L6-v2  # Download ONNX model
     ///   manx embedding test "sample query"        # Test current embedding setup
     Embedding {
@@ -397,6 +800,16 @@ pub enum Commands {
         command: EmbeddingCommands,
     },
 
+    /// Maintain the local RAG index
+    ///
+    /// EXAMPLES:
+    ///   manx rag export backup.jsonl     # Dump every chunk with its embedding
+    ///   manx rag import backup.jsonl     # Load a dump into the local index
+    Rag {
+        #[command(subcommand)]
+        command: RagCommands,
+    },
+
     /// Interactive setup wizard for configuring manx
     ///
     /// WHAT IT DOES:
@@ -410,9 +823,71 @@ pub enum Commands {
     ///   • Reconfiguring after updates
     ///   • Testing different providers
     ///
-    /// EXAMPLE:
+    /// Without a TTY, pass --non-interactive with a JSON answers file and/or the answer flags.
+    /// Unanswered settings keep their current values; an invalid answer exits non-zero naming
+    /// the step that failed.
+    ///
+    /// EXAMPLES:
     ///   manx init     # Start interactive setup wizard
-    Init,
+    ///   manx setup --non-interactive --from answers.json
+    ///   manx setup --non-interactive --embedding-provider hash --llm-provider groq --llm-api-key gsk_...
+    #[command(visible_alias = "setup")]
+    Init {
+        /// Configure without prompts, from --from and the answer flags
+        #[arg(long)]
+        non_interactive: bool,
+        /// JSON file of setup answers, using the answer flag names with underscores
+        #[arg(long, value_name = "FILE", requires = "non_interactive")]
+        from: Option<PathBuf>,
+        /// Context7 API key (empty removes the saved key)
+        #[arg(long, value_name = "KEY", requires = "non_interactive")]
+        context7_api_key: Option<String>,
+        /// Search engine: hash, onnx:<model>, openai:<model>, ... as for `embedding set`
+        #[arg(long, value_name = "PROVIDER", requires = "non_interactive")]
+        embedding_provider: Option<String>,
+        /// API key for a hosted embedding provider
+        #[arg(long, value_name = "KEY", requires = "non_interactive")]
+        embedding_api_key: Option<String>,
+        /// AI provider: openai, anthropic, groq or zai
+        #[arg(long, value_name = "PROVIDER", requires = "non_interactive")]
+        llm_provider: Option<String>,
+        /// API key for the AI provider
+        #[arg(long, value_name = "KEY", requires = "non_interactive")]
+        llm_api_key: Option<String>,
+        /// Model for the AI provider (default: the wizard's recommended model)
+        #[arg(long, value_name = "MODEL", requires = "non_interactive")]
+        llm_model: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum HistoryCommands {
+    /// Delete all recorded queries
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Write settings as TOML, leaving out API keys and proxy credentials unless --include-secrets
+    Export {
+        /// Write to FILE instead of stdout
+        #[arg(short = 'o', long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Include API keys and a proxy URL with credentials in the export
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Check that configured LLM and embedding providers accept their keys
+    Test,
+    /// Merge settings from an exported TOML file after showing what will change
+    Import {
+        /// TOML file written by 'manx config export'
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// Apply without asking for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -423,12 +898,31 @@ pub enum CacheCommands {
     Stats,
     /// Show all currently cached libraries and their sizes
     List,
+    /// Delete expired entries and evict least recently used ones over the size limit
+    Prune,
+    /// Pretty-print the entry stored under a key, with its size and modification time
+    Get {
+        /// Cache category
+        #[arg(value_name = "CATEGORY", value_parser = clap::builder::PossibleValuesParser::new(crate::cache::CATEGORIES))]
+        category: String,
+        /// Entry key, as shown by 'manx cache list'
+        #[arg(value_name = "KEY")]
+        key: String,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum SourceCommands {
     /// List all indexed document sources
     List,
+    /// List the tags in the index, with how many chunks carry each, for 'search --rag --tag'
+    Tags,
+    /// Show chunk counts and content sizes of the index, by source type or per source
+    Stats {
+        /// List every source with its chunk count, size, last modified time and type
+        #[arg(long)]
+        per_source: bool,
+    },
     /// Add a document source to the index
     Add {
         /// Path to document or directory
@@ -437,14 +931,86 @@ pub enum SourceCommands {
         #[arg(long)]
         id: Option<String>,
     },
+    /// Remove one indexed source (file path, directory prefix or URL)
+    Remove {
+        /// Source path, directory prefix or URL as shown by 'manx sources list'
+        #[arg(value_name = "SOURCE")]
+        source: String,
+    },
     /// Clear all indexed documents
     Clear,
+    /// Manage custom web search domains and their priority tiers
+    Domains {
+        #[command(subcommand)]
+        command: DomainCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DomainCommands {
+    /// List custom domains and their tiers
+    List,
+    /// Add a domain, or change the tier of one already added
+    Add {
+        /// Domain or URL, e.g. docs.internal.example
+        #[arg(value_name = "DOMAIN")]
+        domain: String,
+        /// Priority tier for results from this domain
+        #[arg(long, value_enum, default_value = "official-docs")]
+        tier: SourceTier,
+    },
+    /// Remove a custom domain
+    Remove {
+        /// Domain as shown by 'manx sources domains list'
+        #[arg(value_name = "DOMAIN")]
+        domain: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RagCommands {
+    /// Export the local index (content, metadata and embeddings) to a JSONL file
+    Export {
+        /// Destination file (.jsonl)
+        #[arg(value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Import chunks from a JSONL export into the local index
+    Import {
+        /// JSONL file produced by 'manx rag export'
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Re-embed every indexed chunk with the current embedding provider
+    Reindex,
+    /// Check the index for corrupt records, wrong embedding sizes and deleted source files
+    Verify {
+        /// Rewrite the index without corrupt or mismatched chunks
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Show how an indexed source was split into chunks
+    Chunks {
+        /// Source path, directory prefix or URL as shown by 'manx sources list'
+        #[arg(value_name = "SOURCE")]
+        source: String,
+        /// Print each chunk's entire content instead of a preview
+        #[arg(long)]
+        full: bool,
+        /// Print the chunks as JSON
+        #[arg(long, conflicts_with = "full")]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum EmbeddingCommands {
     /// Show current embedding provider status and configuration
-    Status,
+    Status {
+        /// Print the configuration and check results as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Set embedding provider (hash, onnx:model, ollama:model, openai:model, huggingface:model, custom:url)
     Set {
         /// Provider specification
@@ -456,6 +1022,9 @@ pub enum EmbeddingCommands {
         /// Custom endpoint URL (for custom provider)
         #[arg(long, value_name = "URL")]
         endpoint: Option<String>,
+        /// Model requested from the custom endpoint, for servers hosting several
+        #[arg(long, value_name = "NAME")]
+        model: Option<String>,
         /// Embedding dimension (default: 384)
         #[arg(long, value_name = "DIMENSION")]
         dimension: Option<usize>,
@@ -484,11 +1053,44 @@ pub enum EmbeddingCommands {
         #[arg(long)]
         verbose: bool,
     },
+    /// Print the cosine similarity of two texts and each vector's norm
+    ///
+    /// With --matrix, compares any number of texts pairwise.
+    ///
+    /// EXAMPLES:
+    ///   manx embedding compare "jwt auth" "json web tokens"
+    ///   manx embedding compare --matrix "react hooks" "vue composables" "tax law"
+    Compare {
+        /// Texts to embed
+        #[arg(value_name = "TEXT", num_args = 2.., required = true)]
+        texts: Vec<String>,
+        /// Print a pairwise similarity matrix of all texts
+        #[arg(long)]
+        matrix: bool,
+        /// Provider to compare with instead of the configured one (hash, onnx:model, openai:model, ...)
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+    },
+    /// Measure cold-start latency, warm latency percentiles and throughput
+    ///
+    /// Embeds a fixed corpus with the configured provider (or --provider) so providers can
+    /// be compared before re-indexing.
+    Benchmark {
+        /// Provider to benchmark instead of the configured one (hash, onnx:model, openai:model, ...)
+        #[arg(long, value_name = "PROVIDER")]
+        provider: Option<String>,
+        /// Passes over the 20-text corpus
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        rounds: usize,
+    },
     /// Remove downloaded local models
     Remove {
         /// Model name to remove
         #[arg(value_name = "MODEL_NAME")]
         model: String,
+        /// Remove without asking for confirmation (required when stdin is not a terminal)
+        #[arg(short = 'y', long)]
+        yes: bool,
     },
 }
 
@@ -498,6 +1100,53 @@ impl Cli {
     }
 }
 
+/// Parse an on/off flag value
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err("use 'on' or 'off'".to_string()),
+    }
+}
+
+/// Parse a score between 0 and 1 inclusive
+fn parse_unit_interval(value: &str) -> Result<f32, String> {
+    let score: f32 = value.parse().map_err(|_| "expected a number".to_string())?;
+    if (0.0..=1.0).contains(&score) {
+        Ok(score)
+    } else {
+        Err("must be between 0 and 1".to_string())
+    }
+}
+
+/// A `--since` cutoff: a duration back from now (`30d`, `6w`, `3mo`, `1y`) or a `YYYY-MM-DD` date
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| "expected a duration like 30d, 6w, 3mo, 1y or a date like 2024-01-31")?;
+    let days_per_unit = match unit {
+        "d" => 1,
+        "w" => 7,
+        "mo" => 30,
+        "y" => 365,
+        _ => return Err(format!("unknown unit '{}'; use d, w, mo or y", unit)),
+    };
+    amount
+        .checked_mul(days_per_unit)
+        .and_then(TimeDelta::try_days)
+        .and_then(|ago| Utc::now().checked_sub_signed(ago))
+        .ok_or_else(|| format!("'{}' reaches back further than supported", value))
+}
+
 fn get_version_info() -> &'static str {
     concat!(
         "\n",
@@ -521,3 +1170,25 @@ fn get_version_info() -> &'static str {
         " • blazing-fast docs finder\n"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let cutoff = parse_since("2w").unwrap();
+        let days = (Utc::now() - cutoff).num_days();
+        assert!((13..=14).contains(&days));
+        assert_eq!(
+            parse_since("2024-01-31").unwrap().to_rfc3339(),
+            "2024-01-31T00:00:00+00:00"
+        );
+        assert!(parse_since("3h").unwrap_err().contains("unknown unit"));
+        // Too far back for a date, or for the day count itself
+        assert!(parse_since("1000000y")
+            .unwrap_err()
+            .contains("further than supported"));
+        assert!(parse_since("9223372036854775807y").is_err());
+    }
+}
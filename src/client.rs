@@ -1,3 +1,4 @@
+use crate::cache::CacheManager;
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -11,6 +12,10 @@ const REQUEST_TIMEOUT: u64 = 30;
 pub struct Context7Client {
     client: Client,
     api_key: Option<String>,
+    /// Remembers resolved library IDs under the "libraries" category
+    library_cache: Option<CacheManager>,
+    /// Resolve library IDs again instead of reading them from `library_cache`
+    refresh_libraries: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -42,6 +47,7 @@ struct JsonRpcError {
     data: Option<serde_json::Value>,
 }
 
+#[allow(dead_code)] // Public API struct - may be used by external code
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LibraryInfo {
     pub id: String,
@@ -50,12 +56,14 @@ pub struct LibraryInfo {
     pub description: Option<String>,
 }
 
+#[allow(dead_code)] // Public API struct - may be used by external code
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Documentation {
     pub library: LibraryInfo,
     pub sections: Vec<DocSection>,
 }
 
+#[allow(dead_code)] // Public API struct - may be used by external code
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DocSection {
     pub id: String,
@@ -65,6 +73,7 @@ pub struct DocSection {
     pub url: Option<String>,
 }
 
+#[allow(dead_code)] // Public API struct - may be used by external code
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CodeExample {
     pub language: String,
@@ -84,13 +93,21 @@ pub struct SearchResult {
 
 impl Context7Client {
     pub fn new(api_key: Option<String>) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(REQUEST_TIMEOUT))
-            .user_agent(format!("manx/{}", env!("CARGO_PKG_VERSION")))
-            .build()
-            .context("Failed to create HTTP client")?;
+        let client = crate::http_client::client(Duration::from_secs(REQUEST_TIMEOUT))?;
+
+        Ok(Self {
+            client,
+            api_key,
+            library_cache: None,
+            refresh_libraries: false,
+        })
+    }
 
-        Ok(Self { client, api_key })
+    /// Remember resolved library IDs in `cache`; with `refresh` they are looked up again
+    pub fn with_library_cache(mut self, cache: CacheManager, refresh: bool) -> Self {
+        self.library_cache = Some(cache);
+        self.refresh_libraries = refresh;
+        self
     }
 
     fn get_base_url(&self) -> &str {
@@ -98,36 +115,44 @@ impl Context7Client {
         CONTEXT7_MCP_URL
     }
 
-    pub async fn resolve_library(&self, library_name: &str) -> Result<(String, String)> {
-        // Always use MCP tools/call format for now
-        let request = JsonRpcRequest {
-            jsonrpc: "2.0".to_string(),
-            method: "tools/call".to_string(),
-            params: json!({
-                "name": "resolve-library-id",
-                "arguments": {
-                    "libraryName": library_name
+    /// Resolve a library name to its Context7 ID and title
+    ///
+    /// When `version` is given the ID is pinned (`/org/project/<version>`). If the
+    /// requested version is not published for the library, the error lists the
+    /// versions that are.
+    pub async fn resolve_library(
+        &self,
+        library_name: &str,
+        version: Option<&str>,
+    ) -> Result<(String, String)> {
+        let cache_key = library_cache_key(library_name, version);
+        if let Some(cache) = &self.library_cache {
+            if !self.refresh_libraries {
+                if let Ok(Some(resolved)) =
+                    cache.get::<(String, String)>("libraries", &cache_key).await
+                {
+                    log::debug!("Using cached library ID {} for {}", resolved.0, cache_key);
+                    return Ok(resolved);
                 }
-            }),
-            id: 1,
-        };
-
-        let response = self.send_request(request).await?;
-
-        if let Some(error) = response.error {
-            anyhow::bail!("API error: {} (code: {})", error.message, error.code);
+            }
         }
 
-        let result = response.result.context("No result in response")?;
+        let resolved = self.lookup_library(library_name, version).await?;
+        if let Some(cache) = &self.library_cache {
+            cache.set("libraries", &cache_key, &resolved).await.ok();
+        }
+        Ok(resolved)
+    }
 
-        // Extract the library ID from the response text
-        let content = result
-            .get("content")
-            .and_then(|c| c.as_array())
-            .and_then(|arr| arr.first())
-            .and_then(|item| item.get("text"))
-            .and_then(|text| text.as_str())
-            .context("Failed to extract content from response")?;
+    /// Ask Context7's `resolve-library-id` tool for the best match
+    async fn lookup_library(
+        &self,
+        library_name: &str,
+        version: Option<&str>,
+    ) -> Result<(String, String)> {
+        let arguments = resolve_arguments(library_name)?;
+        let library_name = library_name.trim();
+        let content = self.call_tool(1, "resolve-library-id", arguments).await?;
 
         // Parse the response following Context7's selection criteria:
         // 1. First result is pre-ranked by Context7 (prioritize it)
@@ -135,6 +160,9 @@ impl Context7Client {
         // 3. Secondary: higher snippet count, trust score 7-10
         let lines: Vec<&str> = content.lines().collect();
         let mut libraries = Vec::new();
+        // Published versions per library ID (from "Versions:" lines)
+        let mut versions: std::collections::HashMap<String, Vec<String>> =
+            std::collections::HashMap::new();
 
         // Parse all libraries from response
         let mut current_lib: Option<(String, String, f64, u32)> = None; // (id, title, trust_score, snippets)
@@ -171,6 +199,16 @@ impl Context7Client {
                     }
                 }
             }
+            // Look for published versions (apply to the most recently parsed library)
+            else if let Some(list) = line.split("Versions:").nth(1) {
+                if let Some((id, _, _, _)) = libraries.last() {
+                    versions.entry(id.clone()).or_default().extend(
+                        list.split(',')
+                            .map(|v| v.trim().to_string())
+                            .filter(|v| !v.is_empty()),
+                    );
+                }
+            }
             // Look for trust score
             else if line.contains("Trust Score:") {
                 if let Some((_, _, trust, _)) = current_lib.as_mut() {
@@ -247,34 +285,60 @@ impl Context7Client {
                 snippet_count,
                 index + 1
             );
-            Ok((library_id.clone(), title.clone()))
+            let Some(requested) = version else {
+                return Ok((library_id.clone(), title.clone()));
+            };
+
+            let available = versions.get(library_id).cloned().unwrap_or_default();
+            match match_version(requested, &available) {
+                Some(matched) => {
+                    log::debug!("Pinned {} to version {}", library_id, matched);
+                    Ok((format!("{}/{}", library_id, matched), title.clone()))
+                }
+                None if available.is_empty() => anyhow::bail!(
+                    "Version '{}' of '{}' is not available: Context7 only serves the latest docs for {}",
+                    requested,
+                    library_name,
+                    library_id
+                ),
+                None => anyhow::bail!(
+                    "Version '{}' of '{}' is not available. Available versions: {}",
+                    requested,
+                    library_name,
+                    available.join(", ")
+                ),
+            }
         } else {
-            // Extract available library names for suggestions
-            let available_libraries: Vec<String> = lines
-                .iter()
-                .filter_map(|line| {
-                    if line.contains("- Title: ") {
-                        Some(line.replace("- Title: ", "").trim().to_string())
-                    } else {
-                        None
+            let mut suggestions =
+                crate::search::suggest_libraries(library_name, &library_titles(&content));
+
+            // Nothing close in this response (often a typo): ask again with a looser name
+            if suggestions.is_empty() {
+                if let Some(loose_name) = loose_library_name(library_name) {
+                    log::debug!("Looking for libraries close to '{}'", loose_name);
+                    match self
+                        .call_tool(1, "resolve-library-id", resolve_arguments(&loose_name)?)
+                        .await
+                    {
+                        Ok(content) => {
+                            suggestions = crate::search::suggest_libraries(
+                                library_name,
+                                &library_titles(&content),
+                            );
+                        }
+                        Err(e) => log::debug!("Suggestion lookup failed: {}", e),
                     }
-                })
-                .collect();
-
-            if !available_libraries.is_empty() {
-                let suggestions =
-                    crate::search::fuzzy_find_libraries(library_name, &available_libraries);
-                if !suggestions.is_empty() {
-                    let suggestion_text: Vec<String> =
-                        suggestions.iter().map(|(name, _)| name.clone()).collect();
-                    anyhow::bail!(
-                        "Library '{}' not found. Did you mean one of: {}?",
-                        library_name,
-                        suggestion_text.join(", ")
-                    );
                 }
             }
 
+            if !suggestions.is_empty() {
+                anyhow::bail!(
+                    "Library '{}' not found on Context7. Did you mean: {}?",
+                    library_name,
+                    suggestions.join(", ")
+                );
+            }
+
             anyhow::bail!(
                 "No library ID found in response for '{}': {}",
                 library_name,
@@ -284,43 +348,59 @@ impl Context7Client {
     }
 
     pub async fn get_documentation(&self, library_id: &str, topic: Option<&str>) -> Result<String> {
-        let mut params = json!({
-            "context7CompatibleLibraryID": library_id
-        });
-
-        if let Some(topic_str) = topic {
-            params["topic"] = json!(topic_str);
-        }
+        let params = documentation_arguments(library_id, topic)?;
+        self.call_tool(2, "get-library-docs", params).await
+    }
 
-        // Always use MCP tools/call format for now
+    /// Call a Context7 MCP tool and return the text of its first content item
+    ///
+    /// Tool failures, including MCP argument validation errors, are reported with the tool name.
+    async fn call_tool(&self, id: u64, tool: &str, arguments: serde_json::Value) -> Result<String> {
         let request = JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
             method: "tools/call".to_string(),
             params: json!({
-                "name": "get-library-docs",
-                "arguments": params
+                "name": tool,
+                "arguments": arguments
             }),
-            id: 2,
+            id,
         };
+        log::debug!(
+            "Context7 {} request: {}",
+            tool,
+            serde_json::to_string(&request)?
+        );
 
         let response = self.send_request(request).await?;
 
         if let Some(error) = response.error {
-            anyhow::bail!("API error: {} (code: {})", error.message, error.code);
+            anyhow::bail!(
+                "Context7 {} error: {} (code: {})",
+                tool,
+                error.message,
+                error.code
+            );
         }
 
         let result = response.result.context("No result in response")?;
-
-        // Extract the documentation text from the response
-        let content = result
+        let text = result
             .get("content")
             .and_then(|c| c.as_array())
             .and_then(|arr| arr.first())
             .and_then(|item| item.get("text"))
-            .and_then(|text| text.as_str())
-            .context("Failed to extract documentation from response")?;
+            .and_then(|text| text.as_str());
+
+        // MCP reports tool failures in the result rather than as JSON-RPC errors
+        if result.get("isError").and_then(|e| e.as_bool()) == Some(true) {
+            anyhow::bail!(
+                "Context7 {} failed: {}",
+                tool,
+                text.unwrap_or("no details returned")
+            );
+        }
 
-        Ok(content.to_string())
+        text.map(str::to_string)
+            .with_context(|| format!("No text content in Context7 {} response", tool))
     }
 
     async fn send_request(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
@@ -378,3 +458,161 @@ impl Context7Client {
         }
     }
 }
+
+/// Library titles listed in a `resolve-library-id` response
+fn library_titles(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("- Title: "))
+        .map(|title| title.trim().to_string())
+        .collect()
+}
+
+/// A shorter form of a library name that still finds its neighbours (`reactt` -> `reac`)
+///
+/// `None` when the name is already too short to loosen.
+fn loose_library_name(library_name: &str) -> Option<String> {
+    let chars: Vec<char> = library_name.trim().chars().collect();
+    let keep = (chars.len() * 2).div_ceil(3).max(3);
+    (keep < chars.len()).then(|| chars[..keep].iter().collect())
+}
+
+/// Cache key for a resolved library; names are matched case-insensitively
+fn library_cache_key(library_name: &str, version: Option<&str>) -> String {
+    let name = library_name.trim().to_lowercase();
+    match version {
+        Some(version) => format!("{}@{}", name, version),
+        None => name,
+    }
+}
+
+/// Arguments for `resolve-library-id`
+///
+/// The tool requires `query`; it gets the library name, never the snippet search query.
+pub(crate) fn resolve_arguments(library_name: &str) -> Result<serde_json::Value> {
+    let library_name = library_name.trim();
+    if library_name.is_empty() {
+        anyhow::bail!("Library name was empty after parsing; pass a library such as `react`");
+    }
+    Ok(json!({
+        "libraryName": library_name,
+        "query": library_name
+    }))
+}
+
+/// Arguments for `get-library-docs`; a blank topic is left out rather than sent empty
+fn documentation_arguments(library_id: &str, topic: Option<&str>) -> Result<serde_json::Value> {
+    let library_id = library_id.trim();
+    if library_id.is_empty() {
+        anyhow::bail!("Library ID was empty after parsing; resolve the library name first");
+    }
+
+    let mut params = json!({
+        "context7CompatibleLibraryID": library_id
+    });
+    match topic.map(str::trim) {
+        Some("") => log::debug!("Ignoring empty documentation topic"),
+        Some(topic) => params["topic"] = json!(topic),
+        None => {}
+    }
+    Ok(params)
+}
+
+/// Find the published version matching a requested one (`18`, `v18`, `18.2.0`)
+///
+/// Exact matches win (ignoring a leading `v`); otherwise the first version whose
+/// components start with the requested ones is used.
+pub fn match_version(requested: &str, available: &[String]) -> Option<String> {
+    let normalize = |v: &str| v.trim().trim_start_matches('v').to_string();
+    let wanted = normalize(requested);
+    if wanted.is_empty() {
+        return None;
+    }
+
+    available
+        .iter()
+        .find(|v| normalize(v) == wanted)
+        .or_else(|| {
+            available.iter().find(|v| {
+                let candidate = normalize(v);
+                candidate
+                    .strip_prefix(&wanted)
+                    .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('-'))
+            })
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_version() {
+        let available = vec![
+            "v14.3.0-canary.87".to_string(),
+            "v13.5.11".to_string(),
+            "v13.5".to_string(),
+        ];
+        assert_eq!(match_version("13.5", &available), Some("v13.5".to_string()));
+        assert_eq!(
+            match_version("v13.5.11", &available),
+            Some("v13.5.11".to_string())
+        );
+        assert_eq!(
+            match_version("14", &available),
+            Some("v14.3.0-canary.87".to_string())
+        );
+        assert_eq!(match_version("1", &available), None);
+        assert_eq!(match_version("12", &available), None);
+    }
+
+    #[test]
+    fn test_library_suggestion_helpers() {
+        let content = "Available libraries:\n\n- Title: React\n- Context7-compatible library ID: /facebook/react\n----------\n- Title: Preact\n";
+        assert_eq!(library_titles(content), ["React", "Preact"]);
+
+        assert_eq!(loose_library_name("reactt").as_deref(), Some("reac"));
+        assert_eq!(loose_library_name("vue"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_library_uses_cache() {
+        let dir = std::env::temp_dir().join(format!("manx_libraries_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        let resolved = ("/facebook/react".to_string(), "React".to_string());
+        cache
+            .set("libraries", &library_cache_key("React", None), &resolved)
+            .await
+            .unwrap();
+
+        // Served from the cache without contacting Context7
+        let client = Context7Client::new(None)
+            .unwrap()
+            .with_library_cache(cache, false);
+        assert_eq!(
+            client.resolve_library("react", None).await.unwrap(),
+            resolved
+        );
+        assert_eq!(library_cache_key("react", Some("18")), "react@18");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_empty_arguments_rejected_before_request() {
+        let client = Context7Client::new(None).unwrap();
+        let error = client.resolve_library("  ", None).await.unwrap_err();
+        assert!(error.to_string().contains("Library name was empty"));
+
+        assert!(documentation_arguments("", Some("hooks")).is_err());
+        assert_eq!(
+            documentation_arguments("/facebook/react", Some(" ")).unwrap(),
+            json!({ "context7CompatibleLibraryID": "/facebook/react" })
+        );
+        assert_eq!(
+            documentation_arguments("/facebook/react", Some("hooks")).unwrap()["topic"],
+            "hooks"
+        );
+    }
+}
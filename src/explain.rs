@@ -0,0 +1,137 @@
+//! Score breakdowns behind `manx explain`
+//!
+//! Web and local searches keep the components of each result's score as it is ranked: the
+//! raw similarity, any BM25 keyword blend, the official-source boost and every later
+//! adjustment. The most recent search's breakdowns are cached so `manx explain 3` can show
+//! why result 3 ranked where it did.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheManager;
+
+/// Cache category holding the most recent search's breakdowns
+pub const CATEGORY: &str = "explain";
+const LAST_SEARCH_KEY: &str = "last_search";
+
+/// How one result's score was put together
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// How `similarity` was measured, such as `embedding` or `word match`
+    pub method: String,
+    /// Relevance before any boost or blending
+    pub similarity: f32,
+    /// BM25 keyword score (0 to 1) blended into hybrid local search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword_score: Option<f32>,
+    /// Share of the blended score taken from `keyword_score`
+    #[serde(default)]
+    pub keyword_weight: f32,
+    /// Official-source tier multiplier of a web result, including any framework-domain boost
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_boost: Option<f32>,
+    /// Every change after `similarity`, in order, with the score it left
+    #[serde(default)]
+    pub steps: Vec<ScoreStep>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreStep {
+    pub label: String,
+    pub score: f32,
+}
+
+impl ScoreBreakdown {
+    pub fn new(method: &str, similarity: f32) -> Self {
+        Self {
+            method: method.to_string(),
+            similarity,
+            ..Self::default()
+        }
+    }
+
+    /// Record a change to the score
+    pub fn step(&mut self, label: impl Into<String>, score: f32) {
+        self.steps.push(ScoreStep {
+            label: label.into(),
+            score,
+        });
+    }
+}
+
+/// A ranked result as recorded for `manx explain`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExplainedResult {
+    /// Position in the full ranking, as numbered in search output
+    pub rank: usize,
+    /// URL of a web result, chunk ID of a local one
+    pub id: String,
+    pub title: String,
+    pub score: f32,
+    pub breakdown: Option<ScoreBreakdown>,
+}
+
+/// The most recent search's results and how each was scored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExplainedSearch {
+    /// `web` or `local`
+    pub kind: String,
+    pub query: String,
+    pub results: Vec<ExplainedResult>,
+}
+
+impl ExplainedSearch {
+    pub async fn save(&self, cache: &CacheManager) -> Result<()> {
+        cache.set(CATEGORY, LAST_SEARCH_KEY, self).await
+    }
+
+    pub async fn load(cache: &CacheManager) -> Result<Option<Self>> {
+        cache.get(CATEGORY, LAST_SEARCH_KEY).await
+    }
+
+    /// The result numbered `id` in the search output, or with that URL or chunk ID
+    pub fn find(&self, id: &str) -> Option<&ExplainedResult> {
+        let id = id.trim().trim_start_matches('#');
+        match id.parse::<usize>() {
+            Ok(rank) => self.results.iter().find(|result| result.rank == rank),
+            Err(_) => self.results.iter().find(|result| result.id == id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_explained_search_round_trip() {
+        let dir = std::env::temp_dir().join(format!("manx_explain_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        assert_eq!(ExplainedSearch::load(&cache).await.unwrap(), None);
+
+        let mut breakdown = ScoreBreakdown::new("embedding", 0.6);
+        breakdown.source_boost = Some(10.0);
+        breakdown.step("phrase match x1.2", 0.72);
+        breakdown.step("official docs boost x10.0", 7.2);
+        let search = ExplainedSearch {
+            kind: "web".to_string(),
+            query: "react hooks".to_string(),
+            results: vec![ExplainedResult {
+                rank: 3,
+                id: "https://react.dev/reference/react".to_string(),
+                title: "Hooks".to_string(),
+                score: 7.2,
+                breakdown: Some(breakdown),
+            }],
+        };
+        search.save(&cache).await.unwrap();
+
+        let loaded = ExplainedSearch::load(&cache).await.unwrap().unwrap();
+        assert_eq!(loaded, search);
+        assert_eq!(loaded.find("3").unwrap().title, "Hooks");
+        assert_eq!(loaded.find("#3").unwrap().title, "Hooks");
+        assert!(loaded.find("https://react.dev/reference/react").is_some());
+        assert!(loaded.find("1").is_none());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
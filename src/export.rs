@@ -1,7 +1,65 @@
 use crate::client::{Documentation, SearchResult};
 use anyhow::{Context, Result};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
+use std::sync::OnceLock;
+
+/// Heading for the current command when exports append instead of overwrite
+static APPEND_HEADING: OnceLock<String> = OnceLock::new();
+
+/// Make every export of this run append to its file under `heading` (`--output-append`)
+pub fn enable_append(heading: String) {
+    let _ = APPEND_HEADING.set(heading);
+}
+
+/// Write export content to `path`, honoring append mode
+///
+/// In append mode Markdown exports get a timestamped `## <heading>` section added to
+/// the end of the file, and JSON exports are appended as one JSON record per line
+/// (`{"query", "timestamp", "results"}`). The file is created when absent.
+pub fn write_output(path: &Path, content: &str) -> Result<()> {
+    let Some(heading) = APPEND_HEADING.get() else {
+        return fs::write(path, content).with_context(|| format!("Failed to write to {:?}", path));
+    };
+
+    let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
+    let entry = match ExportFormat::from_path(path) {
+        ExportFormat::Json => {
+            let results = serde_json::from_str::<serde_json::Value>(content)
+                .unwrap_or_else(|_| serde_json::Value::String(content.to_string()));
+            format!(
+                "{}\n",
+                serde_json::json!({
+                    "query": heading,
+                    "timestamp": timestamp.to_string(),
+                    "results": results,
+                })
+            )
+        }
+        ExportFormat::Markdown => {
+            let separator = match fs::metadata(path) {
+                Ok(meta) if meta.len() > 0 => "\n",
+                _ => "",
+            };
+            format!(
+                "{}## {}\n\n*{}*\n\n{}\n",
+                separator,
+                heading,
+                timestamp,
+                content.trim_end()
+            )
+        }
+    };
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {:?} for appending", path))?;
+    file.write_all(entry.as_bytes())
+        .with_context(|| format!("Failed to write to {:?}", path))
+}
 
 pub enum ExportFormat {
     Markdown,
@@ -28,11 +86,12 @@ impl Exporter {
             ExportFormat::Markdown => Self::search_results_to_markdown(results),
         };
 
-        fs::write(path, content).with_context(|| format!("Failed to write to {:?}", path))?;
+        write_output(path, &content)?;
 
         Ok(())
     }
 
+    #[allow(dead_code)] // Public API method - may be used by external code
     pub fn export_documentation(doc: &Documentation, path: &Path) -> Result<()> {
         let format = ExportFormat::from_path(path);
 
@@ -41,7 +100,7 @@ impl Exporter {
             ExportFormat::Markdown => Self::documentation_to_markdown(doc),
         };
 
-        fs::write(path, content).with_context(|| format!("Failed to write to {:?}", path))?;
+        write_output(path, &content)?;
 
         Ok(())
     }
@@ -74,6 +133,7 @@ impl Exporter {
         md
     }
 
+    #[allow(dead_code)]
     fn documentation_to_markdown(doc: &Documentation) -> String {
         let mut md = String::new();
 
@@ -151,7 +211,7 @@ impl Exporter {
             Self::batch_snippets_to_markdown(results, library, cache_manager).await?
         };
 
-        fs::write(path, content).with_context(|| format!("Failed to write to {:?}", path))?;
+        write_output(path, &content)?;
 
         Ok(())
     }
@@ -0,0 +1,172 @@
+//! Record of recent `search`, `snippet` and `doc` invocations for `manx history`
+//!
+//! Entries are appended to `history.jsonl` in the config directory, one JSON object per
+//! line. This is separate from the result cache: it remembers what was asked, not what
+//! came back.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Subcommand that ran: `search`, `snippet` or `doc`
+    pub command: String,
+    pub library: Option<String>,
+    pub query: String,
+    pub timestamp: DateTime<Utc>,
+    pub result_count: usize,
+    /// Command-line arguments after the program name, used to re-run the entry
+    pub args: Vec<String>,
+}
+
+pub struct History {
+    path: PathBuf,
+    limit: usize,
+}
+
+impl History {
+    /// History in the config directory, keeping at most `config.history_limit` entries
+    pub fn from_config(config: &crate::config::Config) -> Result<Self> {
+        let dir = ProjectDirs::from("", "", "manx")
+            .context("Failed to determine config directory")?
+            .config_dir()
+            .to_path_buf();
+        Ok(Self::at(dir.join(HISTORY_FILE), config.history_limit))
+    }
+
+    pub fn at(path: PathBuf, limit: usize) -> Self {
+        Self { path, limit }
+    }
+
+    /// Append an entry, dropping the oldest ones once the file exceeds the limit
+    ///
+    /// Does nothing when the limit is 0.
+    pub fn record(&self, entry: &HistoryEntry) -> Result<()> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open history file")?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        drop(file);
+
+        let entries = self.entries()?;
+        if entries.len() > self.limit {
+            self.write_all(&entries[entries.len() - self.limit..])?;
+        }
+        Ok(())
+    }
+
+    /// Recorded entries, oldest first; lines that fail to parse are skipped
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to read history file"),
+        };
+        Ok(data
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Entry `number` as shown by `manx history`, counting from 1
+    pub fn get(&self, number: usize) -> Result<HistoryEntry> {
+        let entries = self.entries()?;
+        number
+            .checked_sub(1)
+            .and_then(|i| entries.get(i).cloned())
+            .with_context(|| {
+                format!(
+                    "No history entry {} ({} recorded). Run 'manx history' to list them.",
+                    number,
+                    entries.len()
+                )
+            })
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).context("Failed to remove history file")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write_all(&self, entries: &[HistoryEntry]) -> Result<()> {
+        let mut data = String::new();
+        for entry in entries {
+            data.push_str(&serde_json::to_string(entry)?);
+            data.push('\n');
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, data).context("Failed to write history file")?;
+        fs::rename(&tmp, &self.path).context("Failed to replace history file")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str) -> HistoryEntry {
+        HistoryEntry {
+            command: "snippet".to_string(),
+            library: Some("react".to_string()),
+            query: query.to_string(),
+            timestamp: Utc::now(),
+            result_count: 3,
+            args: vec![
+                "snippet".to_string(),
+                "react".to_string(),
+                query.to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_record_caps_entries_and_numbers_from_one() {
+        let dir = std::env::temp_dir().join(format!("manx_history_{}", uuid::Uuid::new_v4()));
+        let history = History::at(dir.join(HISTORY_FILE), 2);
+
+        for query in ["hooks", "state", "effects"] {
+            history.record(&entry(query)).unwrap();
+        }
+        let queries: Vec<_> = history
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.query)
+            .collect();
+        assert_eq!(queries, vec!["state", "effects"]);
+        assert_eq!(history.get(1).unwrap().query, "state");
+        assert!(history.get(0).is_err());
+        assert!(history.get(3).is_err());
+
+        history.clear().unwrap();
+        assert!(history.entries().unwrap().is_empty());
+        history.clear().unwrap();
+
+        let disabled = History::at(dir.join("disabled.jsonl"), 0);
+        disabled.record(&entry("hooks")).unwrap();
+        assert!(disabled.entries().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,11 +4,15 @@
 //! including RAG (Retrieval-Augmented Generation) capabilities, web search,
 //! caching, and rendering utilities.
 
+pub mod ask;
 pub mod cache;
 pub mod cli;
 pub mod client;
 pub mod config;
+pub mod explain;
 pub mod export;
+pub mod history;
+pub mod http_client;
 pub mod rag;
 pub mod render;
 pub mod search;
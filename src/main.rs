@@ -1,28 +1,38 @@
+mod ask;
 mod cache;
 mod cli;
 mod client;
 mod config;
+mod explain;
 mod export;
+mod history;
+mod http_client;
 mod rag;
 mod render;
 mod search;
+mod serve;
 mod update;
 mod web_search;
 mod wizard;
 
 use anyhow::{Context, Result};
 use colored::{control, Colorize};
+use std::io::{self, Write};
 use std::process;
 
-use crate::cache::CacheManager;
-use crate::cli::{CacheCommands, Cli, Commands, EmbeddingCommands, SourceCommands};
+use crate::cache::{CacheManager, ItemRef};
+use crate::cli::{
+    CacheCommands, Cli, ColorChoice, Commands, ConfigCommands, DomainCommands, EmbeddingCommands,
+    HistoryCommands, RagCommands, SourceCommands,
+};
 use crate::client::Context7Client;
 use crate::config::Config;
 use crate::export::Exporter;
-use crate::render::Renderer;
+use crate::render::{OutputFormat, Renderer};
 use crate::search::SearchEngine;
 use crate::update::SelfUpdater;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 
@@ -30,6 +40,27 @@ use tokio::sync::OnceCell;
 static SHARED_EMBEDDING_MODEL: OnceCell<Arc<crate::rag::embeddings::EmbeddingModel>> =
     OnceCell::const_new();
 
+// Configured embedding model shared by RAG and web search when both run in one command
+static RAG_EMBEDDING_MODEL: OnceCell<Option<Arc<crate::rag::embeddings::EmbeddingModel>>> =
+    OnceCell::const_new();
+
+/// Load the configured embedding model once per process so subsystems can share it
+async fn shared_rag_embedding_model(
+    config: &Config,
+) -> Option<Arc<crate::rag::embeddings::EmbeddingModel>> {
+    RAG_EMBEDDING_MODEL
+        .get_or_init(|| async {
+            crate::rag::search_engine::SmartSearchEngine::initialize_embedding_model(&config.rag)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to initialize shared embedding model: {}", e);
+                    None
+                })
+        })
+        .await
+        .clone()
+}
+
 /// Create SearchEngine with shared embedding model for better performance
 /// Uses model pooling to avoid redundant initialization
 async fn create_search_engine_with_pooling(
@@ -95,17 +126,76 @@ async fn create_search_engine_with_pooling(
     }
 }
 
+/// Exit code for failures
+const EXIT_ERROR: i32 = 1;
+/// Exit code for a search that worked but found nothing, including offline cache misses
+const EXIT_NO_RESULTS: i32 = 2;
+
+/// Error returned from `run()` when a lookup found no results
+///
+/// The message is empty when the command already told the user, so nothing more is printed.
+#[derive(Debug)]
+struct NoResults(String);
+
+impl std::fmt::Display for NoResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NoResults {}
+
+/// Turn an empty result count into [`NoResults`]
+fn ensure_results(result_count: usize) -> Result<()> {
+    if result_count == 0 {
+        return Err(NoResults(String::new()).into());
+    }
+    Ok(())
+}
+
+/// Set from `--json-errors`/`MANX_JSON` once arguments are parsed
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Print a failure to stderr as `{"error": ..., "context": [...]}` for `--json-errors`
+///
+/// `context` lists the underlying causes, outermost first.
+fn print_json_error(e: &anyhow::Error) {
+    eprintln!("{}", error_json(e));
+}
+
+/// `{"error": ..., "context": [...]}` for an error, shared by `--json-errors` and `manx serve`
+fn error_json(e: &anyhow::Error) -> serde_json::Value {
+    let context: Vec<String> = e.chain().skip(1).map(|cause| cause.to_string()).collect();
+    serde_json::json!({ "error": e.to_string(), "context": context })
+}
+
 #[tokio::main]
 async fn main() {
     if let Err(e) = run().await {
-        eprintln!("Error: {:#}", e);
-        process::exit(1);
+        let json_errors = JSON_ERRORS.load(Ordering::Relaxed);
+        if let Some(NoResults(message)) = e.downcast_ref::<NoResults>() {
+            if message.is_empty() {
+                // The command already said so
+            } else if json_errors {
+                print_json_error(&e);
+            } else {
+                eprintln!("{}", message);
+            }
+            process::exit(EXIT_NO_RESULTS);
+        }
+        if json_errors {
+            print_json_error(&e);
+        } else {
+            eprintln!("Error: {:#}", e);
+        }
+        process::exit(EXIT_ERROR);
     }
 }
 
 async fn run() -> Result<()> {
     // Parse CLI arguments
     let args = Cli::parse_args();
+    JSON_ERRORS.store(args.json_errors, Ordering::Relaxed);
 
     // Initialize logging if debug mode
     if args.debug {
@@ -116,11 +206,44 @@ async fn run() -> Result<()> {
     let mut config = Config::load().unwrap_or_default();
 
     // Merge CLI arguments with config
-    config.merge_with_cli(args.api_key, args.cache_dir, args.offline);
+    config.merge_with_cli(args.api_key, args.cache_dir, args.offline, args.timeout);
 
-    // Handle NO_COLOR environment variable
-    if std::env::var("NO_COLOR").is_ok() || !config.color_output {
-        control::set_override(false);
+    // --color/--no-color win over NO_COLOR and the config setting for this run
+    let color = if args.no_color {
+        Some(ColorChoice::Never)
+    } else {
+        args.color
+    };
+    match color {
+        Some(ColorChoice::Always) => control::set_override(true),
+        Some(ColorChoice::Never) => control::set_override(false),
+        Some(ColorChoice::Auto) | None => {
+            if std::env::var("NO_COLOR").is_ok() || !config.color_output {
+                control::set_override(false);
+            }
+        }
+    }
+
+    // Hash embedding notice is informational only; honor --quiet and the config opt-out
+    if args.quiet || config.rag.hide_hash_notice {
+        rag::embeddings::suppress_hash_notice();
+    }
+
+    if args.no_cache {
+        rag::llm::disable_response_cache();
+    }
+    rag::usage::set_show_usage(args.show_usage || args.debug);
+    if let Err(e) = http_client::set_proxy(config.proxy.as_deref()) {
+        eprintln!(
+            "Warning: {:#}; ignoring the proxy setting (fix it with `manx config --proxy`)",
+            e
+        );
+    }
+    if let Err(e) = http_client::set_user_agent(config.user_agent.as_deref()) {
+        eprintln!(
+            "Warning: {:#}; using the default (fix it with `manx config --user-agent`)",
+            e
+        );
     }
 
     // Create renderer
@@ -128,11 +251,7 @@ async fn run() -> Result<()> {
 
     // Handle global flags first
     if args.clear_cache {
-        let cache_manager = if let Some(dir) = &config.cache_dir {
-            CacheManager::with_custom_dir(dir.clone())?
-        } else {
-            CacheManager::new()?
-        };
+        let cache_manager = CacheManager::from_config(&config)?;
 
         let pb = renderer.show_progress("Clearing cache...");
         cache_manager.clear().await?;
@@ -158,34 +277,59 @@ async fn run() -> Result<()> {
     // Handle commands
     match args.command {
         Some(Commands::Config {
+            command,
             show,
             api_key,
             cache_dir,
             auto_cache,
             cache_ttl,
             max_cache_size,
+            history_limit,
+            proxy,
+            user_agent,
             openai_api,
             anthropic_api,
             groq_api,
             openrouter_api,
             huggingface_api,
+            azure_api,
+            azure_resource,
+            azure_deployment,
+            azure_api_version,
             custom_endpoint,
+            ollama_endpoint,
             llm_provider,
             llm_model,
             llm_api,
             rag,
+            search_provider,
+            search_api_key,
+            official_boost,
             add_official_domain,
             embedding_provider,
             embedding_api_key,
+            embedding_model,
             embedding_model_path,
             embedding_dimension,
+            embedding_rpm,
+            embedding_tpm,
+            smart_search_prefer_semantic,
+            smart_search_query_enhancement,
+            smart_search_result_verification,
+            smart_search_min_confidence,
+            smart_search_query_variations,
+            smart_search_multi_stage,
+            smart_search_adaptive_chunking,
+            smart_search_keyword_weight,
         }) => {
-            if show {
+            if let Some(command) = command {
+                handle_config_subcommand(command, &renderer).await?;
+            } else if show {
                 println!("{}", config.display());
 
                 // Also show web search configuration if debug is enabled
                 if args.debug {
-                    let web_config = crate::web_search::WebSearchConfig::default();
+                    let web_config = web_search_config(&config);
                     let search_system = match crate::web_search::DocumentationSearchSystem::new(
                         web_config,
                         None,
@@ -220,7 +364,7 @@ async fn run() -> Result<()> {
                 }
 
                 if let Some(dir) = cache_dir {
-                    config.cache_dir = Some(dir);
+                    config.cache_dir = Some(config::expand_path(&dir));
                     renderer.print_success("Cache directory updated");
                     updated = true;
                 }
@@ -255,6 +399,43 @@ async fn run() -> Result<()> {
                     updated = true;
                 }
 
+                if let Some(limit) = history_limit {
+                    config.history_limit = limit;
+                    renderer.print_success(&format!("History limit set to {} entries", limit));
+                    updated = true;
+                }
+
+                if let Some(url) = proxy {
+                    if url.is_empty() {
+                        config.proxy = None;
+                        renderer.print_success("Proxy cleared; using HTTP_PROXY/HTTPS_PROXY");
+                    } else {
+                        http_client::parse_proxy(&url)?;
+                        renderer.print_success(&format!(
+                            "Proxy set to {}",
+                            http_client::redact_proxy(&url)
+                        ));
+                        config.proxy = Some(url);
+                    }
+                    updated = true;
+                }
+
+                if let Some(user_agent) = user_agent {
+                    let user_agent = user_agent.trim();
+                    if user_agent.is_empty() {
+                        config.user_agent = None;
+                        renderer.print_success(&format!(
+                            "User agent reset to {}",
+                            http_client::USER_AGENT
+                        ));
+                    } else {
+                        http_client::parse_user_agent(user_agent)?;
+                        renderer.print_success(&format!("User agent set to {}", user_agent));
+                        config.user_agent = Some(user_agent.to_string());
+                    }
+                    updated = true;
+                }
+
                 if let Some(llm_key) = llm_api {
                     if llm_key.is_empty() {
                         config.set_llm_api_key("".to_string())?;
@@ -297,12 +478,42 @@ async fn run() -> Result<()> {
                     updated = true;
                 }
 
+                if let Some(key) = azure_api {
+                    config.set_azure_api_key(key)?;
+                    renderer.print_success("Azure OpenAI API key updated");
+                    updated = true;
+                }
+
+                if azure_resource.is_some()
+                    || azure_deployment.is_some()
+                    || azure_api_version.is_some()
+                {
+                    config.set_azure_deployment(
+                        azure_resource,
+                        azure_deployment,
+                        azure_api_version,
+                    )?;
+                    renderer.print_success("Azure OpenAI deployment updated");
+                    updated = true;
+                }
+
                 if let Some(endpoint) = custom_endpoint {
                     config.set_custom_endpoint(endpoint)?;
                     renderer.print_success("Custom endpoint updated");
                     updated = true;
                 }
 
+                if let Some(endpoint) = ollama_endpoint {
+                    if endpoint.is_empty() {
+                        config.set_ollama_endpoint(endpoint)?;
+                        renderer.print_success("Ollama endpoint cleared");
+                    } else {
+                        renderer.print_success(&format!("Ollama endpoint set to {}", endpoint));
+                        config.set_ollama_endpoint(endpoint)?;
+                    }
+                    updated = true;
+                }
+
                 if let Some(provider) = llm_provider {
                     match config.set_llm_provider(provider.clone()) {
                         Ok(_) => {
@@ -342,20 +553,61 @@ async fn run() -> Result<()> {
                     }
                 }
 
-                if let Some(domain) = add_official_domain {
-                    // Demonstrate adding custom domain to official sources
-                    let mut official_sources =
-                        crate::web_search::official_sources::OfficialSourceManager::new();
-                    official_sources.add_official_domain(
-                        domain.clone(),
-                        crate::web_search::official_sources::SourceTier::OfficialDocs,
-                    );
+                if let Some(provider) = search_provider {
+                    match provider.parse::<web_search::search_engine::SearchProviderConfig>() {
+                        Ok(mut provider) => {
+                            // Keep a Brave key set earlier when switching back to Brave
+                            if let (
+                                web_search::search_engine::SearchProviderConfig::Brave { api_key },
+                                web_search::search_engine::SearchProviderConfig::Brave {
+                                    api_key: old_key,
+                                },
+                            ) = (&mut provider, &config.search_provider)
+                            {
+                                *api_key = old_key.clone();
+                            }
+                            config.search_provider = provider;
+                            renderer.print_success(&format!(
+                                "Web search provider set to {}",
+                                config.search_provider
+                            ));
+                            updated = true;
+                        }
+                        Err(e) => renderer.print_error(&e.to_string()),
+                    }
+                }
 
-                    renderer.print_success(&format!(
-                        "Custom official domain '{}' added to web search priorities",
-                        domain
-                    ));
-                    renderer.print_success("Domain added to current session");
+                if let Some(key) = search_api_key {
+                    match &mut config.search_provider {
+                        web_search::search_engine::SearchProviderConfig::Brave { api_key } => {
+                            *api_key = (!key.is_empty()).then_some(key);
+                            renderer.print_success("Web search API key updated");
+                            updated = true;
+                        }
+                        _ => renderer.print_error(
+                            "Only Brave Search takes an API key: manx config --search-provider brave",
+                        ),
+                    }
+                }
+
+                if let Some(boost) = official_boost {
+                    if boost < 0.0 || !boost.is_finite() {
+                        renderer.print_error("Official boost must be 0 or greater");
+                    } else {
+                        config.source_weights.official_boost = boost;
+                        renderer.print_success(&format!("Official source boost set to {}", boost));
+                        updated = true;
+                    }
+                }
+
+                if let Some(domain) = add_official_domain {
+                    handle_domain_command(
+                        DomainCommands::Add {
+                            domain,
+                            tier: crate::web_search::official_sources::SourceTier::OfficialDocs,
+                        },
+                        &renderer,
+                    )?;
                     updated = true;
                 }
 
@@ -397,6 +649,12 @@ async fn run() -> Result<()> {
                     updated = true;
                 }
 
+                if let Some(model) = embedding_model {
+                    config.set_embedding_model(model)?;
+                    renderer.print_success("Embedding endpoint model updated");
+                    updated = true;
+                }
+
                 if let Some(path) = embedding_model_path {
                     config.set_embedding_model_path(path.clone())?;
                     renderer
@@ -419,6 +677,63 @@ async fn run() -> Result<()> {
                     }
                 }
 
+                if embedding_rpm.is_some() || embedding_tpm.is_some() {
+                    config.set_embedding_rate_limit(embedding_rpm, embedding_tpm)?;
+                    let describe = |limit: Option<u32>| match limit {
+                        Some(limit) if limit > 0 => limit.to_string(),
+                        _ => "no limit".to_string(),
+                    };
+                    renderer.print_success(&format!(
+                        "Embedding rate limit: {} requests/min, {} tokens/min",
+                        describe(config.rag.embedding.requests_per_minute),
+                        describe(config.rag.embedding.tokens_per_minute)
+                    ));
+                    updated = true;
+                }
+
+                let smart_search = &mut config.rag.smart_search;
+                let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+                let mut smart_search_changes = Vec::new();
+                if let Some(enabled) = smart_search_prefer_semantic {
+                    smart_search.prefer_semantic = enabled;
+                    smart_search_changes.push(format!("semantic preference {}", on_off(enabled)));
+                }
+                if let Some(enabled) = smart_search_query_enhancement {
+                    smart_search.enable_query_enhancement = enabled;
+                    smart_search_changes.push(format!("query enhancement {}", on_off(enabled)));
+                }
+                if let Some(enabled) = smart_search_result_verification {
+                    smart_search.enable_result_verification = enabled;
+                    smart_search_changes.push(format!("result verification {}", on_off(enabled)));
+                }
+                if let Some(score) = smart_search_min_confidence {
+                    smart_search.min_confidence_score = score;
+                    smart_search_changes.push(format!("minimum confidence {}", score));
+                }
+                if let Some(count) = smart_search_query_variations {
+                    smart_search.max_query_variations = count as usize;
+                    smart_search_changes.push(format!("query variations {}", count));
+                }
+                if let Some(enabled) = smart_search_multi_stage {
+                    smart_search.enable_multi_stage = enabled;
+                    smart_search_changes.push(format!("multi-stage search {}", on_off(enabled)));
+                }
+                if let Some(enabled) = smart_search_adaptive_chunking {
+                    smart_search.adaptive_chunking = enabled;
+                    smart_search_changes.push(format!("adaptive chunking {}", on_off(enabled)));
+                }
+                if let Some(weight) = smart_search_keyword_weight {
+                    smart_search.keyword_weight = weight;
+                    smart_search_changes.push(format!("keyword weight {}", weight));
+                }
+                if !smart_search_changes.is_empty() {
+                    renderer.print_success(&format!(
+                        "Smart search: {}",
+                        smart_search_changes.join(", ")
+                    ));
+                    updated = true;
+                }
+
                 if updated {
                     config.save()?;
                 } else {
@@ -428,11 +743,7 @@ async fn run() -> Result<()> {
         }
 
         Some(Commands::Cache { command }) => {
-            let cache_manager = if let Some(dir) = &config.cache_dir {
-                CacheManager::with_custom_dir(dir.clone())?
-            } else {
-                CacheManager::new()?
-            };
+            let cache_manager = CacheManager::from_config(&config)?;
 
             match command {
                 CacheCommands::Clear => {
@@ -446,7 +757,60 @@ async fn run() -> Result<()> {
                     println!("Cache Statistics:");
                     println!("  Total size: {:.2} MB", stats.total_size_mb);
                     println!("  Files: {}", stats.file_count);
-                    println!("  Categories: {}", stats.categories.join(", "));
+                    println!(
+                        "  Expired: {} (past the {}h TTL, evicted on next access)",
+                        stats.expired_count, config.cache_ttl_hours
+                    );
+
+                    if !stats.categories.is_empty() {
+                        let when = |time: Option<chrono::DateTime<chrono::Utc>>| {
+                            time.map(|t| {
+                                t.with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M")
+                                    .to_string()
+                            })
+                            .unwrap_or_else(|| "-".to_string())
+                        };
+                        println!(
+                            "\n  {:<12} {:>6} {:>10}  {:<16}  {:<16}  {:>7}",
+                            "Category", "Files", "Size", "Oldest", "Newest", "Expired"
+                        );
+                        for category in &stats.categories {
+                            println!(
+                                "  {:<12} {:>6} {:>7.2} MB  {:<16}  {:<16}  {:>7}",
+                                category.name,
+                                category.file_count,
+                                category.size_mb,
+                                when(category.oldest),
+                                when(category.newest),
+                                category.expired_count
+                            );
+                        }
+                    }
+                }
+                CacheCommands::Get { category, key } => {
+                    let inspection = cache_manager.inspect(&category, &key)?;
+                    println!("{} {}", "Entry:".bold(), inspection.path.display());
+                    println!("{} {} bytes", "Size:".bold(), inspection.size_bytes);
+                    if let Some(modified) = inspection.modified {
+                        println!(
+                            "{} {}",
+                            "Modified:".bold(),
+                            modified
+                                .with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M:%S")
+                        );
+                    }
+                    println!("{}", serde_json::to_string_pretty(&inspection.entry)?);
+                }
+                CacheCommands::Prune => {
+                    let report = cache_manager.prune().await?;
+                    renderer.print_success(&format!(
+                        "Pruned {} expired and {} least recently used entries ({:.2} MB freed)",
+                        report.expired,
+                        report.evicted,
+                        report.freed_bytes as f64 / 1_048_576.0
+                    ));
                 }
                 CacheCommands::List => {
                     let items = cache_manager.list_cached().await?;
@@ -470,10 +834,27 @@ async fn run() -> Result<()> {
             query,
             output,
             limit,
+            section,
+            refresh,
             no_llm,
+            model,
             rag,
+            min_score,
+            hybrid,
+            keyword_weight,
+            format,
+            raw,
         }) => {
-            if rag {
+            override_llm_model(&mut config, model)?;
+            config.set_min_score(min_score);
+            config.set_keyword_weight(hybrid, keyword_weight);
+            let renderer = formatted_renderer(renderer, format).with_raw(raw);
+            let output = append_output(
+                output,
+                &args.output_append,
+                format!("{} {}", library, query).trim().to_string(),
+            )?;
+            let result_count = if rag {
                 handle_rag_doc_command(
                     &library,
                     &query,
@@ -483,7 +864,7 @@ async fn run() -> Result<()> {
                     limit.as_ref(),
                     &no_llm,
                 )
-                .await?;
+                .await?
             } else {
                 handle_doc_command(
                     &library,
@@ -493,10 +874,14 @@ async fn run() -> Result<()> {
                     &renderer,
                     false,
                     limit,
+                    section,
                     no_llm,
+                    refresh,
                 )
-                .await?;
-            }
+                .await?
+            };
+            record_history(&config, "doc", Some(&library), &query, result_count);
+            ensure_results(result_count)?;
         }
 
         Some(Commands::Snippet {
@@ -509,10 +894,35 @@ async fn run() -> Result<()> {
             json,
             limit,
             no_llm,
+            model,
             rag,
+            min_score,
+            hybrid,
+            keyword_weight,
+            languages,
+            tags,
+            rerank,
+            context7_version,
+            auto_version,
+            refresh,
+            format,
+            raw,
+            interactive,
         }) => {
+            ensure_interactive_terminal(interactive)?;
+            override_llm_model(&mut config, model)?;
+            config.set_min_score(min_score);
+            config.set_keyword_weight(hybrid, keyword_weight);
+            config.set_rerank(rerank);
+            let renderer = formatted_renderer(renderer, format).with_raw(raw);
             let query_str = query.unwrap_or_default();
-            if rag {
+            let library = versioned_library_spec(library, context7_version, auto_version);
+            let output = append_output(
+                output,
+                &args.output_append,
+                format!("{} {}", library, query_str).trim().to_string(),
+            )?;
+            let result_count = if rag {
                 handle_rag_snippet_command(
                     &library,
                     &query_str,
@@ -524,8 +934,10 @@ async fn run() -> Result<()> {
                     &json,
                     limit.as_ref(),
                     &no_llm,
+                    &crate::rag::SearchFilters { languages, tags },
+                    interactive,
                 )
-                .await?;
+                .await?
             } else {
                 handle_search_command(
                     &library,
@@ -539,71 +951,178 @@ async fn run() -> Result<()> {
                     json,
                     limit,
                     no_llm,
+                    interactive,
+                    refresh,
                 )
-                .await?;
-            }
+                .await?
+            };
+            record_history(&config, "snippet", Some(&library), &query_str, result_count);
+            ensure_results(result_count)?;
         }
 
         Some(Commands::Search {
             query,
+            stdin,
             no_llm,
+            model,
             output,
             limit,
+            offset,
             rag,
+            no_web_fallback,
+            languages,
+            tags,
+            rerank,
+            min_score,
+            hybrid,
+            keyword_weight,
+            prefer_community,
+            since,
+            format,
+            raw,
+            interactive,
         }) => {
-            if rag {
+            ensure_interactive_terminal(interactive)?;
+            override_llm_model(&mut config, model)?;
+            config.set_min_score(min_score);
+            config.set_keyword_weight(hybrid, keyword_weight);
+            config.set_rerank(rerank);
+            let renderer = formatted_renderer(renderer, format).with_raw(raw);
+            let query = match query {
+                Some(query) if query != "-" && !stdin => query,
+                _ => read_stdin_query()?,
+            };
+            let output = append_output(output, &args.output_append, query.clone())?;
+            let result_count = if rag {
                 handle_rag_search_command(
                     &query,
                     &no_llm,
                     output.as_ref(),
                     limit.as_ref(),
+                    offset,
+                    !no_web_fallback,
+                    &crate::rag::SearchFilters { languages, tags },
                     &config,
                     &renderer,
+                    interactive,
                 )
-                .await?;
+                .await?
             } else {
                 handle_web_search_command(
                     &query,
                     no_llm,
                     output.as_ref(),
                     limit,
+                    offset,
+                    prefer_community,
+                    since,
                     &config,
                     &renderer,
+                    interactive,
                 )
-                .await?;
+                .await?
+            };
+            if !query.trim().is_empty() {
+                record_history(&config, "search", None, &query, result_count);
+            }
+            ensure_results(result_count)?;
+        }
+
+        Some(Commands::History { run, command }) => {
+            let history = crate::history::History::from_config(&config)?;
+            match (command, run) {
+                (Some(HistoryCommands::Clear), _) => {
+                    history.clear()?;
+                    renderer.print_success("History cleared");
+                }
+                (None, Some(number)) => {
+                    let entry = history.get(number)?;
+                    let exe = std::env::current_exe().context("Failed to locate manx binary")?;
+                    let status = process::Command::new(exe)
+                        .args(&entry.args)
+                        .status()
+                        .context("Failed to re-run history entry")?;
+                    if !status.success() {
+                        process::exit(status.code().unwrap_or(1));
+                    }
+                }
+                (None, None) => print_history(&history.entries()?),
             }
         }
 
+        Some(Commands::Ask {
+            question,
+            no_llm,
+            output,
+            limit,
+        }) => {
+            let output = append_output(output, &args.output_append, question.clone())?;
+            handle_ask_command(
+                &question,
+                no_llm,
+                output.as_ref(),
+                limit,
+                &config,
+                &renderer,
+            )
+            .await?;
+        }
+
         Some(Commands::Get { id, output }) => {
+            let output = append_output(output, &args.output_append, id.clone())?;
             handle_get_command(&id, output.as_ref(), &config, &renderer, false).await?;
         }
 
         Some(Commands::Open { id, output }) => {
+            let output = append_output(output, &args.output_append, id.clone())?;
             handle_open_command(&id, output.as_ref(), &config, &renderer).await?;
         }
 
-        Some(Commands::Update { check, force }) => {
+        Some(Commands::Explain { id }) => {
+            handle_explain_command(&id, &config).await?;
+        }
+
+        Some(Commands::Update {
+            check,
+            force,
+            channel,
+            rollback,
+        }) => {
             let updater = SelfUpdater::new(renderer)?;
+            let channel = match channel {
+                Some(channel) => {
+                    // Saved from the file rather than this run's config so overrides stay out
+                    Config::load()?.set_update_channel(channel)?;
+                    channel
+                }
+                None => config.update_channel,
+            };
 
-            if check {
-                let update_info = updater.check_for_updates().await?;
+            if rollback {
+                updater.rollback()?;
+            } else if check {
+                let update_info = updater.check_for_updates(channel).await?;
 
                 if update_info.update_available {
-                    println!("Update available!");
+                    println!("Update available on the {} channel!", update_info.channel);
                     println!("Current version: v{}", update_info.current_version);
                     println!("Latest version:  v{}", update_info.latest_version);
                     println!("\nRun 'manx update' to install the latest version.");
                 } else {
                     println!(
-                        "You're already on the latest version (v{})",
-                        update_info.current_version
+                        "You're already on the latest {} version (v{})",
+                        update_info.channel, update_info.current_version
                     );
                 }
             } else {
-                updater.perform_update(force).await?;
+                updater.perform_update(force, channel).await?;
             }
         }
 
+        Some(Commands::Serve { port, host }) => {
+            serve::run(config, &host, port).await?;
+        }
+
         Some(Commands::Index {
             path,
             id,
@@ -613,9 +1132,38 @@ async fn run() -> Result<()> {
             live_index,
             embed_concurrency,
             crawl_max_pages,
+            resume,
+            json_progress,
+            sitemap,
+            sitemap_filter,
+            force,
+            chunk_size,
+            chunk_overlap,
+            notebook_outputs,
+            dry_run,
+            include,
+            exclude,
+            ignore_robots,
+            retry_failed,
         }) => {
+            rag::progress::set_json_progress(json_progress);
+            if let Some(size) = chunk_size {
+                config.rag.chunk_size = size;
+            }
+            if let Some(overlap) = chunk_overlap {
+                config.rag.chunk_overlap = overlap;
+            }
+            if notebook_outputs {
+                config.rag.include_notebook_outputs = true;
+            }
+            if ignore_robots {
+                config.rag.ignore_robots = true;
+            }
+            config.rag.chunk_params()?;
+            let filter = rag::indexer::PathFilter::new(&include, &exclude)?;
             handle_index_command(
-                &path,
+                path.as_deref(),
+                retry_failed,
                 id,
                 crawl,
                 crawl_depth,
@@ -623,6 +1171,12 @@ async fn run() -> Result<()> {
                 live_index,
                 embed_concurrency,
                 crawl_max_pages,
+                resume,
+                sitemap,
+                &sitemap_filter,
+                force,
+                dry_run,
+                &filter,
                 &config,
                 &renderer,
             )
@@ -637,10 +1191,39 @@ async fn run() -> Result<()> {
             handle_embedding_command(command, &mut config, &renderer).await?;
         }
 
-        Some(Commands::Init) => {
+        Some(Commands::Rag { command }) => {
+            handle_rag_command(command, &mut config, &renderer).await?;
+        }
+
+        Some(Commands::Init {
+            non_interactive,
+            from,
+            context7_api_key,
+            embedding_provider,
+            embedding_api_key,
+            llm_provider,
+            llm_api_key,
+            llm_model,
+        }) => {
             // Run the setup wizard
             let mut wizard = wizard::SetupWizard::new()?;
-            wizard.run().await?;
+            if non_interactive {
+                let answers = match &from {
+                    Some(path) => wizard::SetupAnswers::load(path)?,
+                    None => wizard::SetupAnswers::default(),
+                };
+                let answers = answers.merge(wizard::SetupAnswers {
+                    context7_api_key,
+                    embedding_provider,
+                    embedding_api_key,
+                    llm_provider,
+                    llm_api_key,
+                    llm_model,
+                });
+                wizard.run_non_interactive(&answers).await?;
+            } else {
+                wizard.run().await?;
+            }
         }
 
         None => {
@@ -653,83 +1236,135 @@ async fn run() -> Result<()> {
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn handle_search_command(
-    library: &str,
-    query: &str,
-    output: Option<&std::path::PathBuf>,
-    config: &Config,
-    renderer: &Renderer,
-    offline: bool,
-    save_numbers: Option<&String>,
-    save_all: bool,
-    json_format: bool,
-    limit: Option<usize>,
-    no_llm: bool,
-) -> Result<()> {
-    let cache_manager = if let Some(dir) = &config.cache_dir {
-        CacheManager::with_custom_dir(dir.clone())?
-    } else {
-        CacheManager::new()?
-    };
-
-    let cache_key = format!("{}_{}", library, query);
+/// Resolve the export target, switching exports to append mode for `--output-append`
+fn append_output(
+    output: Option<PathBuf>,
+    output_append: &Option<PathBuf>,
+    heading: String,
+) -> Result<Option<PathBuf>> {
+    match (output, output_append) {
+        (Some(_), Some(_)) => anyhow::bail!("Use either --output or --output-append, not both"),
+        (None, Some(path)) => {
+            export::enable_append(heading);
+            Ok(Some(config::expand_path(path)))
+        }
+        (output, None) => Ok(output.map(|path| config::expand_path(&path))),
+    }
+}
 
-    // Try cache first if offline mode
-    if offline || config.offline_mode {
-        if let Some(results) = cache_manager
-            .get::<Vec<crate::client::SearchResult>>("search", &cache_key)
-            .await?
-        {
-            renderer.render_search_results(&results)?;
-            if let Some(path) = output {
-                Exporter::export_search_results(&results, path)?;
-                renderer.print_success(&format!("Results exported to {:?}", path));
+/// Apply `--context7-version` / `--auto-version` to a library name
+///
+/// An explicit `library@version` always wins over the flags; a leading `@` is a package scope.
+fn versioned_library_spec(library: String, version: Option<String>, auto_version: bool) -> String {
+    if library.get(1..).is_some_and(|rest| rest.contains('@')) {
+        return library;
+    }
+    if let Some(version) = version {
+        return format!("{}@{}", library, version);
+    }
+    if auto_version {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        match search::detect_installed_version(&library, &cwd) {
+            Some((version, manifest)) => {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "📌 Using {} {} (from {})",
+                        library,
+                        version,
+                        manifest.display()
+                    )
+                    .dimmed()
+                );
+                return format!("{}@{}", library, version);
             }
-            return Ok(());
-        } else if offline || config.offline_mode {
-            anyhow::bail!("No cached results available in offline mode");
+            None => eprintln!(
+                "{}",
+                format!(
+                    "No version of {} found in local manifests, using latest",
+                    library
+                )
+                .dimmed()
+            ),
         }
     }
+    library
+}
 
-    // Initialize semantically-enhanced search engine for snippets
-    // Try to reuse shared embedding model for better performance
+/// Context7 client that remembers resolved library IDs when auto-caching is on
+///
+/// `refresh` resolves again and overwrites the remembered ID.
+fn context7_client(config: &Config, refresh: bool) -> Result<Context7Client> {
     let client = Context7Client::new(config.api_key.clone())?;
-    let search_engine = create_search_engine_with_pooling(client, renderer, library, query).await?;
+    if !config.auto_cache_enabled {
+        return Ok(client);
+    }
+    Ok(client.with_library_cache(CacheManager::from_config(config)?, refresh))
+}
+
+/// Switch to `--format` output: results only, on stdout, without colors or spinners
+fn formatted_renderer(renderer: Renderer, format: Option<OutputFormat>) -> Renderer {
+    if format.is_some() {
+        control::set_override(false);
+    }
+    renderer.with_format(format)
+}
+
+/// The local RAG system when it is enabled, used to add indexed matches to other searches
+async fn local_rag_system(config: &Config) -> Option<crate::rag::RagSystem> {
+    if !config.rag.enabled {
+        return None;
+    }
+    match crate::rag::RagSystem::new(config.rag.clone()).await {
+        Ok(rag_system) => Some(rag_system),
+        Err(e) => {
+            log::warn!("Failed to initialize RAG system: {}", e);
+            None
+        }
+    }
+}
+
+/// Search Context7 for snippets, add local RAG matches when available, and cache the results
+///
+/// Returns the results with the resolved library title and ID.
+async fn fetch_snippets(
+    search_engine: &SearchEngine,
+    rag_system: Option<&crate::rag::RagSystem>,
+    library: &str,
+    query: &str,
+    config: &Config,
+) -> Result<(Vec<crate::client::SearchResult>, String, String)> {
+    let cache_manager = CacheManager::from_config(config)?;
+    let cache_key = format!("{}_{}", library, query);
 
     let (mut results, library_title, library_id) = search_engine
         .search(library, query, Some(config.default_limit))
         .await?;
 
     // Also search local RAG system if enabled
-    if config.rag.enabled {
-        match crate::rag::RagSystem::new(config.rag.clone()).await {
-            Ok(rag_system) => {
-                match rag_system.search(query, Some(5)).await {
-                    Ok(rag_results) => {
-                        log::info!(
-                            "Found {} additional results from local RAG system",
-                            rag_results.len()
-                        );
-                        // Convert RAG results to SearchResult format and append
-                        for rag_result in rag_results {
-                            let search_result = crate::client::SearchResult {
-                                id: format!("rag-{}", rag_result.id),
-                                library: "Local".to_string(),
-                                title: rag_result
-                                    .title
-                                    .unwrap_or_else(|| "Local Document".to_string()),
-                                excerpt: rag_result.content,
-                                url: None,
-                                relevance_score: rag_result.score,
-                            };
-                            results.push(search_result);
-                        }
-                    }
-                    Err(e) => log::warn!("RAG search failed: {}", e),
+    if let Some(rag_system) = rag_system {
+        match rag_system.search(query, Some(5)).await {
+            Ok(rag_results) => {
+                log::info!(
+                    "Found {} additional results from local RAG system",
+                    rag_results.len()
+                );
+                // Convert RAG results to SearchResult format and append
+                for rag_result in rag_results {
+                    let search_result = crate::client::SearchResult {
+                        id: format!("rag-{}", rag_result.id),
+                        library: "Local".to_string(),
+                        title: rag_result
+                            .title
+                            .unwrap_or_else(|| "Local Document".to_string()),
+                        excerpt: rag_result.content,
+                        url: None,
+                        relevance_score: rag_result.score,
+                    };
+                    results.push(search_result);
                 }
             }
-            Err(e) => log::warn!("Failed to initialize RAG system: {}", e),
+            Err(e) => log::warn!("RAG search failed: {}", e),
         }
     }
 
@@ -738,29 +1373,113 @@ async fn handle_search_command(
         cache_manager.set("search", &cache_key, &results).await.ok();
 
         // Cache individual snippets for get command
+        let mut items = Vec::new();
         for result in &results {
             let snippet_key = format!("{}_{}", result.library, result.id);
             cache_manager
                 .set("snippets", &snippet_key, &result.excerpt)
                 .await
                 .ok();
+            items.push(ItemRef::new(&result.library, &result.id, &snippet_key));
         }
+        cache_manager.record_items("snippets", &items).ok();
     }
 
-    // Apply LLM synthesis if configured and not disabled
-    if config.should_use_llm(no_llm) && !results.is_empty() {
-        println!("Synthesizing answer with AI...");
+    Ok((results, library_title, library_id))
+}
 
-        // Convert search results to RAG format for LLM synthesis
-        let rag_results: Vec<crate::rag::RagSearchResult> = results
-            .iter()
-            .take(5) // Use top 5 results for synthesis
-            .map(|result| {
-                use chrono::Utc;
-                crate::rag::RagSearchResult {
-                    id: result.id.clone(),
-                    content: result.excerpt.clone(),
-                    title: Some(result.title.clone()),
+/// Fetch Context7 documentation for a library, focused on `query` when given, and cache it
+async fn fetch_documentation(
+    search_engine: &SearchEngine,
+    library: &str,
+    query: &str,
+    config: &Config,
+) -> Result<String> {
+    let doc_text = search_engine
+        .get_documentation(library, if query.is_empty() { None } else { Some(query) })
+        .await?;
+
+    // Cache documentation only if auto-caching is enabled
+    if config.auto_cache_enabled {
+        let cache_manager = CacheManager::from_config(config)?;
+        let cache_key = docs_cache_key(library, query);
+        cache_manager.set("docs", &cache_key, &doc_text).await.ok();
+    }
+    Ok(doc_text)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_search_command(
+    library: &str,
+    query: &str,
+    output: Option<&std::path::PathBuf>,
+    config: &Config,
+    renderer: &Renderer,
+    offline: bool,
+    save_numbers: Option<&String>,
+    save_all: bool,
+    json_format: bool,
+    limit: Option<usize>,
+    no_llm: bool,
+    interactive: bool,
+    refresh: bool,
+) -> Result<usize> {
+    let cache_manager = CacheManager::from_config(config)?;
+
+    let cache_key = format!("{}_{}", library, query);
+
+    // Try cache first if offline mode
+    if offline || config.offline_mode {
+        if let Some(results) = cache_manager
+            .get::<Vec<crate::client::SearchResult>>("search", &cache_key)
+            .await?
+        {
+            if let Some(format) = renderer.output_format() {
+                renderer
+                    .print_formatted(format, render::apply_limit(&results, limit.unwrap_or(10)))?;
+            } else {
+                renderer.render_search_results(&results, query)?;
+            }
+            if let Some(path) = output {
+                Exporter::export_search_results(&results, path)?;
+                renderer.print_success(&format!("Results exported to {:?}", path));
+            }
+            if interactive {
+                pick_results(&snippet_picker_items(&results, limit), 0, renderer)?;
+            }
+            return Ok(results.len());
+        } else if offline || config.offline_mode {
+            return Err(
+                NoResults("No cached results available in offline mode".to_string()).into(),
+            );
+        }
+    }
+
+    // Initialize semantically-enhanced search engine for snippets
+    // Try to reuse shared embedding model for better performance
+    let client = context7_client(config, refresh)?;
+    let search_engine = create_search_engine_with_pooling(client, renderer, library, query).await?;
+    let rag_system = local_rag_system(config).await;
+
+    let (results, library_title, library_id) =
+        fetch_snippets(&search_engine, rag_system.as_ref(), library, query, config).await?;
+
+    // Apply LLM synthesis if configured and not disabled (skipped for --format output)
+    let use_llm = config.should_use_llm(no_llm) && renderer.output_format().is_none();
+    let mut answered = false;
+    if use_llm && !results.is_empty() {
+        eprintln!("Synthesizing answer with AI...");
+
+        // Convert search results to RAG format for LLM synthesis
+        let rag_results: Vec<crate::rag::RagSearchResult> = results
+            .iter()
+            .take(5) // Use top 5 results for synthesis
+            .map(|result| {
+                use chrono::Utc;
+                crate::rag::RagSearchResult {
+                    id: result.id.clone(),
+                    content: result.excerpt.clone(),
+                    title: Some(result.title.clone()),
                     score: result.relevance_score,
                     source_path: std::path::PathBuf::from(&result.library),
                     source_type: crate::rag::SourceType::Curated,
@@ -772,73 +1491,18 @@ async fn handle_search_command(
                         modified: Utc::now(),
                         tags: vec![result.library.clone()],
                         language: None,
+                        line_range: None,
                     },
+                    score_breakdown: None,
                 }
             })
             .collect();
 
         // Initialize LLM client and synthesize answer
-        match crate::rag::llm::LlmClient::new(config.llm.clone()) {
+        match llm_client(config) {
             Ok(llm_client) => {
-                match llm_client.synthesize_answer(query, &rag_results).await {
-                    Ok(synthesis) => {
-                        println!("\n{}", "AI Summary".bold().cyan());
-
-                        // Clean, colorized AI response
-                        for line in synthesis.answer.lines() {
-                            if line.trim().is_empty() {
-                                println!();
-                                continue;
-                            }
-
-                            let trimmed = line.trim();
-                            if trimmed.starts_with("**Quick Answer**") {
-                                println!(
-                                    "  {}",
-                                    trimmed.replace(
-                                        "**Quick Answer**",
-                                        &format!("{}", "> Quick Answer".bold().green())
-                                    )
-                                );
-                            } else if trimmed.starts_with("**Key Points**") {
-                                println!(
-                                    "  {}",
-                                    trimmed.replace(
-                                        "**Key Points**",
-                                        &format!("{}", "> Key Points".bold().blue())
-                                    )
-                                );
-                            } else if trimmed.starts_with("**Code Example**") {
-                                println!(
-                                    "  {}",
-                                    trimmed.replace(
-                                        "**Code Example**",
-                                        &format!("{}", "> Code Example".bold().magenta())
-                                    )
-                                );
-                            } else if trimmed.starts_with("- ") {
-                                // Bullet points in cyan
-                                println!("  {}", trimmed.cyan());
-                            } else if trimmed.starts_with("```") {
-                                // Code blocks in yellow background
-                                println!("  {}", trimmed.on_bright_black().yellow());
-                            } else if trimmed.contains("[Source") {
-                                // Lines with source citations in dim white
-                                println!("  {}", trimmed.bright_white());
-                            } else {
-                                // Regular text in white
-                                println!("  {}", trimmed.white());
-                            }
-                        }
-
-                        if !synthesis.citations.is_empty() && synthesis.citations.len() <= 3 {
-                            println!("\n  {}", "Sources used:".dimmed());
-                            for citation in synthesis.citations.iter().take(3) {
-                                println!("  {} {}", "•".dimmed(), citation.source_title.dimmed());
-                            }
-                        }
-                        println!();
-                    }
+                match stream_ai_summary(&llm_client, query, &rag_results, renderer).await {
+                    Ok(()) => answered = true,
                     Err(e) => {
                         log::warn!("LLM synthesis failed: {}", e);
                         renderer.print_error(
@@ -849,22 +1513,27 @@ async fn handle_search_command(
             }
             Err(e) => {
                 log::warn!("Failed to initialize LLM client: {}", e);
-                renderer.print_error("Failed to initialize AI client");
+                renderer.print_error(&format!("Failed to initialize AI client: {}", e));
             }
         }
     }
 
     // Add clear separation before search results
-    if config.should_use_llm(no_llm) && !results.is_empty() {
+    if use_llm && !results.is_empty() && !renderer.raw_mode() {
         println!("\n{}", "Detailed Results".bold().blue());
     }
 
-    // Render results with library information and limit
-    renderer.render_search_results_with_library(
-        &results,
-        Some((&library_title, &library_id)),
-        limit,
-    )?;
+    // Render results with library information and limit (--raw shows only the answer)
+    if let Some(format) = renderer.output_format() {
+        renderer.print_formatted(format, render::apply_limit(&results, limit.unwrap_or(10)))?;
+    } else if !(answered && renderer.raw_mode()) {
+        renderer.render_search_results_with_library(
+            &results,
+            Some((&library_title, &library_id)),
+            limit,
+            query,
+        )?;
+    }
 
     // Export if requested
     if let Some(path) = output {
@@ -939,7 +1608,10 @@ async fn handle_search_command(
         }
     }
 
-    Ok(())
+    if interactive {
+        pick_results(&snippet_picker_items(&results, limit), 0, renderer)?;
+    }
+    Ok(results.len())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -951,49 +1623,45 @@ async fn handle_doc_command(
     renderer: &Renderer,
     offline: bool,
     limit: Option<usize>,
+    section: Option<usize>,
     no_llm: bool,
-) -> Result<()> {
-    let cache_manager = if let Some(dir) = &config.cache_dir {
-        CacheManager::with_custom_dir(dir.clone())?
+    refresh: bool,
+) -> Result<usize> {
+    let offline = offline || config.offline_mode;
+    let doc_text = if offline {
+        cached_documentation(config, library, query, renderer).await?
     } else {
-        CacheManager::new()?
+        // Initialize semantically-enhanced search engine with pooling
+        let client = context7_client(config, refresh)?;
+        let search_engine =
+            create_search_engine_with_pooling(client, renderer, library, "").await?;
+        fetch_documentation(&search_engine, library, query, config).await?
     };
 
-    let cache_key = format!("{}_{}", library, query);
-
-    // Try cache first if offline mode
-    if offline || config.offline_mode {
-        if let Some(doc) = cache_manager
-            .get::<crate::client::Documentation>("docs", &cache_key)
-            .await?
-        {
-            renderer.render_documentation(&doc)?;
-            if let Some(path) = output {
-                Exporter::export_documentation(&doc, path)?;
-                renderer.print_success(&format!("Documentation exported to {:?}", path));
-            }
-            return Ok(());
-        } else if offline || config.offline_mode {
-            anyhow::bail!("No cached documentation available in offline mode");
+    // A single section is shown as-is, without synthesis over the whole document
+    if let Some(number) = section {
+        let section_text = renderer.doc_section(library, &doc_text, number)?;
+        if let Some(format) = renderer.output_format() {
+            let records = renderer.doc_section_records(library, &doc_text);
+            renderer.print_formatted(format, &records[number - 1..number])?;
+        } else {
+            renderer.render_open_section(&format!("{} [{}]", library, number), &section_text)?;
         }
+        if let Some(path) = output {
+            export::write_output(path, &section_text)?;
+            renderer.print_success(&format!("Section exported to {:?}", path));
+        }
+        return Ok(1);
     }
 
-    // Initialize semantically-enhanced search engine with pooling
-    let client = Context7Client::new(config.api_key.clone())?;
-    let search_engine = create_search_engine_with_pooling(client, renderer, library, "").await?;
-
-    let doc_text = search_engine
-        .get_documentation(library, if query.is_empty() { None } else { Some(query) })
-        .await?;
-
-    // Cache documentation only if auto-caching is enabled
-    if config.auto_cache_enabled {
-        cache_manager.set("docs", &cache_key, &doc_text).await.ok();
-    }
-
-    // Apply LLM synthesis if configured and not disabled
-    if config.should_use_llm(no_llm) && !doc_text.trim().is_empty() {
-        println!("Synthesizing documentation with AI...");
+    // Apply LLM synthesis if configured and not disabled (skipped for --format output)
+    let mut answered = false;
+    if config.should_use_llm(no_llm)
+        && !offline
+        && renderer.output_format().is_none()
+        && !doc_text.trim().is_empty()
+    {
+        eprintln!("Synthesizing documentation with AI...");
 
         // Convert documentation to RAG format for LLM synthesis
         let doc_sections: Vec<crate::rag::RagSearchResult> = doc_text
@@ -1018,7 +1686,9 @@ async fn handle_doc_command(
                         modified: Utc::now(),
                         tags: vec![library.to_string(), "documentation".to_string()],
                         language: Some("en".to_string()),
+                        line_range: None,
                     },
+                    score_breakdown: None,
                 }
             })
             .collect();
@@ -1030,63 +1700,10 @@ async fn handle_doc_command(
         };
 
         // Initialize LLM client and synthesize answer
-        match crate::rag::llm::LlmClient::new(config.llm.clone()) {
+        match llm_client(config) {
             Ok(llm_client) => {
-                match llm_client.synthesize_answer(&ai_query, &doc_sections).await {
-                    Ok(synthesis) => {
-                        println!("\n{}", "AI Summary".bold().cyan());
-
-                        // Clean, colorized AI response
-                        for line in synthesis.answer.lines() {
-                            if line.trim().is_empty() {
-                                println!();
-                                continue;
-                            }
-
-                            let trimmed = line.trim();
-                            if trimmed.starts_with("**Quick Answer**") {
-                                println!(
-                                    "  {}",
-                                    trimmed.replace(
-                                        "**Quick Answer**",
-                                        &format!("{}", "> Quick Answer".bold().green())
-                                    )
-                                );
-                            } else if trimmed.starts_with("**Key Points**") {
-                                println!(
-                                    "  {}",
-                                    trimmed.replace(
-                                        "**Key Points**",
-                                        &format!("{}", "> Key Points".bold().blue())
-                                    )
-                                );
-                            } else if trimmed.starts_with("**Code Example**") {
-                                println!(
-                                    "  {}",
-                                    trimmed.replace(
-                                        "**Code Example**",
-                                        &format!("{}", "> Code Example".bold().magenta())
-                                    )
-                                );
-                            } else if trimmed.starts_with("- ") {
-                                println!("  {}", trimmed.cyan());
-                            } else if trimmed.starts_with("```") {
-                                println!("  {}", trimmed.on_bright_black().yellow());
-                            } else if trimmed.contains("[Source") {
-                                println!("  {}", trimmed.bright_white());
-                            } else {
-                                println!("  {}", trimmed.white());
-                            }
-                        }
-
-                        if !synthesis.citations.is_empty() && synthesis.citations.len() <= 3 {
-                            println!("\n  {}", "Sources used:".dimmed());
-                            for citation in synthesis.citations.iter().take(3) {
-                                println!("  {} {}", "•".dimmed(), citation.source_title.dimmed());
-                            }
-                        }
-                        println!();
-                    }
+                match stream_ai_summary(&llm_client, &ai_query, &doc_sections, renderer).await {
+                    Ok(()) => answered = true,
                     Err(e) => {
                         log::warn!("LLM synthesis failed: {}", e);
                         renderer.print_error("AI synthesis failed, showing documentation only");
@@ -1095,25 +1712,79 @@ async fn handle_doc_command(
             }
             Err(e) => {
                 log::warn!("Failed to initialize LLM client: {}", e);
-                renderer.print_error("Failed to initialize AI client");
+                renderer.print_error(&format!("Failed to initialize AI client: {}", e));
             }
         }
 
         // Add clear separation before documentation
-        println!("\n{}", "Full Documentation".bold().blue());
+        if !renderer.raw_mode() {
+            println!("\n{}", "Full Documentation".bold().blue());
+        }
     }
 
     // Render documentation using the new Context7 parser
-    renderer.render_context7_documentation_with_limit(library, &doc_text, limit)?;
+    let sections = renderer.doc_section_records(library, &doc_text);
+    let shown = render::apply_limit(&sections, limit.unwrap_or(10));
+    let sections_shown = shown.len();
+    if let Some(format) = renderer.output_format() {
+        renderer.print_formatted(format, shown)?;
+    } else if !(answered && renderer.raw_mode()) {
+        renderer.render_context7_documentation_with_limit(library, &doc_text, limit)?;
+    }
 
     // Export if requested
     if let Some(path) = output {
         // For now, just write the raw text - we can improve this later
-        std::fs::write(path, &doc_text)?;
+        export::write_output(path, &doc_text)?;
         renderer.print_success(&format!("Documentation exported to {:?}", path));
     }
 
-    Ok(())
+    Ok(sections_shown)
+}
+
+/// Separates library and query in `docs` cache keys; library names never contain it
+const DOCS_KEY_DELIMITER: char = '#';
+
+/// Cache key for documentation fetched for `library`, focused on `query`
+fn docs_cache_key(library: &str, query: &str) -> String {
+    format!("{}{}{}", library, DOCS_KEY_DELIMITER, query)
+}
+
+/// Documentation cached for `library`, for offline `manx doc`
+///
+/// Uses the entry for this exact query if there is one, otherwise the newest entry for the
+/// library with a note that it was fetched for a different query.
+async fn cached_documentation(
+    config: &Config,
+    library: &str,
+    query: &str,
+    renderer: &Renderer,
+) -> Result<String> {
+    let cache_manager = CacheManager::from_config(config)?;
+    // Entries written before the delimiter changed are still found by their exact key
+    for cache_key in [
+        docs_cache_key(library, query),
+        format!("{}_{}", library, query),
+    ] {
+        if let Some(doc_text) = cache_manager.get::<String>("docs", &cache_key).await? {
+            return Ok(doc_text);
+        }
+    }
+
+    let prefix = format!("{}{}", library, DOCS_KEY_DELIMITER);
+    if let Some((key, written)) = cache_manager.newest_entry("docs", &prefix) {
+        if let Some(doc_text) = cache_manager.get::<String>("docs", &key).await? {
+            renderer.print_warning(&format!(
+                "Showing cached docs from {}, query may not match",
+                written
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+            ));
+            return Ok(doc_text);
+        }
+    }
+
+    Err(NoResults("No cached documentation available in offline mode".to_string()).into())
 }
 
 async fn handle_open_command(
@@ -1122,64 +1793,20 @@ async fn handle_open_command(
     config: &Config,
     renderer: &Renderer,
 ) -> Result<()> {
-    let cache_manager = if let Some(dir) = &config.cache_dir {
-        CacheManager::with_custom_dir(dir.clone())?
-    } else {
-        CacheManager::new()?
-    };
+    let cache_manager = CacheManager::from_config(config)?;
 
     let pb = renderer.show_progress(&format!("Looking for section {}...", id));
+    let lookup = lookup_cached_item(&cache_manager, "doc_sections", &[id.to_string()]).await;
+    pb.finish_and_clear();
 
-    let mut found_section: Option<String> = None;
-    let mut library_name = String::new();
-
-    // Get the doc_sections cache directory and scan for the section ID
-    let dummy_path = cache_manager.cache_key("doc_sections", "dummy");
-    let sections_cache_dir = dummy_path
-        .parent()
-        .ok_or_else(|| anyhow::anyhow!("Failed to get doc_sections cache directory"))?;
-
-    if sections_cache_dir.exists() {
-        // Read all cached section files and find the most recent one with the matching ID
-        let mut matching_files = Vec::new();
-
-        if let Ok(entries) = std::fs::read_dir(sections_cache_dir) {
-            for entry in entries.flatten() {
-                let filename = entry.file_name();
-                if let Some(filename_str) = filename.to_str() {
-                    // Section files are named like "libraryname_doc-1.json"
-                    if filename_str.ends_with(&format!("{}.json", id)) {
-                        if let Ok(metadata) = entry.metadata() {
-                            if let Ok(modified) = metadata.modified() {
-                                matching_files.push((filename_str.to_string(), modified));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // Sort by modification time (most recent first)
-        matching_files.sort_by(|a, b| b.1.cmp(&a.1));
-
-        // Try to load the most recent matching section
-        for (filename, _) in matching_files {
-            if let Some(underscore_pos) = filename.rfind('_') {
-                library_name = filename[..underscore_pos].to_string();
-                let section_key = format!("{}_{}", library_name, id);
-
-                if let Ok(Some(content)) = cache_manager
-                    .get::<String>("doc_sections", &section_key)
-                    .await
-                {
-                    found_section = Some(content);
-                    break;
-                }
-            }
+    let (found_section, library_name) = match lookup {
+        ItemLookup::Found(item, content) => (Some(content), item.library),
+        ItemLookup::Ambiguous(items) => {
+            print_ambiguous_item(id, "open", &items, renderer);
+            return Ok(());
         }
-    }
-
-    pb.finish_and_clear();
+        ItemLookup::Missing => (None, String::new()),
+    };
 
     match found_section {
         Some(content) => {
@@ -1188,7 +1815,7 @@ async fn handle_open_command(
 
             // Export if requested
             if let Some(path) = output {
-                std::fs::write(path, &content)?;
+                export::write_output(path, &content)?;
                 renderer.print_success(&format!("Section exported to {:?}", path));
             }
         }
@@ -1205,6 +1832,96 @@ async fn handle_open_command(
     Ok(())
 }
 
+/// Print how result `id` of the last web or local search was scored
+async fn handle_explain_command(id: &str, config: &Config) -> Result<()> {
+    let cache_manager = CacheManager::from_config(config)?;
+    let Some(search) = explain::ExplainedSearch::load(&cache_manager).await? else {
+        return Err(
+            NoResults("No recent search to explain; run 'manx search' first".to_string()).into(),
+        );
+    };
+    let Some(result) = search.find(id) else {
+        anyhow::bail!(
+            "No result '{}' in the last {} search for '{}' ({} results)",
+            id,
+            search.kind,
+            search.query,
+            search.results.len()
+        );
+    };
+
+    println!(
+        "{} {} {}",
+        format!("#{}", result.rank).bold(),
+        result.title.bold(),
+        format!("({} search for '{}')", search.kind, search.query).dimmed()
+    );
+    println!("  {}", result.id.bright_blue());
+    let Some(breakdown) = &result.breakdown else {
+        println!("\n  No breakdown recorded; final score {:.3}", result.score);
+        return Ok(());
+    };
+
+    println!();
+    println!(
+        "  {:<28} {:.3}",
+        format!("Similarity ({})", breakdown.method),
+        breakdown.similarity
+    );
+    if let Some(keyword) = breakdown.keyword_score {
+        println!(
+            "  {:<28} {:.3} x {:.2} weight = {:.3}",
+            "Keyword match (BM25)",
+            keyword,
+            breakdown.keyword_weight,
+            keyword * breakdown.keyword_weight
+        );
+    }
+    if let Some(boost) = breakdown.source_boost {
+        println!("  {:<28} x{:.2}", "Official-source boost", boost);
+    }
+    if !breakdown.steps.is_empty() {
+        println!("\n  {}", "Steps".dimmed());
+        for step in &breakdown.steps {
+            println!("  {:<48} {:.3}", step.label, step.score);
+        }
+    }
+    println!("\n  {:<28} {:.3}", "Final score".bold(), result.score);
+    Ok(())
+}
+
+/// Remember how each result of this search was scored, for `manx explain`
+async fn record_explanations(
+    config: &Config,
+    kind: &str,
+    query: &str,
+    results: impl Iterator<Item = (String, String, f32, Option<explain::ScoreBreakdown>)>,
+) {
+    let search = explain::ExplainedSearch {
+        kind: kind.to_string(),
+        query: query.to_string(),
+        results: results
+            .enumerate()
+            .map(
+                |(i, (id, title, score, breakdown))| explain::ExplainedResult {
+                    rank: i + 1,
+                    id,
+                    title,
+                    score,
+                    breakdown,
+                },
+            )
+            .collect(),
+    };
+    let saved = match CacheManager::from_config(config) {
+        Ok(cache_manager) => search.save(&cache_manager).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        log::debug!("Failed to record score breakdowns: {}", e);
+    }
+}
+
 async fn handle_get_command(
     id: &str,
     output: Option<&std::path::PathBuf>,
@@ -1212,11 +1929,7 @@ async fn handle_get_command(
     renderer: &Renderer,
     _offline: bool,
 ) -> Result<()> {
-    let cache_manager = if let Some(dir) = &config.cache_dir {
-        CacheManager::with_custom_dir(dir.clone())?
-    } else {
-        CacheManager::new()?
-    };
+    let cache_manager = CacheManager::from_config(config)?;
 
     let pb = renderer.show_progress(&format!("Looking for item {}...", id));
 
@@ -1224,126 +1937,42 @@ async fn handle_get_command(
     let mut library_name = String::new();
     let mut content_type = String::new();
 
-    // Handle both old format (doc-N) and new format (library-doc-N) snippet IDs
+    // Snippet IDs are either bare (doc-N) or qualified with their library (library-doc-N)
     if id.starts_with("doc-") || id.contains("-doc-") {
         content_type = "snippet".to_string();
-        let dummy_path = cache_manager.cache_key("snippets", "dummy");
-        if let Some(snippets_cache_dir) = dummy_path.parent() {
-            if snippets_cache_dir.exists() {
-                let mut matching_files = Vec::new();
-
-                // For new format IDs (library-doc-N), extract the actual doc ID
-                let (target_library, doc_id) = if id.contains("-doc-") {
-                    let parts: Vec<&str> = id.splitn(2, "-doc-").collect();
-                    if parts.len() == 2 {
-                        (Some(parts[0]), format!("doc-{}", parts[1]))
-                    } else {
-                        (None, id.to_string())
-                    }
-                } else {
-                    // Old format (doc-N) - no library specified
-                    (None, id.to_string())
-                };
-
-                if let Ok(entries) = std::fs::read_dir(snippets_cache_dir) {
-                    for entry in entries.flatten() {
-                        let filename = entry.file_name();
-                        if let Some(filename_str) = filename.to_str() {
-                            if filename_str.ends_with(&format!("{}.json", &doc_id)) {
-                                // If library is specified, ensure it matches
-                                if let Some(target_lib) = target_library {
-                                    if !filename_str.starts_with(&format!("{}_", target_lib)) {
-                                        continue; // Skip if library doesn't match
-                                    }
-                                }
-
-                                if let Ok(metadata) = entry.metadata() {
-                                    if let Ok(modified) = metadata.modified() {
-                                        matching_files.push((filename_str.to_string(), modified));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Sort by modification time (most recent first) for old format
-                // For new format with specific library, there should be only one match
-                matching_files.sort_by(|a, b| b.1.cmp(&a.1));
-
-                for (filename, _) in matching_files {
-                    if let Some(underscore_pos) = filename.rfind('_') {
-                        library_name = filename[..underscore_pos].to_string();
-                        let snippet_key = format!("{}_{}", library_name, &doc_id);
-
-                        if let Ok(Some(content)) =
-                            cache_manager.get::<String>("snippets", &snippet_key).await
-                        {
-                            found_content = Some(content);
-                            break;
-                        }
-                    }
-                }
+        match lookup_cached_item(&cache_manager, "snippets", &[id.to_string()]).await {
+            ItemLookup::Found(item, content) => {
+                library_name = item.library;
+                found_content = Some(content);
+            }
+            ItemLookup::Ambiguous(items) => {
+                pb.finish_and_clear();
+                print_ambiguous_item(id, "get", &items, renderer);
+                return Ok(());
             }
+            ItemLookup::Missing => {}
         }
     }
 
-    // If not found in snippets, try doc_sections cache (for section-N IDs or doc-N fallback)
+    // If not found in snippets, try doc_sections cache, where sections are stored as doc-N
     if found_content.is_none() {
         content_type = "doc_section".to_string();
-        let dummy_path = cache_manager.cache_key("doc_sections", "dummy");
-        if let Some(sections_cache_dir) = dummy_path.parent() {
-            if sections_cache_dir.exists() {
-                let mut matching_files = Vec::new();
-
-                if let Ok(entries) = std::fs::read_dir(sections_cache_dir) {
-                    for entry in entries.flatten() {
-                        let filename = entry.file_name();
-                        if let Some(filename_str) = filename.to_str() {
-                            // Try both the original ID and with section- prefix
-                            let id_variants = if id.starts_with("doc-") {
-                                vec![id.to_string(), id.replace("doc-", "section-")]
-                            } else if id.starts_with("section-") {
-                                vec![id.to_string()]
-                            } else {
-                                vec![format!("section-{}", id)]
-                            };
-
-                            for variant in id_variants {
-                                if filename_str.ends_with(&format!("{}.json", variant)) {
-                                    if let Ok(metadata) = entry.metadata() {
-                                        if let Ok(modified) = metadata.modified() {
-                                            matching_files.push((
-                                                filename_str.to_string(),
-                                                modified,
-                                                variant,
-                                            ));
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                matching_files.sort_by(|a, b| b.1.cmp(&a.1));
-
-                for (filename, _, variant) in matching_files {
-                    if let Some(underscore_pos) = filename.rfind('_') {
-                        library_name = filename[..underscore_pos].to_string();
-                        let section_key = format!("{}_{}", library_name, variant);
-
-                        if let Ok(Some(content)) = cache_manager
-                            .get::<String>("doc_sections", &section_key)
-                            .await
-                        {
-                            found_content = Some(content);
-                            break;
-                        }
-                    }
-                }
+        let section_ids = match id.strip_prefix("section-") {
+            Some(n) => vec![id.to_string(), format!("doc-{}", n)],
+            None if id.chars().all(|c| c.is_ascii_digit()) => vec![format!("doc-{}", id)],
+            None => vec![id.to_string()],
+        };
+        match lookup_cached_item(&cache_manager, "doc_sections", &section_ids).await {
+            ItemLookup::Found(item, content) => {
+                library_name = item.library;
+                found_content = Some(content);
             }
+            ItemLookup::Ambiguous(items) => {
+                pb.finish_and_clear();
+                print_ambiguous_item(id, "get", &items, renderer);
+                return Ok(());
+            }
+            ItemLookup::Missing => {}
         }
     }
 
@@ -1362,7 +1991,7 @@ async fn handle_get_command(
 
             // Export if requested
             if let Some(path) = output {
-                std::fs::write(path, &content)?;
+                export::write_output(path, &content)?;
                 renderer.print_success(&format!("Item exported to {:?}", path));
             }
         }
@@ -1430,7 +2059,7 @@ async fn handle_get_command(
 
                 // Export if requested
                 if let Some(path) = output {
-                    std::fs::write(path, &content)?;
+                    export::write_output(path, &content)?;
                     renderer.print_success(&format!("Item exported to {:?}", path));
                 }
             } else {
@@ -1456,20 +2085,519 @@ async fn handle_get_command(
     Ok(())
 }
 
-/// Handle the index command for RAG document indexing
-#[allow(clippy::too_many_arguments)]
-async fn handle_index_command(
-    path_or_url: &str,
-    _id: Option<String>,
-    crawl: bool,
-    crawl_depth: Option<u32>,
-    crawl_all: bool,
-    _live_index: bool,
-    embed_concurrency: Option<usize>,
-    crawl_max_pages: Option<usize>,
-    config: &Config,
-    renderer: &Renderer,
-) -> Result<()> {
+/// Result of resolving a short item ID through the cache's item index
+enum ItemLookup {
+    Found(ItemRef, String),
+    /// The ID is cached for more than one library
+    Ambiguous(Vec<ItemRef>),
+    Missing,
+}
+
+/// Look up the first of `ids` that is cached in `category`
+///
+/// An ID held by several libraries is reported rather than resolved to whichever was cached last.
+async fn lookup_cached_item(
+    cache_manager: &CacheManager,
+    category: &str,
+    ids: &[String],
+) -> ItemLookup {
+    for id in ids {
+        let mut items = cache_manager.find_items(category, id);
+        if items.len() > 1 {
+            return ItemLookup::Ambiguous(items);
+        }
+        if let Some(item) = items.pop() {
+            if let Ok(Some(content)) = cache_manager.get::<String>(category, &item.key).await {
+                return ItemLookup::Found(item, content);
+            }
+        }
+    }
+    ItemLookup::Missing
+}
+
+/// List the library-qualified IDs to use when a bare ID matches several libraries
+fn print_ambiguous_item(id: &str, command: &str, items: &[ItemRef], renderer: &Renderer) {
+    renderer.print_warning(&format!(
+        "'{}' is cached for {} libraries. Pick one:",
+        id,
+        items.len()
+    ));
+    for item in items {
+        eprintln!("  manx {} {}-{}", command, item.library, item.id);
+    }
+}
+
+/// Query text piped to `manx search -`, trimmed of surrounding whitespace
+fn read_stdin_query() -> Result<String> {
+    let mut query = String::new();
+    io::Read::read_to_string(&mut io::stdin(), &mut query)
+        .context("Failed to read query from stdin")?;
+    Ok(query.trim().to_string())
+}
+
+/// Append a search, snippet or doc invocation to the query history
+///
+/// Failures are logged rather than reported: history must never break the command itself.
+fn record_history(
+    config: &Config,
+    command: &str,
+    library: Option<&str>,
+    query: &str,
+    result_count: usize,
+) {
+    let entry = crate::history::HistoryEntry {
+        command: command.to_string(),
+        library: library.map(str::to_string),
+        query: query.to_string(),
+        timestamp: chrono::Utc::now(),
+        result_count,
+        args: history_args(std::env::args().skip(1), command, query),
+    };
+    if let Err(e) = crate::history::History::from_config(config).and_then(|h| h.record(&entry)) {
+        log::warn!("Failed to record history: {}", e);
+    }
+}
+
+/// Arguments to store for re-running a command, without API keys passed on the command line
+///
+/// `--api-key` and any `--*-api-key` flag are dropped along with their value, so history
+/// never holds a key in plaintext; a re-run uses the configured key instead. A query piped
+/// to `search -` is stored inline so re-running it doesn't wait on stdin.
+fn history_args(args: impl Iterator<Item = String>, command: &str, query: &str) -> Vec<String> {
+    let is_key_flag = |flag: &str| flag == "--api-key" || flag.ends_with("-api-key");
+    let mut kept = Vec::new();
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, _)) => (flag, true),
+            None => (arg.as_str(), false),
+        };
+        if flag.starts_with("--") && is_key_flag(flag) {
+            if !inline_value {
+                // The value is the next argument unless another flag follows
+                args.next_if(|value| !value.starts_with("--"));
+            }
+            continue;
+        }
+        kept.push(match arg.as_str() {
+            "-" | "--stdin" if command == "search" => query.to_string(),
+            _ => arg,
+        });
+    }
+    kept
+}
+
+/// Numbered history list, oldest first so the newest entry sits next to the prompt
+fn print_history(entries: &[crate::history::HistoryEntry]) {
+    if entries.is_empty() {
+        println!("No history recorded yet");
+        return;
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        // Multi-line queries from stdin are listed on one line
+        let query = entry.query.split_whitespace().collect::<Vec<_>>().join(" ");
+        let target = match &entry.library {
+            Some(library) if query.is_empty() => library.clone(),
+            Some(library) => format!("{} {}", library, query),
+            None => query,
+        };
+        println!(
+            "{:>4}  {}  {:<8} {}  {}",
+            i + 1,
+            entry
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            entry.command,
+            target,
+            format!("({} results)", entry.result_count).dimmed()
+        );
+    }
+    println!("\nRe-run an entry with: manx history --run <N>");
+}
+
+/// Export settings to TOML, merge them in from a file, or test the configured providers
+///
+/// Works on the saved config rather than this run's, so `--api-key` and `--cache-dir`
+/// overrides are never exported or written back.
+async fn handle_config_subcommand(command: ConfigCommands, renderer: &Renderer) -> Result<()> {
+    match command {
+        ConfigCommands::Export {
+            output,
+            include_secrets,
+        } => {
+            let text = Config::load()?.to_toml(include_secrets)?;
+            match output {
+                Some(path) => {
+                    let path = config::expand_path(&path);
+                    std::fs::write(&path, text)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    renderer
+                        .print_success(&format!("Configuration exported to {}", path.display()));
+                    if !include_secrets {
+                        println!("API keys were left out; pass --include-secrets to export them.");
+                    }
+                }
+                None => print!("{}", text),
+            }
+        }
+        ConfigCommands::Test => test_configured_providers(&Config::load()?, renderer).await?,
+        ConfigCommands::Import { file, yes } => {
+            let file = config::expand_path(&file);
+            let text = std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            let current = Config::load()?;
+            let (merged, changed) = current.merge_toml(&text)?;
+            if changed.is_empty() {
+                renderer.print_success("Configuration already matches; nothing to import");
+                return Ok(());
+            }
+
+            println!("Importing {} would change:", file.display());
+            for key in &changed {
+                if crate::config::is_secret_key(key) {
+                    println!("  {} {}", key.yellow(), "(API key)".yellow());
+                } else {
+                    println!("  {}", key);
+                }
+            }
+            let confirmed = yes
+                || {
+                    use std::io::IsTerminal;
+                    if !std::io::stdin().is_terminal() {
+                        anyhow::bail!(
+                        "Refusing to import without confirmation; pass --yes when not running in a terminal"
+                    );
+                    }
+                    dialoguer::Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                        .with_prompt("Apply these changes?")
+                        .default(false)
+                        .interact()?
+                };
+            if !confirmed {
+                println!("❌ Cancelled.");
+                return Ok(());
+            }
+            merged.save()?;
+            renderer.print_success(&format!("Imported {} setting(s)", changed.len()));
+        }
+    }
+    Ok(())
+}
+
+/// Make a live request to every configured LLM provider and load the embedding model
+///
+/// Fails with the number of failed checks so scripts can tell when a key stopped working.
+async fn test_configured_providers(config: &Config, renderer: &Renderer) -> Result<()> {
+    use crate::rag::llm::{LlmClient, LlmProvider, ProviderCheck};
+
+    let mut failures = 0;
+    println!("{}", "LLM providers".bold());
+    let client = LlmClient::new(config.llm.clone())?;
+    let configured: Vec<LlmProvider> = [
+        LlmProvider::OpenAI,
+        LlmProvider::AzureOpenAI,
+        LlmProvider::Anthropic,
+        LlmProvider::Groq,
+        LlmProvider::OpenRouter,
+        LlmProvider::HuggingFace,
+        LlmProvider::Zai,
+        LlmProvider::Ollama,
+        LlmProvider::Custom,
+    ]
+    .into_iter()
+    .filter(|provider| client.is_provider_available(provider))
+    .collect();
+    if configured.is_empty() {
+        println!("  None configured. Add one with: manx config --openai-api <key>");
+    }
+    for provider in configured {
+        let label = format!("{:?} ({})", provider, client.get_model_name(&provider));
+        match client.check_provider(&provider).await {
+            ProviderCheck::Authenticated => {
+                println!("  {} {}: authenticated", "✓".green(), label)
+            }
+            ProviderCheck::Reachable(message) => {
+                failures += 1;
+                println!("  {} {}: reachable, but {}", "!".yellow(), label, message);
+            }
+            ProviderCheck::Unauthorized(message) => {
+                failures += 1;
+                println!("  {} {}: key rejected ({})", "✗".red(), label, message);
+            }
+            ProviderCheck::Failed(message) => {
+                failures += 1;
+                println!("  {} {}: failed ({})", "✗".red(), label, message);
+            }
+        }
+    }
+
+    println!("\n{}", "Embeddings".bold());
+    println!("  Provider: {:?}", config.rag.embedding.provider);
+    let status = crate::rag::embeddings::EmbeddingStatus::check(&config.rag.embedding).await;
+    print_embedding_checks(&status, renderer);
+    if status.load_error.is_some() || status.health_check_error.is_some() {
+        failures += 1;
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{} provider check(s) failed", failures);
+    }
+    println!();
+    renderer.print_success("All configured providers are working");
+    Ok(())
+}
+
+/// Print a synthesized LLM answer with colorized section headers
+fn print_ai_answer(answer: &str) {
+    let mut printer = AnswerPrinter::default();
+    printer.push(answer);
+    printer.finish();
+}
+
+/// Use `model` for this run's LLM synthesis instead of the configured one, without saving it
+///
+/// The name is checked against the provider that will answer first, when one is available.
+fn override_llm_model(config: &mut Config, model: Option<String>) -> Result<()> {
+    let Some(model) = model else {
+        return Ok(());
+    };
+    if let Some(provider) = crate::rag::llm::LlmClient::new(config.llm.clone())?.get_best_provider()
+    {
+        crate::rag::llm::validate_model_for_provider(&provider, &model)?;
+    }
+    config.llm.model_name = Some(model);
+    Ok(())
+}
+
+/// Print tokens used and an estimated cost for a synthesis when `--show-usage` is on
+fn print_llm_usage(synthesis: &crate::rag::llm::LlmResponse) {
+    use crate::rag::usage;
+
+    if !usage::show_usage() {
+        return;
+    }
+    if synthesis.cached {
+        eprintln!("\n  {}", "Usage: cached answer, no tokens used".dimmed());
+        return;
+    }
+    let Some(tokens) = synthesis.usage else {
+        return;
+    };
+    let approx = if tokens.estimated { "~" } else { "" };
+    let cost = match usage::estimate_cost(&synthesis.provider_used, &synthesis.model_used, &tokens)
+    {
+        Some(cost) => format!("~${:.4}", cost),
+        None => "cost unknown".to_string(),
+    };
+    eprintln!(
+        "\n  {}",
+        format!(
+            "Usage: {}{} tokens ({}{} in, {}{} out) · {} · {:?} {}",
+            approx,
+            tokens.total(),
+            approx,
+            tokens.input_tokens,
+            approx,
+            tokens.output_tokens,
+            cost,
+            synthesis.provider_used,
+            synthesis.model_used
+        )
+        .dimmed()
+    );
+}
+
+/// LLM client whose answers are cached alongside other results for `cache_ttl_hours`
+fn llm_client(config: &Config) -> Result<crate::rag::llm::LlmClient> {
+    Ok(crate::rag::llm::LlmClient::new(config.llm.clone())?
+        .with_cache(CacheManager::from_config(config)?))
+}
+
+/// Synthesize an answer, printing it as it streams in, followed by the sources it cited
+///
+/// With `--raw` only the answer text itself is printed.
+async fn stream_ai_summary(
+    llm_client: &crate::rag::llm::LlmClient,
+    query: &str,
+    results: &[crate::rag::RagSearchResult],
+    renderer: &Renderer,
+) -> Result<()> {
+    if renderer.raw_mode() {
+        let synthesis = llm_client
+            .synthesize_answer_streaming(query, results, |text| {
+                print!("{}", text);
+                io::stdout().flush().ok();
+            })
+            .await?;
+        if !synthesis.answer.ends_with('\n') {
+            println!();
+        }
+        print_llm_usage(&synthesis);
+        return Ok(());
+    }
+
+    let mut printer = AnswerPrinter::default();
+    let synthesis = llm_client
+        .synthesize_answer_streaming(query, results, |text| printer.push(text))
+        .await;
+    printer.finish();
+    let synthesis = synthesis?;
+    print_llm_usage(&synthesis);
+
+    if !synthesis.citations.is_empty() && synthesis.citations.len() <= 3 {
+        println!("\n  {}", "Sources used:".dimmed());
+        for citation in synthesis.citations.iter().take(3) {
+            println!("  {} {}", "•".dimmed(), citation_label(citation).dimmed());
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// A cited source's title, followed by `path:start-end` when it is a span of a local file
+fn citation_label(citation: &crate::rag::llm::Citation) -> String {
+    match citation.location() {
+        Some(location) => format!("{}  {}", citation.source_title, location),
+        None => citation.source_title.clone(),
+    }
+}
+
+/// Section markers in LLM answers and the labels they are shown as
+const ANSWER_HEADINGS: [(&str, &str); 3] = [
+    ("**Quick Answer**", "> Quick Answer"),
+    ("**Key Points**", "> Key Points"),
+    ("**Code Example**", "> Code Example"),
+];
+
+#[derive(Clone, Copy)]
+enum AnswerLineStyle {
+    Bullet,
+    CodeFence,
+    Citation,
+    Text,
+}
+
+impl AnswerLineStyle {
+    fn paint(self, text: &str) -> colored::ColoredString {
+        match self {
+            AnswerLineStyle::Bullet => text.cyan(),
+            AnswerLineStyle::CodeFence => text.on_bright_black().yellow(),
+            AnswerLineStyle::Citation => text.bright_white(),
+            AnswerLineStyle::Text => text.white(),
+        }
+    }
+}
+
+/// Prints answer text as it arrives, colorizing each line by how it starts
+///
+/// A line's style is chosen once its first characters are known (enough to recognize a
+/// section heading); after that the rest of the line is printed token by token.
+#[derive(Default)]
+struct AnswerPrinter {
+    started: bool,
+    /// Start of the current line, held back until its style is known
+    pending: String,
+    style: Option<AnswerLineStyle>,
+}
+
+impl AnswerPrinter {
+    fn push(&mut self, text: &str) {
+        if !self.started {
+            println!("\n{}", "AI Summary".bold().cyan());
+            self.started = true;
+        }
+
+        for piece in text.split_inclusive('\n') {
+            match piece.strip_suffix('\n') {
+                Some(segment) => {
+                    self.write(segment, true);
+                    println!();
+                    self.style = None;
+                }
+                None => self.write(piece, false),
+            }
+        }
+        io::stdout().flush().ok();
+    }
+
+    /// Print whatever remains of the last line
+    fn finish(&mut self) {
+        if self.style.is_some() || !self.pending.trim().is_empty() {
+            self.write("", true);
+            println!();
+        }
+        self.pending.clear();
+        self.style = None;
+    }
+
+    fn write(&mut self, segment: &str, line_complete: bool) {
+        if let Some(style) = self.style {
+            print!("{}", style.paint(segment));
+            return;
+        }
+
+        self.pending.push_str(segment);
+        let longest_marker = ANSWER_HEADINGS[0].0.len();
+        if !line_complete && self.pending.trim_start().len() < longest_marker {
+            return;
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+        let line = pending.trim_start();
+        if line.is_empty() {
+            return;
+        }
+
+        for (marker, label) in ANSWER_HEADINGS {
+            if let Some(rest) = line.strip_prefix(marker) {
+                let label = match marker {
+                    "**Quick Answer**" => label.bold().green(),
+                    "**Key Points**" => label.bold().blue(),
+                    _ => label.bold().magenta(),
+                };
+                print!("  {}{}", label, rest);
+                self.style = Some(AnswerLineStyle::Text);
+                return;
+            }
+        }
+
+        let style = if line.starts_with("- ") {
+            AnswerLineStyle::Bullet
+        } else if line.starts_with("```") {
+            AnswerLineStyle::CodeFence
+        } else if line_complete && line.contains("[Source") {
+            AnswerLineStyle::Citation
+        } else {
+            AnswerLineStyle::Text
+        };
+        print!("  {}", style.paint(line));
+        self.style = Some(style);
+    }
+}
+
+/// Handle the index command for RAG document indexing
+#[allow(clippy::too_many_arguments)]
+async fn handle_index_command(
+    path_or_url: Option<&str>,
+    retry_failed: bool,
+    _id: Option<String>,
+    crawl: bool,
+    crawl_depth: Option<u32>,
+    crawl_all: bool,
+    _live_index: bool,
+    embed_concurrency: Option<usize>,
+    crawl_max_pages: Option<usize>,
+    resume: bool,
+    sitemap: bool,
+    sitemap_filter: &[String],
+    force: bool,
+    dry_run: bool,
+    filter: &rag::indexer::PathFilter,
+    config: &Config,
+    renderer: &Renderer,
+) -> Result<()> {
     use crate::rag::RagSystem;
 
     if !config.rag.enabled {
@@ -1479,15 +2607,78 @@ async fn handle_index_command(
         return Ok(());
     }
 
+    if retry_failed {
+        let rag_system = RagSystem::new(config.rag.clone()).await?;
+        let stored = rag_system
+            .retry_failed_chunks(path_or_url, embed_concurrency)
+            .await?;
+        if stored > 0 && !rag::progress::is_json() {
+            renderer.print_success(&format!("Stored {} previously failed chunks", stored));
+        }
+        return Ok(());
+    }
+    let path_or_url =
+        path_or_url.ok_or_else(|| anyhow::anyhow!("A path or URL to index is required"))?;
+
     // Determine if input is URL or file path
     let is_url = path_or_url.starts_with("http://") || path_or_url.starts_with("https://");
 
+    if resume && (!is_url || sitemap) {
+        return Err(anyhow::anyhow!(
+            "--resume continues an interrupted crawl; use it with a URL and --crawl, --crawl-depth or --crawl-all"
+        ));
+    }
+
+    if sitemap && !is_url {
+        return Err(anyhow::anyhow!(
+            "--sitemap requires a sitemap URL (e.g. https://example.com/sitemap.xml)"
+        ));
+    }
+
+    // docrawl builds its own client, which only knows the HTTP_PROXY/HTTPS_PROXY variables
+    let uses_docrawl =
+        is_url && !sitemap && (crawl_all || crawl_depth.is_some_and(|d| d > 1) || resume);
+    let env_proxy =
+        std::env::var_os("HTTP_PROXY").is_some() || std::env::var_os("HTTPS_PROXY").is_some();
+    if uses_docrawl && config.proxy.is_some() && !env_proxy {
+        return Err(anyhow::anyhow!(
+            "--crawl-depth above 1, --crawl-all and --resume can't use the `proxy` setting; \
+             export HTTPS_PROXY for the crawl, or crawl one level with --crawl"
+        ));
+    }
+
+    if dry_run {
+        let rag_system = RagSystem::new(config.rag.clone()).await?;
+        if is_url {
+            let follow_links = crawl || crawl_all || crawl_depth.is_some_and(|d| d > 0);
+            let sitemap_filters = sitemap.then_some(sitemap_filter);
+            let pb = renderer.show_progress("Discovering pages...");
+            let pages = rag_system
+                .discover_pages(path_or_url, sitemap_filters, follow_links, crawl_max_pages)
+                .await;
+            pb.finish_and_clear();
+            print_url_plan(&pages?, crawl_all || crawl_depth.is_some_and(|d| d > 1));
+        } else {
+            let plan = rag::indexer::plan_documents(
+                std::path::Path::new(path_or_url),
+                &config.rag,
+                filter,
+            )?;
+            print_index_plan(&plan);
+        }
+        return Ok(());
+    }
+
     // Don't show progress spinner for indexing operations - let underlying tools handle progress display
     let pb: Option<indicatif::ProgressBar> = None;
 
     match RagSystem::new(config.rag.clone()).await {
         Ok(mut rag_system) => {
-            let indexed_count = if is_url {
+            let indexed_count = if sitemap {
+                rag_system
+                    .index_sitemap(path_or_url, sitemap_filter, embed_concurrency)
+                    .await?
+            } else if is_url {
                 // Determine effective crawl behavior based on flags
                 // Semantics:
                 //   - No flag: depth = 0 (single page)
@@ -1512,6 +2703,7 @@ async fn handle_index_command(
                         effective_crawl_all,
                         embed_concurrency,
                         crawl_max_pages,
+                        resume,
                     )
                     .await?
             } else {
@@ -1520,7 +2712,9 @@ async fn handle_index_command(
                 if path.is_file() {
                     rag_system.index_document(path).await?
                 } else if path.is_dir() {
-                    rag_system.index_directory(path).await?
+                    rag_system
+                        .index_directory(path, force, filter, embed_concurrency)
+                        .await?
                 } else {
                     if let Some(pb) = pb {
                         pb.finish_and_clear();
@@ -1536,11 +2730,16 @@ async fn handle_index_command(
                 pb.finish_and_clear();
             }
 
+            // Structured events already reported the outcome on stderr
+            if rag::progress::is_json() {
+                return Ok(());
+            }
+
             if indexed_count == 0 {
                 if is_url {
                     renderer.print_error("No content was extracted from the URL. The page might be empty or contain unsupported content.");
                 } else {
-                    renderer.print_error("No documents were indexed. Make sure the path contains supported files (.md, .txt, .docx, .pdf)");
+                    renderer.print_error("No new or changed documents were indexed. Make sure the path contains supported files (.md, .txt, .docx, .pdf), or use --force to re-index everything");
                 }
             } else {
                 let source_type = if is_url { "URL" } else { "path" };
@@ -1571,6 +2770,90 @@ async fn handle_index_command(
     Ok(())
 }
 
+/// Skipped files listed per reason in a dry run before summarizing the rest
+const DRY_RUN_SKIPPED_SHOWN: usize = 10;
+
+/// Print the files a local index run would process, grouped by extension
+fn print_index_plan(plan: &rag::indexer::DocumentScan) {
+    use std::collections::BTreeMap;
+
+    let mut by_extension: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    for (file, size) in &plan.files {
+        let extension = file
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy().to_lowercase()))
+            .unwrap_or_else(|| "(none)".to_string());
+        let entry = by_extension.entry(extension).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+    }
+    let total_size: u64 = plan.files.iter().map(|(_, size)| size).sum();
+
+    println!("{}", "Dry run: nothing will be embedded or written".bold());
+    println!(
+        "\nWould index {} files ({})",
+        plan.files.len(),
+        format_bytes(total_size)
+    );
+    for (extension, (count, size)) in &by_extension {
+        println!(
+            "  {:<8} {:>6} files  {:>10}",
+            extension,
+            count,
+            format_bytes(*size)
+        );
+    }
+
+    if plan.skipped.is_empty() {
+        return;
+    }
+    let mut by_reason: BTreeMap<rag::indexer::SkipReason, Vec<&PathBuf>> = BTreeMap::new();
+    for (file, reason) in &plan.skipped {
+        by_reason.entry(*reason).or_default().push(file);
+    }
+    println!("\nWould skip {} files", plan.skipped.len());
+    for (reason, files) in by_reason {
+        println!("  {} ({}):", reason, files.len());
+        for file in files.iter().take(DRY_RUN_SKIPPED_SHOWN) {
+            println!("    {}", file.display().to_string().dimmed());
+        }
+        if files.len() > DRY_RUN_SKIPPED_SHOWN {
+            println!("    ... and {} more", files.len() - DRY_RUN_SKIPPED_SHOWN);
+        }
+    }
+}
+
+/// Print the pages a URL index run would fetch
+fn print_url_plan(pages: &[String], crawls_deeper: bool) {
+    println!("{}", "Dry run: nothing will be embedded or written".bold());
+    println!("\nWould index {} pages", pages.len());
+    for page in pages {
+        println!("  {}", page);
+    }
+    if crawls_deeper {
+        println!(
+            "{}",
+            "\nPages beyond the first level are only discovered while crawling".dimmed()
+        );
+    }
+}
+
+/// Human-readable byte count (B, KB, MB, GB)
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
 /// Handle the sources command for managing RAG document sources
 async fn handle_sources_command(
     command: SourceCommands,
@@ -1638,9 +2921,92 @@ async fn handle_sources_command(
             }
         }
 
+        SourceCommands::Tags => {
+            if !config.rag.enabled {
+                renderer.print_error("Local RAG is not enabled.");
+                return Ok(());
+            }
+
+            let tags = RagSystem::new(config.rag.clone()).await?.tag_counts()?;
+            if tags.is_empty() {
+                renderer.print_success("No tags in the index yet.");
+                return Ok(());
+            }
+            println!("{:>7}  Tag", "Chunks");
+            for (tag, chunks) in &tags {
+                println!("{:>7}  {}", chunks, tag);
+            }
+            eprintln!("\n💡 Filter searches with: manx search --rag --tag <TAG> \"query\"");
+        }
+
+        SourceCommands::Stats { per_source } => {
+            if !config.rag.enabled {
+                renderer.print_error("Local RAG is not enabled.");
+                return Ok(());
+            }
+
+            let stats = RagSystem::new(config.rag.clone()).await?.source_stats()?;
+            if stats.is_empty() {
+                renderer.print_success("No documents indexed yet.");
+                renderer.print_success("Use 'manx index <path>' to add documents.");
+                return Ok(());
+            }
+
+            let chunks: usize = stats.iter().map(|s| s.chunks).sum();
+            let bytes: u64 = stats.iter().map(|s| s.content_bytes).sum();
+            println!(
+                "{} sources, {} chunks, {} of text",
+                stats.len(),
+                chunks,
+                format_bytes(bytes)
+            );
+
+            if per_source {
+                println!(
+                    "\n{:>7}  {:>9}  {:<16}  {:<7}  Source",
+                    "Chunks", "Size", "Last modified", "Type"
+                );
+                for source in &stats {
+                    println!(
+                        "{:>7}  {:>9}  {:<16}  {:<7}  {}",
+                        source.chunks,
+                        format_bytes(source.content_bytes),
+                        source.last_modified.format("%Y-%m-%d %H:%M"),
+                        format!("{:?}", source.source_type),
+                        source.source
+                    );
+                }
+                eprintln!("\n💡 Remove a noisy source with: manx sources remove <SOURCE>");
+            } else {
+                let mut by_type: Vec<(String, usize, usize, u64)> = Vec::new();
+                for source in &stats {
+                    let label = format!("{:?}", source.source_type);
+                    match by_type.iter_mut().find(|(name, ..)| *name == label) {
+                        Some(entry) => {
+                            entry.1 += 1;
+                            entry.2 += source.chunks;
+                            entry.3 += source.content_bytes;
+                        }
+                        None => by_type.push((label, 1, source.chunks, source.content_bytes)),
+                    }
+                }
+                for (label, sources, chunks, bytes) in by_type {
+                    println!(
+                        "  {:<7} {} sources, {} chunks, {}",
+                        label,
+                        sources,
+                        chunks,
+                        format_bytes(bytes)
+                    );
+                }
+                eprintln!("\n💡 See each source with: manx sources stats --per-source");
+            }
+        }
+
         SourceCommands::Add { path, id: _id } => {
             handle_index_command(
-                &path.to_string_lossy(),
+                Some(&path.to_string_lossy()),
+                false,
                 None,
                 false,
                 None,
@@ -1648,12 +3014,72 @@ async fn handle_sources_command(
                 false,
                 None,
                 None,
+                false,
+                false,
+                &[],
+                false,
+                false,
+                &Default::default(),
                 config,
                 renderer,
             )
             .await?;
         }
 
+        SourceCommands::Remove { source } => {
+            if !config.rag.enabled {
+                renderer.print_error("Local RAG is not enabled.");
+                return Ok(());
+            }
+
+            let rag_system = RagSystem::new(config.rag.clone()).await?;
+            let selection = rag_system.select_source(&source)?;
+            if selection.chunk_keys.is_empty() {
+                renderer.print_error(&format!("No indexed source matches '{}'", source));
+                eprintln!("💡 See indexed sources with: manx sources list");
+                return Ok(());
+            }
+
+            // Prefix matches can sweep up more than intended; confirm with the user first
+            if selection.partial {
+                println!(
+                    "'{}' matches {} sources ({} chunks):",
+                    source,
+                    selection.sources.len(),
+                    selection.chunk_keys.len()
+                );
+                for matched in selection.sources.iter().take(10) {
+                    println!("  • {}", matched);
+                }
+                if selection.sources.len() > 10 {
+                    println!("  ... and {} more", selection.sources.len() - 10);
+                }
+
+                let confirmed = dialoguer::Confirm::with_theme(
+                    &dialoguer::theme::ColorfulTheme::default(),
+                )
+                .with_prompt("Remove all of these sources?")
+                .default(false)
+                .interact()
+                .context(
+                    "Removing a partial match needs confirmation from an interactive terminal",
+                )?;
+                if !confirmed {
+                    println!("Cancelled, nothing removed.");
+                    return Ok(());
+                }
+            }
+
+            let removed = rag_system.remove_source(&selection)?;
+            renderer.print_success(&format!(
+                "Removed {} chunks from {} source(s)",
+                removed,
+                selection.sources.len()
+            ));
+        }
+
+        SourceCommands::Domains { command } => handle_domain_command(command, renderer)?,
+
         SourceCommands::Clear => {
             if !config.rag.enabled {
                 renderer.print_error("Local RAG is not enabled.");
@@ -1684,32 +3110,270 @@ async fn handle_sources_command(
     Ok(())
 }
 
-/// Smart text truncation with word boundary awareness
-fn truncate_text(text: &str, max_length: usize, preserve_sentences: bool) -> String {
-    if text.len() <= max_length {
-        return text.to_string();
+/// List, add or remove custom web search domains, saved in the config directory
+fn handle_domain_command(command: DomainCommands, renderer: &Renderer) -> Result<()> {
+    use crate::web_search::official_sources::{CustomDomains, OfficialSourceManager};
+
+    let mut custom = CustomDomains::load()?;
+    let builtin = OfficialSourceManager::builtin();
+
+    match command {
+        DomainCommands::List => {
+            if custom.domains().is_empty() {
+                println!("No custom domains added");
+            } else {
+                println!("Custom domains:");
+                for (domain, tier) in custom.domains() {
+                    println!("  {:<40} {}", domain, builtin.get_tier_description(tier));
+                }
+            }
+            println!(
+                "\n{} built-in official domains are always included",
+                builtin.get_official_domains().len()
+            );
+        }
+        DomainCommands::Add { domain, tier } => {
+            let description = builtin.get_tier_description(&tier);
+            let domain = custom.add(&domain, tier)?;
+            custom.save()?;
+            renderer.print_success(&format!(
+                "Domain '{}' saved as {} for web search",
+                domain, description
+            ));
+        }
+        DomainCommands::Remove { domain } => {
+            if custom.remove(&domain).is_some() {
+                custom.save()?;
+                renderer.print_success(&format!("Domain '{}' removed", domain));
+            } else if builtin.is_official_domain(&domain.to_lowercase()) {
+                anyhow::bail!(
+                    "'{}' is a built-in official domain and can't be removed",
+                    domain
+                );
+            } else {
+                anyhow::bail!(
+                    "'{}' is not a custom domain. Run 'manx sources domains list' to see them.",
+                    domain
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Smart text truncation with word boundary awareness; `max_length` counts characters
+fn truncate_text(text: &str, max_length: usize, preserve_sentences: bool) -> String {
+    // Count characters, not bytes, so multibyte text is never cut mid-character
+    let Some((limit, _)) = text.char_indices().nth(max_length) else {
+        return text.to_string();
+    };
+    let head = &text[..limit];
+
+    if preserve_sentences {
+        // Try to find a sentence boundary within the limit
+        let truncation_point = [". ", "! ", "? "]
+            .iter()
+            .find_map(|end| head.rfind(end).map(|pos| pos + 1))
+            .or_else(|| {
+                ['。', '！', '？']
+                    .iter()
+                    .find_map(|end| head.rfind(*end).map(|pos| pos + end.len_utf8()))
+            });
+
+        if let Some(pos) = truncation_point {
+            if head[..pos].chars().count() > max_length / 2 {
+                // Only use sentence boundary if it's not too short
+                return format!("{}...", head[..pos].trim());
+            }
+        }
+    }
+
+    // Fallback to word boundary
+    let truncation_point = head.rfind(' ').unwrap_or(head.len());
+
+    format!("{}...", head[..truncation_point].trim())
+}
+
+async fn handle_rag_command(
+    command: RagCommands,
+    config: &mut Config,
+    renderer: &Renderer,
+) -> Result<()> {
+    use crate::rag::RagSystem;
+
+    if !config.rag.enabled {
+        renderer.print_error("Local RAG is not enabled.");
+        return Ok(());
+    }
+    let rag_system = RagSystem::new(config.rag.clone()).await?;
+
+    match command {
+        RagCommands::Export { output } => {
+            let pb = renderer.show_progress("Exporting local index...");
+            let count = rag_system.export_index(&output);
+            pb.finish_and_clear();
+            renderer.print_success(&format!(
+                "Exported {} chunks to {}",
+                count?,
+                output.display()
+            ));
+        }
+        RagCommands::Import { file } => {
+            let pb = renderer.show_progress("Importing chunks...");
+            let count = rag_system.import_index(&file);
+            pb.finish_and_clear();
+            renderer.print_success(&format!(
+                "Imported {} chunks from {}",
+                count?,
+                file.display()
+            ));
+        }
+        RagCommands::Chunks { source, full, json } => {
+            let chunks = rag_system.source_chunks(&source)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&chunks)?);
+                return Ok(());
+            }
+            if chunks.is_empty() {
+                renderer.print_error(&format!("No indexed chunks match '{}'", source));
+                eprintln!("💡 See indexed sources with: manx sources list");
+                return Ok(());
+            }
+
+            let mut current_source = None;
+            for chunk in &chunks {
+                if current_source != Some(&chunk.source_path) {
+                    current_source = Some(&chunk.source_path);
+                    let count = chunks
+                        .iter()
+                        .filter(|c| c.source_path == chunk.source_path)
+                        .count();
+                    println!(
+                        "\n{} {}",
+                        chunk.source_path.display().to_string().bold(),
+                        format!("({} chunks)", count).dimmed()
+                    );
+                }
+                println!(
+                    "  {} {} {}",
+                    format!("[{}]", chunk.chunk_index).cyan(),
+                    chunk.section.as_deref().unwrap_or("(no section)"),
+                    format!("· {} chars", chunk.content.chars().count()).dimmed()
+                );
+                let content = if full {
+                    chunk.content.trim().to_string()
+                } else {
+                    truncate_text(chunk.content.trim(), 160, false).replace('\n', " ")
+                };
+                for line in content.lines() {
+                    println!("      {}", line);
+                }
+            }
+        }
+        RagCommands::Verify { repair } => {
+            let report = rag_system.verify_index(repair)?;
+            print_index_verification(&report, renderer);
+            if !report.repaired && report.repairable_problems() > 0 {
+                anyhow::bail!(
+                    "Index has {} problem(s); run 'manx rag verify --repair' to remove them",
+                    report.repairable_problems()
+                );
+            }
+        }
+        RagCommands::Reindex => {
+            let pb = renderer.show_progress("Re-embedding chunks...");
+            let summary = rag_system.reindex_embeddings(|done, total| {
+                pb.set_message(format!("Re-embedding chunks... {}/{}", done, total));
+            });
+            let summary = summary.await;
+            pb.finish_and_clear();
+            let summary = summary?;
+
+            if summary.reembedded == 0 && summary.dropped == 0 {
+                renderer.print_success("Index is empty; nothing to re-embed");
+                return Ok(());
+            }
+            if let Some(previous) = summary
+                .previous_dimension
+                .filter(|previous| *previous != summary.dimension)
+            {
+                renderer.print_warning(&format!(
+                    "Embedding dimension changed from {} to {}: every chunk now uses the new model, \
+                     and exports or indexes built with the old model are no longer compatible",
+                    previous, summary.dimension
+                ));
+            }
+            if summary.dropped > 0 {
+                renderer.print_warning(&format!(
+                    "Dropped {} chunks with no stored content; re-index their sources with 'manx index'",
+                    summary.dropped
+                ));
+            }
+            if config.rag.embedding.dimension != summary.dimension {
+                config.set_embedding_dimension(summary.dimension)?;
+            }
+            renderer.print_success(&format!(
+                "Re-embedded {} chunks ({} dimensions)",
+                summary.reembedded, summary.dimension
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the findings of `manx rag verify`
+fn print_index_verification(report: &crate::rag::IndexVerification, renderer: &Renderer) {
+    println!(
+        "Chunks: {} readable, {} dead records awaiting compaction",
+        report.chunks, report.dead_records
+    );
+
+    let fixed = if report.repaired { " (removed)" } else { "" };
+    if report.corrupt_records > 0 {
+        renderer.print_warning(&format!(
+            "{} corrupt records{}",
+            report.corrupt_records, fixed
+        ));
     }
-
-    if preserve_sentences {
-        // Try to find a sentence boundary within the limit
-        let truncation_point = text[..max_length]
-            .rfind(". ")
-            .or_else(|| text[..max_length].rfind("! "))
-            .or_else(|| text[..max_length].rfind("? "))
-            .map(|pos| pos + 1);
-
-        if let Some(pos) = truncation_point {
-            if pos > max_length / 2 {
-                // Only use sentence boundary if it's not too short
-                return format!("{}...", text[..pos].trim());
-            }
+    if report.truncated_bytes > 0 {
+        renderer.print_warning(&format!(
+            "Half-written record at the end of the store ({} bytes){}",
+            report.truncated_bytes, fixed
+        ));
+    }
+    if !report.dimension_mismatches.is_empty() {
+        renderer.print_warning(&format!(
+            "Chunks whose embedding size isn't the configured {} dimensions{}:",
+            report.dimension, fixed
+        ));
+        for (source, count) in &report.dimension_mismatches {
+            println!("  {} ({} chunks)", source, count);
+        }
+        if !report.repaired {
+            println!("  💡 'manx rag reindex' re-embeds them with the current provider instead");
         }
     }
+    if !report.legacy_leftovers.is_empty() {
+        renderer.print_warning(&format!("Unreadable legacy chunk files{}:", fixed));
+        for path in &report.legacy_leftovers {
+            println!("  {}", path.display());
+        }
+    }
+    if !report.missing_sources.is_empty() {
+        renderer.print_warning("Indexed files that no longer exist:");
+        for (path, count) in &report.missing_sources {
+            println!("  {} ({} chunks)", path.display(), count);
+        }
+        println!("  💡 Drop them with: manx sources remove <path>");
+    }
 
-    // Fallback to word boundary
-    let truncation_point = text[..max_length].rfind(' ').unwrap_or(max_length);
-
-    format!("{}...", text[..truncation_point].trim())
+    if report.repaired {
+        renderer.print_success("Index repaired");
+    } else if report.repairable_problems() == 0 && report.missing_sources.is_empty() {
+        renderer.print_success("Index is healthy");
+    }
 }
 
 /// Handle embedding commands for managing semantic search models
@@ -1718,10 +3382,16 @@ async fn handle_embedding_command(
     config: &mut Config,
     renderer: &Renderer,
 ) -> Result<()> {
-    use crate::rag::embeddings::EmbeddingModel;
+    use crate::rag::embeddings::{EmbeddingModel, EmbeddingStatus};
 
     match command {
-        EmbeddingCommands::Status => {
+        EmbeddingCommands::Status { json } => {
+            if json {
+                let status = EmbeddingStatus::check(&config.rag.embedding).await;
+                println!("{}", serde_json::to_string_pretty(&status)?);
+                return Ok(());
+            }
+
             renderer.print_success("🧠 Embedding Configuration:");
             println!("  Provider: {:?}", config.rag.embedding.provider);
             println!("  Dimension: {}", config.rag.embedding.dimension);
@@ -1738,71 +3408,21 @@ async fn handle_embedding_command(
                 println!("  Endpoint: {}", endpoint);
             }
 
+            if let Some(model) = &config.rag.embedding.model {
+                println!("  Endpoint Model: {}", model);
+            }
+
             // Test if embedding model loads successfully and detect dimension
             println!("\n🔄 Testing embedding model...");
-            match EmbeddingModel::new_with_config(config.rag.embedding.clone()).await {
-                Ok(model) => {
-                    // Test health check
-                    match model.health_check().await {
-                        Ok(()) => renderer.print_success("✅ Embedding model loads successfully"),
-                        Err(e) => {
-                            renderer.print_error(&format!("❌ Health check failed: {}", e));
-                            return Ok(());
-                        }
-                    }
-
-                    // Get actual dimension
-                    match model.get_dimension().await {
-                        Ok(dim) => {
-                            if dim != config.rag.embedding.dimension {
-                                println!(
-                                    "   ⚠️  Actual dimension: {} (config shows: {})",
-                                    dim, config.rag.embedding.dimension
-                                );
-                            } else {
-                                println!("   ✅ Confirmed dimension: {}", dim);
-                            }
-                        }
-                        Err(e) => {
-                            println!("   ⚠️  Could not detect dimension: {}", e);
-                        }
-                    }
-
-                    // Show provider info
-                    let provider_info = model.get_provider_info();
-                    println!("   Provider: {}", provider_info.name);
-                    println!("   Type: {}", provider_info.provider_type);
-                    if let Some(model_name) = &provider_info.model_name {
-                        println!("   Model: {}", model_name);
-                    }
-                    if let Some(max_len) = provider_info.max_input_length {
-                        println!("   Max input length: {}", max_len);
-                    }
-                    if !provider_info.description.is_empty() {
-                        println!("   Description: {}", provider_info.description);
-                    }
-
-                    // Show current config
-                    let current_config = model.get_config();
-                    if let Some(endpoint) = &current_config.endpoint {
-                        println!("   Configured endpoint: {}", endpoint);
-                    }
-                    if current_config.api_key.is_some() {
-                        println!("   API key configured: Yes");
-                    }
-                }
-                Err(e) => {
-                    renderer.print_error(&format!("❌ Embedding model failed to load: {}", e));
-                    println!("   Tip: Use 'manx embedding download <model>' for ONNX models");
-                    println!("   Tip: Check API keys for cloud providers");
-                }
-            }
+            let status = EmbeddingStatus::check(&config.rag.embedding).await;
+            print_embedding_checks(&status, renderer);
         }
 
         EmbeddingCommands::Set {
             provider,
             api_key,
             endpoint,
+            model,
             dimension,
         } => {
             // Set provider
@@ -1829,6 +3449,11 @@ async fn handle_embedding_command(
                 renderer.print_success(&format!("Embedding endpoint set to: {}", url));
             }
 
+            if let Some(name) = model {
+                config.set_embedding_model(name.clone())?;
+                renderer.print_success(&format!("Embedding endpoint model set to: {}", name));
+            }
+
             // Set dimension if provided
             if let Some(dim) = dimension {
                 match config.set_embedding_dimension(dim) {
@@ -1846,6 +3471,19 @@ async fn handle_embedding_command(
             match EmbeddingModel::new_with_config(config.rag.embedding.clone()).await {
                 Ok(_) => {
                     renderer.print_success("✅ New embedding configuration works!");
+                    // Probe API providers for their dimension unless one was given
+                    if dimension.is_none() {
+                        match config.rag.embedding.detect_and_update_dimension().await {
+                            Ok(()) => {
+                                config.save()?;
+                                println!(
+                                    "   Embedding dimension: {}",
+                                    config.rag.embedding.dimension
+                                );
+                            }
+                            Err(e) => log::warn!("Could not detect dimension: {}", e),
+                        }
+                    }
                 }
                 Err(e) => {
                     renderer.print_error(&format!("❌ New configuration failed: {}", e));
@@ -1856,11 +3494,10 @@ async fn handle_embedding_command(
         EmbeddingCommands::Download { model, force } => {
             use crate::rag::providers::onnx::OnnxProvider;
 
-            let pb = renderer.show_progress(&format!("Downloading model: {}", model));
+            eprintln!("Downloading model: {}", model);
 
             match OnnxProvider::download_model(&model, force).await {
                 Ok(()) => {
-                    pb.finish_and_clear();
                     renderer.print_success(&format!("Successfully downloaded model: {}", model));
 
                     // Update config to detect and store the actual dimension
@@ -1869,7 +3506,6 @@ async fn handle_embedding_command(
                     }
                 }
                 Err(e) => {
-                    pb.finish_and_clear();
                     renderer.print_error(&format!("Failed to download model: {}", e));
                 }
             }
@@ -2011,16 +3647,25 @@ async fn handle_embedding_command(
                     // Provide helpful error messages based on provider type
                     match &config.rag.embedding.provider {
                         crate::rag::EmbeddingProvider::Onnx(model) => {
-                            println!("💡 Try: manx embedding download {}", model);
+                            eprintln!("💡 Try: manx embedding download {}", model);
                         }
                         crate::rag::EmbeddingProvider::OpenAI(_) => {
-                            println!("💡 Check: manx config --embedding-api-key <your-openai-key>");
+                            eprintln!(
+                                "💡 Check: manx config --embedding-api-key <your-openai-key>"
+                            );
+                        }
+                        crate::rag::EmbeddingProvider::AzureOpenAI(deployment) => {
+                            eprintln!(
+                                "💡 Check: manx config --embedding-api-key <your-azure-key>, and that deployment '{}' exists on {}",
+                                deployment.deployment,
+                                deployment.endpoint()
+                            );
                         }
                         crate::rag::EmbeddingProvider::HuggingFace(_) => {
-                            println!("💡 Check: manx config --embedding-api-key <your-hf-key>");
+                            eprintln!("💡 Check: manx config --embedding-api-key <your-hf-key>");
                         }
                         crate::rag::EmbeddingProvider::Ollama(_) => {
-                            println!("💡 Check: Is Ollama server running? (ollama serve)");
+                            eprintln!("💡 Check: Is Ollama server running? (ollama serve)");
                         }
                         _ => {}
                     }
@@ -2028,7 +3673,58 @@ async fn handle_embedding_command(
             }
         }
 
-        EmbeddingCommands::Remove { model } => {
+        EmbeddingCommands::Compare {
+            texts,
+            matrix,
+            provider,
+        } => {
+            if !matrix && texts.len() != 2 {
+                anyhow::bail!(
+                    "Compare takes two texts; use --matrix to compare {} texts pairwise",
+                    texts.len()
+                );
+            }
+            let mut embedding_config = config.rag.embedding.clone();
+            if let Some(provider) = provider {
+                embedding_config.provider = Config::parse_embedding_provider(&provider)?;
+            }
+
+            let model = EmbeddingModel::new_with_config(embedding_config).await?;
+            let provider_info = model.get_provider_info();
+            match &provider_info.model_name {
+                Some(model_name) => println!("Provider: {} ({})", provider_info.name, model_name),
+                None => println!("Provider: {}", provider_info.name),
+            }
+            let text_refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+            let embeddings = model.embed_batch(&text_refs).await?;
+            print!("{}", format_similarities(&texts, &embeddings, matrix));
+        }
+
+        EmbeddingCommands::Benchmark { provider, rounds } => {
+            use crate::rag::benchmarks;
+
+            let mut embedding_config = config.rag.embedding.clone();
+            if let Some(provider) = provider {
+                embedding_config.provider = Config::parse_embedding_provider(&provider)?;
+            }
+            if rounds == 0 {
+                anyhow::bail!("--rounds must be at least 1");
+            }
+
+            let test_data = benchmarks::BenchmarkTestData::extended();
+            let pb = renderer.show_progress(&format!(
+                "Benchmarking {:?} over {} texts",
+                embedding_config.provider,
+                test_data.texts.len() * rounds
+            ));
+            let result =
+                benchmarks::benchmark_embedding_model(embedding_config, &test_data, rounds).await;
+            pb.finish_and_clear();
+
+            benchmarks::print_latency_benchmark(&result?);
+        }
+
+        EmbeddingCommands::Remove { model, yes } => {
             use crate::rag::model_metadata::ModelMetadataManager;
 
             match ModelMetadataManager::new() {
@@ -2039,39 +3735,48 @@ async fn handle_embedding_command(
                         println!("   Type: {}", metadata.provider_type);
                         println!("   Size: {:.1} MB", metadata.size_mb);
 
-                        // Simple confirmation (in a real CLI, you might use a proper prompt)
-                        println!("\nAre you sure? Type 'yes' to confirm:");
-                        let mut input = String::new();
-                        if std::io::stdin().read_line(&mut input).is_ok() {
-                            if input.trim().to_lowercase() == "yes" {
-                                // Remove model files if they exist
-                                if let Some(model_path) = &metadata.model_path {
-                                    if model_path.exists() {
-                                        if let Err(e) = std::fs::remove_dir_all(model_path) {
-                                            renderer.print_error(&format!(
-                                                "Failed to remove model files: {}",
-                                                e
-                                            ));
-                                            return Ok(());
-                                        }
-                                    }
+                        let confirmed = yes
+                            || {
+                                use std::io::IsTerminal;
+                                if !std::io::stdin().is_terminal() {
+                                    anyhow::bail!(
+                                    "Refusing to remove '{}' without confirmation; pass --yes when not running in a terminal",
+                                    model
+                                );
                                 }
+                                dialoguer::Confirm::with_theme(
+                                    &dialoguer::theme::ColorfulTheme::default(),
+                                )
+                                .with_prompt("Remove this model?")
+                                .default(false)
+                                .interact()?
+                            };
+                        if !confirmed {
+                            println!("❌ Cancelled.");
+                            return Ok(());
+                        }
 
-                                // Remove from metadata
-                                if let Err(e) = manager.remove_model(&model) {
-                                    renderer
-                                        .print_error(&format!("Failed to update metadata: {}", e));
+                        // Remove model files if they exist
+                        if let Some(model_path) = &metadata.model_path {
+                            if model_path.exists() {
+                                if let Err(e) = std::fs::remove_dir_all(model_path) {
+                                    renderer.print_error(&format!(
+                                        "Failed to remove model files: {}",
+                                        e
+                                    ));
                                     return Ok(());
                                 }
-
-                                renderer.print_success(&format!(
-                                    "✅ Successfully removed model: {}",
-                                    model
-                                ));
-                            } else {
-                                println!("❌ Cancelled.");
                             }
                         }
+
+                        // Remove from metadata
+                        if let Err(e) = manager.remove_model(&model) {
+                            renderer.print_error(&format!("Failed to update metadata: {}", e));
+                            return Ok(());
+                        }
+
+                        renderer
+                            .print_success(&format!("✅ Successfully removed model: {}", model));
                     } else {
                         renderer.print_error(&format!("Model '{}' not found.", model));
                     }
@@ -2086,18 +3791,104 @@ async fn handle_embedding_command(
     Ok(())
 }
 
+/// Each text's vector norm, then the similarity of the pair or the pairwise matrix
+fn format_similarities(texts: &[String], embeddings: &[Vec<f32>], matrix: bool) -> String {
+    use crate::rag::embeddings::EmbeddingModel;
+    use std::fmt::Write;
+
+    let mut out = String::from("\n");
+    for (i, (text, embedding)) in texts.iter().zip(embeddings).enumerate() {
+        let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let _ = writeln!(
+            out,
+            "  [{}] {} ({} dims, norm {:.4})",
+            i + 1,
+            truncate_text(text, 60, false),
+            embedding.len(),
+            norm
+        );
+    }
+    out.push('\n');
+
+    if !matrix {
+        let _ = writeln!(
+            out,
+            "Cosine similarity: {:.4}",
+            EmbeddingModel::cosine_similarity(&embeddings[0], &embeddings[1])
+        );
+        return out;
+    }
+
+    out.push_str("      ");
+    for column in 1..=texts.len() {
+        let _ = write!(out, "{:>8}", format!("[{}]", column));
+    }
+    out.push('\n');
+    for (row, a) in embeddings.iter().enumerate() {
+        let _ = write!(out, "  {:<4}", format!("[{}]", row + 1));
+        for b in embeddings {
+            let _ = write!(out, "{:>8.4}", EmbeddingModel::cosine_similarity(a, b));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Print the load, health-check and dimension results from `manx embedding status`
+fn print_embedding_checks(status: &crate::rag::embeddings::EmbeddingStatus, renderer: &Renderer) {
+    if let Some(e) = &status.load_error {
+        renderer.print_error(&format!("❌ Embedding model failed to load: {}", e));
+        println!("   Tip: Use 'manx embedding download <model>' for ONNX models");
+        println!("   Tip: Check API keys for cloud providers");
+        return;
+    }
+    if let Some(e) = &status.health_check_error {
+        renderer.print_error(&format!("❌ Health check failed: {}", e));
+        return;
+    }
+    renderer.print_success("✅ Embedding model loads successfully");
+
+    match (status.detected_dimension, &status.dimension_error) {
+        (Some(dim), _) if dim != status.dimension => println!(
+            "   ⚠️  Actual dimension: {} (config shows: {})",
+            dim, status.dimension
+        ),
+        (Some(dim), _) => println!("   ✅ Confirmed dimension: {}", dim),
+        (None, Some(e)) => println!("   ⚠️  Could not detect dimension: {}", e),
+        (None, None) => {}
+    }
+
+    if let Some(provider_info) = &status.provider_info {
+        println!("   Provider: {}", provider_info.name);
+        println!("   Type: {}", provider_info.provider_type);
+        if let Some(model_name) = &provider_info.model_name {
+            println!("   Model: {}", model_name);
+        }
+        if let Some(max_len) = provider_info.max_input_length {
+            println!("   Max input length: {}", max_len);
+        }
+        if !provider_info.description.is_empty() {
+            println!("   Description: {}", provider_info.description);
+        }
+    }
+}
+
 /// Handle web search command for official documentation
+#[allow(clippy::too_many_arguments)]
 async fn handle_web_search_command(
     query: &str,
     no_llm: bool,
     output: Option<&PathBuf>,
     limit: Option<usize>,
+    offset: usize,
+    prefer_community: bool,
+    since: Option<chrono::DateTime<chrono::Utc>>,
     config: &Config,
     renderer: &render::Renderer,
-) -> Result<()> {
+    interactive: bool,
+) -> Result<usize> {
     if query.trim().is_empty() {
-        renderer.print_error("Search query cannot be empty");
-        return Ok(());
+        anyhow::bail!("Search query cannot be empty");
     }
 
     // Initialize LLM config - auto-detect if API is configured
@@ -2121,11 +3912,9 @@ async fn handle_web_search_command(
     let pb = renderer.show_progress(&format!("{} for '{}'", search_mode, query));
 
     // Initialize web search configuration
-    let mut web_search_config = web_search::WebSearchConfig::default();
-    if let Some(max_results) = limit {
-        web_search_config.max_results = max_results.min(20); // Cap at 20 for performance
-    }
-    let max_display_results = web_search_config.max_results;
+    let (mut web_search_config, max_display_results) =
+        web_search_page_config(config, limit, offset, prefer_community);
+    web_search_config.since = since;
 
     // Create web search system
     let mut search_system = match web_search::DocumentationSearchSystem::new(
@@ -2138,15 +3927,31 @@ async fn handle_web_search_command(
         Ok(system) => system,
         Err(e) => {
             pb.finish_and_clear();
-            renderer.print_error(&format!("Failed to initialize search system: {}", e));
-            return Ok(());
+            return Err(e.context("Failed to initialize search system"));
         }
     };
 
     // Perform search
     match search_system.search(query).await {
-        Ok(response) => {
+        Ok(mut response) => {
             pb.finish_and_clear();
+            filter_min_score(&mut response.results, config, renderer, |r| {
+                r.similarity_score
+            });
+            record_explanations(
+                config,
+                "web",
+                query,
+                response.results.iter().map(|r| {
+                    (
+                        r.url.clone(),
+                        r.title.clone(),
+                        r.final_score,
+                        r.score_breakdown.clone(),
+                    )
+                }),
+            )
+            .await;
 
             // Display search info
             if response.used_fallback {
@@ -2160,16 +3965,51 @@ async fn handle_web_search_command(
                     response.official_results_count
                 ));
             }
+            if response.timed_out {
+                renderer.print_warning(&format!(
+                    "Web search returned {} partial results (timed out after {}s)",
+                    response.results.len(),
+                    search_system.config().search_timeout_seconds
+                ));
+            }
+            if since.is_some()
+                && !response.results.is_empty()
+                && response.results.iter().all(|r| r.timestamp.is_none())
+            {
+                renderer.print_warning(
+                    "--since had no effect: this search provider doesn't report publication dates",
+                );
+            }
+
+            if let Some(format) = renderer.output_format() {
+                let page = response.results.get(offset..).unwrap_or_default();
+                renderer.print_formatted(format, render::apply_limit(page, max_display_results))?;
+                if let Some(output_path) = output {
+                    let export_content = serde_json::to_string_pretty(&response)?;
+                    export::write_output(output_path, &export_content)
+                        .context("Failed to write export file")?;
+                }
+                return Ok(page.len().min(max_display_results));
+            }
 
             // Display results
             if response.results.is_empty() {
                 renderer.print_error("No relevant documentation found");
-                return Ok(());
+                return Ok(0);
+            }
+            if offset >= response.results.len() {
+                renderer.print_error(&format!(
+                    "Only {} results found, nothing past --offset {} (web search returns at most 20)",
+                    response.results.len(),
+                    offset
+                ));
+                return Ok(0);
             }
 
             // Apply LLM synthesis if configured and not disabled
+            let mut answered = false;
             if config.should_use_llm(no_llm) && !response.results.is_empty() {
-                println!("Synthesizing answer with AI...");
+                eprintln!("Synthesizing answer with AI...");
 
                 // Convert search results to RAG format for LLM synthesis
                 let rag_results: Vec<crate::rag::RagSearchResult> = response
@@ -2201,193 +4041,544 @@ async fn handle_web_search_command(
                                     "community".to_string()
                                 }],
                                 language: Some("en".to_string()),
+                                line_range: None,
                             },
+                            score_breakdown: None,
+                        }
+                    })
+                    .collect();
+
+                // Initialize LLM client and synthesize answer
+                match llm_client(config) {
+                    Ok(llm_client) => {
+                        match stream_ai_summary(&llm_client, query, &rag_results, renderer).await {
+                            Ok(()) => answered = true,
+                            Err(e) => {
+                                log::warn!("LLM synthesis failed: {}", e);
+                                renderer.print_error(
+                                    "AI synthesis failed, showing search results only check API status",
+                                );
+                            }
                         }
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to initialize LLM client: {}", e);
+                        renderer.print_error(&format!("Failed to initialize AI client: {}", e));
+                    }
+                }
+            }
+
+            // --raw prints only the answer
+            if !(answered && renderer.raw_mode()) {
+                // Add clear separation before search results
+                if config.should_use_llm(no_llm) && !response.results.is_empty() {
+                    println!("\n{}", "Detailed Results".bold().blue());
+                }
+
+                // Show summary (truncated)
+                println!("\nSummary:");
+                let summary = truncate_text(&response.summary, 150, true);
+                println!("{}", summary);
+
+                // Show top results
+                println!("\nDocumentation Results:");
+                let separator = "-".repeat(70);
+                for (i, result) in response
+                    .results
+                    .iter()
+                    .enumerate()
+                    .skip(offset)
+                    .take(max_display_results)
+                {
+                    if i > offset {
+                        println!("{}", separator.dimmed());
+                    }
+                    // Truncate title if too long
+                    let title = truncate_text(&result.title, 80, false);
+                    println!("\n{}. {}", i + 1, title);
+                    println!("   URL: {}", result.url.bright_blue().underline());
+
+                    let source_indicator = if result.is_official {
+                        "Official Documentation"
+                    } else {
+                        "Community Source"
+                    };
+                    let relevance = result.similarity_score * 100.0;
+                    let relevance_str = format!("{:.1}%", relevance);
+                    let relevance_colored = if relevance >= 85.0 {
+                        relevance_str.bright_green()
+                    } else if relevance >= 70.0 {
+                        relevance_str.yellow()
+                    } else {
+                        relevance_str.red()
+                    };
+                    println!("   {} • Relevance: {}", source_indicator, relevance_colored);
+
+                    // Show snippet (smart truncated)
+                    // Show a longer preview so users can judge relevance
+                    let snippet = truncate_text(&result.snippet, 220, true);
+                    println!("   {}", snippet);
+                }
+
+                if !response.results.is_empty() {
+                    println!("{}", separator.dimmed());
+                }
+            }
+
+            // Show search stats
+            eprintln!("\nSearch Statistics:");
+            eprintln!("• Total found: {}", response.total_found);
+            eprintln!("• Official sources: {}", response.official_results_count);
+            eprintln!("• Search time: {}ms", response.search_time_ms);
+            eprintln!("• Sources: {}", response.sources.join(", "));
+
+            // Export if requested
+            if let Some(output_path) = output {
+                // Convert response to JSON format for export
+                let export_content = serde_json::to_string_pretty(&response)?;
+                export::write_output(output_path, &export_content)
+                    .context("Failed to write export file")?;
+                renderer.print_success(&format!("Results exported to: {}", output_path.display()));
+            }
+            if interactive {
+                let items: Vec<PickerItem> = response
+                    .results
+                    .iter()
+                    .skip(offset)
+                    .take(max_display_results)
+                    .map(|result| PickerItem {
+                        label: result.title.clone(),
+                        title: result.title.clone(),
+                        content: format!("{}\n\n{}", result.url, result.snippet),
                     })
                     .collect();
+                pick_results(&items, offset, renderer)?;
+            }
+            Ok((response.results.len() - offset).min(max_display_results))
+        }
+
+        Err(e) => {
+            pb.finish_and_clear();
+
+            // Provide helpful suggestions
+            eprintln!("Search Tips:");
+            eprintln!("• Try more specific terms: 'react hooks useEffect' instead of 'react'");
+            eprintln!("• Check your internet connection");
+            eprintln!("• Use quotes for exact phrases: '\"memory management\"'");
+            Err(e.context("Search failed"))
+        }
+    }
+}
+
+/// Handle `manx ask`: query Context7, local RAG and web search together and synthesize one answer
+async fn handle_ask_command(
+    question: &str,
+    no_llm: bool,
+    output: Option<&PathBuf>,
+    limit: Option<usize>,
+    config: &Config,
+    renderer: &render::Renderer,
+) -> Result<()> {
+    use crate::ask::{merge_contexts, ContextSource};
+    use crate::rag::{DocumentMetadata, RagSearchResult, SourceType};
+    use chrono::Utc;
+
+    if question.trim().is_empty() {
+        renderer.print_error("Question cannot be empty");
+        return Ok(());
+    }
+
+    let per_source = limit.unwrap_or(5).max(1);
+
+    // Pick a likely library from the question for Context7
+    let analysis = web_search::query_analyzer::QueryAnalyzer::new()
+        .analyze_query(question, None)
+        .await?;
+    let library = analysis
+        .detected_frameworks
+        .first()
+        .map(|framework| framework.name.to_lowercase());
+
+    let pb = renderer.show_progress(&format!("Gathering context for '{}'", question));
+
+    // Load the embedding model once and share it between local RAG and web search
+    let embedding_model = shared_rag_embedding_model(config).await;
+
+    let context7_task = async {
+        let Some(library) = library.as_deref() else {
+            log::debug!("No library detected in question; skipping Context7");
+            return Vec::new();
+        };
+        if config.offline_mode {
+            return Vec::new();
+        }
+        let client = match context7_client(config, false) {
+            Ok(client) => client,
+            Err(e) => {
+                log::warn!("Context7 client unavailable: {}", e);
+                return Vec::new();
+            }
+        };
+        match SearchEngine::new(client)
+            .search(library, question, Some(per_source))
+            .await
+        {
+            Ok((results, _, _)) => results
+                .into_iter()
+                .map(|result| RagSearchResult {
+                    id: result.id,
+                    content: result.excerpt.clone(),
+                    source_path: PathBuf::from(result.url.unwrap_or(result.library.clone())),
+                    source_type: SourceType::Curated,
+                    title: Some(result.title),
+                    section: None,
+                    score: result.relevance_score,
+                    chunk_index: 0,
+                    metadata: DocumentMetadata {
+                        file_type: "snippet".to_string(),
+                        size: result.excerpt.len() as u64,
+                        modified: Utc::now(),
+                        tags: vec![result.library, "context7".to_string()],
+                        language: None,
+                        line_range: None,
+                    },
+                    score_breakdown: None,
+                })
+                .collect(),
+            Err(e) => {
+                log::warn!("Context7 lookup failed: {}", e);
+                Vec::new()
+            }
+        }
+    };
+
+    let local_task = async {
+        if !config.rag.enabled {
+            return Vec::new();
+        }
+        match crate::rag::RagSystem::new(config.rag.clone()).await {
+            Ok(mut rag_system) => {
+                if let Some(model) = &embedding_model {
+                    rag_system = rag_system.with_shared_embeddings(model.clone());
+                }
+                rag_system
+                    .search(question, Some(per_source))
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("Local RAG search failed: {}", e);
+                        Vec::new()
+                    })
+            }
+            Err(e) => {
+                log::warn!("Local RAG unavailable: {}", e);
+                Vec::new()
+            }
+        }
+    };
+
+    let web_task = async {
+        if config.offline_mode {
+            return Vec::new();
+        }
+        let web_config = web_search::WebSearchConfig {
+            max_results: per_source,
+            ..web_search_config(config)
+        };
+        web_search_contexts(question, web_config, embedding_model.clone()).await
+    };
 
-                // Initialize LLM client and synthesize answer
-                match crate::rag::llm::LlmClient::new(config.llm.clone()) {
-                    Ok(llm_client) => {
-                        match llm_client.synthesize_answer(query, &rag_results).await {
-                            Ok(synthesis) => {
-                                println!("\n{}", "AI Summary".bold().cyan());
-
-                                // Clean, colorized AI response
-                                for line in synthesis.answer.lines() {
-                                    if line.trim().is_empty() {
-                                        println!();
-                                        continue;
-                                    }
+    let (context7_results, local_results, web_results) =
+        tokio::join!(context7_task, local_task, web_task);
+    pb.finish_and_clear();
 
-                                    let trimmed = line.trim();
-                                    if trimmed.starts_with("**Quick Answer**") {
-                                        println!(
-                                            "  {}",
-                                            trimmed.replace(
-                                                "**Quick Answer**",
-                                                &format!("{}", "> Quick Answer".bold().green())
-                                            )
-                                        );
-                                    } else if trimmed.starts_with("**Key Points**") {
-                                        println!(
-                                            "  {}",
-                                            trimmed.replace(
-                                                "**Key Points**",
-                                                &format!("{}", "> Key Points".bold().blue())
-                                            )
-                                        );
-                                    } else if trimmed.starts_with("**Code Example**") {
-                                        println!(
-                                            "  {}",
-                                            trimmed.replace(
-                                                "**Code Example**",
-                                                &format!("{}", "> Code Example".bold().magenta())
-                                            )
-                                        );
-                                    } else if trimmed.starts_with("- ") {
-                                        println!("  {}", trimmed.cyan());
-                                    } else if trimmed.starts_with("```") {
-                                        println!("  {}", trimmed.on_bright_black().yellow());
-                                    } else if trimmed.contains("[Source") {
-                                        println!("  {}", trimmed.bright_white());
-                                    } else {
-                                        println!("  {}", trimmed.white());
-                                    }
-                                }
+    let counts = [
+        (ContextSource::Context7, context7_results.len()),
+        (ContextSource::Local, local_results.len()),
+        (ContextSource::Web, web_results.len()),
+    ];
+    let contexts = merge_contexts(
+        vec![
+            (ContextSource::Context7, context7_results),
+            (ContextSource::Local, local_results),
+            (ContextSource::Web, web_results),
+        ],
+        per_source * 3,
+    );
+
+    if contexts.is_empty() {
+        renderer.print_error("No context found from Context7, local documents or the web");
+        return Ok(());
+    }
 
-                                if !synthesis.citations.is_empty() && synthesis.citations.len() <= 3
-                                {
-                                    println!("\n  {}", "Sources used:".dimmed());
-                                    for citation in synthesis.citations.iter().take(3) {
-                                        println!(
-                                            "  {} {}",
-                                            "•".dimmed(),
-                                            citation.source_title.dimmed()
-                                        );
-                                    }
-                                }
-                                println!();
-                            }
-                            Err(e) => {
-                                log::warn!("LLM synthesis failed: {}", e);
-                                renderer.print_error(
-                                    "AI synthesis failed, showing search results only check API status",
-                                );
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to initialize LLM client: {}", e);
-                        renderer.print_error("Failed to initialize AI client");
-                    }
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|(source, count)| format!("{} {}", count, source.label()))
+        .collect();
+    renderer.print_success(&format!(
+        "Gathered {} unique sources ({})",
+        contexts.len(),
+        summary.join(", ")
+    ));
+
+    let mut answer = None;
+    if config.should_use_llm(no_llm) {
+        let rag_results: Vec<RagSearchResult> = contexts.iter().map(|c| c.result.clone()).collect();
+        match llm_client(config) {
+            Ok(llm_client) => match llm_client.synthesize_answer(question, &rag_results).await {
+                Ok(synthesis) => {
+                    print_ai_answer(&synthesis.answer);
+                    print_llm_usage(&synthesis);
+                    answer = Some(synthesis.answer);
+                }
+                Err(e) => {
+                    log::warn!("LLM synthesis failed: {}", e);
+                    renderer.print_error("AI synthesis failed, showing gathered context only");
                 }
+            },
+            Err(e) => {
+                log::warn!("Failed to initialize LLM client: {}", e);
+                renderer.print_error(&format!("Failed to initialize AI client: {}", e));
             }
+        }
+    }
 
-            // Add clear separation before search results
-            if config.should_use_llm(no_llm) && !response.results.is_empty() {
-                println!("\n{}", "Detailed Results".bold().blue());
-            }
+    // Numbered to match the [Source N] markers used in the synthesized answer
+    println!("\n{}", "Sources".bold().blue());
+    for (i, context) in contexts.iter().enumerate() {
+        let title = context.result.title.as_deref().unwrap_or("Untitled");
+        println!(
+            "  {} {}",
+            format!("[Source {}]", i + 1).bright_white(),
+            truncate_text(title, 90, false)
+        );
+        println!(
+            "     {}",
+            context.result.source_path.to_string_lossy().bright_blue()
+        );
+        if answer.is_none() {
+            println!("     {}", truncate_text(&context.result.content, 220, true));
+        }
+    }
+    println!();
 
-            // Show summary (truncated)
-            println!("\nSummary:");
-            let summary = truncate_text(&response.summary, 150, true);
-            println!("{}", summary);
+    if let Some(output_path) = output {
+        let export = serde_json::json!({
+            "question": question,
+            "library": library,
+            "answer": answer,
+            "sources": contexts,
+        });
+        export::write_output(output_path, &serde_json::to_string_pretty(&export)?)
+            .context("Failed to write export file")?;
+        renderer.print_success(&format!("Answer exported to: {}", output_path.display()));
+    }
 
-            // Show top results
-            println!("\nDocumentation Results:");
-            let separator = "-".repeat(70);
-            for (i, result) in response
-                .results
-                .iter()
-                .enumerate()
-                .take(max_display_results)
-            {
-                if i > 0 {
-                    println!("{}", separator.dimmed());
-                }
-                // Truncate title if too long
-                let title = truncate_text(&result.title, 80, false);
-                println!("\n{}. {}", i + 1, title);
-                println!("   URL: {}", result.url.bright_blue().underline());
+    Ok(())
+}
 
-                let source_indicator = if result.is_official {
-                    "Official Documentation"
-                } else {
-                    "Community Source"
-                };
-                let relevance = result.similarity_score * 100.0;
-                let relevance_str = format!("{:.1}%", relevance);
-                let relevance_colored = if relevance >= 85.0 {
-                    relevance_str.bright_green()
-                } else if relevance >= 70.0 {
-                    relevance_str.yellow()
-                } else {
-                    relevance_str.red()
-                };
-                println!("   {} • Relevance: {}", source_indicator, relevance_colored);
+/// Web search settings from the config file: provider and source weights
+/// Web search settings for one `manx search` page, and how many results the page shows
+fn web_search_page_config(
+    config: &Config,
+    limit: Option<usize>,
+    offset: usize,
+    prefer_community: bool,
+) -> (web_search::WebSearchConfig, usize) {
+    let mut web_search_config = web_search_config(config);
+    if prefer_community {
+        // Rank by relevance alone, and always run the unrestricted search so community
+        // sources are in the pool rather than only appearing as a fallback
+        web_search_config.source_weights.official_boost = 0.0;
+        web_search_config.min_official_results = usize::MAX;
+    }
+    // Fetch enough to reach the requested page; results are numbered by absolute position
+    let max_display_results = limit.unwrap_or(web_search_config.max_results);
+    web_search_config.max_results = (offset + max_display_results).min(20); // Cap at 20 for performance
+    (web_search_config, max_display_results)
+}
 
-                // Show snippet (smart truncated)
-                // Show a longer preview so users can judge relevance
-                let snippet = truncate_text(&result.snippet, 220, true);
-                println!("   {}", snippet);
-            }
+fn web_search_config(config: &Config) -> web_search::WebSearchConfig {
+    let defaults = web_search::WebSearchConfig::default();
+    web_search::WebSearchConfig {
+        source_weights: config.source_weights.clone(),
+        provider: config.search_provider.clone(),
+        search_timeout_seconds: config
+            .search_timeout_seconds
+            .unwrap_or(defaults.search_timeout_seconds),
+        similarity_threshold: config
+            .min_score
+            .map_or(defaults.similarity_threshold, |score| {
+                score.min(defaults.similarity_threshold)
+            }),
+        ..defaults
+    }
+}
 
-            if !response.results.is_empty() {
-                println!("{}", separator.dimmed());
+/// Run a web search and convert the hits into RAG-style context entries
+///
+/// Failures are logged and yield no results so callers can treat web search as optional.
+async fn web_search_contexts(
+    query: &str,
+    web_config: web_search::WebSearchConfig,
+    embedding_model: Option<Arc<rag::embeddings::EmbeddingModel>>,
+) -> Vec<crate::rag::RagSearchResult> {
+    use crate::rag::{DocumentMetadata, RagSearchResult, SourceType};
+    use chrono::Utc;
+
+    let max_results = web_config.max_results;
+    let mut search_system = match web_search::DocumentationSearchSystem::with_shared_embeddings(
+        web_config,
+        None,
+        embedding_model,
+    ) {
+        Ok(system) => system,
+        Err(e) => {
+            log::warn!("Web search unavailable: {}", e);
+            return Vec::new();
+        }
+    };
+    match search_system.search(query).await {
+        Ok(response) => {
+            if response.timed_out {
+                log::warn!(
+                    "Web search timed out; using {} partial results",
+                    response.results.len()
+                );
             }
+            response
+                .results
+                .into_iter()
+                .take(max_results)
+                .map(|result| RagSearchResult {
+                    id: result.url.clone(),
+                    content: result.snippet.clone(),
+                    source_path: PathBuf::from(&result.url),
+                    source_type: SourceType::Web,
+                    title: Some(result.title),
+                    section: None,
+                    score: result.final_score,
+                    chunk_index: 0,
+                    metadata: DocumentMetadata {
+                        file_type: "web".to_string(),
+                        size: result.snippet.len() as u64,
+                        modified: Utc::now(),
+                        tags: vec![result.source_domain],
+                        language: None,
+                        line_range: None,
+                    },
+                    score_breakdown: result.score_breakdown,
+                })
+                .collect()
+        }
+        Err(e) => {
+            log::warn!("Web search failed: {}", e);
+            Vec::new()
+        }
+    }
+}
 
-            // Show search stats
-            println!("\nSearch Statistics:");
-            println!("• Total found: {}", response.total_found);
-            println!("• Official sources: {}", response.official_results_count);
-            println!("• Search time: {}ms", response.search_time_ms);
-            println!("• Sources: {}", response.sources.join(", "));
+/// Search the local index, supplementing weak matches with web results when `web_fallback` is on
+///
+/// `pb` is the caller's spinner; it is finished before anything is printed.
+#[allow(clippy::too_many_arguments)]
+async fn search_local_index(
+    rag_system: &crate::rag::RagSystem,
+    query: &str,
+    max_results: usize,
+    web_fallback: bool,
+    filters: &crate::rag::SearchFilters,
+    config: &Config,
+    renderer: &Renderer,
+    pb: &indicatif::ProgressBar,
+) -> Result<Vec<crate::rag::RagSearchResult>> {
+    let mut results = rag_system
+        .search_filtered(query, Some(max_results), filters)
+        .await?;
 
-            // Export if requested
-            if let Some(output_path) = output {
-                // Convert response to JSON format for export
-                let export_content = serde_json::to_string_pretty(&response)?;
-                std::fs::write(output_path, export_content)
-                    .context("Failed to write export file")?;
-                renderer.print_success(&format!("Results exported to: {}", output_path.display()));
+    // Escalate to web search when the local index only has weak matches
+    let top_score = results.iter().map(|r| r.score).fold(0.0f32, f32::max);
+    if web_fallback && !config.offline_mode && top_score < config.rag.web_fallback_threshold {
+        pb.set_message("Local matches are weak, searching the web...");
+        let web_config = web_search::WebSearchConfig {
+            max_results,
+            ..web_search_config(config)
+        };
+        // Rank web results with the model the local search just used
+        let embedding_model = rag_system.search_model().await.ok().flatten();
+        let web_results = web_search_contexts(query, web_config, embedding_model).await;
+        pb.finish_and_clear();
+
+        if !web_results.is_empty() {
+            if renderer.output_format().is_none() {
+                eprintln!(
+                    "{}",
+                    "ℹ️  Local index had weak matches, supplemented with web results".yellow()
+                );
             }
+            results = crate::ask::merge_contexts(
+                vec![
+                    (crate::ask::ContextSource::Local, results),
+                    (crate::ask::ContextSource::Web, web_results),
+                ],
+                max_results,
+            )
+            .into_iter()
+            .map(|context| context.result)
+            .collect();
         }
+    }
+    Ok(results)
+}
 
-        Err(e) => {
-            pb.finish_and_clear();
-            renderer.print_error(&format!("Search failed: {}", e));
-
-            // Provide helpful suggestions
-            println!("Search Tips:");
-            println!("• Try more specific terms: 'react hooks useEffect' instead of 'react'");
-            println!("• Check your internet connection");
-            println!("• Use quotes for exact phrases: '\"memory management\"'");
+/// LLM client for `--rerank`, or `None` when it wasn't asked for or no provider can serve it
+///
+/// Reranking is skipped with a note rather than failing the search.
+fn rerank_llm_client(
+    config: &Config,
+    no_llm: bool,
+    renderer: &Renderer,
+) -> Option<crate::rag::llm::LlmClient> {
+    if !config.rag.smart_search.llm_rerank {
+        return None;
+    }
+    let client = crate::rag::llm::LlmClient::new(config.llm.clone())
+        .ok()
+        .filter(|client| config.should_use_llm(no_llm) && client.is_available());
+    if client.is_none() && renderer.output_format().is_none() {
+        eprintln!(
+            "{}",
+            "ℹ️  No LLM available for --rerank, showing results by similarity".yellow()
+        );
+        if !no_llm {
+            eprintln!("💡 Add one with: manx config --openai-api <key>");
         }
     }
-
-    Ok(())
+    client
 }
 
 /// Handle RAG search command for searching locally indexed documents
+#[allow(clippy::too_many_arguments)]
 async fn handle_rag_search_command(
     query: &str,
     no_llm: &bool,
     output: Option<&PathBuf>,
     limit: Option<&usize>,
+    offset: usize,
+    web_fallback: bool,
+    filters: &crate::rag::SearchFilters,
     config: &Config,
     renderer: &render::Renderer,
-) -> Result<()> {
+    interactive: bool,
+) -> Result<usize> {
     if query.trim().is_empty() {
-        renderer.print_error("Search query cannot be empty");
-        return Ok(());
+        anyhow::bail!("Search query cannot be empty");
     }
 
     // Check if RAG is enabled
     if !config.rag.enabled {
-        renderer.print_error("RAG (local document search) is not enabled.");
-        println!("💡 Enable with: manx config --rag-enabled");
-        return Ok(());
+        anyhow::bail!(
+            "RAG (local document search) is not enabled. Enable with: manx config --rag-enabled"
+        );
     }
 
     let search_mode = if config.should_use_llm(*no_llm) {
@@ -2396,29 +4587,73 @@ async fn handle_rag_search_command(
         "🔍 Searching indexed documents with semantic matching"
     };
 
+    let rerank_client = rerank_llm_client(config, *no_llm, renderer);
     let pb = renderer.show_progress(&format!("{} for '{}'", search_mode, query));
 
     // Initialize RAG system
-    let rag_system = match crate::rag::RagSystem::new(config.rag.clone()).await {
-        Ok(system) => system,
-        Err(e) => {
-            pb.finish_and_clear();
-            renderer.print_error(&format!("Failed to initialize RAG system: {}", e));
-            println!("💡 Try indexing documents first: manx index /path/to/docs");
-            return Ok(());
-        }
-    };
+    let rag_system =
+        match crate::rag::RagSystem::new_with_llm(config.rag.clone(), rerank_client).await {
+            Ok(system) => system,
+            Err(e) => {
+                pb.finish_and_clear();
+                eprintln!("💡 Try indexing documents first: manx index /path/to/docs");
+                return Err(e.context("Failed to initialize RAG system"));
+            }
+        };
 
-    // Perform search
-    let max_results = limit.copied().unwrap_or(10);
-    match rag_system.search(query, Some(max_results)).await {
-        Ok(results) => {
+    // Perform search; --offset pages through the ranked results
+    let max_results = offset + limit.copied().unwrap_or(10);
+    match search_local_index(
+        &rag_system,
+        query,
+        max_results,
+        web_fallback,
+        filters,
+        config,
+        renderer,
+        &pb,
+    )
+    .await
+    {
+        Ok(mut results) => {
             pb.finish_and_clear();
+            filter_min_score(&mut results, config, renderer, |r| r.score);
+            record_explanations(
+                config,
+                "local",
+                query,
+                results.iter().map(|r| {
+                    (
+                        r.id.clone(),
+                        r.title.clone().unwrap_or_else(|| "Untitled".to_string()),
+                        r.score,
+                        r.score_breakdown.clone(),
+                    )
+                }),
+            )
+            .await;
+            let total = results.len();
+            results.drain(..offset.min(total));
+
+            if let Some(format) = renderer.output_format() {
+                renderer.print_formatted(format, &results)?;
+                if let Some(output_path) = output {
+                    export_rag_results(&results, output_path, renderer)?;
+                }
+                return Ok(results.len());
+            }
 
             if results.is_empty() {
+                if total > 0 {
+                    renderer.print_error(&format!(
+                        "Only {} results found, nothing past --offset {}",
+                        total, offset
+                    ));
+                    return Ok(0);
+                }
                 renderer.print_error("No relevant documents found in local index");
-                println!("💡 Index more documents with: manx index /path/to/docs");
-                return Ok(());
+                eprintln!("💡 Index more documents with: manx index /path/to/docs");
+                return Ok(0);
             }
 
             renderer.print_success(&format!(
@@ -2427,16 +4662,23 @@ async fn handle_rag_search_command(
             ));
 
             // Apply LLM synthesis if configured
+            let mut answered_raw = false;
             if config.should_use_llm(*no_llm) && !results.is_empty() {
                 match synthesize_rag_results(query, &results, config, renderer).await {
+                    Ok(synthesis) if renderer.raw_mode() => {
+                        println!("{}", synthesis.answer.trim_end());
+                        print_llm_usage(&synthesis);
+                        answered_raw = true;
+                    }
                     Ok(synthesis) => {
                         println!("\nAI Analysis:");
                         println!("{}", synthesis.answer);
+                        print_llm_usage(&synthesis);
 
                         if !synthesis.citations.is_empty() {
                             println!("\nSources:");
                             for citation in synthesis.citations.iter().take(5) {
-                                println!("  • {}", citation.source_title);
+                                println!("  • {}", citation_label(citation));
                             }
                         }
                     }
@@ -2449,22 +4691,28 @@ async fn handle_rag_search_command(
                 }
             }
 
-            // Display results
-            display_rag_results(&results, renderer);
+            // Display results (--raw prints only the answer)
+            if !answered_raw {
+                display_rag_results(&results, offset, renderer);
+            }
 
             // Handle output if specified
             if let Some(output_path) = output {
                 export_rag_results(&results, output_path, renderer)?;
             }
+            if interactive {
+                pick_results(&rag_picker_items(&results), offset, renderer)?;
+            }
+            Ok(results.len())
         }
         Err(e) => {
             pb.finish_and_clear();
-            renderer.print_error(&format!("RAG search failed: {}", e));
-            println!("💡 Try: manx config --rag-enabled or index documents with: manx index /path");
+            eprintln!(
+                "💡 Try: manx config --rag-enabled or index documents with: manx index /path"
+            );
+            Err(e.context("RAG search failed"))
         }
     }
-
-    Ok(())
 }
 
 /// Handle RAG snippet command for searching locally indexed documents with library focus
@@ -2480,17 +4728,18 @@ async fn handle_rag_snippet_command(
     json: &bool,
     limit: Option<&usize>,
     no_llm: &bool,
-) -> Result<()> {
+    filters: &crate::rag::SearchFilters,
+    interactive: bool,
+) -> Result<usize> {
     if query.trim().is_empty() {
-        renderer.print_error("Search query cannot be empty");
-        return Ok(());
+        anyhow::bail!("Search query cannot be empty");
     }
 
     // Check if RAG is enabled
     if !config.rag.enabled {
-        renderer.print_error("RAG (local document search) is not enabled.");
-        println!("💡 Enable with: manx config --rag-enabled");
-        return Ok(());
+        anyhow::bail!(
+            "RAG (local document search) is not enabled. Enable with: manx config --rag-enabled"
+        );
     }
 
     // Combine library and query for focused search
@@ -2506,41 +4755,59 @@ async fn handle_rag_snippet_command(
         "🔍 Finding code snippets with semantic matching"
     };
 
+    let rerank_client = rerank_llm_client(config, *no_llm, renderer);
     let pb = renderer.show_progress(&format!("{} for '{}'", search_mode, focused_query));
 
     // Initialize RAG system
-    let rag_system = match crate::rag::RagSystem::new(config.rag.clone()).await {
-        Ok(system) => system,
-        Err(e) => {
-            pb.finish_and_clear();
-            renderer.print_error(&format!("Failed to initialize RAG system: {}", e));
-            return Ok(());
-        }
-    };
+    let rag_system =
+        match crate::rag::RagSystem::new_with_llm(config.rag.clone(), rerank_client).await {
+            Ok(system) => system,
+            Err(e) => {
+                pb.finish_and_clear();
+                return Err(e.context("Failed to initialize RAG system"));
+            }
+        };
 
     // Perform search
     let max_results = limit.copied().unwrap_or(10);
-    match rag_system.search(&focused_query, Some(max_results)).await {
-        Ok(results) => {
+    match rag_system
+        .search_filtered(&focused_query, Some(max_results), filters)
+        .await
+    {
+        Ok(mut results) => {
             pb.finish_and_clear();
+            filter_min_score(&mut results, config, renderer, |r| r.score);
+
+            if let Some(format) = renderer.output_format() {
+                renderer.print_formatted(format, &results)?;
+                handle_snippet_save_and_export(&results, save, save_all, json, output, renderer)?;
+                return Ok(results.len());
+            }
 
             if results.is_empty() {
                 renderer.print_error(&format!(
                     "No code snippets found for '{}' in indexed documents",
                     library
                 ));
-                println!("💡 Try: manx index /path/to/code");
-                return Ok(());
+                eprintln!("💡 Try: manx index /path/to/code");
+                return Ok(0);
             }
 
             renderer.print_success(&format!("Found {} snippets for {}", results.len(), library));
 
             // Apply LLM synthesis if configured
+            let mut answered_raw = false;
             if config.should_use_llm(*no_llm) && !results.is_empty() {
                 match synthesize_rag_results(&focused_query, &results, config, renderer).await {
+                    Ok(synthesis) if renderer.raw_mode() => {
+                        println!("{}", synthesis.answer.trim_end());
+                        print_llm_usage(&synthesis);
+                        answered_raw = true;
+                    }
                     Ok(synthesis) => {
                         println!("\nCode Analysis:");
                         println!("{}", synthesis.answer);
+                        print_llm_usage(&synthesis);
                     }
                     Err(e) => {
                         log::warn!("LLM synthesis failed: {}", e);
@@ -2549,18 +4816,22 @@ async fn handle_rag_snippet_command(
             }
 
             // Display snippet results
-            display_rag_snippet_results(&results, library, renderer);
+            if !answered_raw {
+                display_rag_snippet_results(&results, library, renderer);
+            }
 
             // Handle saving and export
             handle_snippet_save_and_export(&results, save, save_all, json, output, renderer)?;
+            if interactive {
+                pick_results(&rag_picker_items(&results), 0, renderer)?;
+            }
+            Ok(results.len())
         }
         Err(e) => {
             pb.finish_and_clear();
-            renderer.print_error(&format!("RAG snippet search failed: {}", e));
+            Err(e.context("RAG snippet search failed"))
         }
     }
-
-    Ok(())
 }
 
 /// Handle RAG doc command for searching locally indexed documents
@@ -2572,12 +4843,12 @@ async fn handle_rag_doc_command(
     renderer: &render::Renderer,
     limit: Option<&usize>,
     no_llm: &bool,
-) -> Result<()> {
+) -> Result<usize> {
     // Check if RAG is enabled
     if !config.rag.enabled {
-        renderer.print_error("RAG (local document search) is not enabled.");
-        println!("💡 Enable with: manx config --rag-enabled");
-        return Ok(());
+        anyhow::bail!(
+            "RAG (local document search) is not enabled. Enable with: manx config --rag-enabled"
+        );
     }
 
     let search_query = if !query.is_empty() {
@@ -2599,23 +4870,31 @@ async fn handle_rag_doc_command(
         Ok(system) => system,
         Err(e) => {
             pb.finish_and_clear();
-            renderer.print_error(&format!("Failed to initialize RAG system: {}", e));
-            return Ok(());
+            return Err(e.context("Failed to initialize RAG system"));
         }
     };
 
     // Perform search
     let max_results = limit.copied().unwrap_or(10);
     match rag_system.search(&search_query, Some(max_results)).await {
-        Ok(results) => {
+        Ok(mut results) => {
             pb.finish_and_clear();
+            filter_min_score(&mut results, config, renderer, |r| r.score);
+
+            if let Some(format) = renderer.output_format() {
+                renderer.print_formatted(format, &results)?;
+                if let Some(output_path) = output {
+                    export_rag_results(&results, output_path, renderer)?;
+                }
+                return Ok(results.len());
+            }
 
             if results.is_empty() {
                 renderer.print_error(&format!(
                     "No documentation found for '{}' in indexed documents",
                     library
                 ));
-                return Ok(());
+                return Ok(0);
             }
 
             renderer.print_success(&format!(
@@ -2625,11 +4904,18 @@ async fn handle_rag_doc_command(
             ));
 
             // Apply LLM synthesis if configured
+            let mut answered_raw = false;
             if config.should_use_llm(*no_llm) && !results.is_empty() {
                 match synthesize_rag_results(&search_query, &results, config, renderer).await {
+                    Ok(synthesis) if renderer.raw_mode() => {
+                        println!("{}", synthesis.answer.trim_end());
+                        print_llm_usage(&synthesis);
+                        answered_raw = true;
+                    }
                     Ok(synthesis) => {
                         println!("\nDocumentation Summary:");
                         println!("{}", synthesis.answer);
+                        print_llm_usage(&synthesis);
                     }
                     Err(e) => {
                         log::warn!("LLM synthesis failed: {}", e);
@@ -2638,20 +4924,21 @@ async fn handle_rag_doc_command(
             }
 
             // Display documentation results
-            display_rag_doc_results(&results, library, renderer);
+            if !answered_raw {
+                display_rag_doc_results(&results, library, renderer);
+            }
 
             // Handle output if specified
             if let Some(output_path) = output {
                 export_rag_results(&results, output_path, renderer)?;
             }
+            Ok(results.len())
         }
         Err(e) => {
             pb.finish_and_clear();
-            renderer.print_error(&format!("RAG doc search failed: {}", e));
+            Err(e.context("RAG doc search failed"))
         }
     }
-
-    Ok(())
 }
 
 /// Synthesize RAG results using LLM
@@ -2661,28 +4948,155 @@ async fn synthesize_rag_results(
     config: &Config,
     _renderer: &render::Renderer,
 ) -> Result<crate::rag::llm::LlmResponse> {
-    let llm_client = crate::rag::llm::LlmClient::new(config.llm.clone())?;
+    let llm_client = llm_client(config)?;
     llm_client.synthesize_answer(query, results).await
 }
 
+/// A listed result that the `--interactive` picker can open
+struct PickerItem {
+    label: String,
+    title: String,
+    content: String,
+}
+
+/// Fail early when `--interactive` can't show a menu
+fn ensure_interactive_terminal(interactive: bool) -> Result<()> {
+    use std::io::IsTerminal;
+    if interactive && !(io::stdout().is_terminal() && io::stdin().is_terminal()) {
+        anyhow::bail!("--interactive needs a terminal; drop -i when piping or redirecting output");
+    }
+    Ok(())
+}
+
+/// After the results are listed, open them one at a time until Esc, offering to save each
+///
+/// Labels are numbered from `offset + 1` to match the listing.
+fn pick_results(items: &[PickerItem], offset: usize, renderer: &Renderer) -> Result<()> {
+    use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
+
+    if items.is_empty() {
+        return Ok(());
+    }
+    let theme = ColorfulTheme::default();
+    let labels: Vec<String> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            format!(
+                "{}. {}",
+                offset + i + 1,
+                truncate_text(&item.label, 70, false)
+            )
+        })
+        .collect();
+
+    println!();
+    let mut selected = 0;
+    while let Some(index) = Select::with_theme(&theme)
+        .with_prompt("Open a result (Esc to finish)")
+        .items(&labels)
+        .default(selected)
+        .interact_opt()?
+    {
+        selected = index;
+        let item = &items[index];
+        renderer.render_open_section(&item.title, &item.content)?;
+
+        if Confirm::with_theme(&theme)
+            .with_prompt("Save it to a file?")
+            .default(false)
+            .interact()?
+        {
+            let path: String = Input::with_theme(&theme)
+                .with_prompt("File")
+                .with_initial_text(format!("manx-result-{}.md", offset + index + 1))
+                .interact_text()?;
+            export::write_output(std::path::Path::new(&path), &item.content)?;
+            renderer.print_success(&format!("Saved to {}", path));
+        }
+    }
+    Ok(())
+}
+
+fn rag_picker_items(results: &[crate::rag::RagSearchResult]) -> Vec<PickerItem> {
+    results
+        .iter()
+        .map(|result| {
+            let title = result.title.as_deref().unwrap_or("Untitled");
+            PickerItem {
+                label: title.to_string(),
+                title: format!("{} ({})", title, result.source_path.display()),
+                content: result.content.clone(),
+            }
+        })
+        .collect()
+}
+
+fn snippet_picker_items(
+    results: &[crate::client::SearchResult],
+    limit: Option<usize>,
+) -> Vec<PickerItem> {
+    render::apply_limit(results, limit.unwrap_or(10))
+        .iter()
+        .map(|result| PickerItem {
+            label: format!("[{}] {}", result.library, result.title),
+            title: format!("{} - {}", result.library, result.title),
+            content: result.excerpt.clone(),
+        })
+        .collect()
+}
+
+/// Drop results scoring below `--min-score` and say how many went
+///
+/// The count goes to stderr with `--format` so it doesn't mix with the results.
+fn filter_min_score<T>(
+    results: &mut Vec<T>,
+    config: &Config,
+    renderer: &Renderer,
+    score: impl Fn(&T) -> f32,
+) {
+    let Some(min_score) = config.min_score else {
+        return;
+    };
+    let before = results.len();
+    results.retain(|result| score(result) >= min_score);
+    let filtered = before - results.len();
+    if filtered == 0 {
+        return;
+    }
+
+    let note = format!(
+        "Filtered out {} result{} scoring below --min-score {}",
+        filtered,
+        if filtered == 1 { "" } else { "s" },
+        min_score
+    );
+    // On stderr so piped or machine-readable output only carries results
+    if renderer.output_format().is_some() {
+        eprintln!("{}", note);
+    } else {
+        eprintln!("{}", note.dimmed());
+    }
+}
+
 /// Display RAG search results
-fn display_rag_results(results: &[crate::rag::RagSearchResult], _renderer: &render::Renderer) {
+///
+/// Results are numbered from `offset + 1`, their position in the full ranking.
+fn display_rag_results(
+    results: &[crate::rag::RagSearchResult],
+    offset: usize,
+    _renderer: &render::Renderer,
+) {
     println!("\n📄 Local Document Results:");
     for (i, result) in results.iter().enumerate() {
         println!(
             "\n{}. {} (Score: {:.2})",
-            i + 1,
+            offset + i + 1,
             result.title.as_deref().unwrap_or("Untitled"),
             result.score
         );
         println!("   📁 {}", result.source_path.display());
-
-        let preview = if result.content.len() > 150 {
-            format!("{}...", &result.content[..150])
-        } else {
-            result.content.clone()
-        };
-        println!("   {}", preview);
+        println!("   {}", truncate_text(&result.content, 150, false));
     }
 }
 
@@ -2730,20 +5144,14 @@ fn display_rag_doc_results(
             result.score
         );
         println!("   📁 {}", result.source_path.display());
-
-        let preview = if result.content.len() > 200 {
-            format!("{}...", &result.content[..200])
-        } else {
-            result.content.clone()
-        };
-        println!("   {}", preview);
+        println!("   {}", truncate_text(&result.content, 200, false));
     }
 }
 
 /// Export RAG results to file
 fn export_rag_results(
     results: &[crate::rag::RagSearchResult],
-    output_path: &PathBuf,
+    output_path: &std::path::Path,
     renderer: &render::Renderer,
 ) -> Result<()> {
     let export_content = if output_path.extension().and_then(|s| s.to_str()) == Some("json") {
@@ -2767,7 +5175,7 @@ fn export_rag_results(
         content
     };
 
-    std::fs::write(output_path, export_content).context("Failed to write export file")?;
+    export::write_output(output_path, &export_content).context("Failed to write export file")?;
     renderer.print_success(&format!("Results exported to: {}", output_path.display()));
 
     Ok(())
@@ -2861,3 +5269,164 @@ fn handle_snippet_save_and_export(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_json_lists_context() {
+        let error = error_json(&anyhow::anyhow!("inner").context("outer"));
+        assert_eq!(error["error"], "outer");
+        assert_eq!(error["context"][0], "inner");
+    }
+
+    #[tokio::test]
+    async fn test_docs_cache_prefix_is_per_library() {
+        let dir = std::env::temp_dir().join(format!("manx_docs_key_{}", uuid::Uuid::new_v4()));
+        let cache = CacheManager::with_custom_dir(dir.clone()).unwrap();
+        cache
+            .set(
+                "docs",
+                &docs_cache_key("redis_om", "models"),
+                "other library",
+            )
+            .await
+            .unwrap();
+        cache
+            .set("docs", &docs_cache_key("redis", "streams"), "wanted")
+            .await
+            .unwrap();
+        cache
+            .set(
+                "docs",
+                &docs_cache_key("redis_om", "queries"),
+                "newest, other library",
+            )
+            .await
+            .unwrap();
+
+        let prefix = format!("redis{}", DOCS_KEY_DELIMITER);
+        let (key, _) = cache.newest_entry("docs", &prefix).unwrap();
+        assert_eq!(
+            cache.get::<String>("docs", &key).await.unwrap(),
+            Some("wanted".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_similarity_output() {
+        let texts = vec!["first".to_string(), "second".to_string()];
+        let same = vec![vec![1.0, 0.0], vec![1.0, 0.0]];
+        let output = format_similarities(&texts, &same, false);
+        assert!(output.contains("[1] first (2 dims, norm 1.0000)"));
+        assert!(output.contains("Cosine similarity: 1.0000"));
+
+        let orthogonal = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let output = format_similarities(&texts, &orthogonal, false);
+        assert!(output.contains("Cosine similarity: 0.0000"));
+
+        let texts = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let embeddings = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0]];
+        let output = format_similarities(&texts, &embeddings, true);
+        assert!(!output.contains("Cosine similarity"));
+        let rows: Vec<&str> = output.lines().filter(|l| l.starts_with("  [")).collect();
+        // Three text lines, then three matrix rows.
+        assert_eq!(rows.len(), 6);
+        assert!(output.contains("     [1]     [2]     [3]"));
+        assert!(rows[3].contains("1.0000  0.0000  1.0000"));
+    }
+
+    #[test]
+    fn test_history_args_drop_api_keys() {
+        let argv = [
+            "--api-key",
+            "sk-secret-one",
+            "search",
+            "--groq-api-key=gsk-secret-two",
+            "-",
+            "--limit",
+            "5",
+        ];
+        let args = history_args(
+            argv.iter().map(|arg| arg.to_string()),
+            "search",
+            "pool size",
+        );
+        assert_eq!(args, vec!["search", "pool size", "--limit", "5"]);
+        assert!(!args.iter().any(|arg| arg.contains("secret")));
+    }
+
+    #[test]
+    fn test_truncate_text_keeps_short_text() {
+        assert_eq!(truncate_text("short text", 20, true), "short text");
+        assert_eq!(truncate_text("連接池", 3, false), "連接池");
+    }
+
+    #[test]
+    fn test_truncate_text_at_sentence_and_word_boundaries() {
+        let text = "Pools reuse connections. They cut latency for busy services.";
+        assert_eq!(truncate_text(text, 40, true), "Pools reuse connections....");
+        assert_eq!(
+            truncate_text(text, 40, false),
+            "Pools reuse connections. They cut..."
+        );
+    }
+
+    #[test]
+    fn test_truncate_text_handles_multibyte_text() {
+        // CJK has no spaces, so the cut falls on the character limit
+        let cjk = "連接池可以重複使用資料庫連接以降低延遲";
+        assert_eq!(truncate_text(cjk, 5, false), "連接池可以...");
+        assert_eq!(
+            truncate_text("連接池重複使用連接。它降低延遲並保護資料庫", 14, true),
+            "連接池重複使用連接。..."
+        );
+
+        // Every limit lands on a character boundary, including inside emoji runs
+        let emoji = "Deploy 🚀🚀🚀 done ✅ then celebrate 🎉🎉";
+        for max_length in 0..emoji.chars().count() + 2 {
+            let truncated = truncate_text(emoji, max_length, true);
+            assert!(truncated.trim_end_matches("...").chars().count() <= max_length);
+        }
+        assert_eq!(truncate_text(emoji, 10, false), "Deploy...");
+        assert_eq!(truncate_text("🚀🚀🚀🚀", 2, false), "🚀🚀...");
+    }
+
+    #[test]
+    fn test_rag_result_display_handles_multibyte_content() {
+        // Byte offsets 150 and 200 fall inside multibyte characters of this content
+        let content = format!(
+            "#{} {}",
+            "接続プールはデータベース接続を再利用して遅延を減らします。".repeat(4),
+            "fn main() { println!(\"🚀 done ✅\"); }".repeat(6)
+        );
+        assert!(!content.is_char_boundary(150) && !content.is_char_boundary(200));
+        let results = vec![crate::rag::RagSearchResult {
+            id: "guide.md_0".to_string(),
+            content,
+            source_path: PathBuf::from("/docs/ja/guide.md"),
+            source_type: crate::rag::SourceType::Local,
+            title: Some("接続プール".to_string()),
+            section: None,
+            score: 0.9,
+            chunk_index: 0,
+            metadata: crate::rag::DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 0,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: Some("ja".to_string()),
+                line_range: None,
+            },
+            score_breakdown: None,
+        }];
+
+        let renderer = render::Renderer::new(false);
+        display_rag_results(&results, 0, &renderer);
+        display_rag_snippet_results(&results, "postgres", &renderer);
+        display_rag_doc_results(&results, "postgres", &renderer);
+    }
+}
@@ -0,0 +1,89 @@
+//! Azure OpenAI deployments shared by answer synthesis and embeddings
+//!
+//! Azure serves OpenAI models from a per-organization resource, addressed by deployment
+//! name rather than model name, and authenticates with an `api-key` header instead of a
+//! bearer token. Request and response bodies match the OpenAI API.
+
+use serde::{Deserialize, Serialize};
+
+/// API version sent when none is configured
+pub const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Header carrying the Azure API key
+pub const API_KEY_HEADER: &str = "api-key";
+
+/// One model deployment on an Azure OpenAI resource
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AzureDeployment {
+    /// Resource name (`myorg` in `myorg.openai.azure.com`) or the resource's full endpoint URL
+    pub resource: String,
+    pub deployment: String,
+    #[serde(default = "default_api_version")]
+    pub api_version: String,
+}
+
+fn default_api_version() -> String {
+    DEFAULT_API_VERSION.to_string()
+}
+
+impl AzureDeployment {
+    pub fn new(resource: &str, deployment: &str, api_version: Option<&str>) -> Self {
+        Self {
+            resource: resource.trim().to_string(),
+            deployment: deployment.trim().to_string(),
+            api_version: api_version
+                .map(str::trim)
+                .filter(|version| !version.is_empty())
+                .unwrap_or(DEFAULT_API_VERSION)
+                .to_string(),
+        }
+    }
+
+    /// Base URL of the resource, accepting either a bare name or the endpoint from the portal
+    pub fn endpoint(&self) -> String {
+        let resource = self.resource.trim_end_matches('/');
+        if resource.contains("://") {
+            resource.to_string()
+        } else {
+            format!("https://{}.openai.azure.com", resource)
+        }
+    }
+
+    /// URL of `operation` (such as `chat/completions` or `embeddings`) on this deployment
+    pub fn url(&self, operation: &str) -> String {
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            self.endpoint(),
+            self.deployment,
+            operation,
+            self.api_version
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deployment_urls() {
+        let deployment = AzureDeployment::new("myorg", "gpt-4o-prod", None);
+        assert_eq!(
+            deployment.url("chat/completions"),
+            format!(
+                "https://myorg.openai.azure.com/openai/deployments/gpt-4o-prod/chat/completions?api-version={}",
+                DEFAULT_API_VERSION
+            )
+        );
+
+        let deployment = AzureDeployment::new(
+            "https://myorg.openai.azure.com/",
+            "embed-small",
+            Some("2024-02-01"),
+        );
+        assert_eq!(
+            deployment.url("embeddings"),
+            "https://myorg.openai.azure.com/openai/deployments/embed-small/embeddings?api-version=2024-02-01"
+        );
+    }
+}
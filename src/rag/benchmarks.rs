@@ -5,7 +5,9 @@
 
 #![allow(dead_code)] // Benchmarking infrastructure used by examples
 
+use crate::rag::embeddings::EmbeddingModel;
 use crate::rag::providers::EmbeddingProvider as ProviderTrait;
+use crate::rag::EmbeddingConfig;
 use anyhow::Result;
 use std::time::{Duration, Instant};
 
@@ -239,6 +241,103 @@ pub fn print_benchmark_results(results: &[BenchmarkResults]) {
     println!("\n{}", "=".repeat(80));
 }
 
+/// Latency profile of an `EmbeddingModel`, with cold start reported separately
+#[derive(Debug, Clone)]
+pub struct LatencyBenchmark {
+    pub provider_name: String,
+    pub model_name: Option<String>,
+    pub dimension: usize,
+    /// Loading the model plus its first embedding
+    pub cold_start: Duration,
+    /// One sample per warm `embed_text` call
+    pub warm_latencies: Vec<Duration>,
+    pub warm_total: Duration,
+}
+
+impl LatencyBenchmark {
+    pub fn texts_per_second(&self) -> f64 {
+        if self.warm_total.is_zero() {
+            return 0.0;
+        }
+        self.warm_latencies.len() as f64 / self.warm_total.as_secs_f64()
+    }
+
+    /// Nearest-rank percentile of the warm latencies (`p` in 0..=100)
+    pub fn percentile(&self, p: f64) -> Duration {
+        let mut sorted = self.warm_latencies.clone();
+        sorted.sort();
+        if sorted.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+/// Load an `EmbeddingModel` from `config` and time it over `rounds` passes of the corpus
+///
+/// Each pass appends its round number to the texts so the model's embedding cache never
+/// answers for the provider.
+pub async fn benchmark_embedding_model(
+    config: EmbeddingConfig,
+    test_data: &BenchmarkTestData,
+    rounds: usize,
+) -> Result<LatencyBenchmark> {
+    let cold_start_time = Instant::now();
+    let model = EmbeddingModel::new_with_config(config).await?;
+    model.embed_text("manx embedding benchmark warm-up").await?;
+    let cold_start = cold_start_time.elapsed();
+
+    let mut warm_latencies = Vec::with_capacity(test_data.texts.len() * rounds);
+    let warm_start = Instant::now();
+    for round in 0..rounds {
+        for text in &test_data.texts {
+            let text = format!("{} ({})", text, round + 1);
+            let start = Instant::now();
+            model.embed_text(&text).await?;
+            warm_latencies.push(start.elapsed());
+        }
+    }
+    let warm_total = warm_start.elapsed();
+
+    let info = model.get_provider_info();
+    Ok(LatencyBenchmark {
+        provider_name: info.name,
+        model_name: info.model_name,
+        dimension: model.get_dimension().await?,
+        cold_start,
+        warm_latencies,
+        warm_total,
+    })
+}
+
+/// Print a latency benchmark as a two-column table
+pub fn print_latency_benchmark(result: &LatencyBenchmark) {
+    let ms = |d: Duration| format!("{:.2} ms", d.as_secs_f64() * 1000.0);
+    let mut rows = vec![("Provider", result.provider_name.clone())];
+    if let Some(model) = &result.model_name {
+        rows.push(("Model", model.clone()));
+    }
+    rows.extend([
+        ("Dimension", result.dimension.to_string()),
+        ("Texts embedded", result.warm_latencies.len().to_string()),
+        ("Cold start", ms(result.cold_start)),
+        ("Warm p50", ms(result.percentile(50.0))),
+        ("Warm p90", ms(result.percentile(90.0))),
+        ("Warm p99", ms(result.percentile(99.0))),
+        (
+            "Throughput",
+            format!("{:.1} texts/sec", result.texts_per_second()),
+        ),
+    ]);
+
+    println!("{:<16} Value", "Metric");
+    println!("{}", "-".repeat(40));
+    for (metric, value) in rows {
+        println!("{:<16} {}", metric, value);
+    }
+}
+
 /// Get current process memory usage in MB
 fn get_process_memory_mb() -> f64 {
     #[cfg(target_os = "linux")]
@@ -301,6 +400,37 @@ mod tests {
         assert!(result.semantic_quality_score.is_some());
     }
 
+    #[tokio::test]
+    async fn test_benchmark_embedding_model_separates_cold_start() {
+        let test_data = BenchmarkTestData::new_default();
+
+        let result = benchmark_embedding_model(EmbeddingConfig::default(), &test_data, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(result.dimension, 384);
+        assert_eq!(result.warm_latencies.len(), test_data.texts.len() * 2);
+        assert!(result.percentile(50.0) <= result.percentile(99.0));
+        assert!(result.texts_per_second() > 0.0);
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let result = LatencyBenchmark {
+            provider_name: "hash".to_string(),
+            model_name: None,
+            dimension: 384,
+            cold_start: Duration::from_millis(50),
+            warm_latencies: (1..=10).rev().map(Duration::from_millis).collect(),
+            warm_total: Duration::from_millis(55),
+        };
+
+        assert_eq!(result.percentile(50.0), Duration::from_millis(5));
+        assert_eq!(result.percentile(90.0), Duration::from_millis(9));
+        assert_eq!(result.percentile(100.0), Duration::from_millis(10));
+        assert_eq!(result.percentile(0.0), Duration::from_millis(1));
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
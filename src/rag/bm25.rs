@@ -0,0 +1,115 @@
+//! BM25 keyword scoring over stored chunks for hybrid search
+//!
+//! Embeddings blur exact identifiers and acronyms. BM25 rewards chunks that contain the
+//! query's terms, weighting rare terms most. Scores are scaled so the best chunk for a query
+//! gets 1.0, which keeps them comparable with cosine similarity when the two are blended.
+
+use std::collections::HashMap;
+
+/// Term frequency saturation
+const K1: f32 = 1.2;
+/// How strongly long chunks are penalized
+const B: f32 = 0.75;
+
+/// Lowercased words and identifiers; `_` is kept so `parse_args` stays one term
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Term statistics for a fixed set of documents
+pub struct Bm25Index {
+    doc_terms: Vec<HashMap<String, u32>>,
+    doc_lengths: Vec<usize>,
+    avg_length: f32,
+    /// Number of documents each term appears in
+    doc_freq: HashMap<String, usize>,
+}
+
+impl Bm25Index {
+    pub fn new<'a>(documents: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut doc_terms = Vec::new();
+        let mut doc_lengths = Vec::new();
+        let mut doc_freq: HashMap<String, usize> = HashMap::new();
+
+        for document in documents {
+            let mut terms: HashMap<String, u32> = HashMap::new();
+            let mut length = 0;
+            for term in tokenize(document) {
+                *terms.entry(term).or_default() += 1;
+                length += 1;
+            }
+            for term in terms.keys() {
+                *doc_freq.entry(term.clone()).or_default() += 1;
+            }
+            doc_terms.push(terms);
+            doc_lengths.push(length);
+        }
+
+        let total: usize = doc_lengths.iter().sum();
+        let avg_length = (total as f32 / doc_lengths.len().max(1) as f32).max(1.0);
+        Self {
+            doc_terms,
+            doc_lengths,
+            avg_length,
+            doc_freq,
+        }
+    }
+
+    /// Score every document against `query`, scaled to 0-1 (all zero when nothing matches)
+    pub fn scores(&self, query: &str) -> Vec<f32> {
+        let mut query_terms: Vec<String> = tokenize(query).collect();
+        query_terms.sort();
+        query_terms.dedup();
+
+        let count = self.doc_terms.len() as f32;
+        let mut scores = vec![0.0f32; self.doc_terms.len()];
+        for term in &query_terms {
+            let Some(&doc_freq) = self.doc_freq.get(term) else {
+                continue;
+            };
+            let doc_freq = doc_freq as f32;
+            let idf = ((count - doc_freq + 0.5) / (doc_freq + 0.5) + 1.0).ln();
+            for (i, terms) in self.doc_terms.iter().enumerate() {
+                if let Some(&freq) = terms.get(term) {
+                    let freq = freq as f32;
+                    let length_norm = 1.0 - B + B * self.doc_lengths[i] as f32 / self.avg_length;
+                    scores[i] += idf * freq * (K1 + 1.0) / (freq + K1 * length_norm);
+                }
+            }
+        }
+
+        let best = scores.iter().copied().fold(0.0f32, f32::max);
+        if best > 0.0 {
+            for score in &mut scores {
+                *score /= best;
+            }
+        }
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25_favors_rare_exact_terms() {
+        let index = Bm25Index::new([
+            "Call parse_args to read the command line",
+            "The command line is read at startup",
+            "Configure the command line parser",
+        ]);
+
+        let scores = index.scores("parse_args command");
+        assert_eq!(scores[0], 1.0);
+        assert!(scores[1] < 0.5 && scores[2] < 0.5);
+
+        assert_eq!(index.scores("tokio"), vec![0.0, 0.0, 0.0]);
+        assert_eq!(
+            tokenize("HashMap::new(), parse_args").collect::<Vec<_>>(),
+            ["hashmap", "new", "parse_args"]
+        );
+    }
+}
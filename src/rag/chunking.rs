@@ -0,0 +1,544 @@
+//! Structure-aware chunking for code and markdown (`smart_search.adaptive_chunking`)
+//!
+//! Word-count chunking cuts functions and fenced code blocks in half. These chunkers split at
+//! blank lines instead, preferring the shallowest ones: between top-level items in code, and
+//! between paragraphs in markdown. A piece that is still larger than the chunk size is split
+//! again at its next-deepest blank lines, and only falls back to a line-by-line split when it
+//! has none left, and to a word split for a single line that is too long on its own. Markdown documents are first cut at their headings, so every chunk belongs to
+//! exactly one section.
+
+/// Leading chunk words matched against the document to find where a chunk starts
+const ANCHOR_WORDS: usize = 8;
+/// Shortest anchor tried after skipping a prefix, so a stray common word can't match
+const MIN_ANCHOR_WORDS: usize = 3;
+/// Leading chunk words that may be added text, such as a heading trail, rather than document text
+const MAX_PREFIX_WORDS: usize = 24;
+
+/// Approximate 1-based line range of each chunk within the document it was cut from
+///
+/// Chunks are matched word by word, so whitespace the chunker collapsed or a heading trail it
+/// prepended doesn't matter. Chunks must be in document order; one that can't be found gets
+/// `None`.
+pub fn line_ranges<'a>(
+    content: &str,
+    chunks: impl IntoIterator<Item = &'a str>,
+) -> Vec<Option<(usize, usize)>> {
+    let words: Vec<(&str, usize)> = content
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| line.split_whitespace().map(move |word| (word, i + 1)))
+        .collect();
+
+    let mut from = 0;
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let chunk_words: Vec<&str> = chunk.split_whitespace().collect();
+            if chunk_words.is_empty() {
+                return None;
+            }
+            let min_anchor = MIN_ANCHOR_WORDS.min(chunk_words.len());
+            let max_prefix = MAX_PREFIX_WORDS.min(chunk_words.len() - min_anchor);
+            let (start, prefix) = (from..words.len()).find_map(|start| {
+                (0..=max_prefix)
+                    .find(|&prefix| {
+                        let anchor = &chunk_words[prefix..];
+                        let anchor = &anchor[..ANCHOR_WORDS.min(anchor.len())];
+                        words
+                            .get(start..start + anchor.len())
+                            .is_some_and(|window| {
+                                window
+                                    .iter()
+                                    .map(|(word, _)| *word)
+                                    .eq(anchor.iter().copied())
+                            })
+                    })
+                    .map(|prefix| (start, prefix))
+            })?;
+            let end = (start + chunk_words.len() - prefix - 1).min(words.len() - 1);
+            // Overlapping chunks start after the previous one, not after its end
+            from = start + 1;
+            Some((words[start].1, words[end].1))
+        })
+        .collect()
+}
+
+/// Split source code so functions, classes and other items stay whole where possible
+///
+/// Sizes are in words, like [`crate::rag::embeddings::preprocessing::chunk_text`]. Blank lines
+/// are ranked by how deeply they are nested, using brace depth and the indentation of the next
+/// line, so a blank line between two methods of an `impl` or Python class is only used once
+/// the whole block is too large.
+pub fn chunk_code(text: &str, chunk_words: usize) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let boundaries = code_boundaries(&lines);
+    let mut chunks = Vec::new();
+    split_lines(&lines, &boundaries, chunk_words.max(1), &mut chunks);
+    chunks
+}
+
+/// Split markdown between paragraphs, keeping fenced code blocks intact
+///
+/// A fence is only split, at its own code boundaries, when it alone exceeds the chunk size.
+pub fn chunk_markdown(text: &str, chunk_words: usize) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let boundaries = markdown_boundaries(&lines);
+    let mut chunks = Vec::new();
+    split_lines(&lines, &boundaries, chunk_words.max(1), &mut chunks);
+    chunks
+}
+
+/// A markdown chunk and the headings it falls under
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionChunk {
+    /// Heading trail such as `Guide > Installation > macOS`; `None` before the first heading
+    pub section: Option<String>,
+    pub text: String,
+}
+
+/// Split markdown at its headings, then chunk each section with [`chunk_markdown`]
+///
+/// Every chunk's text starts with its heading trail, so the embedding carries the section
+/// context even when the heading itself landed in an earlier chunk. Headings inside fenced
+/// code blocks (such as `# comments`) are ignored, and a heading directly followed by a
+/// subheading produces no chunk of its own.
+pub fn chunk_markdown_sections(text: &str, chunk_words: usize) -> Vec<SectionChunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut chunks = Vec::new();
+    let mut trail: Vec<(usize, String)> = Vec::new();
+    let mut section: Option<String> = None;
+    let mut section_start = 0;
+    let mut fence = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(marker) = fence {
+            if line.trim_start().starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = fence_marker(line) {
+            fence = Some(marker);
+            continue;
+        }
+        let Some((level, title)) = heading(line) else {
+            continue;
+        };
+
+        push_section(
+            &lines[section_start..i],
+            section.as_deref(),
+            chunk_words,
+            &mut chunks,
+        );
+        trail.retain(|(parent, _)| *parent < level);
+        trail.push((level, title.to_string()));
+        section = Some(
+            trail
+                .iter()
+                .map(|(_, title)| title.as_str())
+                .collect::<Vec<_>>()
+                .join(" > "),
+        );
+        section_start = i;
+    }
+    push_section(
+        &lines[section_start..],
+        section.as_deref(),
+        chunk_words,
+        &mut chunks,
+    );
+
+    chunks
+}
+
+/// Level and text of an ATX heading (`## Installation`)
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    (!title.is_empty()).then_some((level, title))
+}
+
+fn push_section(
+    lines: &[&str],
+    section: Option<&str>,
+    chunk_words: usize,
+    chunks: &mut Vec<SectionChunk>,
+) {
+    // Every section but the preamble starts with its heading line
+    let body = if section.is_some() {
+        &lines[1..]
+    } else {
+        lines
+    };
+    if body.iter().all(|line| line.trim().is_empty()) {
+        return;
+    }
+
+    let trail_words = section.map_or(0, |section| section.split_whitespace().count());
+    let words = chunk_words.saturating_sub(trail_words).max(1);
+    for text in chunk_markdown(&lines.join("\n"), words) {
+        chunks.push(SectionChunk {
+            section: section.map(str::to_string),
+            text: match section {
+                Some(section) => format!("{}\n\n{}", section, text),
+                None => text,
+            },
+        });
+    }
+}
+
+/// Split depth of each line: `Some(depth)` for blank lines that may end a chunk
+type Boundaries = Vec<Option<usize>>;
+
+fn code_boundaries(lines: &[&str]) -> Boundaries {
+    let mut boundaries = Vec::with_capacity(lines.len());
+    let mut brace_depth = 0usize;
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            let next_indent = lines[i + 1..]
+                .iter()
+                .find(|next| !next.trim().is_empty())
+                .map_or(0, |next| indent_level(next));
+            boundaries.push(Some(brace_depth.max(next_indent)));
+            continue;
+        }
+
+        boundaries.push(None);
+        for c in line.chars() {
+            match c {
+                '{' => brace_depth += 1,
+                '}' => brace_depth = brace_depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    boundaries
+}
+
+fn markdown_boundaries(lines: &[&str]) -> Boundaries {
+    let mut boundaries = Vec::with_capacity(lines.len());
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some(fence) = fence_marker(lines[i]) else {
+            boundaries.push(lines[i].trim().is_empty().then_some(0));
+            i += 1;
+            continue;
+        };
+
+        // The fence runs to its closing marker, or to the end of an unterminated block
+        let close = lines[i + 1..]
+            .iter()
+            .position(|line| line.trim_start().starts_with(fence))
+            .map_or(lines.len(), |offset| i + 1 + offset);
+        boundaries.push(None);
+        boundaries.extend(
+            code_boundaries(&lines[i + 1..close])
+                .into_iter()
+                .map(|depth| depth.map(|depth| depth + 1)),
+        );
+        if close < lines.len() {
+            boundaries.push(None);
+        }
+        i = close + 1;
+    }
+
+    boundaries
+}
+
+fn fence_marker(line: &str) -> Option<&'static str> {
+    let trimmed = line.trim_start();
+    ["```", "~~~"]
+        .into_iter()
+        .find(|marker| trimmed.starts_with(marker))
+}
+
+/// Indentation in levels of four columns (a tab counts as one level)
+fn indent_level(line: &str) -> usize {
+    let columns: usize = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    columns / 4
+}
+
+fn word_count(lines: &[&str]) -> usize {
+    lines
+        .iter()
+        .map(|line| line.split_whitespace().count())
+        .sum()
+}
+
+fn push_chunk(lines: &[&str], chunks: &mut Vec<String>) {
+    let chunk = lines.join("\n");
+    let chunk = chunk.trim_matches('\n');
+    if !chunk.trim().is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+/// Split at the shallowest boundaries, packing neighbouring pieces up to `max_words`
+fn split_lines(
+    lines: &[&str],
+    boundaries: &[Option<usize>],
+    max_words: usize,
+    out: &mut Vec<String>,
+) {
+    if word_count(lines) <= max_words {
+        push_chunk(lines, out);
+        return;
+    }
+    let Some(depth) = boundaries.iter().flatten().min().copied() else {
+        split_by_lines(lines, max_words, out);
+        return;
+    };
+
+    // Pieces between the boundaries at this depth; deeper boundaries stay inside them
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, boundary) in boundaries.iter().enumerate() {
+        if *boundary == Some(depth) {
+            if i > start {
+                pieces.push(start..i);
+            }
+            start = i + 1;
+        }
+    }
+    if start < lines.len() {
+        pieces.push(start..lines.len());
+    }
+
+    let mut group: Option<std::ops::Range<usize>> = None;
+    let mut group_words = 0;
+    for piece in pieces {
+        let words = word_count(&lines[piece.clone()]);
+        if words > max_words {
+            if let Some(range) = group.take() {
+                push_chunk(&lines[range], out);
+            }
+            split_lines(&lines[piece.clone()], &boundaries[piece], max_words, out);
+            continue;
+        }
+        match &mut group {
+            Some(range) if group_words + words <= max_words => {
+                range.end = piece.end;
+                group_words += words;
+            }
+            _ => {
+                if let Some(range) = group.replace(piece) {
+                    push_chunk(&lines[range], out);
+                }
+                group_words = words;
+            }
+        }
+    }
+    if let Some(range) = group {
+        push_chunk(&lines[range], out);
+    }
+}
+
+/// Last resort for a block without blank lines: fill chunks line by line
+///
+/// A single line longer than `max_words`, such as minified code, is split by words.
+fn split_by_lines(lines: &[&str], max_words: usize, out: &mut Vec<String>) {
+    let mut start = 0;
+    let mut words = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let line_words = line.split_whitespace().count();
+        if line_words > max_words {
+            push_chunk(&lines[start..i], out);
+            let line_words: Vec<&str> = line.split_whitespace().collect();
+            out.extend(line_words.chunks(max_words).map(|words| words.join(" ")));
+            start = i + 1;
+            words = 0;
+            continue;
+        }
+        if words > 0 && words + line_words > max_words {
+            push_chunk(&lines[start..i], out);
+            start = i;
+            words = 0;
+        }
+        words += line_words;
+    }
+    push_chunk(&lines[start..], out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SOURCE: &str = r#"use std::collections::HashMap;
+
+/// Count words in a text
+fn count_words(text: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+
+    for word in text.split_whitespace() {
+        *counts.entry(word.to_lowercase()).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+fn most_common(counts: &HashMap<String, usize>) -> Option<(&String, &usize)> {
+    let mut entries: Vec<_> = counts.iter().collect();
+
+    entries.sort_by(|a, b| b.1.cmp(a.1));
+    entries.into_iter().next()
+}
+
+struct Pool {
+    size: usize,
+}
+
+impl Pool {
+    fn new(size: usize) -> Self {
+        Self { size }
+    }
+
+    fn resize(&mut self, size: usize) {
+        self.size = size;
+    }
+}
+"#;
+
+    fn balanced(chunk: &str) -> bool {
+        chunk.matches('{').count() == chunk.matches('}').count()
+    }
+
+    #[test]
+    fn test_chunk_code_keeps_rust_functions_whole() {
+        let chunks = chunk_code(RUST_SOURCE, 30);
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| balanced(chunk)), "{:#?}", chunks);
+        let count_words = chunks
+            .iter()
+            .find(|chunk| chunk.contains("fn count_words"))
+            .unwrap();
+        assert!(count_words.starts_with("/// Count words"));
+        assert!(count_words.trim_end().ends_with("counts\n}"));
+
+        // Too small for the whole impl, so it splits between the methods
+        let chunks = chunk_code(RUST_SOURCE, 16);
+        let new = chunks
+            .iter()
+            .find(|chunk| chunk.contains("fn new"))
+            .unwrap();
+        let resize = chunks
+            .iter()
+            .find(|chunk| chunk.contains("fn resize"))
+            .unwrap();
+        assert_ne!(new, resize);
+        assert!(new.contains("Self { size }"));
+        assert!(resize.contains("self.size = size;"));
+
+        assert_eq!(chunk_code(RUST_SOURCE, 1000), vec![RUST_SOURCE.trim_end()]);
+    }
+
+    #[test]
+    fn test_chunk_code_uses_python_indentation() {
+        let source = "def load(path):\n    data = read(path)\n\n    return parse(data)\n\n\ndef save(path, data):\n    write(path, dump(data))\n";
+        let chunks = chunk_code(source, 8);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].starts_with("def load") && chunks[0].ends_with("return parse(data)"));
+        assert!(chunks[1].starts_with("def save"));
+    }
+
+    #[test]
+    fn test_chunk_code_splits_an_oversized_line() {
+        let minified = (0..25)
+            .map(|i| format!("v{}=1;", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = format!("let a = 1;\n{}\nlet b = 2;", minified);
+        let chunks = chunk_code(&text, 10);
+
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks[0], "let a = 1;");
+        assert!(chunks.iter().all(|c| c.split_whitespace().count() <= 10));
+        assert!(chunks[1].starts_with("v0=1;"));
+        assert!(chunks[3].starts_with("v20=1;"));
+        assert_eq!(chunks[4], "let b = 2;");
+    }
+
+    #[test]
+    fn test_chunk_markdown_keeps_fences_intact() {
+        let markdown = "# Pools\n\nA pool reuses connections between requests.\n\n```rust\nlet pool = Pool::new(4);\n\nlet conn = pool.get().await?;\n```\n\nReturn connections by dropping them.\n";
+        let chunks = chunk_markdown(markdown, 10);
+
+        let fence = chunks
+            .iter()
+            .find(|chunk| chunk.contains("```rust"))
+            .unwrap();
+        assert!(fence.contains("Pool::new(4)") && fence.contains("pool.get()"));
+        assert_eq!(fence.matches("```").count(), 2);
+        assert!(chunks.last().unwrap().starts_with("Return connections"));
+    }
+
+    #[test]
+    fn test_chunk_markdown_sections_carry_heading_trail() {
+        let markdown = "Intro before any heading.\n\n# Guide\n\n## Installation\n\nDownload the installer.\n\n### macOS\n\n```sh\n# not a heading\nbrew install manx\n```\n\n## Usage\n\nRun manx.\n";
+        let chunks = chunk_markdown_sections(markdown, 100);
+
+        let sections: Vec<Option<&str>> = chunks
+            .iter()
+            .map(|chunk| chunk.section.as_deref())
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                None,
+                Some("Guide > Installation"),
+                Some("Guide > Installation > macOS"),
+                Some("Guide > Usage"),
+            ]
+        );
+        assert_eq!(chunks[0].text, "Intro before any heading.");
+        assert!(chunks[2]
+            .text
+            .starts_with("Guide > Installation > macOS\n\n### macOS"));
+        assert!(chunks[2]
+            .text
+            .contains("# not a heading\nbrew install manx"));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("## Setup ##"), Some((2, "Setup")));
+
+        let ranges = line_ranges(markdown, chunks.iter().map(|chunk| chunk.text.as_str()));
+        assert_eq!(
+            ranges,
+            [Some((1, 1)), Some((5, 7)), Some((9, 14)), Some((16, 18))]
+        );
+    }
+
+    #[test]
+    fn test_line_ranges_follow_overlapping_word_chunks() {
+        let text = "one two three four\nfive six seven eight\nnine ten eleven twelve\n";
+        let chunks = [
+            "one two three four five six",
+            "five six seven eight nine ten eleven twelve",
+        ];
+        assert_eq!(line_ranges(text, chunks), [Some((1, 2)), Some((2, 3))]);
+        assert_eq!(line_ranges(text, ["not in the text"]), [None]);
+
+        let chunks = chunk_code(RUST_SOURCE, 30);
+        let ranges = line_ranges(RUST_SOURCE, chunks.iter().map(String::as_str));
+        let count_words = chunks
+            .iter()
+            .position(|chunk| chunk.contains("fn count_words"))
+            .unwrap();
+        assert_eq!(ranges[count_words], Some((3, 12)));
+    }
+}
@@ -4,16 +4,43 @@
 //! Supports multiple embedding providers: hash-based (default), local models, and API services.
 //! Users can configure their preferred embedding method via `manx config --embedding-provider`.
 
+use crate::rag::providers::rate_limit::RetryableError;
 use crate::rag::providers::{
     custom, hash, huggingface, ollama, onnx, openai, EmbeddingProvider as ProviderTrait,
+    ProviderInfo,
 };
 use crate::rag::{EmbeddingConfig, EmbeddingProvider};
 use anyhow::{anyhow, Result};
 use lru::LruCache;
+use serde::Serialize;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
+
+/// Set once the hash-embedding notice has been printed (or suppressed) for this run
+static HASH_NOTICE_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Suppress the hash-embedding notice for the rest of this run (e.g. `--quiet`)
+pub fn suppress_hash_notice() {
+    HASH_NOTICE_SHOWN.store(true, Ordering::Relaxed);
+}
+
+/// Retries for a rate limited (429) or failing (5xx) API before a batch is given up on
+const MAX_THROTTLED_RETRIES: u32 = 6;
+
+/// Longest `Retry-After` honored; longer requests are capped so indexing keeps moving
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Chunks that needed retries while indexing, and chunks that never got an embedding
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkOutcomes {
+    pub retried: usize,
+    pub failed: usize,
+}
 
 /// Text embedding model wrapper with configurable providers and LRU cache
 /// Supports hash-based embeddings (default), local ONNX models, and API services.
@@ -23,6 +50,8 @@ pub struct EmbeddingModel {
     config: EmbeddingConfig,
     /// LRU cache for recent embeddings (text hash -> embedding vector)
     cache: Mutex<LruCache<u64, Vec<f32>>>,
+    retried_chunks: AtomicUsize,
+    failed_chunks: AtomicUsize,
 }
 
 impl EmbeddingModel {
@@ -44,6 +73,7 @@ impl EmbeddingModel {
             config.provider
         );
 
+        let timeout = std::time::Duration::from_secs(config.timeout_seconds);
         let provider: Box<dyn ProviderTrait + Send + Sync> = match &config.provider {
             EmbeddingProvider::Hash => {
                 log::info!("Using hash-based embeddings (default provider)");
@@ -56,8 +86,11 @@ impl EmbeddingModel {
             }
             EmbeddingProvider::Ollama(model_name) => {
                 log::info!("Connecting to Ollama model: {}", model_name);
-                let ollama_provider =
-                    ollama::OllamaProvider::new(model_name.clone(), config.endpoint.clone());
+                let ollama_provider = ollama::OllamaProvider::new(
+                    model_name.clone(),
+                    config.endpoint.clone(),
+                    timeout,
+                );
                 // Test connection
                 ollama_provider.health_check().await?;
                 Box::new(ollama_provider)
@@ -68,9 +101,25 @@ impl EmbeddingModel {
                     anyhow!("OpenAI API key required. Use 'manx config --embedding-api-key <key>'")
                 })?;
                 let openai_provider =
-                    openai::OpenAiProvider::new(api_key.clone(), model_name.clone());
+                    openai::OpenAiProvider::new(api_key.clone(), model_name.clone(), timeout)
+                        .with_rate_limit(config.requests_per_minute, config.tokens_per_minute);
                 Box::new(openai_provider)
             }
+            EmbeddingProvider::AzureOpenAI(deployment) => {
+                log::info!(
+                    "Connecting to Azure OpenAI deployment: {}",
+                    deployment.deployment
+                );
+                let api_key = config.api_key.as_ref().ok_or_else(|| {
+                    anyhow!(
+                        "Azure OpenAI API key required. Use 'manx config --embedding-api-key <key>'"
+                    )
+                })?;
+                Box::new(
+                    openai::OpenAiProvider::azure(api_key.clone(), deployment.clone(), timeout)
+                        .with_rate_limit(config.requests_per_minute, config.tokens_per_minute),
+                )
+            }
             EmbeddingProvider::HuggingFace(model_name) => {
                 log::info!("Connecting to HuggingFace model: {}", model_name);
                 let api_key = config.api_key.as_ref().ok_or_else(|| {
@@ -78,14 +127,22 @@ impl EmbeddingModel {
                         "HuggingFace API key required. Use 'manx config --embedding-api-key <key>'"
                     )
                 })?;
-                let hf_provider =
-                    huggingface::HuggingFaceProvider::new(api_key.clone(), model_name.clone());
+                let hf_provider = huggingface::HuggingFaceProvider::new(
+                    api_key.clone(),
+                    model_name.clone(),
+                    timeout,
+                )
+                .with_rate_limit(config.requests_per_minute, config.tokens_per_minute);
                 Box::new(hf_provider)
             }
             EmbeddingProvider::Custom(endpoint) => {
                 log::info!("Connecting to custom endpoint: {}", endpoint);
-                let custom_provider =
-                    custom::CustomProvider::new(endpoint.clone(), config.api_key.clone());
+                let custom_provider = custom::CustomProvider::new(
+                    endpoint.clone(),
+                    config.api_key.clone(),
+                    config.model.clone(),
+                    timeout,
+                );
                 Box::new(custom_provider)
             }
         };
@@ -98,6 +155,8 @@ impl EmbeddingModel {
             provider,
             config,
             cache,
+            retried_chunks: AtomicUsize::new(0),
+            failed_chunks: AtomicUsize::new(0),
         })
     }
 
@@ -121,7 +180,7 @@ impl EmbeddingModel {
 
         // Cache miss - generate embedding with retry logic
         log::debug!("Cache miss for text embedding, generating...");
-        let embedding = Self::retry_with_backoff(
+        let (embedding, _) = Self::retry_with_backoff(
             || async { self.provider.embed_text(text).await },
             3, // max retries
         )
@@ -136,8 +195,11 @@ impl EmbeddingModel {
         Ok(embedding)
     }
 
-    /// Retry async operation with exponential backoff
-    async fn retry_with_backoff<F, Fut, T>(mut operation: F, max_retries: u32) -> Result<T>
+    /// Retry async operation with exponential backoff, returning how many retries it took
+    ///
+    /// Rate limits and server errors wait as long as the API's `Retry-After` asks (or back
+    /// off from one second) and get up to `MAX_THROTTLED_RETRIES` attempts.
+    async fn retry_with_backoff<F, Fut, T>(mut operation: F, max_retries: u32) -> Result<(T, u32)>
     where
         F: FnMut() -> Fut,
         Fut: std::future::Future<Output = Result<T>>,
@@ -145,28 +207,53 @@ impl EmbeddingModel {
         let mut retries = 0;
         loop {
             match operation().await {
-                Ok(result) => return Ok(result),
+                Ok(result) => return Ok((result, retries)),
                 Err(e) => {
                     retries += 1;
-                    if retries > max_retries {
-                        log::error!("Operation failed after {} retries: {}", max_retries, e);
+                    let throttled = e.downcast_ref::<RetryableError>();
+                    let limit = match throttled {
+                        Some(_) => MAX_THROTTLED_RETRIES,
+                        None => max_retries,
+                    };
+                    if retries > limit {
+                        log::error!("Operation failed after {} retries: {}", limit, e);
                         return Err(e);
                     }
 
-                    let delay_ms = 100 * (2_u64.pow(retries - 1)); // 100ms, 200ms, 400ms
+                    let delay = match throttled {
+                        Some(error) => error
+                            .retry_after
+                            .map(|wait| wait.min(MAX_RETRY_AFTER))
+                            .unwrap_or(Duration::from_secs(1 << (retries - 1))),
+                        None => Duration::from_millis(100 * (2_u64.pow(retries - 1))), // 100ms, 200ms, 400ms
+                    };
                     log::warn!(
-                        "Operation failed (attempt {}/{}), retrying in {}ms: {}",
+                        "Operation failed (attempt {}/{}), retrying in {:?}: {}",
                         retries,
-                        max_retries,
-                        delay_ms,
+                        limit,
+                        delay,
                         e
                     );
-                    tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+                    tokio::time::sleep(delay).await;
                 }
             }
         }
     }
 
+    /// Count chunks that were embedded only after a retry, and chunks that never were
+    pub(crate) fn record_chunk_outcomes(&self, retried: usize, failed: usize) {
+        self.retried_chunks.fetch_add(retried, Ordering::Relaxed);
+        self.failed_chunks.fetch_add(failed, Ordering::Relaxed);
+    }
+
+    /// Chunk outcomes recorded since the last call, for an indexing summary
+    pub fn take_chunk_outcomes(&self) -> ChunkOutcomes {
+        ChunkOutcomes {
+            retried: self.retried_chunks.swap(0, Ordering::Relaxed),
+            failed: self.failed_chunks.swap(0, Ordering::Relaxed),
+        }
+    }
+
     /// Hash text for cache key
     fn hash_text(text: &str) -> u64 {
         let mut hasher = DefaultHasher::new();
@@ -174,74 +261,59 @@ impl EmbeddingModel {
         hasher.finish()
     }
 
-    /// Generate embeddings for multiple texts (batch processing)
-    /// More efficient than calling embed_text repeatedly - uses native batch for ONNX providers
+    /// Generate embeddings for multiple texts, one per input in the same order
+    ///
+    /// Cached texts are answered from the LRU cache; the rest go to the provider in a single
+    /// `embed_batch` call, which API providers send as one request.
     pub async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        if texts.is_empty() {
-            return Ok(vec![]);
+        if texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(anyhow!("Cannot embed empty text"));
         }
 
-        // Check which provider we're using and delegate appropriately
-        match &self.config.provider {
-            EmbeddingProvider::Onnx(_) => {
-                // Use ONNX's native batch processing - bypasses cache for batch operations
-                // This is more efficient as it processes all texts in one go
-                log::debug!(
-                    "Using ONNX native batch processing for {} texts",
-                    texts.len()
-                );
-
-                // Get the ONNX provider and call its batch method directly
-                // Use the as_any method to downcast the trait object
-                if let Some(onnx_provider) =
-                    self.provider.as_any().downcast_ref::<onnx::OnnxProvider>()
-                {
-                    return onnx_provider.embed_batch(texts).await;
+        let mut embeddings: Vec<Option<Vec<f32>>> = Vec::with_capacity(texts.len());
+        let mut misses = Vec::new();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            for (i, text) in texts.iter().enumerate() {
+                let cached = cache.get(&Self::hash_text(text)).cloned();
+                if cached.is_none() {
+                    misses.push(i);
                 }
-
-                // Fallback if downcast fails (shouldn't happen)
-                log::warn!("Failed to downcast ONNX provider, using sequential processing");
-                self.embed_batch_sequential(texts).await
-            }
-            _ => {
-                // For other providers, use sequential processing with cache
-                self.embed_batch_sequential(texts).await
+                embeddings.push(cached);
             }
         }
-    }
-
-    /// Sequential batch processing with caching (fallback for non-ONNX providers)
-    async fn embed_batch_sequential(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        let mut embeddings = Vec::with_capacity(texts.len());
-        let mut failed_count = 0;
 
-        for (i, text) in texts.iter().enumerate() {
-            match self.embed_text(text).await {
-                Ok(embedding) => embeddings.push(embedding),
-                Err(e) => {
-                    log::warn!("Failed to embed text {} in batch: {}", i, e);
-                    failed_count += 1;
-                    continue;
-                }
+        if !misses.is_empty() {
+            log::debug!(
+                "Embedding batch of {} texts ({} cached)",
+                misses.len(),
+                texts.len() - misses.len()
+            );
+            let miss_texts: Vec<&str> = misses.iter().map(|&i| texts[i]).collect();
+            let (generated, retries) = Self::retry_with_backoff(
+                || async { self.provider.embed_batch(&miss_texts).await },
+                3, // max retries
+            )
+            .await?;
+            if retries > 0 {
+                self.record_chunk_outcomes(miss_texts.len(), 0);
+            }
+            if generated.len() != miss_texts.len() {
+                return Err(anyhow!(
+                    "Provider returned {} embeddings for {} texts",
+                    generated.len(),
+                    miss_texts.len()
+                ));
             }
-        }
-
-        if embeddings.is_empty() {
-            return Err(anyhow!(
-                "Batch embedding failed for all {} texts",
-                texts.len()
-            ));
-        }
 
-        if failed_count > 0 {
-            log::warn!(
-                "Batch embedding completed with {} failures out of {} texts",
-                failed_count,
-                texts.len()
-            );
+            let mut cache = self.cache.lock().unwrap();
+            for (i, embedding) in misses.into_iter().zip(generated) {
+                cache.put(Self::hash_text(texts[i]), embedding.clone());
+                embeddings[i] = Some(embedding);
+            }
         }
 
-        Ok(embeddings)
+        Ok(embeddings.into_iter().flatten().collect())
     }
 
     /// Get the dimension of embeddings produced by this model
@@ -264,6 +336,25 @@ impl EmbeddingModel {
         &self.config
     }
 
+    /// Check if this model uses the basic hash-based provider
+    pub fn is_hash_provider(&self) -> bool {
+        matches!(self.config.provider, EmbeddingProvider::Hash)
+    }
+
+    /// Print a one-time (per run) notice when low-quality hash embeddings are in use
+    pub fn notify_if_hash(&self) {
+        if !self.is_hash_provider() || HASH_NOTICE_SHOWN.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        use colored::Colorize;
+        eprintln!(
+            "{}",
+            "Using basic hash embeddings; for better results run `manx embedding download <model>` or configure OpenAI."
+                .dimmed()
+        );
+    }
+
     /// Calculate cosine similarity between two embeddings
     pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
         if a.len() != b.len() {
@@ -363,6 +454,64 @@ impl EmbeddingModel {
     }
 }
 
+/// Result of loading and probing the configured embedding model, for `manx embedding status`
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingStatus {
+    pub provider: EmbeddingProvider,
+    pub dimension: usize,
+    pub model_path: Option<PathBuf>,
+    pub api_key_configured: bool,
+    pub endpoint_configured: bool,
+    /// Error from loading the model; the checks below are skipped when set
+    pub load_error: Option<String>,
+    pub health_check_passed: bool,
+    pub health_check_error: Option<String>,
+    /// Dimension reported by the loaded model, which may differ from `dimension`
+    pub detected_dimension: Option<usize>,
+    pub dimension_error: Option<String>,
+    pub provider_info: Option<ProviderInfo>,
+}
+
+impl EmbeddingStatus {
+    /// Load the model described by `config` and run its health check and dimension detection
+    pub async fn check(config: &EmbeddingConfig) -> Self {
+        let mut status = Self {
+            provider: config.provider.clone(),
+            dimension: config.dimension,
+            model_path: config.model_path.clone(),
+            api_key_configured: config.api_key.is_some(),
+            endpoint_configured: config.endpoint.is_some(),
+            load_error: None,
+            health_check_passed: false,
+            health_check_error: None,
+            detected_dimension: None,
+            dimension_error: None,
+            provider_info: None,
+        };
+
+        let model = match EmbeddingModel::new_with_config(config.clone()).await {
+            Ok(model) => model,
+            Err(e) => {
+                status.load_error = Some(e.to_string());
+                return status;
+            }
+        };
+        status.provider_info = Some(model.get_provider_info());
+
+        if let Err(e) = model.health_check().await {
+            status.health_check_error = Some(e.to_string());
+            return status;
+        }
+        status.health_check_passed = true;
+
+        match model.get_dimension().await {
+            Ok(dim) => status.detected_dimension = Some(dim),
+            Err(e) => status.dimension_error = Some(e.to_string()),
+        }
+        status
+    }
+}
+
 /// Utility functions for text preprocessing
 pub mod preprocessing {
     /// Clean and normalize text for embedding
@@ -640,6 +789,94 @@ mod tests {
         assert!(embedding.iter().any(|&x| x != 0.0));
     }
 
+    #[tokio::test]
+    async fn test_embed_batch_matches_inputs_in_order() {
+        let model = EmbeddingModel::new().await.unwrap();
+        let cached = model.embed_text("tokio runtime").await.unwrap();
+
+        let texts = ["react hooks", "tokio runtime", "django models"];
+        let batch = model.embed_batch(&texts).await.unwrap();
+
+        assert_eq!(batch.len(), texts.len());
+        assert_eq!(batch[1], cached);
+        assert_eq!(batch[0], model.embed_text("react hooks").await.unwrap());
+        assert!(model.embed_batch(&["ok", " "]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_embed_batch_waits_out_rate_limits() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let replies = [
+                ("429 Too Many Requests", "Retry-After: 0\r\n", "{}".to_string()),
+                (
+                    "200 OK",
+                    "",
+                    r#"{"data":[{"embedding":[0.5,0.5],"index":1},{"embedding":[1.0,0.0],"index":0}],"model":"embed","usage":{"prompt_tokens":2,"total_tokens":2}}"#
+                        .to_string(),
+                ),
+            ];
+            for (status, headers, body) in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 8192];
+                let _ = socket.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let model = EmbeddingModel::new_with_config(EmbeddingConfig {
+            provider: EmbeddingProvider::AzureOpenAI(crate::rag::azure::AzureDeployment::new(
+                &format!("http://{}", addr),
+                "embed",
+                None,
+            )),
+            api_key: Some("azure-key".to_string()),
+            ..EmbeddingConfig::default()
+        })
+        .await
+        .unwrap();
+
+        let batch = model.embed_batch(&["first", "second"]).await.unwrap();
+        assert_eq!(batch, vec![vec![1.0, 0.0], vec![0.5, 0.5]]);
+        assert_eq!(
+            model.take_chunk_outcomes(),
+            ChunkOutcomes {
+                retried: 2,
+                failed: 0
+            }
+        );
+        assert_eq!(model.take_chunk_outcomes(), ChunkOutcomes::default());
+    }
+
+    #[tokio::test]
+    async fn test_embedding_status_reports_checks() {
+        let status = EmbeddingStatus::check(&EmbeddingConfig::default()).await;
+        assert!(status.load_error.is_none());
+        assert!(status.health_check_passed);
+        assert_eq!(status.detected_dimension, Some(status.dimension));
+        assert!(status.provider_info.is_some());
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["provider"], "Hash");
+        assert_eq!(json["endpoint_configured"], false);
+    }
+
+    #[tokio::test]
+    async fn test_hash_provider_detection() {
+        let model = EmbeddingModel::new().await.unwrap();
+        assert!(model.is_hash_provider());
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 2.0, 3.0];
@@ -8,33 +8,27 @@ use chrono::{DateTime, Utc};
 use docrawl::{crawl, Config, CrawlConfig};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 use walkdir::WalkDir;
 
+use crate::rag::chunking::{self, SectionChunk};
 use crate::rag::embeddings::preprocessing;
+use crate::rag::robots::{self, Robots};
 use crate::rag::{DocumentChunk, DocumentMetadata, RagConfig, SourceType};
 
 /// Document indexer for the RAG system
 pub struct Indexer {
     config: RagConfig,
     index_path: PathBuf,
+    robots: Arc<Robots>,
 }
 
 impl Indexer {
     /// Create new indexer with configuration
     pub fn new(config: &RagConfig) -> Result<Self> {
-        let index_path = if config.index_path.to_string_lossy().starts_with("~") {
-            // Expand home directory
-            let home = std::env::var("HOME")
-                .or_else(|_| std::env::var("USERPROFILE"))
-                .map_err(|_| anyhow!("Cannot determine home directory"))?;
-            let path_str = config.index_path.to_string_lossy();
-            let without_tilde = path_str.strip_prefix("~/").unwrap_or(&path_str[1..]);
-            PathBuf::from(home).join(without_tilde)
-        } else {
-            config.index_path.clone()
-        };
+        let index_path = crate::config::expand_path(&config.index_path);
 
         // Ensure index directory exists
         if !index_path.exists() {
@@ -45,6 +39,7 @@ impl Indexer {
         Ok(Self {
             config: config.clone(),
             index_path,
+            robots: Arc::new(Robots::new(config.ignore_robots)),
         })
     }
 
@@ -53,17 +48,39 @@ impl Indexer {
         &self.index_path
     }
 
+    /// Fail when robots.txt disallows the page a user asked to index
+    async fn ensure_robots_allow(&self, url: &str) -> Result<()> {
+        if self.robots.allows(url).await {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "robots.txt disallows {}; use --ignore-robots if you own the site",
+                url
+            ))
+        }
+    }
+
+    /// The URLs robots.txt allows, in order
+    pub async fn robots_allowed(&self, urls: Vec<String>) -> Vec<String> {
+        let mut allowed = Vec::with_capacity(urls.len());
+        for url in urls {
+            if self.robots.allows(&url).await {
+                allowed.push(url);
+            }
+        }
+        allowed
+    }
+
     /// Index a single document
     pub fn index_document(&self, path: PathBuf) -> Result<Vec<DocumentChunk>> {
         index_document(path, &self.config)
     }
 
-    /// Index all documents in a directory
-    pub fn index_directory(&self, dir_path: PathBuf) -> Result<Vec<DocumentChunk>> {
-        let documents = find_documents(&dir_path)?;
+    /// Index the given files, skipping (and logging) any that fail
+    pub fn index_files(&self, files: &[PathBuf]) -> Vec<DocumentChunk> {
         let mut all_chunks = Vec::new();
 
-        for doc_path in documents {
+        for doc_path in files {
             match self.index_document(doc_path.clone()) {
                 Ok(mut chunks) => all_chunks.append(&mut chunks),
                 Err(e) => {
@@ -74,11 +91,11 @@ impl Indexer {
         }
 
         log::info!(
-            "Indexed {} chunks from {} directory",
+            "Indexed {} chunks from {} files",
             all_chunks.len(),
-            dir_path.display()
+            files.len()
         );
-        Ok(all_chunks)
+        all_chunks
     }
 
     /// Index a single URL without invoking the crawler (depth 0 semantics)
@@ -87,10 +104,10 @@ impl Indexer {
 
         // Validate URL
         let _ = Url::parse(url).map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+        self.ensure_robots_allow(url).await?;
 
         // Fetch page
-        let client = reqwest::Client::builder()
-            .user_agent("Manx/0.5.0 (Single Page Indexer)")
+        let client = crate::http_client::builder()
             .timeout(Duration::from_secs(30))
             .build()?;
 
@@ -100,48 +117,11 @@ impl Indexer {
         }
         let html = resp.text().await?;
 
-        // Extract title (best-effort)
-        let page_title = extract_html_title(&html).or_else(|| extract_h1(&html));
-
-        // Convert HTML to plain text (markdown-like)
-        let text = clean_html_to_text(&html);
-        if text.trim().is_empty() {
+        let document_chunks = html_page_to_chunks(url, &html, "single-page", &self.config);
+        if document_chunks.is_empty() {
             return Err(anyhow!("Fetched page contains no indexable text: {}", url));
         }
 
-        // Chunk the content
-        let chunks = chunk_content(&text, DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_OVERLAP);
-
-        // Build metadata
-        let mut tags = vec!["documentation".to_string(), "single-page".to_string()];
-        if let Some(domain) = extract_domain_from_url(url) {
-            tags.push(domain);
-        }
-
-        let metadata = DocumentMetadata {
-            file_type: "html".to_string(),
-            size: text.len() as u64,
-            modified: Utc::now(),
-            tags,
-            language: Some("en".to_string()),
-        };
-
-        // Create document chunks
-        let mut document_chunks = Vec::new();
-        for (i, chunk_content) in chunks.into_iter().enumerate() {
-            let chunk = DocumentChunk {
-                id: format!("{}_{}", url, i),
-                content: preprocessing::clean_text(&chunk_content),
-                source_path: PathBuf::from(url),
-                source_type: SourceType::Web,
-                title: page_title.clone(),
-                section: None,
-                chunk_index: i,
-                metadata: metadata.clone(),
-            };
-            document_chunks.push(chunk);
-        }
-
         log::info!(
             "Indexed {} chunks from single URL without crawl: {}",
             document_chunks.len(),
@@ -150,6 +130,94 @@ impl Indexer {
         Ok(document_chunks)
     }
 
+    /// Fetch a page and list it with the same-host pages it links to, without indexing anything
+    pub async fn discover_links(&self, url: &str, max_pages: Option<usize>) -> Result<Vec<String>> {
+        let client = crate::http_client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        let resp = client.get(url).send().await?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("Failed to fetch URL {}: {}", url, resp.status()));
+        }
+        let final_url = resp.url().clone();
+        let html = resp.text().await?;
+
+        let mut pages = vec![final_url.to_string()];
+        pages.extend(
+            same_host_links(&final_url, &html, max_pages)
+                .into_iter()
+                .map(|link| link.to_string()),
+        );
+        Ok(self.robots_allowed(pages).await)
+    }
+
+    /// Fetch a sitemap and return every listed page URL, following nested sitemap indexes
+    pub async fn fetch_sitemap_urls(
+        &self,
+        client: &reqwest::Client,
+        sitemap_url: &str,
+    ) -> Result<Vec<String>> {
+        use std::collections::{HashSet, VecDeque};
+
+        let mut pending = VecDeque::from([sitemap_url.to_string()]);
+        let mut visited_sitemaps = HashSet::new();
+        let mut seen_pages = HashSet::new();
+        let mut pages = Vec::new();
+
+        while let Some(current) = pending.pop_front() {
+            if !visited_sitemaps.insert(current.clone()) {
+                continue;
+            }
+
+            let resp = client.get(&current).send().await?;
+            if !resp.status().is_success() {
+                // Nested sitemaps are best-effort; only the root one must load
+                if current == sitemap_url {
+                    return Err(anyhow!(
+                        "Failed to fetch sitemap {}: {}",
+                        current,
+                        resp.status()
+                    ));
+                }
+                log::warn!("Skipping sitemap {}: {}", current, resp.status());
+                continue;
+            }
+            let xml = resp.text().await?;
+
+            let (page_urls, child_sitemaps) = parse_sitemap_xml(&xml);
+            log::debug!(
+                "Sitemap {}: {} pages, {} nested sitemaps",
+                current,
+                page_urls.len(),
+                child_sitemaps.len()
+            );
+            pending.extend(child_sitemaps);
+            for page in page_urls {
+                if seen_pages.insert(page.clone()) {
+                    pages.push(page);
+                }
+            }
+        }
+
+        Ok(pages)
+    }
+
+    /// Fetch and chunk a single page listed in a sitemap
+    pub async fn index_sitemap_page(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<Vec<DocumentChunk>> {
+        self.robots.wait_turn(url).await;
+        let resp = client.get(url).send().await?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("Failed to fetch URL {}: {}", url, resp.status()));
+        }
+        let html = resp.text().await?;
+        Ok(html_page_to_chunks(url, &html, "sitemap", &self.config))
+    }
+
     /// Index content from a URL
     #[allow(dead_code)]
     pub async fn index_url(&self, url: String) -> Result<Vec<DocumentChunk>> {
@@ -193,12 +261,14 @@ impl Indexer {
 
         // Parse the URL
         let base_url = Url::parse(&url)?;
+        let (rate_limit_per_sec, concurrency) =
+            robots::docrawl_pacing(self.robots.crawl_delay(&base_url).await, 10, 4);
 
         // Configure doccrawl with silenced output so Manx renders progress
         let config = CrawlConfig {
             base_url,
             output_dir: temp_dir.clone(),
-            user_agent: "Manx/0.5.0 (Documentation Crawler)".to_string(),
+            user_agent: crate::http_client::user_agent(),
             max_depth: if let Some(depth) = crawl_depth {
                 Some(depth as usize)
             } else if crawl_all {
@@ -207,9 +277,9 @@ impl Indexer {
                 Some(3) // Default depth
             },
             silence: true, // Silence docrawl; Manx renders its own progress UI
-            rate_limit_per_sec: 10,
+            rate_limit_per_sec,
             follow_sitemaps: true,
-            concurrency: 4,
+            concurrency,
             timeout: Some(Duration::from_secs(30)),
             resume: false,
             config: Config::default(),
@@ -299,8 +369,9 @@ impl Indexer {
         md_file: &Path,
         base_url: &str,
     ) -> Result<Vec<DocumentChunk>> {
-        // Read the markdown content
-        let content = std::fs::read_to_string(md_file)?;
+        // Read the markdown content, minus the front matter docrawl writes
+        let mut content = std::fs::read_to_string(md_file)?;
+        let front_matter = strip_front_matter(&mut content);
 
         if content.trim().is_empty() {
             return Err(anyhow!(
@@ -310,22 +381,27 @@ impl Indexer {
         }
 
         // Create metadata for this markdown file
-        let metadata = self.create_markdown_metadata(md_file, &content, base_url)?;
+        let mut metadata = self.create_markdown_metadata(md_file, &content, base_url)?;
+        merge_tags(&mut metadata.tags, front_matter.tags);
 
         // Detect document structure (title, sections) from markdown content
         let (title, sections) = detect_structure(&content, md_file);
+        let title = front_matter.title.or(title);
 
         // Derive a logical page URL from the file path and base URL
         let page_url = self.derive_page_url(md_file, base_url);
 
         // Chunk the content
-        let chunks = chunk_content(&content, DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_OVERLAP);
+        let chunks = chunk_document(&content, md_file, &self.config);
 
         // Create document chunks
         let mut document_chunks = Vec::new();
-        for (i, chunk_content) in chunks.into_iter().enumerate() {
-            // Try to determine which section this chunk belongs to
-            let section = find_section_for_chunk(&chunk_content, &sections);
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let chunk_content = chunk.text;
+            // Use the heading trail, or try to determine which section this chunk belongs to
+            let section = chunk
+                .section
+                .or_else(|| find_section_for_chunk(&chunk_content, &sections));
 
             let chunk = DocumentChunk {
                 id: format!("{}_{}", page_url, i),
@@ -374,11 +450,12 @@ impl Indexer {
             modified: modified_datetime,
             tags,
             language,
+            line_range: None,
         })
     }
 
     /// Derive a logical page URL from the markdown file path
-    fn derive_page_url(&self, md_file: &Path, base_url: &str) -> String {
+    pub(crate) fn derive_page_url(&self, md_file: &Path, base_url: &str) -> String {
         // Get the relative path from the temp directory
         let file_name = md_file
             .file_stem()
@@ -400,15 +477,14 @@ impl Indexer {
         max_pages: Option<usize>,
     ) -> Result<Vec<DocumentChunk>> {
         use indicatif::{ProgressBar, ProgressStyle};
-        use scraper::{Html, Selector};
         use tokio::task::JoinSet;
 
-        let client = reqwest::Client::builder()
-            .user_agent("Manx/0.5.0 (Shallow Crawler)")
+        let client = crate::http_client::builder()
             .timeout(Duration::from_secs(30))
             .build()?;
 
         // Fetch base page
+        self.ensure_robots_allow(url).await?;
         let resp = client.get(url).send().await?;
         if !resp.status().is_success() {
             return Err(anyhow!("Failed to fetch URL {}: {}", url, resp.status()));
@@ -416,94 +492,37 @@ impl Indexer {
         let final_url = resp.url().clone();
         let base_html = resp.text().await?;
 
-        eprintln!("\n🌐 Shallow crawl starting: {}", url);
+        progress_note!("\n🌐 Shallow crawl starting: {}", url);
 
         // Helper to build chunks from HTML
         let mut all_chunks: Vec<DocumentChunk> = Vec::new();
         let make_chunks = |page_url: &str, html: &str| -> Result<Vec<DocumentChunk>> {
-            let page_title = extract_html_title(html).or_else(|| extract_h1(html));
-            let text = clean_html_to_text(html);
-            if text.trim().is_empty() {
-                return Ok(vec![]);
-            }
-            let chunks = chunk_content(&text, DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_OVERLAP);
-
-            // Build metadata
-            let mut tags = vec!["documentation".to_string(), "shallow-crawl".to_string()];
-            if let Some(domain) = extract_domain_from_url(page_url) {
-                tags.push(domain);
-            }
-            let metadata = DocumentMetadata {
-                file_type: "html".to_string(),
-                size: text.len() as u64,
-                modified: Utc::now(),
-                tags,
-                language: Some("en".to_string()),
-            };
-
-            let mut docs = Vec::new();
-            for (i, chunk_content) in chunks.into_iter().enumerate() {
-                docs.push(DocumentChunk {
-                    id: format!("{}_{}", page_url, i),
-                    content: preprocessing::clean_text(&chunk_content),
-                    source_path: PathBuf::from(page_url),
-                    source_type: SourceType::Web,
-                    title: page_title.clone(),
-                    section: None,
-                    chunk_index: i,
-                    metadata: metadata.clone(),
-                });
-            }
-            Ok(docs)
+            Ok(html_page_to_chunks(
+                page_url,
+                html,
+                "shallow-crawl",
+                &self.config,
+            ))
         };
 
         // Include base page
-        eprintln!("🔎 Fetching base page: {}", final_url);
+        progress_note!("🔎 Fetching base page: {}", final_url);
         all_chunks.extend(make_chunks(final_url.as_str(), &base_html)?);
 
-        // Parse links from base page
-        let doc = Html::parse_document(&base_html);
-        let a_sel = Selector::parse("a[href]").unwrap();
-        let base_host = final_url.host_str().unwrap_or("");
-        let norm = |h: &str| h.strip_prefix("www.").unwrap_or(h).to_string();
-        let base_norm = norm(base_host);
-
-        use std::collections::HashSet;
-        let mut seen: HashSet<String> = HashSet::new();
-        seen.insert(final_url.as_str().to_string());
-
-        let page_cap = max_pages.unwrap_or(usize::MAX);
-        let mut targets: Vec<url::Url> = Vec::new();
-        for a in doc.select(&a_sel) {
-            if let Some(href) = a.value().attr("href") {
-                // Resolve relative links
-                if let Ok(abs) = final_url.join(href) {
-                    // Same-host (treat www. as equivalent)
-                    if let Some(h) = abs.host_str() {
-                        if norm(h) != base_norm {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-                    // Deduplicate
-                    let s = abs.as_str().to_string();
-                    if !seen.insert(s.clone()) {
-                        continue;
-                    }
-                    targets.push(abs);
-                    if seen.len() >= page_cap {
-                        break;
-                    }
-                }
+        let targets = same_host_links(&final_url, &base_html, max_pages);
+        let mut allowed = Vec::with_capacity(targets.len());
+        for target in targets {
+            if self.robots.allows(target.as_str()).await {
+                allowed.push(target);
             }
         }
+        let targets = allowed;
 
-        eprintln!("🔗 Found {} same-host links", targets.len());
+        progress_note!("🔗 Found {} same-host links", targets.len());
 
         // Fetch first-level pages with small concurrency
         let pb = if !targets.is_empty() {
-            let pb = ProgressBar::new(targets.len() as u64);
+            let pb = crate::rag::progress::attach(ProgressBar::new(targets.len() as u64));
             pb.set_style(
                 ProgressStyle::default_bar()
                     .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} pages ({percent}%) | {msg}")
@@ -519,7 +538,9 @@ impl Indexer {
         let client2 = client.clone();
         for t in targets.into_iter() {
             let client3 = client2.clone();
+            let robots = self.robots.clone();
             set.spawn(async move {
+                robots.wait_turn(t.as_str()).await;
                 let r = client3.get(t.clone()).send().await.ok()?;
                 if !r.status().is_success() {
                     return None;
@@ -550,6 +571,77 @@ impl Indexer {
     }
 }
 
+/// Same-host links on a page (treating `www.` as the same host), deduplicated
+///
+/// `max_pages` caps the page itself plus its links, as the shallow crawl does.
+fn same_host_links(page_url: &Url, html: &str, max_pages: Option<usize>) -> Vec<Url> {
+    use scraper::{Html, Selector};
+    use std::collections::HashSet;
+
+    let doc = Html::parse_document(html);
+    let a_sel = Selector::parse("a[href]").unwrap();
+    let norm = |h: &str| h.strip_prefix("www.").unwrap_or(h).to_string();
+    let base_norm = norm(page_url.host_str().unwrap_or(""));
+
+    let mut seen: HashSet<String> = HashSet::new();
+    seen.insert(page_url.as_str().to_string());
+
+    let page_cap = max_pages.unwrap_or(usize::MAX);
+    let mut targets: Vec<Url> = Vec::new();
+    for a in doc.select(&a_sel) {
+        if seen.len() >= page_cap {
+            break;
+        }
+        let Some(href) = a.value().attr("href") else {
+            continue;
+        };
+        // Resolve relative links
+        let Ok(abs) = page_url.join(href) else {
+            continue;
+        };
+        if abs.host_str().map(norm) != Some(base_norm.clone()) {
+            continue;
+        }
+        if seen.insert(abs.as_str().to_string()) {
+            targets.push(abs);
+        }
+    }
+    targets
+}
+
+/// Why a supported file is left out of the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SkipReason {
+    Filtered,
+    Hidden,
+    IgnoredDirectory,
+    TooLarge,
+    PdfDisabled,
+    CodeDisabled,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            SkipReason::Filtered => "not matched by --include/--exclude",
+            SkipReason::Hidden => "hidden file",
+            SkipReason::IgnoredDirectory => "ignored directory",
+            SkipReason::TooLarge => "larger than 100MB",
+            SkipReason::PdfDisabled => "PDF processing disabled",
+            SkipReason::CodeDisabled => "code processing disabled",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Supported files found under a path: those that would be indexed and those skipped
+#[derive(Debug, Default)]
+pub struct DocumentScan {
+    /// Files to index with their sizes in bytes
+    pub files: Vec<(PathBuf, u64)>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
 /// Supported file extensions for indexing
 const SUPPORTED_EXTENSIONS: &[&str] = &[
     // Documentation
@@ -559,6 +651,7 @@ const SUPPORTED_EXTENSIONS: &[&str] = &[
     ".doc",
     ".docx",
     ".rst",
+    ".ipynb",
     // Web/Frontend
     ".js",
     ".jsx",
@@ -635,14 +728,102 @@ const SUPPORTED_EXTENSIONS: &[&str] = &[
     ".el",
 ];
 
-/// Default chunk size in tokens (approximately)
-const DEFAULT_CHUNK_SIZE: usize = 500;
+/// `--include` / `--exclude` globs, matched against paths relative to the indexed directory
+///
+/// A pattern ending in `/` matches everything under that directory, and excludes also
+/// match any parent directory (so `vendor` drops `vendor/lib/readme.md`).
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    includes: Option<globset::GlobSet>,
+    excludes: globset::GlobSet,
+}
 
-/// Overlap between chunks in tokens
-const DEFAULT_CHUNK_OVERLAP: usize = 50;
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let build = |patterns: &[String]| -> Result<globset::GlobSet> {
+            let mut builder = globset::GlobSetBuilder::new();
+            for pattern in patterns {
+                let pattern = match pattern.strip_suffix('/') {
+                    Some(dir) => format!("{}/**", dir),
+                    None => pattern.clone(),
+                };
+                builder.add(
+                    globset::Glob::new(&pattern)
+                        .map_err(|e| anyhow!("Invalid glob '{}': {}", pattern, e))?,
+                );
+            }
+            Ok(builder.build()?)
+        };
+
+        Ok(Self {
+            includes: if include.is_empty() {
+                None
+            } else {
+                Some(build(include)?)
+            },
+            excludes: build(exclude)?,
+        })
+    }
+
+    /// Whether a file (relative to the indexed directory) passes the filter
+    pub fn allows(&self, relative: &Path) -> bool {
+        if let Some(includes) = &self.includes {
+            if !includes.is_match(relative) {
+                return false;
+            }
+        }
+        !relative
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| self.excludes.is_match(p))
+    }
+}
 
 /// Find all indexable documents in a directory using WalkDir for performance
-pub fn find_documents(dir_path: &Path) -> Result<Vec<PathBuf>> {
+pub fn find_documents(dir_path: &Path, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    Ok(scan_documents(dir_path, filter)?
+        .files
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Plan indexing of a file or directory without reading any content
+///
+/// Files that `index_document` would skip under the current configuration (PDF or
+/// code processing disabled) are reported as skipped. `filter` applies to directories.
+pub fn plan_documents(
+    path: &Path,
+    config: &RagConfig,
+    filter: &PathFilter,
+) -> Result<DocumentScan> {
+    let scan = if path.is_file() {
+        if !is_supported_file(path) {
+            return Err(anyhow!("Unsupported file type: {:?}", path));
+        }
+        DocumentScan {
+            files: vec![(path.to_path_buf(), fs::metadata(path)?.len())],
+            skipped: Vec::new(),
+        }
+    } else {
+        scan_documents(path, filter)?
+    };
+
+    let mut plan = DocumentScan {
+        files: Vec::new(),
+        skipped: scan.skipped,
+    };
+    for (file, size) in scan.files {
+        match disabled_reason(&file, config) {
+            Some(reason) => plan.skipped.push((file, reason)),
+            None => plan.files.push((file, size)),
+        }
+    }
+    Ok(plan)
+}
+
+/// Walk a directory, sorting supported files into indexable and skipped
+fn scan_documents(dir_path: &Path, filter: &PathFilter) -> Result<DocumentScan> {
     if !dir_path.exists() {
         return Err(anyhow!("Directory does not exist: {:?}", dir_path));
     }
@@ -651,7 +832,7 @@ pub fn find_documents(dir_path: &Path) -> Result<Vec<PathBuf>> {
         return Err(anyhow!("Path is not a directory: {:?}", dir_path));
     }
 
-    let mut documents = Vec::new();
+    let mut scan = DocumentScan::default();
     let max_depth = 10; // Prevent infinite recursion
     let max_file_size = 100 * 1024 * 1024; // 100MB limit per file
 
@@ -676,16 +857,19 @@ pub fn find_documents(dir_path: &Path) -> Result<Vec<PathBuf>> {
             continue;
         }
 
+        if !filter.allows(path.strip_prefix(dir_path).unwrap_or(path)) {
+            scan.skipped
+                .push((path.to_path_buf(), SkipReason::Filtered));
+            continue;
+        }
+
         // Check file size limits
-        if let Ok(metadata) = entry.metadata() {
-            if metadata.len() > max_file_size {
-                log::warn!(
-                    "Skipping large file ({}MB): {:?}",
-                    metadata.len() / 1024 / 1024,
-                    path
-                );
-                continue;
-            }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if size > max_file_size {
+            log::warn!("Skipping large file ({}MB): {:?}", size / 1024 / 1024, path);
+            scan.skipped
+                .push((path.to_path_buf(), SkipReason::TooLarge));
+            continue;
         }
 
         // Skip hidden files and directories (starting with .)
@@ -696,6 +880,7 @@ pub fn find_documents(dir_path: &Path) -> Result<Vec<PathBuf>> {
             .unwrap_or(false)
         {
             log::debug!("Skipping hidden file: {:?}", path);
+            scan.skipped.push((path.to_path_buf(), SkipReason::Hidden));
             continue;
         }
 
@@ -716,20 +901,22 @@ pub fn find_documents(dir_path: &Path) -> Result<Vec<PathBuf>> {
             .any(|pattern| path_str.contains(pattern))
         {
             log::debug!("Skipping file in ignored directory: {:?}", path);
+            scan.skipped
+                .push((path.to_path_buf(), SkipReason::IgnoredDirectory));
             continue;
         }
 
-        documents.push(path.to_path_buf());
+        scan.files.push((path.to_path_buf(), size));
     }
 
     log::info!(
         "Found {} indexable documents in {:?} (max depth: {})",
-        documents.len(),
+        scan.files.len(),
         dir_path,
         max_depth
     );
 
-    if documents.is_empty() {
+    if scan.files.is_empty() {
         log::warn!(
             "No supported documents found in {:?}. Supported formats: {:?}",
             dir_path,
@@ -737,15 +924,50 @@ pub fn find_documents(dir_path: &Path) -> Result<Vec<PathBuf>> {
         );
     }
 
-    Ok(documents)
+    Ok(scan)
+}
+
+/// Reason the configuration excludes a supported file from indexing, if any
+fn disabled_reason(path: &Path, config: &RagConfig) -> Option<SkipReason> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    // SECURITY: PDF and code processing can be switched off
+    if extension == "pdf" && !config.allow_pdf_processing {
+        return Some(SkipReason::PdfDisabled);
+    }
+    let code_extensions = [
+        "js", "jsx", "ts", "tsx", "py", "rb", "php", "java", "scala", "kotlin", "rs", "go", "c",
+        "cpp", "sh", "bash", "ps1",
+    ];
+    if code_extensions.contains(&extension.as_str()) && !config.allow_code_processing {
+        return Some(SkipReason::CodeDisabled);
+    }
+    None
 }
 
 /// Check if a file is supported for indexing
 pub fn is_supported_file(path: &Path) -> bool {
+    let extension = file_extension(path);
+    !extension.is_empty() && SUPPORTED_EXTENSIONS.contains(&format!(".{}", extension).as_str())
+}
+
+/// Lowercase file extension, with dotenv files (`.env`, `.env.local`) reported as `env`
+///
+/// The file name is checked first: `Path::extension` is empty for `.env` and `local` for
+/// `.env.local`.
+fn file_extension(path: &Path) -> String {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if name == ".env" || name.starts_with(".env.") {
+        return "env".to_string();
+    }
     path.extension()
         .and_then(|ext| ext.to_str())
-        .map(|ext| SUPPORTED_EXTENSIONS.contains(&format!(".{}", ext.to_lowercase()).as_str()))
-        .unwrap_or(false)
+        .unwrap_or("")
+        .to_lowercase()
 }
 
 /// Index a single document and return chunks
@@ -758,50 +980,59 @@ pub fn index_document(path: PathBuf, config: &RagConfig) -> Result<Vec<DocumentC
         return Err(anyhow!("Unsupported file type: {:?}", path));
     }
 
-    // SECURITY: Check if PDF processing is disabled
-    let extension = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    if extension == "pdf" && !config.allow_pdf_processing {
-        log::warn!("PDF processing disabled for security. Skipping: {:?}", path);
-        return Ok(vec![]); // Skip PDF files when disabled
-    }
-
-    // SECURITY: Check if code processing is disabled
-    let code_extensions = [
-        "js", "jsx", "ts", "tsx", "py", "rb", "php", "java", "scala", "kotlin", "rs", "go", "c",
-        "cpp", "sh", "bash", "ps1",
-    ];
-    if code_extensions.contains(&extension.as_str()) && !config.allow_code_processing {
-        log::warn!("Code processing disabled. Skipping: {:?}", path);
-        return Ok(vec![]); // Skip code files when disabled
+    if let Some(reason) = disabled_reason(&path, config) {
+        log::warn!("Skipping {:?}: {}", path, reason);
+        return Ok(vec![]);
     }
 
     log::info!("Indexing document: {:?}", path);
 
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ipynb"))
+    {
+        return index_notebook(path, config);
+    }
+
     // Extract text content with configuration
-    let content = extract_text(&path, config)?;
+    let mut content = extract_text(&path, config)?;
+    let front_matter = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+    {
+        strip_front_matter(&mut content)
+    } else {
+        FrontMatter::default()
+    };
     if content.trim().is_empty() {
         return Err(anyhow!("Document contains no text content: {:?}", path));
     }
 
     // Get file metadata
-    let metadata = extract_metadata(&path)?;
+    let mut metadata = extract_metadata(&path)?;
+    merge_tags(&mut metadata.tags, front_matter.tags);
 
     // Detect document structure (title, sections)
     let (title, sections) = detect_structure(&content, &path);
+    let title = front_matter.title.or(title);
 
     // Chunk the content
-    let chunks = chunk_content(&content, DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_OVERLAP);
+    let chunks = chunk_document(&content, &path, config);
+    // Extracted PDF and Word text has no lines a citation could point back to
+    let line_ranges = if has_source_lines(&path) {
+        chunking::line_ranges(&content, chunks.iter().map(|chunk| chunk.text.as_str()))
+    } else {
+        vec![None; chunks.len()]
+    };
 
     // Create document chunks
     let mut document_chunks = Vec::new();
-    for (i, chunk_content) in chunks.into_iter().enumerate() {
-        // Try to determine which section this chunk belongs to
-        let section = find_section_for_chunk(&chunk_content, &sections);
+    for (i, (chunk, line_range)) in chunks.into_iter().zip(line_ranges).enumerate() {
+        let chunk_content = chunk.text;
+        // Use the heading trail, or try to determine which section this chunk belongs to
+        let section = chunk
+            .section
+            .or_else(|| find_section_for_chunk(&chunk_content, &sections));
 
         let chunk = DocumentChunk {
             id: format!("{}_{}", path.to_string_lossy(), i),
@@ -811,7 +1042,10 @@ pub fn index_document(path: PathBuf, config: &RagConfig) -> Result<Vec<DocumentC
             title: title.clone(),
             section: section.clone(),
             chunk_index: i,
-            metadata: metadata.clone(),
+            metadata: DocumentMetadata {
+                line_range,
+                ..metadata.clone()
+            },
         };
 
         document_chunks.push(chunk);
@@ -821,26 +1055,188 @@ pub fn index_document(path: PathBuf, config: &RagConfig) -> Result<Vec<DocumentC
     Ok(document_chunks)
 }
 
+/// Index a Jupyter notebook, chunking each run of same-type cells separately
+///
+/// Chunks are tagged `notebook` plus `markdown-cell` or `code-cell`; code chunks carry the
+/// kernel language. Title and sections come from markdown cells only, so `#` comments in
+/// code don't count as headings.
+fn index_notebook(path: PathBuf, config: &RagConfig) -> Result<Vec<DocumentChunk>> {
+    let notebook = extract_ipynb_text(&path, config)?;
+    if notebook
+        .cells
+        .iter()
+        .all(|cell| cell.text.trim().is_empty())
+    {
+        return Err(anyhow!("Document contains no text content: {:?}", path));
+    }
+
+    let metadata = extract_metadata(&path)?;
+    let markdown: Vec<&str> = notebook
+        .cells
+        .iter()
+        .filter(|cell| cell.kind == NotebookCellKind::Markdown)
+        .map(|cell| cell.text.as_str())
+        .collect();
+    let (title, sections) = detect_structure(&markdown.join("\n\n"), &path);
+
+    let mut document_chunks = Vec::new();
+    for group in notebook.cells.chunk_by(|a, b| a.kind == b.kind) {
+        let kind = group[0].kind;
+        let text: Vec<&str> = group.iter().map(|cell| cell.text.as_str()).collect();
+        let mut group_metadata = metadata.clone();
+        group_metadata.tags.push("notebook".to_string());
+        group_metadata.tags.push(kind.tag().to_string());
+        if kind == NotebookCellKind::Code {
+            group_metadata.language = Some(notebook.language.clone());
+        }
+
+        let text = text.join("\n\n");
+        // Code cells are fenced, so both kinds chunk as markdown
+        let chunks = if config.smart_search.adaptive_chunking {
+            chunking::chunk_markdown(&text, tokens_to_words(config.chunk_size))
+        } else {
+            chunk_content(&text, config.chunk_size, config.chunk_overlap)
+        };
+        for chunk_content in chunks {
+            let i = document_chunks.len();
+            document_chunks.push(DocumentChunk {
+                id: format!("{}_{}", path.to_string_lossy(), i),
+                section: find_section_for_chunk(&chunk_content, &sections),
+                content: preprocessing::clean_text(&chunk_content),
+                source_path: path.clone(),
+                source_type: SourceType::Local,
+                title: title.clone(),
+                chunk_index: i,
+                metadata: group_metadata.clone(),
+            });
+        }
+    }
+
+    log::info!(
+        "Created {} chunks from notebook {:?}",
+        document_chunks.len(),
+        path
+    );
+    Ok(document_chunks)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NotebookCellKind {
+    Markdown,
+    Code,
+}
+
+impl NotebookCellKind {
+    fn tag(self) -> &'static str {
+        match self {
+            NotebookCellKind::Markdown => "markdown-cell",
+            NotebookCellKind::Code => "code-cell",
+        }
+    }
+}
+
+struct NotebookCell {
+    kind: NotebookCellKind,
+    text: String,
+}
+
+struct Notebook {
+    /// Kernel language, e.g. `python`
+    language: String,
+    cells: Vec<NotebookCell>,
+}
+
+/// Parse a notebook's markdown and code cell text
+///
+/// Code cells are fenced with the kernel language and skipped when code processing is
+/// disabled; outputs are appended only with `include_notebook_outputs`. Raw cells are ignored.
+fn extract_ipynb_text(path: &Path, config: &RagConfig) -> Result<Notebook> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read notebook {:?}: {}", path, e))?;
+    let json: serde_json::Value = serde_json::from_str(&data)
+        .map_err(|e| anyhow!("Failed to parse notebook {:?}: {}", path, e))?;
+
+    let language = json["metadata"]["language_info"]["name"]
+        .as_str()
+        .or_else(|| json["metadata"]["kernelspec"]["language"].as_str())
+        .unwrap_or("python")
+        .to_string();
+
+    let mut cells = Vec::new();
+    for cell in json["cells"].as_array().into_iter().flatten() {
+        let source = notebook_text(&cell["source"]);
+        match cell["cell_type"].as_str() {
+            Some("markdown") => cells.push(NotebookCell {
+                kind: NotebookCellKind::Markdown,
+                text: source,
+            }),
+            Some("code") if config.allow_code_processing => {
+                let source = if config.mask_secrets {
+                    sanitize_code_content(&source)
+                } else {
+                    source
+                };
+                let mut text = format!("```{}\n{}\n```", language, source.trim_end());
+                if config.include_notebook_outputs {
+                    for output in cell["outputs"].as_array().into_iter().flatten() {
+                        let output_text = notebook_output_text(output);
+                        if !output_text.trim().is_empty() {
+                            text.push_str("\nOutput:\n");
+                            text.push_str(output_text.trim_end());
+                        }
+                    }
+                }
+                cells.push(NotebookCell {
+                    kind: NotebookCellKind::Code,
+                    text,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Notebook { language, cells })
+}
+
+/// Notebook cell sources and outputs are a string or a list of lines
+fn notebook_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(lines) => lines.iter().filter_map(|line| line.as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+fn notebook_output_text(output: &serde_json::Value) -> String {
+    match output["output_type"].as_str() {
+        Some("stream") => notebook_text(&output["text"]),
+        Some("execute_result") | Some("display_data") => {
+            notebook_text(&output["data"]["text/plain"])
+        }
+        Some("error") => format!(
+            "{}: {}",
+            output["ename"].as_str().unwrap_or("Error"),
+            output["evalue"].as_str().unwrap_or("")
+        ),
+        _ => String::new(),
+    }
+}
+
 /// Extract text content from various file formats
 fn extract_text(path: &Path, config: &RagConfig) -> Result<String> {
-    let extension = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    let extension = file_extension(path);
 
     match extension.as_str() {
         "md" | "txt" | "rst" => extract_text_file(path),
         "pdf" => extract_pdf_text(path),
         "doc" | "docx" => extract_doc_text(path),
+        "html" => extract_html_text(path, config),
         // Code files
-        "js" | "jsx" | "ts" | "tsx" | "vue" | "svelte" | "html" | "css" | "scss" | "sass"
-        | "less" | "py" | "rb" | "php" | "java" | "scala" | "kotlin" | "groovy" | "c" | "cpp"
-        | "cc" | "cxx" | "h" | "hpp" | "rs" | "go" | "zig" | "ml" | "mli" | "hs" | "elm"
-        | "clj" | "cljs" | "erl" | "ex" | "exs" | "swift" | "m" | "mm" | "kt" | "dart" | "r"
-        | "jl" | "lua" | "vim" | "el" | "sql" | "graphql" | "prisma" => {
-            extract_code_text(path, config)
-        }
+        "js" | "jsx" | "ts" | "tsx" | "vue" | "svelte" | "css" | "scss" | "sass" | "less"
+        | "py" | "rb" | "php" | "java" | "scala" | "kotlin" | "groovy" | "c" | "cpp" | "cc"
+        | "cxx" | "h" | "hpp" | "rs" | "go" | "zig" | "ml" | "mli" | "hs" | "elm" | "clj"
+        | "cljs" | "erl" | "ex" | "exs" | "swift" | "m" | "mm" | "kt" | "dart" | "r" | "jl"
+        | "lua" | "vim" | "el" | "sql" | "graphql" | "prisma" => extract_code_text(path, config),
         // Config files
         "json" | "yaml" | "yml" | "toml" | "xml" | "ini" | "properties" => {
             extract_config_text(path, config)
@@ -853,6 +1249,18 @@ fn extract_text(path: &Path, config: &RagConfig) -> Result<String> {
     }
 }
 
+/// Whether indexed text keeps the file's lines, unlike text extracted from PDF, Word and HTML files
+fn has_source_lines(path: &Path) -> bool {
+    !path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["pdf", "doc", "docx", "html"]
+                .iter()
+                .any(|binary| ext.eq_ignore_ascii_case(binary))
+        })
+}
+
 /// Extract text from plain text files (markdown, txt)
 fn extract_text_file(path: &Path) -> Result<String> {
     fs::read_to_string(path).map_err(|e| anyhow!("Failed to read text file {:?}: {}", path, e))
@@ -865,6 +1273,26 @@ fn extract_pdf_text(path: &Path) -> Result<String> {
     // SECURITY: Validate PDF before processing
     validate_pdf_security(path)?;
 
+    match extract_pdf_text_layer(path) {
+        Ok(text) if !text.trim().is_empty() => {
+            log::info!(
+                "Extracted {} characters of text from PDF {:?}",
+                text.len(),
+                path
+            );
+            return Ok(text);
+        }
+        Ok(_) => log::warn!(
+            "PDF {:?} has no text layer (scanned?), indexing filename and metadata only",
+            path
+        ),
+        Err(e) => log::warn!(
+            "Failed to extract text from PDF {:?}, indexing filename and metadata only: {}",
+            path,
+            e
+        ),
+    }
+
     // Create a metadata entry that includes the filename and basic information
     // Note: Basic PDF metadata extraction with security validation
     let file_name = path
@@ -904,6 +1332,27 @@ fn extract_pdf_text(path: &Path) -> Result<String> {
     Ok(content)
 }
 
+/// Read the text of every page of a PDF
+///
+/// The parser prints font warnings to stdout and can panic on malformed files, so its
+/// output is silenced and panics become errors. Stdout stays locked meanwhile: other
+/// threads' output waits for the parser instead of being silenced with it.
+fn extract_pdf_text_layer(path: &Path) -> Result<String> {
+    use std::io::Write;
+
+    let bytes = fs::read(path)?;
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.flush();
+    let quiet = gag::Gag::stdout().ok();
+    let text = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(&bytes));
+    // Drain the parser's buffered output while it is still redirected
+    let _ = stdout.flush();
+    drop(quiet);
+    drop(stdout);
+    text.map_err(|_| anyhow!("PDF parser panicked"))?
+        .map_err(|e| anyhow!("{}", e))
+}
+
 /// Extract text from DOC/DOCX files
 fn extract_doc_text(path: &Path) -> Result<String> {
     log::info!("Processing DOC/DOCX file: {:?}", path);
@@ -946,23 +1395,328 @@ fn extract_doc_text(path: &Path) -> Result<String> {
 }
 
 /// Safely extract DOCX text with error handling
+///
+/// Paragraphs are separated by blank lines and table rows become `|`-joined cell text.
+/// `Title` and `HeadingN` paragraphs are written as markdown headings (`#`, `##`, `###`...)
+/// so `detect_structure` picks them up as the title and sections.
 fn extract_docx_text_safe(path: &Path) -> Result<String> {
-    use docx_rs::read_docx;
+    use docx_rs::{read_docx, DocumentChild};
 
     // Read the DOCX file as bytes
     let file_bytes = std::fs::read(path).map_err(|e| anyhow!("Failed to read DOCX file: {}", e))?;
 
-    let _docx = read_docx(&file_bytes).map_err(|e| anyhow!("Failed to parse DOCX file: {}", e))?;
+    let docx = read_docx(&file_bytes).map_err(|e| anyhow!("Failed to parse DOCX file: {}", e))?;
+
+    let mut blocks = Vec::new();
+    for child in &docx.document.children {
+        match child {
+            DocumentChild::Paragraph(paragraph) => blocks.extend(docx_paragraph_block(paragraph)),
+            DocumentChild::Table(table) => blocks.extend(docx_table_block(table)),
+            _ => {}
+        }
+    }
+
+    if blocks.is_empty() {
+        return Err(anyhow!("DOCX file contains no text"));
+    }
+    Ok(blocks.join("\n\n"))
+}
+
+/// Paragraph text, prefixed with markdown heading marks for heading styles
+fn docx_paragraph_block(paragraph: &docx_rs::Paragraph) -> Option<String> {
+    let mut text = String::new();
+    push_docx_paragraph_text(&paragraph.children, &mut text);
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
 
-    // Extract text content from document (basic implementation)
-    let mut text_content = String::new();
+    let level = paragraph
+        .property
+        .style
+        .as_ref()
+        .and_then(|style| docx_heading_level(&style.val));
+    Some(match level {
+        Some(level) => format!("{} {}", "#".repeat(level), text),
+        None => text.to_string(),
+    })
+}
+
+/// Markdown heading level for a paragraph style id: `Title` is 1, `HeadingN` is N + 1
+fn docx_heading_level(style_id: &str) -> Option<usize> {
+    let id = style_id.to_lowercase();
+    if id == "title" {
+        return Some(1);
+    }
+    let level: usize = id.strip_prefix("heading")?.trim().parse().ok()?;
+    Some((level + 1).clamp(2, 6))
+}
+
+fn push_docx_paragraph_text(children: &[docx_rs::ParagraphChild], out: &mut String) {
+    use docx_rs::{InsertChild, ParagraphChild};
+
+    for child in children {
+        match child {
+            ParagraphChild::Run(run) => push_docx_run_text(run, out),
+            ParagraphChild::Hyperlink(link) => push_docx_paragraph_text(&link.children, out),
+            ParagraphChild::Insert(insert) => {
+                for child in &insert.children {
+                    if let InsertChild::Run(run) = child {
+                        push_docx_run_text(run, out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
-    // Simple text extraction - this may need enhancement based on docx-rs API
-    text_content.push_str(&format!("DOCX Document from: {}\n", path.display()));
-    text_content.push_str("Document content successfully parsed.\n");
-    text_content.push_str("Note: Basic DOCX processing - text extraction can be enhanced.");
+fn push_docx_run_text(run: &docx_rs::Run, out: &mut String) {
+    use docx_rs::RunChild;
 
-    Ok(text_content)
+    for child in &run.children {
+        match child {
+            RunChild::Text(text) => out.push_str(&text.text),
+            RunChild::Tab(_) => out.push('\t'),
+            RunChild::Break(_) => out.push('\n'),
+            _ => {}
+        }
+    }
+}
+
+/// One line per table row with cells joined by ` | `; empty rows are dropped
+fn docx_table_block(table: &docx_rs::Table) -> Option<String> {
+    use docx_rs::{TableCellContent, TableChild, TableRowChild};
+
+    let rows: Vec<String> = table
+        .rows
+        .iter()
+        .filter_map(|TableChild::TableRow(row)| {
+            let cells: Vec<String> = row
+                .cells
+                .iter()
+                .map(|TableRowChild::TableCell(cell)| {
+                    let mut parts = Vec::new();
+                    for content in &cell.children {
+                        match content {
+                            TableCellContent::Paragraph(paragraph) => {
+                                parts.extend(docx_paragraph_block(paragraph))
+                            }
+                            TableCellContent::Table(table) => parts.extend(docx_table_block(table)),
+                            _ => {}
+                        }
+                    }
+                    parts.join(" ")
+                })
+                .collect();
+            cells
+                .iter()
+                .any(|cell| !cell.is_empty())
+                .then(|| cells.join(" | "))
+        })
+        .collect();
+
+    (!rows.is_empty()).then(|| rows.join("\n"))
+}
+
+/// Extract the visible text of an HTML file as markdown, masking secrets when configured
+fn extract_html_text(path: &Path, config: &RagConfig) -> Result<String> {
+    let html = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read HTML file {:?}: {}", path, e))?;
+    let text = html_to_markdown(&html);
+    Ok(if config.mask_secrets {
+        mask_secrets(&text)
+    } else {
+        text
+    })
+}
+
+/// Elements left out: content never shown on the page, and site navigation
+const SKIPPED_HTML_ELEMENTS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "iframe", "object", "nav",
+];
+
+/// Elements that start and end a paragraph of text
+const BLOCK_HTML_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "aside",
+    "blockquote",
+    "figure",
+    "ul",
+    "ol",
+    "dl",
+    "table",
+];
+
+/// Convert an HTML document to markdown-style text
+///
+/// Headings become `#` lines so `detect_structure` and section chunking find them, `<pre>`
+/// blocks become fenced code with their whitespace intact, inline `<code>` keeps its
+/// backticks and list items become `-` lines. Invisible elements and `<nav>` are dropped, and the
+/// `<title>` serves as the title when the page has no `<h1>`.
+fn html_to_markdown(html: &str) -> String {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(html);
+    let body_selector = Selector::parse("body").unwrap();
+    let root = document
+        .select(&body_selector)
+        .next()
+        .unwrap_or_else(|| document.root_element());
+
+    let mut text = String::new();
+    render_html_element(root, &mut text);
+
+    // Tidy spacing: no trailing spaces and at most one blank line in a row
+    let mut markdown = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 || markdown.is_empty() {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        markdown.push_str(line);
+        markdown.push('\n');
+    }
+    let markdown = markdown.trim_end().to_string();
+
+    let has_h1 = markdown.lines().any(|line| line.starts_with("# "));
+    match extract_html_title(html) {
+        Some(title) if !has_h1 => format!("# {}\n\n{}", title, markdown),
+        _ => markdown,
+    }
+}
+
+/// Append the markdown for `element`'s children to `out`
+fn render_html_element(element: scraper::ElementRef, out: &mut String) {
+    use scraper::ElementRef;
+
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            push_inline_text(out, text);
+            continue;
+        }
+        let Some(child) = ElementRef::wrap(child) else {
+            continue;
+        };
+        let name = child.value().name();
+        match name {
+            _ if SKIPPED_HTML_ELEMENTS.contains(&name) => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                let heading = collapse_whitespace(&child.text().collect::<String>());
+                if !heading.is_empty() {
+                    end_block(out);
+                    out.push_str(&"#".repeat(level));
+                    out.push(' ');
+                    out.push_str(&heading);
+                    end_block(out);
+                }
+            }
+            "pre" => {
+                let code = child.text().collect::<String>();
+                end_block(out);
+                out.push_str("```");
+                out.push_str(&code_language(child).unwrap_or_default());
+                out.push('\n');
+                out.push_str(code.trim_matches('\n'));
+                out.push_str("\n```");
+                end_block(out);
+            }
+            "code" => {
+                let code = collapse_whitespace(&child.text().collect::<String>());
+                if !code.is_empty() {
+                    push_inline_text(out, " ");
+                    out.push('`');
+                    out.push_str(&code);
+                    out.push('`');
+                }
+            }
+            "br" => out.push('\n'),
+            "li" => {
+                end_line(out);
+                out.push_str("- ");
+                render_html_element(child, out);
+                end_line(out);
+            }
+            "tr" | "dt" | "dd" => {
+                end_line(out);
+                render_html_element(child, out);
+                end_line(out);
+            }
+            "td" | "th" => {
+                render_html_element(child, out);
+                out.push_str(" | ");
+            }
+            _ if BLOCK_HTML_ELEMENTS.contains(&name) => {
+                end_block(out);
+                render_html_element(child, out);
+                end_block(out);
+            }
+            _ => render_html_element(child, out),
+        }
+    }
+}
+
+/// Language of a code block from a `language-*` or `lang-*` class on it or its `<code>`
+fn code_language(pre: scraper::ElementRef) -> Option<String> {
+    let code_selector = scraper::Selector::parse("code").unwrap();
+    std::iter::once(pre)
+        .chain(pre.select(&code_selector))
+        .flat_map(|element| element.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .map(str::to_string)
+}
+
+/// Append text with its whitespace collapsed, without leading spaces on a line
+fn push_inline_text(out: &mut String, text: &str) {
+    let starts_with_space = text.starts_with(char::is_whitespace);
+    let ends_with_space = text.ends_with(char::is_whitespace);
+    let words = collapse_whitespace(text);
+    let at_line_start = out.is_empty() || out.ends_with(['\n', ' ']);
+    if (starts_with_space || words.is_empty()) && !at_line_start {
+        out.push(' ');
+    }
+    out.push_str(&words);
+    if ends_with_space && !words.is_empty() {
+        out.push(' ');
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Start a new line unless already at one
+fn end_line(out: &mut String) {
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Leave a blank line before what follows
+fn end_block(out: &mut String) {
+    end_line(out);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
 }
 
 /// Extract text from code files with security validation
@@ -1526,6 +2280,7 @@ fn extract_metadata(path: &Path) -> Result<DocumentMetadata> {
         modified: modified_datetime,
         tags,
         language,
+        line_range: None,
     })
 }
 
@@ -1564,11 +2319,150 @@ fn extract_tags_from_path(path: &Path) -> Vec<String> {
     tags
 }
 
-/// Detect document language
-fn detect_language(_path: &Path) -> Option<String> {
-    // For now, assume English. In a real implementation,
-    // you could use language detection libraries
-    Some("en".to_string())
+/// Detect document language from the file extension
+///
+/// Code, markup and config files get the name of their language (`Rust`, `Python`,
+/// `Markdown`, `YAML`); prose formats fall back to `en`, as there is no natural-language
+/// detection yet.
+fn detect_language(path: &Path) -> Option<String> {
+    let language = extension_language(&file_extension(path)).unwrap_or("en");
+    Some(language.to_string())
+}
+
+/// Language recorded for files with a (lowercase) `extension`, or `None` if it isn't known
+///
+/// Also lets `--lang` take an extension (`rs`, `py`, `md`) in place of the language name.
+pub(crate) fn extension_language(extension: &str) -> Option<&'static str> {
+    let language = match extension {
+        "txt" | "pdf" | "doc" | "docx" => "en",
+        "md" => "Markdown",
+        "ipynb" => "Jupyter Notebook",
+        "rst" => "reStructuredText",
+        "js" | "jsx" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "html" => "HTML",
+        "css" => "CSS",
+        "scss" | "sass" => "Sass",
+        "less" => "Less",
+        "py" => "Python",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "java" => "Java",
+        "scala" => "Scala",
+        "kotlin" | "kt" => "Kotlin",
+        "groovy" => "Groovy",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "cxx" | "hpp" => "C++",
+        "rs" => "Rust",
+        "go" => "Go",
+        "zig" => "Zig",
+        "ml" | "mli" => "OCaml",
+        "hs" => "Haskell",
+        "elm" => "Elm",
+        "clj" | "cljs" => "Clojure",
+        "erl" => "Erlang",
+        "ex" | "exs" => "Elixir",
+        "swift" => "Swift",
+        "m" | "mm" => "Objective-C",
+        "dart" => "Dart",
+        "r" => "R",
+        "jl" => "Julia",
+        "lua" => "Lua",
+        "vim" => "Vim script",
+        "el" => "Emacs Lisp",
+        "sql" => "SQL",
+        "graphql" => "GraphQL",
+        "prisma" => "Prisma",
+        "json" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        "xml" => "XML",
+        "ini" | "properties" => "INI",
+        "sh" | "bash" | "zsh" => "Shell",
+        "fish" => "Fish",
+        "ps1" => "PowerShell",
+        "bat" | "cmd" => "Batch",
+        "env" => "dotenv",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// `title` and `tags` from a markdown file's YAML front matter
+#[derive(Debug, Default, PartialEq)]
+struct FrontMatter {
+    title: Option<String>,
+    tags: Vec<String>,
+}
+
+/// Read and blank out YAML front matter (between `---` lines at the top of the file)
+///
+/// The front matter lines are emptied rather than removed so chunk line ranges still point
+/// at the right lines. Content whose opening block isn't a YAML mapping is left alone.
+fn strip_front_matter(content: &mut String) -> FrontMatter {
+    let body = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut lines = body.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some("---") {
+        return FrontMatter::default();
+    }
+    let mut yaml = String::new();
+    let mut line_count = 1;
+    let mut closed = false;
+    for line in lines {
+        line_count += 1;
+        if matches!(line.trim_end(), "---" | "...") {
+            closed = true;
+            break;
+        }
+        yaml.push_str(line);
+    }
+    if !closed {
+        return FrontMatter::default();
+    }
+    let Ok(serde_yaml::Value::Mapping(fields)) = serde_yaml::from_str(&yaml) else {
+        return FrontMatter::default();
+    };
+
+    let text = |value: &serde_yaml::Value| match value {
+        serde_yaml::Value::String(text) => Some(text.trim().to_string()),
+        serde_yaml::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+    let title = fields
+        .get("title")
+        .and_then(text)
+        .filter(|title| !title.is_empty());
+    // Tags may be a YAML list or a comma-separated string
+    let tags = match fields.get("tags") {
+        Some(serde_yaml::Value::Sequence(items)) => items.iter().filter_map(text).collect(),
+        Some(serde_yaml::Value::String(list)) => list.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .map(|tag: String| tag.trim().to_lowercase())
+    .filter(|tag| !tag.is_empty())
+    .collect();
+
+    let front_matter_end: usize = body
+        .split_inclusive('\n')
+        .take(line_count)
+        .map(str::len)
+        .sum();
+    let rest = body[front_matter_end..].to_string();
+    *content = format!("{}{}", "\n".repeat(line_count), rest);
+
+    FrontMatter { title, tags }
+}
+
+/// Add `extra` tags not already present
+fn merge_tags(tags: &mut Vec<String>, extra: Vec<String>) {
+    for tag in extra {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
 }
 
 /// Detect document structure (title, sections)
@@ -1577,8 +2471,12 @@ fn detect_structure(content: &str, path: &Path) -> (Option<String>, Vec<String>)
     let mut title = None;
     let mut sections = Vec::new();
 
-    // For markdown files, look for headers
-    if path.extension().and_then(|s| s.to_str()) == Some("md") {
+    // For markdown files (and DOCX, HTML and notebooks, whose headings are extracted as
+    // markdown), look for headers
+    if matches!(
+        path.extension().and_then(|s| s.to_str()),
+        Some("md") | Some("docx") | Some("html") | Some("ipynb")
+    ) {
         for line in &lines {
             let trimmed = line.trim();
 
@@ -1617,16 +2515,159 @@ fn find_section_for_chunk(chunk: &str, sections: &[String]) -> Option<String> {
     None
 }
 
-/// Chunk content into smaller pieces
+/// Chunk content into smaller pieces (`chunk_size` and `overlap` in approximate tokens)
 fn chunk_content(content: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
-    // Convert approximate token count to word count (rough estimate: 1 token ≈ 0.75 words)
-    let word_chunk_size = (chunk_size as f32 * 0.75) as usize;
-    let word_overlap = (overlap as f32 * 0.75) as usize;
+    let word_chunk_size = tokens_to_words(chunk_size);
+    // Rounding can make the overlap reach the chunk size, which would never advance
+    let word_overlap = ((overlap as f32 * 0.75) as usize).min(word_chunk_size - 1);
 
     // Use the preprocessing chunk_text function
     crate::rag::embeddings::preprocessing::chunk_text(content, word_chunk_size, word_overlap)
 }
 
+/// Convert approximate token count to word count (rough estimate: 1 token ≈ 0.75 words)
+fn tokens_to_words(tokens: usize) -> usize {
+    ((tokens as f32 * 0.75) as usize).max(1)
+}
+
+/// Chunk a document by its type
+///
+/// With `smart_search.adaptive_chunking`, markdown (and DOCX and HTML, whose headings are
+/// extracted as markdown) is split at headings and then between paragraphs, keeping fenced code blocks
+/// whole; those chunks carry their heading trail as the section. Code is split between
+/// functions and other items. Prose, and everything when the setting is off, uses word-count
+/// chunking with overlap and no section.
+fn chunk_document(content: &str, path: &Path, config: &RagConfig) -> Vec<SectionChunk> {
+    let unsectioned = |chunks: Vec<String>| {
+        chunks
+            .into_iter()
+            .map(|text| SectionChunk {
+                section: None,
+                text,
+            })
+            .collect()
+    };
+    if !config.smart_search.adaptive_chunking {
+        return unsectioned(chunk_content(
+            content,
+            config.chunk_size,
+            config.chunk_overlap,
+        ));
+    }
+
+    let words = tokens_to_words(config.chunk_size);
+    let extracted_markdown = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("docx") || ext.eq_ignore_ascii_case("html"));
+    match detect_language(path).as_deref() {
+        Some("Markdown") => chunking::chunk_markdown_sections(content, words),
+        _ if extracted_markdown => chunking::chunk_markdown_sections(content, words),
+        None | Some("en") | Some("reStructuredText") | Some("Jupyter Notebook") => unsectioned(
+            chunk_content(content, config.chunk_size, config.chunk_overlap),
+        ),
+        Some(_) => unsectioned(chunking::chunk_code(content, words)),
+    }
+}
+
+/// Convert a fetched HTML page into document chunks (empty if the page has no text)
+fn html_page_to_chunks(
+    page_url: &str,
+    html: &str,
+    crawl_tag: &str,
+    config: &RagConfig,
+) -> Vec<DocumentChunk> {
+    let page_title = extract_html_title(html).or_else(|| extract_h1(html));
+    let text = clean_html_to_text(html);
+    if text.trim().is_empty() {
+        return vec![];
+    }
+    let chunks = chunk_content(&text, config.chunk_size, config.chunk_overlap);
+
+    // Build metadata
+    let mut tags = vec!["documentation".to_string(), crawl_tag.to_string()];
+    if let Some(domain) = extract_domain_from_url(page_url) {
+        tags.push(domain);
+    }
+    let metadata = DocumentMetadata {
+        file_type: "html".to_string(),
+        size: text.len() as u64,
+        modified: Utc::now(),
+        tags,
+        language: Some("en".to_string()),
+        line_range: None,
+    };
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk_content)| DocumentChunk {
+            id: format!("{}_{}", page_url, i),
+            content: preprocessing::clean_text(&chunk_content),
+            source_path: PathBuf::from(page_url),
+            source_type: SourceType::Web,
+            title: page_title.clone(),
+            section: None,
+            chunk_index: i,
+            metadata: metadata.clone(),
+        })
+        .collect()
+}
+
+/// Parse sitemap XML into (page URLs, nested sitemap URLs)
+///
+/// A `<sitemapindex>` document lists further sitemaps; a `<urlset>` lists pages.
+pub fn parse_sitemap_xml(xml: &str) -> (Vec<String>, Vec<String>) {
+    use regex::Regex;
+    let re_loc = Regex::new(r"(?is)<loc>\s*(.*?)\s*</loc>").unwrap();
+    let locs: Vec<String> = re_loc
+        .captures_iter(xml)
+        .filter_map(|caps| caps.get(1))
+        .map(|m| {
+            m.as_str()
+                .trim_start_matches("<![CDATA[")
+                .trim_end_matches("]]>")
+                .replace("&amp;", "&")
+                .trim()
+                .to_string()
+        })
+        .filter(|loc| !loc.is_empty())
+        .collect();
+
+    if xml.contains("<sitemapindex") {
+        (vec![], locs)
+    } else {
+        (locs, vec![])
+    }
+}
+
+/// Apply `--sitemap-filter` globs to sitemap URLs
+///
+/// Plain patterns include matching URLs (all URLs are included when none are given);
+/// patterns prefixed with `!` exclude matching URLs.
+pub fn filter_sitemap_urls(urls: Vec<String>, patterns: &[String]) -> Result<Vec<String>> {
+    use globset::{Glob, GlobSetBuilder};
+
+    let mut includes = GlobSetBuilder::new();
+    let mut excludes = GlobSetBuilder::new();
+    let mut has_includes = false;
+    for pattern in patterns {
+        if let Some(excluded) = pattern.strip_prefix('!') {
+            excludes.add(Glob::new(excluded)?);
+        } else {
+            includes.add(Glob::new(pattern)?);
+            has_includes = true;
+        }
+    }
+    let includes = includes.build()?;
+    let excludes = excludes.build()?;
+
+    Ok(urls
+        .into_iter()
+        .filter(|url| !has_includes || includes.is_match(url))
+        .filter(|url| !excludes.is_match(url))
+        .collect())
+}
+
 /// Extract domain from URL without requiring external dependencies
 fn extract_domain_from_url(url: &str) -> Option<String> {
     // Simple domain extraction without full URL parsing
@@ -1722,6 +2763,340 @@ mod tests {
     // use std::fs::File;
     // use std::io::Write;
 
+    #[test]
+    fn test_parse_sitemap_urlset() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://docs.example.com/guide/intro</loc></url>
+  <url><loc> https://docs.example.com/api?a=1&amp;b=2 </loc></url>
+</urlset>"#;
+        let (pages, nested) = parse_sitemap_xml(xml);
+        assert_eq!(
+            pages,
+            vec![
+                "https://docs.example.com/guide/intro",
+                "https://docs.example.com/api?a=1&b=2"
+            ]
+        );
+        assert!(nested.is_empty());
+    }
+
+    #[test]
+    fn test_parse_sitemap_index() {
+        let xml = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://docs.example.com/sitemap-1.xml</loc></sitemap>
+  <sitemap><loc><![CDATA[https://docs.example.com/sitemap-2.xml]]></loc></sitemap>
+</sitemapindex>"#;
+        let (pages, nested) = parse_sitemap_xml(xml);
+        assert!(pages.is_empty());
+        assert_eq!(
+            nested,
+            vec![
+                "https://docs.example.com/sitemap-1.xml",
+                "https://docs.example.com/sitemap-2.xml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_sitemap_urls() {
+        let urls = vec![
+            "https://docs.example.com/guide/intro".to_string(),
+            "https://docs.example.com/guide/legacy/old".to_string(),
+            "https://docs.example.com/blog/post".to_string(),
+        ];
+
+        let all = filter_sitemap_urls(urls.clone(), &[]).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let filtered =
+            filter_sitemap_urls(urls, &["*/guide/*".to_string(), "!*/legacy/*".to_string()])
+                .unwrap();
+        assert_eq!(filtered, vec!["https://docs.example.com/guide/intro"]);
+    }
+
+    #[test]
+    fn test_plan_documents_reports_skipped_files() {
+        let dir = std::env::temp_dir().join(format!("manx_plan_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("node_modules")).unwrap();
+        std::fs::write(dir.join("guide.md"), "# Guide").unwrap();
+        std::fs::write(dir.join(".notes.md"), "hidden").unwrap();
+        std::fs::write(dir.join("node_modules").join("pkg.json"), "{}").unwrap();
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("image.png"), "not indexed").unwrap();
+
+        let config = RagConfig {
+            allow_code_processing: false,
+            ..RagConfig::default()
+        };
+        let plan = plan_documents(&dir, &config, &PathFilter::default()).unwrap();
+        assert_eq!(plan.files, vec![(dir.join("guide.md"), 7)]);
+
+        let mut skipped: Vec<_> = plan.skipped.iter().map(|(_, reason)| *reason).collect();
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            vec![
+                SkipReason::Hidden,
+                SkipReason::IgnoredDirectory,
+                SkipReason::CodeDisabled
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let filter =
+            PathFilter::new(&["docs/**/*.md".to_string()], &["vendor/".to_string()]).unwrap();
+        assert!(filter.allows(Path::new("docs/guide/intro.md")));
+        assert!(!filter.allows(Path::new("src/lib.md")));
+        assert!(!filter.allows(Path::new("docs/guide/intro.txt")));
+
+        let filter = PathFilter::new(&[], &["vendor".to_string(), "*.log.md".to_string()]).unwrap();
+        assert!(filter.allows(Path::new("README.md")));
+        assert!(!filter.allows(Path::new("vendor/lib/README.md")));
+        assert!(!filter.allows(Path::new("notes/debug.log.md")));
+
+        assert!(PathFilter::new(&["docs/[".to_string()], &[]).is_err());
+    }
+
+    /// Single-page PDF showing `text` in Helvetica
+    fn minimal_pdf(text: &str) -> Vec<u8> {
+        let stream = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", text);
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R \
+             /Resources << /Font << /F1 5 0 R >> >> >>"
+                .to_string(),
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                stream.len(),
+                stream
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+        for offset in offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).bytes());
+        }
+        pdf.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .bytes(),
+        );
+        pdf
+    }
+
+    #[test]
+    fn test_extract_pdf_text_reads_pages_and_falls_back() {
+        let dir = std::env::temp_dir().join(format!("manx_pdf_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let good = dir.join("pooling-guide.pdf");
+        fs::write(&good, minimal_pdf("Connection pooling with deadpool")).unwrap();
+        let text = extract_pdf_text(&good).unwrap();
+        assert!(
+            text.contains("Connection pooling with deadpool"),
+            "{}",
+            text
+        );
+
+        let broken = dir.join("broken-guide.pdf");
+        fs::write(&broken, b"%PDF-1.4\nnot really a pdf body\n").unwrap();
+        let text = extract_pdf_text(&broken).unwrap();
+        assert!(text.contains("PDF Document: broken-guide"));
+
+        fs::write(dir.join("fake.pdf"), b"hello").unwrap();
+        assert!(extract_pdf_text(&dir.join("fake.pdf")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_docx_text_keeps_paragraphs_tables_and_headings() {
+        use docx_rs::{Docx, Paragraph, Run, Table, TableCell, TableRow};
+
+        let text_paragraph = |text: &str| Paragraph::new().add_run(Run::new().add_text(text));
+        let cell = |text: &str| TableCell::new().add_paragraph(text_paragraph(text));
+
+        let dir = std::env::temp_dir().join(format!("manx_docx_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("handbook.docx");
+        let file = fs::File::create(&path).unwrap();
+        Docx::new()
+            .add_paragraph(text_paragraph("Pooling Handbook").style("Title"))
+            .add_paragraph(text_paragraph("Configuration").style("Heading1"))
+            .add_paragraph(text_paragraph("Set the pool size before startup."))
+            .add_table(Table::new(vec![
+                TableRow::new(vec![cell("Setting"), cell("Default")]),
+                TableRow::new(vec![cell("max_size"), cell("16")]),
+            ]))
+            .build()
+            .pack(file)
+            .unwrap();
+
+        let text = extract_docx_text_safe(&path).unwrap();
+        assert_eq!(
+            text,
+            "# Pooling Handbook\n\n## Configuration\n\nSet the pool size before startup.\n\n\
+             Setting | Default\nmax_size | 16"
+        );
+
+        let (title, sections) = detect_structure(&text, &path);
+        assert_eq!(title.as_deref(), Some("Pooling Handbook"));
+        assert_eq!(sections, vec!["Configuration".to_string()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_html_files_index_visible_text_headings_and_code() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head><title>Pool Guide</title><style>body { color: red; }</style></head>
+<body>
+  <nav><a href="/">Home</a></nav>
+  <h2>Sizing <small>v2</small></h2>
+  <p>Call <code>create_pool</code> with a
+     <b>size</b>&nbsp;&amp; timeout.</p>
+  <pre><code class="language-python">pool = create_pool(
+    size=8,
+)</code></pre>
+  <ul><li>Keep it small</li><li>Reuse it</li></ul>
+  <script>trackPageView();</script>
+</body>
+</html>"#;
+        assert_eq!(
+            html_to_markdown(html),
+            "# Pool Guide\n\n## Sizing v2\n\n\
+             Call `create_pool` with a size & timeout.\n\n\
+             ```python\npool = create_pool(\n    size=8,\n)\n```\n\n\
+             - Keep it small\n- Reuse it"
+        );
+
+        let dir = std::env::temp_dir().join(format!("manx_html_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pool.html");
+        fs::write(&path, html).unwrap();
+        let chunks = index_document(path, &RagConfig::default()).unwrap();
+        assert_eq!(chunks[0].title.as_deref(), Some("Pool Guide"));
+        assert!(chunks
+            .iter()
+            .any(|c| c.section.as_deref() == Some("Pool Guide > Sizing v2")));
+        assert!(chunks.iter().all(|c| !c.content.contains('<')));
+        assert!(chunks.iter().all(|c| !c.content.contains("trackPageView")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_markdown_front_matter_sets_title_and_tags() {
+        let mut content =
+            "---\ntitle: Connection Pools\ntags: [Database, tuning]\n---\n# Pools\n\nSize them small.\n"
+                .to_string();
+        assert_eq!(
+            strip_front_matter(&mut content),
+            FrontMatter {
+                title: Some("Connection Pools".to_string()),
+                tags: vec!["database".to_string(), "tuning".to_string()],
+            }
+        );
+        // Blanked, not removed, so line numbers still match the file
+        assert_eq!(content, "\n\n\n\n# Pools\n\nSize them small.\n");
+
+        // A leading rule that isn't YAML front matter stays in the content
+        let mut content = "---\nJust a rule, then prose\n---\n".to_string();
+        assert_eq!(strip_front_matter(&mut content), FrontMatter::default());
+        assert_eq!(content, "---\nJust a rule, then prose\n---\n");
+
+        let dir = std::env::temp_dir().join(format!("manx_front_matter_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pools.md");
+        fs::write(
+            &path,
+            "---\ntitle: Connection Pools\ntags: database, tuning\nauthor: ops\n---\n\n## Sizing\n\nKeep pools small.\n",
+        )
+        .unwrap();
+        let chunks = index_document(path, &RagConfig::default()).unwrap();
+        assert_eq!(chunks[0].title.as_deref(), Some("Connection Pools"));
+        assert!(chunks[0].metadata.tags.contains(&"tuning".to_string()));
+        assert!(chunks.iter().all(|c| !c.content.contains("author")));
+        assert_eq!(
+            chunks[0].metadata.line_range.map(|(start, _)| start),
+            Some(7)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_index_notebook_tags_cells_and_reads_markdown_headings() {
+        let dir = std::env::temp_dir().join(format!("manx_ipynb_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pooling.ipynb");
+        let notebook = serde_json::json!({
+            "metadata": { "language_info": { "name": "python" } },
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Pool Tuning\n", "## Sizing\n", "Pick a pool size."] },
+                {
+                    "cell_type": "code",
+                    "source": "# not a heading\npool = create_pool(size=8)",
+                    "outputs": [{ "output_type": "stream", "name": "stdout", "text": ["pool ready\n"] }]
+                },
+                { "cell_type": "raw", "source": "ignored" }
+            ]
+        });
+        fs::write(&path, notebook.to_string()).unwrap();
+
+        let config = RagConfig::default();
+        let chunks = index_document(path.clone(), &config).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].title.as_deref(), Some("Pool Tuning"));
+        assert_eq!(chunks[0].section.as_deref(), Some("Sizing"));
+        assert!(chunks[0]
+            .metadata
+            .tags
+            .contains(&"markdown-cell".to_string()));
+        assert!(chunks[1].metadata.tags.contains(&"code-cell".to_string()));
+        assert!(chunks[1].metadata.tags.contains(&"notebook".to_string()));
+        assert_eq!(chunks[1].metadata.language.as_deref(), Some("python"));
+        assert!(chunks[1].content.contains("create_pool(size=8)"));
+        assert!(!chunks[1].content.contains("pool ready"));
+        assert!(chunks.iter().all(|c| !c.content.contains("ignored")));
+
+        let with_outputs = RagConfig {
+            include_notebook_outputs: true,
+            ..RagConfig::default()
+        };
+        let chunks = index_document(path.clone(), &with_outputs).unwrap();
+        assert!(chunks[1].content.contains("pool ready"));
+
+        let no_code = RagConfig {
+            allow_code_processing: false,
+            ..RagConfig::default()
+        };
+        let chunks = index_document(path, &no_code).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0]
+            .metadata
+            .tags
+            .contains(&"markdown-cell".to_string()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_is_supported_file() {
         assert!(is_supported_file(Path::new("test.md")));
@@ -1732,6 +3107,21 @@ mod tests {
         assert!(!is_supported_file(Path::new("test")));
     }
 
+    #[test]
+    fn test_detect_language_from_extension() {
+        let language = |name: &str| detect_language(Path::new(name));
+        assert_eq!(language("src/pool.rs").as_deref(), Some("Rust"));
+        assert_eq!(language("app/pool.PY").as_deref(), Some("Python"));
+        assert_eq!(language("README.md").as_deref(), Some("Markdown"));
+        assert_eq!(language("config.yml").as_deref(), Some("YAML"));
+        assert_eq!(language("notes.txt").as_deref(), Some("en"));
+        assert_eq!(language("manual.pdf").as_deref(), Some("en"));
+        assert_eq!(language("unknown.xyz").as_deref(), Some("en"));
+        assert_eq!(language("app/.env").as_deref(), Some("dotenv"));
+        assert_eq!(language("app/.env.local").as_deref(), Some("dotenv"));
+        assert!(is_supported_file(Path::new("app/.env.local")));
+    }
+
     #[test]
     fn test_detect_structure() {
         let content = r#"# Main Title
@@ -1794,4 +3184,24 @@ More content.
             assert!(overlap_found);
         }
     }
+    #[test]
+    fn test_chunk_content_respects_configured_sizes() {
+        let content = (0..40)
+            .map(|i| format!("word{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // 8 tokens ≈ 6 words per chunk, advancing 6 - 1 words at a time
+        let chunks = chunk_content(&content, 8, 2);
+        assert_eq!(chunks[0].split_whitespace().count(), 6);
+        assert!(chunks[1].starts_with("word5 "));
+
+        // Rounding 5/4 tokens to 3/3 words must still make progress
+        assert!(chunk_content(&content, 5, 4).len() < 40);
+
+        let mut config = RagConfig::default();
+        assert!(config.chunk_params().is_ok());
+        config.chunk_overlap = config.chunk_size;
+        assert!(config.chunk_params().is_err());
+    }
 }
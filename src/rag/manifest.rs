@@ -0,0 +1,570 @@
+//! Index manifest: an append-only inventory of indexed sources
+//!
+//! Every time a source (file, page) finishes storing its chunks, one JSON line is
+//! appended to `manifest.jsonl` in the index directory and synced to disk. An
+//! interrupted index therefore leaves a manifest that describes exactly the sources
+//! whose chunks are durable, instead of orphaned chunk files with no inventory.
+//!
+//! Local files are recorded with their modification time and SHA-256 so re-indexing a
+//! directory only re-embeds files that are new or changed, and drops deleted ones.
+//! Records also carry the chunk size and overlap the source was split with, so a run
+//! with different chunking re-splits files even when their content is unchanged.
+//!
+//! Crawls write their pages under a directory keyed by the start URL, with the time the
+//! crawl began. `index --resume` reuses it and skips pages recorded since that time.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE: &str = "manifest.jsonl";
+/// Directory under the index holding crawl output until a crawl completes
+const CRAWLS_DIR: &str = "crawls";
+/// File in a crawl directory holding the time the crawl began
+const CRAWL_STARTED_FILE: &str = ".manx_crawl_started";
+
+/// Chunk size and overlap (approximate tokens) a source was split with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkParams {
+    pub size: usize,
+    pub overlap: usize,
+    /// Whether notebook code cell outputs were indexed (`rag.include_notebook_outputs`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub notebook_outputs: bool,
+}
+
+impl Default for ChunkParams {
+    fn default() -> Self {
+        Self {
+            size: 500,
+            overlap: 50,
+            notebook_outputs: false,
+        }
+    }
+}
+
+impl ChunkParams {
+    /// Whether `file` split with these parameters matches a split with `other`
+    ///
+    /// Notebook outputs only change how notebooks are split.
+    fn splits_like(&self, other: &ChunkParams, file: &Path) -> bool {
+        let notebook = file
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ipynb"));
+        self.size == other.size
+            && self.overlap == other.overlap
+            && (!notebook || self.notebook_outputs == other.notebook_outputs)
+    }
+}
+
+/// Manifest entry describing the chunks stored for one source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceRecord {
+    pub source: String,
+    /// Vector store keys of the source's chunks
+    ///
+    /// Older manifests call this `chunk_files`, from when chunks were files under `embeddings/`.
+    #[serde(alias = "chunk_files")]
+    pub chunk_keys: Vec<String>,
+    pub indexed_at: DateTime<Utc>,
+    /// Modification time of a local source file when it was indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    /// SHA-256 of a local source file's content when it was indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Chunking used for the stored chunks (absent for imported sources and older indexes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkParams>,
+    /// IDs of chunks whose embedding failed; `manx index --retry-failed` re-attempts them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_chunks: Vec<String>,
+    /// Tombstone: the source and its chunks were removed from the index
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+}
+
+impl SourceRecord {
+    /// Start a record for a source, fingerprinting it when it is a local file
+    pub fn new(source: String) -> Self {
+        let (modified, sha256) = file_fingerprint(Path::new(&source)).unzip();
+        Self {
+            source,
+            chunk_keys: Vec::new(),
+            indexed_at: Utc::now(),
+            modified,
+            sha256,
+            chunking: None,
+            failed_chunks: Vec::new(),
+            removed: false,
+        }
+    }
+
+    /// Record marking `source` as removed from the index
+    pub fn tombstone(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            chunk_keys: Vec::new(),
+            indexed_at: Utc::now(),
+            modified: None,
+            sha256: None,
+            chunking: None,
+            failed_chunks: Vec::new(),
+            removed: true,
+        }
+    }
+}
+
+/// Which files of a directory need (re-)indexing against the manifest
+#[derive(Debug, Default)]
+pub struct ReindexPlan {
+    /// New or changed files to chunk and embed
+    pub changed: Vec<PathBuf>,
+    /// Number of files whose content matches the manifest
+    pub unchanged: usize,
+    /// Unchanged files whose mtime moved; re-recorded so they skip hashing next time
+    pub refreshed: Vec<SourceRecord>,
+    /// Previously indexed files under the directory that no longer exist
+    pub deleted: Vec<SourceRecord>,
+}
+
+/// Compare the files found in `dir` with the manifest records
+///
+/// A file is unchanged when its mtime matches the record, or failing that, when its
+/// SHA-256 does. Files split with different chunk parameters than `chunking` (records
+/// without parameters predate them and used the defaults), including notebooks indexed
+/// with a different `include_notebook_outputs`, are changed, and with `force` every file is.
+pub fn plan_reindex(
+    records: &HashMap<String, SourceRecord>,
+    dir: &Path,
+    files: &[PathBuf],
+    chunking: ChunkParams,
+    force: bool,
+) -> ReindexPlan {
+    let mut plan = ReindexPlan::default();
+
+    for file in files {
+        let source = file.to_string_lossy().to_string();
+        let record = match records.get(&source) {
+            Some(record)
+                if !force
+                    && record.sha256.is_some()
+                    && record
+                        .chunking
+                        .unwrap_or_default()
+                        .splits_like(&chunking, file) =>
+            {
+                record
+            }
+            _ => {
+                plan.changed.push(file.clone());
+                continue;
+            }
+        };
+
+        let modified = file_modified(file);
+        if modified.is_some() && modified == record.modified {
+            plan.unchanged += 1;
+            continue;
+        }
+
+        match file_sha256(file) {
+            Some(hash) if Some(&hash) == record.sha256.as_ref() => {
+                plan.unchanged += 1;
+                plan.refreshed.push(SourceRecord {
+                    modified,
+                    indexed_at: Utc::now(),
+                    ..record.clone()
+                });
+            }
+            _ => plan.changed.push(file.clone()),
+        }
+    }
+
+    let present: HashSet<String> = files
+        .iter()
+        .map(|f| f.to_string_lossy().to_string())
+        .collect();
+    plan.deleted = records
+        .values()
+        .filter(|record| record.sha256.is_some() && !present.contains(&record.source))
+        .filter(|record| {
+            let path = Path::new(&record.source);
+            path.starts_with(dir) && !path.exists()
+        })
+        .cloned()
+        .collect();
+
+    plan
+}
+
+/// How an indexed source relates to a user-supplied source argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceMatch {
+    /// The same file path or URL
+    Exact,
+    /// The source lives under the given directory or URL prefix (or merely starts with it)
+    Prefix,
+    None,
+}
+
+/// Match a stored source path or URL against a source argument
+///
+/// Trailing slashes are ignored so `https://site/docs/` and `https://site/docs` match.
+pub fn match_source(stored: &str, wanted: &str) -> SourceMatch {
+    let stored = stored.trim_end_matches('/');
+    let wanted = wanted.trim_end_matches('/');
+    if wanted.is_empty() {
+        return SourceMatch::None;
+    }
+    if stored == wanted {
+        SourceMatch::Exact
+    } else if stored.starts_with(wanted) {
+        SourceMatch::Prefix
+    } else {
+        SourceMatch::None
+    }
+}
+
+/// Modification time of a file, if it is a readable local file
+pub fn file_modified(path: &Path) -> Option<DateTime<Utc>> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Utc>::from(modified))
+}
+
+/// Hex SHA-256 of a file's content
+pub fn file_sha256(path: &Path) -> Option<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).ok()?;
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Modification time and content hash of a local file (`None` for URLs and missing files)
+fn file_fingerprint(path: &Path) -> Option<(DateTime<Utc>, String)> {
+    if !path.is_file() {
+        return None;
+    }
+    Some((file_modified(path)?, file_sha256(path)?))
+}
+
+/// Output directory for a new crawl starting at `url`
+///
+/// Each run gets its own directory, so two crawls of one URL never write into each other's;
+/// [`interrupted_crawl`] finds it again for `--resume`.
+pub fn crawl_dir(index_path: &Path, url: &str) -> PathBuf {
+    index_path.join(CRAWLS_DIR).join(format!(
+        "{}-{}",
+        crawl_key(url),
+        uuid::Uuid::new_v4().simple()
+    ))
+}
+
+/// Prefix of the crawl directory names for `url`
+fn crawl_key(url: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let hash = format!("{:x}", Sha256::digest(url.trim_end_matches('/').as_bytes()));
+    hash[..16].to_string()
+}
+
+/// Create `dir` and record that a crawl begins now
+pub fn start_crawl(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(CRAWL_STARTED_FILE), Utc::now().to_rfc3339())?;
+    Ok(())
+}
+
+/// Directory and start time of the most recent interrupted crawl of `url`
+///
+/// Directories from before crawls had a per-run suffix are named after the URL alone.
+pub fn interrupted_crawl(index_path: &Path, url: &str) -> Option<(PathBuf, DateTime<Utc>)> {
+    let key = crawl_key(url);
+    let prefix = format!("{}-", key);
+    std::fs::read_dir(index_path.join(CRAWLS_DIR))
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name == key || name.starts_with(&prefix)
+        })
+        .filter_map(|entry| {
+            let dir = entry.path();
+            crawl_started(&dir).map(|started| (dir, started))
+        })
+        .max_by_key(|(_, started)| *started)
+}
+
+/// When the crawl in `dir` began
+fn crawl_started(dir: &Path) -> Option<DateTime<Utc>> {
+    let started = std::fs::read_to_string(dir.join(CRAWL_STARTED_FILE)).ok()?;
+    let started = DateTime::parse_from_rfc3339(started.trim()).ok()?;
+    Some(started.with_timezone(&Utc))
+}
+
+/// Sources whose chunks were stored at or after `since`
+pub fn stored_since(
+    records: &HashMap<String, SourceRecord>,
+    since: DateTime<Utc>,
+) -> HashSet<String> {
+    records
+        .values()
+        .filter(|record| record.indexed_at >= since && !record.chunk_keys.is_empty())
+        .map(|record| record.source.clone())
+        .collect()
+}
+
+/// Append-only manifest stored alongside the embeddings
+pub struct IndexManifest {
+    path: PathBuf,
+}
+
+impl IndexManifest {
+    /// Manifest for the given index directory
+    pub fn new(index_path: &Path) -> Self {
+        Self {
+            path: index_path.join(MANIFEST_FILE),
+        }
+    }
+
+    /// Append a record and sync it to disk
+    pub fn append(&self, record: &SourceRecord) -> Result<()> {
+        // Single write per line so concurrent appenders never interleave records
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Load the latest record per source (later lines win); tolerates a torn last line
+    pub fn load(&self) -> Result<HashMap<String, SourceRecord>> {
+        let mut records = HashMap::new();
+        if !self.path.exists() {
+            return Ok(records);
+        }
+
+        let reader = BufReader::new(std::fs::File::open(&self.path)?);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<SourceRecord>(&line) {
+                Ok(record) if record.removed => {
+                    records.remove(&record.source);
+                }
+                Ok(record) => {
+                    records.insert(record.source.clone(), record);
+                }
+                Err(e) => log::warn!("Skipping unreadable manifest entry: {}", e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Remove the manifest (used when the whole index is cleared)
+    pub fn clear(&self) -> Result<()> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_append_and_load() {
+        let dir = std::env::temp_dir().join(format!("manx_manifest_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = IndexManifest::new(&dir);
+
+        for (files, failed) in [(vec!["a"], vec![]), (vec!["b", "c"], vec!["guide_3"])] {
+            manifest
+                .append(&SourceRecord {
+                    chunk_keys: files.into_iter().map(String::from).collect(),
+                    failed_chunks: failed.into_iter().map(String::from).collect(),
+                    ..SourceRecord::new("/docs/guide.md".to_string())
+                })
+                .unwrap();
+        }
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.join(MANIFEST_FILE))
+            .unwrap();
+        // A record written before the field was renamed
+        file.write_all(
+            b"{\"source\":\"/docs/old.md\",\"chunk_files\":[\"old_0\"],\"indexed_at\":\"2025-01-01T00:00:00Z\"}\n",
+        )
+        .unwrap();
+        // Simulate a crash mid-write of a last record
+        file.write_all(b"{\"source\":\"/docs/other").unwrap();
+
+        let records = manifest.load().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records["/docs/old.md"].chunk_keys, vec!["old_0"]);
+        assert_eq!(records["/docs/guide.md"].chunk_keys, vec!["b", "c"]);
+        assert_eq!(records["/docs/guide.md"].failed_chunks, vec!["guide_3"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plan_reindex_skips_unchanged_files() {
+        let dir = std::env::temp_dir().join(format!("manx_reindex_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = IndexManifest::new(&dir);
+
+        let same = dir.join("same.md");
+        let edited = dir.join("edited.md");
+        let gone = dir.join("gone.md");
+        for path in [&same, &edited, &gone] {
+            std::fs::write(path, "original").unwrap();
+            manifest
+                .append(&SourceRecord {
+                    chunk_keys: vec!["chunk".to_string()],
+                    ..SourceRecord::new(path.to_string_lossy().to_string())
+                })
+                .unwrap();
+        }
+        std::fs::write(&edited, "edited content").unwrap();
+        std::fs::remove_file(&gone).unwrap();
+        let added = dir.join("added.md");
+        std::fs::write(&added, "new").unwrap();
+
+        let records = manifest.load().unwrap();
+        let files = vec![same.clone(), edited.clone(), added.clone()];
+        let chunking = ChunkParams::default();
+        let plan = plan_reindex(&records, &dir, &files, chunking, false);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.changed, vec![edited.clone(), added.clone()]);
+        assert_eq!(plan.deleted.len(), 1);
+        assert_eq!(plan.deleted[0].source, gone.to_string_lossy());
+
+        let forced = plan_reindex(&records, &dir, &files, chunking, true);
+        assert_eq!(forced.changed.len(), 3);
+        assert_eq!(forced.unchanged, 0);
+
+        // Different chunk parameters re-split unchanged files too
+        let rechunked = plan_reindex(
+            &records,
+            &dir,
+            &files,
+            ChunkParams {
+                size: 200,
+                overlap: 20,
+                notebook_outputs: false,
+            },
+            false,
+        );
+        assert_eq!(rechunked.changed.len(), 3);
+
+        // Toggling notebook outputs re-splits notebooks only
+        let notebook = dir.join("analysis.ipynb");
+        std::fs::write(&notebook, "{}").unwrap();
+        manifest
+            .append(&SourceRecord {
+                chunking: Some(chunking),
+                ..SourceRecord::new(notebook.to_string_lossy().to_string())
+            })
+            .unwrap();
+        let records = manifest.load().unwrap();
+        let files = vec![same.clone(), notebook.clone()];
+        let with_outputs = ChunkParams {
+            notebook_outputs: true,
+            ..chunking
+        };
+        let plan = plan_reindex(&records, &dir, &files, with_outputs, false);
+        assert_eq!(plan.changed, vec![notebook.clone()]);
+        assert_eq!(plan.unchanged, 1);
+
+        // Tombstones drop a source from the loaded manifest
+        manifest
+            .append(&SourceRecord::tombstone(&gone.to_string_lossy()))
+            .unwrap();
+        assert!(!manifest
+            .load()
+            .unwrap()
+            .contains_key(gone.to_string_lossy().as_ref()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crawl_dir_resume_state() {
+        let index = std::env::temp_dir().join(format!("manx_crawls_{}", uuid::Uuid::new_v4()));
+        let url = "https://docs.example.com/";
+        let first = crawl_dir(&index, url);
+        let second = crawl_dir(&index, "https://docs.example.com");
+        assert_ne!(first, second);
+        assert_eq!(interrupted_crawl(&index, url), None);
+
+        start_crawl(&first).unwrap();
+        start_crawl(&second).unwrap();
+        let started = crawl_started(&second).unwrap();
+        std::fs::write(
+            first.join(CRAWL_STARTED_FILE),
+            (started - chrono::Duration::hours(1)).to_rfc3339(),
+        )
+        .unwrap();
+        // The newest crawl of the URL, whichever way it is spelled, is the one resumed
+        assert_eq!(
+            interrupted_crawl(&index, "https://docs.example.com"),
+            Some((second, started))
+        );
+        assert_eq!(interrupted_crawl(&index, "https://docs.example.org"), None);
+
+        let old = SourceRecord {
+            indexed_at: started - chrono::Duration::hours(1),
+            chunk_keys: vec!["a".to_string()],
+            ..SourceRecord::new("https://docs.example.com/old".to_string())
+        };
+        let new = SourceRecord {
+            chunk_keys: vec!["b".to_string()],
+            ..SourceRecord::new("https://docs.example.com/new".to_string())
+        };
+        let records = HashMap::from([(old.source.clone(), old), (new.source.clone(), new)]);
+        assert_eq!(
+            stored_since(&records, started),
+            HashSet::from(["https://docs.example.com/new".to_string()])
+        );
+        std::fs::remove_dir_all(&index).unwrap();
+    }
+
+    #[test]
+    fn test_match_source() {
+        assert_eq!(
+            match_source("/docs/guide.md", "/docs/guide.md"),
+            SourceMatch::Exact
+        );
+        assert_eq!(
+            match_source("https://site.dev/docs/", "https://site.dev/docs"),
+            SourceMatch::Exact
+        );
+        assert_eq!(
+            match_source("/docs/api/intro.md", "/docs/"),
+            SourceMatch::Prefix
+        );
+        assert_eq!(
+            match_source("/docs/guide.md", "/docs/gui"),
+            SourceMatch::Prefix
+        );
+        assert_eq!(match_source("/notes/a.md", "/docs"), SourceMatch::None);
+        assert_eq!(match_source("/notes/a.md", "/"), SourceMatch::None);
+    }
+}
@@ -3,9 +3,10 @@
 //! Provides document indexing, semantic search, and LLM integration
 //! for enhanced documentation discovery and AI synthesis.
 
-use crate::rag::embeddings::EmbeddingModel;
+use crate::rag::embeddings::{ChunkOutcomes, EmbeddingModel};
 use crate::rag::indexer::Indexer;
 use crate::rag::llm::LlmClient;
+use crate::rag::robots::Robots;
 use crate::rag::search_engine::SmartSearchEngine;
 use anyhow::Result;
 use docrawl::{crawl, Config as DocrawlConfig, CrawlConfig};
@@ -20,28 +21,48 @@ use serde::{Deserialize, Serialize};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use walkdir::WalkDir;
 
+/// Print human-readable indexing progress to stderr (silenced by `--json-progress`)
+macro_rules! progress_note {
+    () => {
+        $crate::rag::progress::note(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::rag::progress::note(format_args!($($arg)*))
+    };
+}
+
+pub mod azure;
 pub mod benchmarks;
+pub mod bm25;
+pub mod chunking;
 pub mod embeddings;
 pub mod indexer;
 pub mod llm;
+pub mod manifest;
 pub mod model_metadata;
+pub mod progress;
 pub mod providers;
 pub mod query_enhancer;
 pub mod result_verifier;
+pub mod robots;
 pub mod search_engine;
+pub mod store;
+pub mod usage;
 
 /// Embedding provider types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub enum EmbeddingProvider {
     #[default]
     Hash, // Default hash-based embeddings (current implementation)
-    Onnx(String),        // Local ONNX model path
-    Ollama(String),      // Ollama model name
-    OpenAI(String),      // OpenAI model name (requires API key)
-    HuggingFace(String), // HuggingFace model name (requires API key)
-    Custom(String),      // Custom endpoint URL
+    Onnx(String),                        // Local ONNX model path
+    Ollama(String),                      // Ollama model name
+    OpenAI(String),                      // OpenAI model name (requires API key)
+    HuggingFace(String),                 // HuggingFace model name (requires API key)
+    Custom(String),                      // Custom endpoint URL
+    AzureOpenAI(azure::AzureDeployment), // Azure OpenAI deployment (requires API key)
 }
 
 /// Configuration for embedding generation
@@ -52,8 +73,24 @@ pub struct EmbeddingConfig {
     pub model_path: Option<PathBuf>, // For local models
     pub api_key: Option<String>,     // For API providers
     pub endpoint: Option<String>,    // For custom endpoints
+    /// Model requested from an OpenAI-compatible custom endpoint that serves several
+    #[serde(default)]
+    pub model: Option<String>,
     pub timeout_seconds: u64,
     pub batch_size: usize,
+    /// Batches embedded in parallel when indexing files (`--embed-concurrency` overrides)
+    #[serde(default = "default_max_in_flight")]
+    pub max_in_flight: usize,
+    /// Most requests per minute sent to the OpenAI or HuggingFace embedding API
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Most (estimated) input tokens per minute sent to the OpenAI or HuggingFace embedding API
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+}
+
+fn default_max_in_flight() -> usize {
+    4
 }
 
 impl Default for EmbeddingConfig {
@@ -64,8 +101,12 @@ impl Default for EmbeddingConfig {
             model_path: None,
             api_key: None,
             endpoint: None,
+            model: None,
             timeout_seconds: 30,
             batch_size: 32,
+            max_in_flight: default_max_in_flight(),
+            requests_per_minute: None,
+            tokens_per_minute: None,
         }
     }
 }
@@ -114,6 +155,24 @@ pub struct SmartSearchConfig {
     pub max_query_variations: usize,      // Number of query variations to try
     pub enable_multi_stage: bool,         // Enable multi-stage search strategy
     pub adaptive_chunking: bool,          // Use smart code-aware chunking
+    /// Word-overlap similarity (0-1) at which two results count as copies of the same text;
+    /// only the highest-scoring copy is kept. Values above 1 turn this off
+    #[serde(default = "default_near_duplicate_threshold")]
+    pub near_duplicate_threshold: f32,
+    /// Share of the semantic score given to BM25 keyword matching (0-1); 0 is pure semantic
+    #[serde(default)]
+    pub keyword_weight: f32,
+    /// Have the LLM reorder the top candidates by relevance (`--rerank`); needs
+    /// `enable_result_verification` and a configured LLM provider
+    #[serde(default)]
+    pub llm_rerank: bool,
+}
+
+/// Keyword weight used by `--hybrid` when none is configured
+pub const DEFAULT_KEYWORD_WEIGHT: f32 = 0.3;
+
+fn default_near_duplicate_threshold() -> f32 {
+    0.9
 }
 
 impl Default for SmartSearchConfig {
@@ -126,6 +185,9 @@ impl Default for SmartSearchConfig {
             max_query_variations: 3,
             enable_multi_stage: true,
             adaptive_chunking: true,
+            near_duplicate_threshold: default_near_duplicate_threshold(),
+            keyword_weight: 0.0,
+            llm_rerank: false,
         }
     }
 }
@@ -144,6 +206,60 @@ pub struct RagConfig {
     pub max_file_size_mb: u64,
     pub embedding: EmbeddingConfig,
     pub smart_search: SmartSearchConfig,
+    /// Hide the one-time notice shown when searching with hash embeddings
+    #[serde(default)]
+    pub hide_hash_notice: bool,
+    /// Top local score below which `search --rag` is supplemented with web results (0 disables)
+    #[serde(default = "default_web_fallback_threshold")]
+    pub web_fallback_threshold: f32,
+    /// Chunk size in tokens (approximately) used when splitting documents
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+    /// Overlap between consecutive chunks in tokens; must be smaller than `chunk_size`
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: usize,
+    /// Index the text outputs of Jupyter notebook code cells along with their source
+    #[serde(default)]
+    pub include_notebook_outputs: bool,
+    /// Crawl pages robots.txt disallows and skip its crawl delay, for sites you own
+    #[serde(default)]
+    pub ignore_robots: bool,
+}
+
+fn default_web_fallback_threshold() -> f32 {
+    0.5
+}
+
+fn default_chunk_size() -> usize {
+    manifest::ChunkParams::default().size
+}
+
+fn default_chunk_overlap() -> usize {
+    manifest::ChunkParams::default().overlap
+}
+
+impl RagConfig {
+    /// Validated chunking parameters for an index run
+    pub fn chunk_params(&self) -> Result<manifest::ChunkParams> {
+        if self.chunk_size < 2 {
+            anyhow::bail!(
+                "Chunk size must be at least 2 tokens (got {})",
+                self.chunk_size
+            );
+        }
+        if self.chunk_overlap >= self.chunk_size {
+            anyhow::bail!(
+                "Chunk overlap ({}) must be smaller than chunk size ({})",
+                self.chunk_overlap,
+                self.chunk_size
+            );
+        }
+        Ok(manifest::ChunkParams {
+            size: self.chunk_size,
+            overlap: self.chunk_overlap,
+            notebook_outputs: self.include_notebook_outputs,
+        })
+    }
 }
 
 impl Default for RagConfig {
@@ -160,6 +276,12 @@ impl Default for RagConfig {
             max_file_size_mb: 100, // 100MB default limit
             embedding: EmbeddingConfig::default(),
             smart_search: SmartSearchConfig::default(),
+            hide_hash_notice: false,
+            web_fallback_threshold: default_web_fallback_threshold(),
+            chunk_size: default_chunk_size(),
+            chunk_overlap: default_chunk_overlap(),
+            include_notebook_outputs: false,
+            ignore_robots: false,
         }
     }
 }
@@ -194,6 +316,55 @@ pub struct DocumentMetadata {
     pub modified: chrono::DateTime<chrono::Utc>,
     pub tags: Vec<String>,
     pub language: Option<String>,
+    /// First and last line (1-based) of a local file that a chunk covers, approximately
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_range: Option<(usize, usize)>,
+}
+
+/// Whether `metadata.language` is one of `languages` (case-insensitive); empty matches all
+///
+/// A wanted language may also be given as a file extension, such as `rs` for Rust.
+pub fn matches_language(metadata: &DocumentMetadata, languages: &[String]) -> bool {
+    if languages.is_empty() {
+        return true;
+    }
+    metadata.language.as_deref().is_some_and(|language| {
+        languages.iter().any(|wanted| {
+            let wanted = wanted.trim();
+            wanted.eq_ignore_ascii_case(language)
+                || indexer::extension_language(&wanted.to_lowercase())
+                    .is_some_and(|name| name.eq_ignore_ascii_case(language))
+        })
+    })
+}
+
+/// Whether `metadata.tags` contains every one of `tags` (case-insensitive); empty matches all
+pub fn matches_tags(metadata: &DocumentMetadata, tags: &[String]) -> bool {
+    tags.iter().all(|wanted| {
+        metadata
+            .tags
+            .iter()
+            .any(|tag| tag.eq_ignore_ascii_case(wanted.trim()))
+    })
+}
+
+/// Metadata filters that limit which chunks a search ranks
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// Keep chunks in any of these languages
+    pub languages: Vec<String>,
+    /// Keep chunks carrying all of these tags
+    pub tags: Vec<String>,
+}
+
+impl SearchFilters {
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty() && self.tags.is_empty()
+    }
+
+    pub fn matches(&self, metadata: &DocumentMetadata) -> bool {
+        matches_language(metadata, &self.languages) && matches_tags(metadata, &self.tags)
+    }
 }
 
 /// Search result from RAG
@@ -208,6 +379,19 @@ pub struct RagSearchResult {
     pub score: f32,
     pub chunk_index: usize,
     pub metadata: DocumentMetadata,
+    /// How `score` was reached, for `manx explain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<crate::explain::ScoreBreakdown>,
+}
+
+impl RagSearchResult {
+    /// Replace the score, recording the change in its breakdown
+    pub fn rescore(&mut self, label: impl Into<String>, score: f32) {
+        self.score = score;
+        if let Some(breakdown) = &mut self.score_breakdown {
+            breakdown.step(label, score);
+        }
+    }
 }
 
 /// RAG system stats
@@ -220,7 +404,31 @@ pub struct RagStats {
     pub sources: Vec<String>,
 }
 
-/// Stored chunk with embedding for file-based vector storage
+/// Chunks and content indexed from one source, for `manx sources stats --per-source`
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    /// Source path or URL
+    pub source: String,
+    pub source_type: SourceType,
+    pub chunks: usize,
+    /// Total length of the chunks' text
+    pub content_bytes: u64,
+    /// Newest modification time recorded for the source's chunks
+    pub last_modified: chrono::DateTime<chrono::Utc>,
+}
+
+/// Indexed chunks selected for removal by `manx sources remove`
+#[derive(Debug, Default)]
+pub struct SourceSelection {
+    /// Vector store keys of the chunks belonging to the matched sources
+    pub chunk_keys: Vec<String>,
+    /// Matched source paths or URLs
+    pub sources: std::collections::BTreeSet<String>,
+    /// Whether any source only matched by prefix rather than exactly
+    pub partial: bool,
+}
+
+/// Stored chunk with embedding, as kept in the vector store
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredChunk {
     pub id: String,
@@ -231,15 +439,81 @@ pub struct StoredChunk {
     pub section: Option<String>,
     pub chunk_index: usize,
     pub metadata: DocumentMetadata,
+    /// Kept out of the JSON metadata in the binary store, which writes it as raw floats
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub embedding: Vec<f32>,
 }
 
+/// Outcome of re-embedding every stored chunk with the current provider
+#[derive(Debug)]
+pub struct ReindexSummary {
+    /// Chunks whose embeddings were regenerated
+    pub reembedded: usize,
+    /// Chunks dropped because they had no stored content to embed
+    pub dropped: usize,
+    /// Embedding dimension found in the index before re-embedding
+    pub previous_dimension: Option<usize>,
+    /// Embedding dimension of the current provider
+    pub dimension: usize,
+}
+
+/// Findings of `manx rag verify`
+#[derive(Debug, Default)]
+pub struct IndexVerification {
+    /// Chunks that read back cleanly
+    pub chunks: usize,
+    /// Superseded or deleted records awaiting compaction (not a problem)
+    pub dead_records: usize,
+    /// Records that couldn't be decoded
+    pub corrupt_records: usize,
+    /// Bytes of a half-written record at the end of the store
+    pub truncated_bytes: usize,
+    /// Dimension the configured embedding provider produces
+    pub dimension: usize,
+    /// Readable chunks whose embedding length differs from `dimension`, by source
+    pub dimension_mismatches: Vec<(String, usize)>,
+    /// Legacy chunk files that couldn't be migrated into the store
+    pub legacy_leftovers: Vec<PathBuf>,
+    /// Local sources that no longer exist on disk, with their chunk counts
+    pub missing_sources: Vec<(PathBuf, usize)>,
+    /// Whether `--repair` rewrote the store
+    pub repaired: bool,
+}
+
+impl IndexVerification {
+    /// Problems `--repair` can fix (missing sources are reported, not removed)
+    pub fn repairable_problems(&self) -> usize {
+        self.corrupt_records
+            + usize::from(self.truncated_bytes > 0)
+            + self
+                .dimension_mismatches
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<usize>()
+            + self.legacy_leftovers.len()
+    }
+}
+
 /// Local file-based RAG system
 pub struct RagSystem {
     config: RagConfig,
     llm_client: Option<LlmClient>,
+    /// Configured embedding model, loaded on first use and reused by every later operation
+    ///
+    /// Loading is deferred so commands that never embed, like `manx sources list`, skip it.
+    embedding_model: tokio::sync::OnceCell<Arc<EmbeddingModel>>,
+    /// Model for semantic search, picked on the first search; `None` means text matching
+    search_model: tokio::sync::OnceCell<Option<Arc<EmbeddingModel>>>,
+    /// Stored chunks from the last search, with the store version they were read at
+    ///
+    /// Later searches reuse them until the store changes, so a long-running `manx serve`
+    /// reads the index once rather than per request.
+    index_chunks: std::sync::Mutex<Option<IndexSnapshot>>,
 }
 
+/// Stored chunks read at one version of the vector store
+type IndexSnapshot = (Option<(u64, std::time::SystemTime)>, Arc<Vec<StoredChunk>>);
+
 impl RagSystem {
     pub async fn new(config: RagConfig) -> Result<Self> {
         Self::new_with_llm(config, None).await
@@ -261,7 +535,66 @@ impl RagSystem {
             "RAG system initialized with local vector storage at {:?}",
             index_path
         );
-        Ok(Self { config, llm_client })
+        Ok(Self {
+            config,
+            llm_client,
+            embedding_model: tokio::sync::OnceCell::new(),
+            search_model: tokio::sync::OnceCell::new(),
+            index_chunks: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// Reuse an already-loaded embedding model for indexing and search instead of loading one
+    pub fn with_shared_embeddings(mut self, embedding_model: Arc<EmbeddingModel>) -> Self {
+        self.search_model = tokio::sync::OnceCell::new_with(Some(Some(embedding_model.clone())));
+        self.embedding_model = tokio::sync::OnceCell::new_with(Some(embedding_model));
+        self
+    }
+
+    /// The configured embedding model, loading it the first time it is needed
+    async fn embedding_model(&self) -> Result<Arc<EmbeddingModel>> {
+        self.embedding_model
+            .get_or_try_init(|| async {
+                EmbeddingModel::new_with_config(self.config.embedding.clone())
+                    .await
+                    .map(Arc::new)
+            })
+            .await
+            .cloned()
+    }
+
+    /// The semantic search model, chosen the first time a search runs
+    ///
+    /// Shared with web search run alongside the index, so only one model is loaded.
+    pub async fn search_model(&self) -> Result<Option<Arc<EmbeddingModel>>> {
+        self.search_model
+            .get_or_try_init(|| SmartSearchEngine::initialize_embedding_model(&self.config))
+            .await
+            .cloned()
+    }
+
+    /// Every stored chunk, read again only when the store has changed since the last call
+    fn index_chunks(&self) -> Result<Arc<Vec<StoredChunk>>> {
+        let indexer = Indexer::new(&self.config)?;
+        let store = store::VectorStore::open(indexer.get_index_path())?;
+        let version = store.version();
+
+        let mut snapshot = self.index_chunks.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((read_at, chunks)) = snapshot.as_ref() {
+            if version.is_some() && *read_at == version {
+                return Ok(Arc::clone(chunks));
+            }
+        }
+        let chunks: Arc<Vec<StoredChunk>> = Arc::new(
+            store
+                .load()?
+                .chunks
+                .into_iter()
+                .map(|(_, chunk)| chunk)
+                .collect(),
+        );
+        *snapshot = Some((version, Arc::clone(&chunks)));
+        Ok(chunks)
     }
 
     pub async fn index_document(&mut self, path: PathBuf) -> Result<usize> {
@@ -274,29 +607,219 @@ impl RagSystem {
         let chunk_count = chunks.len();
 
         // Store chunks in local vector storage
-        self.store_chunks_locally(&chunks).await?;
+        let outcomes = self
+            .store_chunks_locally(&chunks, None, &Default::default())
+            .await?;
 
-        log::info!("Successfully indexed and stored {} chunks", chunk_count);
-        Ok(chunk_count)
+        let stored = chunk_count - outcomes.failed;
+        log::info!("Successfully indexed and stored {} chunks", stored);
+        progress::emit("done", done_event(stored, outcomes));
+        Ok(stored)
     }
 
-    pub async fn index_directory(&mut self, path: PathBuf) -> Result<usize> {
+    /// Index a directory, re-embedding only files that are new or changed since the last run
+    ///
+    /// Unchanged files (same mtime or SHA-256 as recorded in the manifest, split with the
+    /// configured chunk size and overlap) are skipped and chunks of deleted files are
+    /// removed. `force` re-indexes every file. Only files passing `filter` are considered;
+    /// previously indexed files it now excludes are left in the index. Up to
+    /// `embed_concurrency` batches (default `embedding.max_in_flight`) are embedded at once.
+    pub async fn index_directory(
+        &mut self,
+        path: PathBuf,
+        force: bool,
+        filter: &indexer::PathFilter,
+        embed_concurrency: Option<usize>,
+    ) -> Result<usize> {
         if !self.config.enabled {
             return Err(anyhow::anyhow!("RAG system is disabled"));
         }
 
         let indexer = Indexer::new(&self.config)?;
-        let chunks = indexer.index_directory(path)?;
+        let index_path = indexer.get_index_path().clone();
+        let manifest = manifest::IndexManifest::new(&index_path);
+        let records = manifest.load()?;
+
+        let path = path.canonicalize().unwrap_or(path);
+        let files = indexer::find_documents(&path, filter)?;
+        let plan =
+            manifest::plan_reindex(&records, &path, &files, self.config.chunk_params()?, force);
+
+        // Drop stale chunks of changed and deleted files before storing new ones
+        let stale: Vec<&manifest::SourceRecord> = plan
+            .changed
+            .iter()
+            .filter_map(|file| records.get(file.to_string_lossy().as_ref()))
+            .chain(plan.deleted.iter())
+            .collect();
+        if !stale.is_empty() {
+            let keys: Vec<String> = stale
+                .iter()
+                .flat_map(|record| record.chunk_keys.iter().cloned())
+                .collect();
+            store::VectorStore::open(&index_path)?.remove(&keys)?;
+            for record in stale {
+                manifest.append(&manifest::SourceRecord::tombstone(&record.source))?;
+            }
+        }
+        for record in &plan.refreshed {
+            manifest.append(record)?;
+        }
+
+        if plan.unchanged > 0 || !plan.deleted.is_empty() {
+            progress_note!(
+                "Skipping {} unchanged files, re-indexing {} new or changed, removed {} deleted",
+                plan.unchanged,
+                plan.changed.len(),
+                plan.deleted.len()
+            );
+        }
+        progress::emit(
+            "plan",
+            serde_json::json!({
+                "changed": plan.changed.len(),
+                "unchanged": plan.unchanged,
+                "deleted": plan.deleted.len(),
+            }),
+        );
+
+        let chunks = indexer.index_files(&plan.changed);
         let chunk_count = chunks.len();
 
         // Store chunks in local vector storage
-        self.store_chunks_locally(&chunks).await?;
+        let outcomes = self
+            .store_chunks_locally(&chunks, embed_concurrency, &Default::default())
+            .await?;
 
+        let stored = chunk_count - outcomes.failed;
         log::info!(
             "Successfully indexed and stored {} chunks from directory",
-            chunk_count
+            stored
         );
-        Ok(chunk_count)
+        progress::emit("done", done_event(stored, outcomes));
+        Ok(stored)
+    }
+
+    /// Re-embed only the chunks whose embedding failed in earlier index runs
+    ///
+    /// Failed chunk IDs come from the manifest; `source` limits the retry to one file, URL,
+    /// directory or URL prefix. Each source is split again, with the chunking it was indexed
+    /// with, and only its failed chunks are embedded, joining the chunks already stored.
+    /// Sources changed since they were indexed are skipped, since their chunks no longer
+    /// line up with the recorded IDs: local files by their SHA-256, pages by comparing the
+    /// re-fetched chunks with the stored ones.
+    pub async fn retry_failed_chunks(
+        &self,
+        source: Option<&str>,
+        embed_concurrency: Option<usize>,
+    ) -> Result<usize> {
+        use std::collections::{HashMap, HashSet};
+
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("RAG system is disabled"));
+        }
+
+        let indexer = Indexer::new(&self.config)?;
+        let records = manifest::IndexManifest::new(indexer.get_index_path()).load()?;
+        let mut failed: Vec<&manifest::SourceRecord> = records
+            .values()
+            .filter(|record| !record.failed_chunks.is_empty())
+            .filter(|record| {
+                source.is_none_or(|wanted| {
+                    manifest::match_source(&record.source, wanted) != manifest::SourceMatch::None
+                })
+            })
+            .collect();
+        failed.sort_by(|a, b| a.source.cmp(&b.source));
+        if failed.is_empty() {
+            progress_note!("No failed chunks to retry");
+            progress::emit("done", done_event(0, ChunkOutcomes::default()));
+            return Ok(0);
+        }
+
+        let is_url = |source: &str| source.starts_with("http://") || source.starts_with("https://");
+        let stored: HashMap<String, StoredChunk> = if failed.iter().any(|r| is_url(&r.source)) {
+            store::VectorStore::open(indexer.get_index_path())?
+                .load()?
+                .chunks
+                .into_iter()
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let mut chunks = Vec::new();
+        let mut missing = 0;
+        for record in &failed {
+            let changed = || {
+                progress_note!(
+                    "Skipping {}: changed since it was indexed, re-index it instead",
+                    record.source
+                );
+            };
+            let path = PathBuf::from(&record.source);
+            if !is_url(&record.source)
+                && record.sha256.is_some()
+                && manifest::file_sha256(&path) != record.sha256
+            {
+                changed();
+                continue;
+            }
+
+            let chunking = record.chunking.unwrap_or_default();
+            let source_indexer = Indexer::new(&RagConfig {
+                chunk_size: chunking.size,
+                chunk_overlap: chunking.overlap,
+                include_notebook_outputs: chunking.notebook_outputs,
+                ..self.config.clone()
+            })?;
+            let split = if is_url(&record.source) {
+                source_indexer
+                    .index_single_url_no_crawl(&record.source)
+                    .await
+            } else {
+                source_indexer.index_document(path)
+            };
+            let source_chunks = match split {
+                Ok(source_chunks) => source_chunks,
+                Err(e) => {
+                    progress_note!("Skipping {}: {}", record.source, e);
+                    continue;
+                }
+            };
+            if is_url(&record.source) && !matches_stored_chunks(&source_chunks, record, &stored) {
+                changed();
+                continue;
+            }
+
+            let wanted: HashSet<&str> = record.failed_chunks.iter().map(String::as_str).collect();
+            let before = chunks.len();
+            chunks.extend(
+                source_chunks
+                    .into_iter()
+                    .filter(|chunk| wanted.contains(chunk.id.as_str())),
+            );
+            missing += wanted.len() - (chunks.len() - before);
+        }
+
+        progress_note!(
+            "Retrying {} failed chunks from {} sources",
+            chunks.len(),
+            failed.len()
+        );
+        if missing > 0 {
+            progress_note!(
+                "{} failed chunks no longer appear in their source; re-index it to replace them",
+                missing
+            );
+        }
+
+        let outcomes = self
+            .store_chunks_locally(&chunks, embed_concurrency, &records)
+            .await?;
+        let stored = chunks.len() - outcomes.failed;
+        progress::emit("done", done_event(stored, outcomes));
+        Ok(stored)
     }
 
     #[allow(dead_code)]
@@ -312,7 +835,8 @@ impl RagSystem {
         let chunk_count = chunks.len();
 
         // Store chunks in local vector storage
-        self.store_chunks_locally(&chunks).await?;
+        self.store_chunks_locally(&chunks, None, &Default::default())
+            .await?;
 
         log::info!(
             "Successfully indexed and stored {} chunks from URL",
@@ -348,7 +872,8 @@ impl RagSystem {
         let chunk_count = chunks.len();
 
         // Store chunks in local vector storage
-        self.store_chunks_locally(&chunks).await?;
+        self.store_chunks_locally(&chunks, None, &Default::default())
+            .await?;
 
         log::info!(
             "Successfully deep indexed and stored {} chunks from URL",
@@ -358,6 +883,10 @@ impl RagSystem {
     }
 
     /// Streamed deep indexing: overlaps crawling and embedding using Tokio for speed
+    ///
+    /// Crawl output is kept under a per-run directory named after `url` until the crawl completes.
+    /// With `resume`, an interrupted crawl continues from that directory, and pages whose
+    /// chunks were stored before the interruption are not embedded again.
     pub async fn index_url_deep_stream(
         &self,
         url: &str,
@@ -365,6 +894,7 @@ impl RagSystem {
         crawl_all: bool,
         embed_concurrency: Option<usize>,
         crawl_max_pages: Option<usize>,
+        resume: bool,
     ) -> Result<usize> {
         use std::collections::HashSet;
         use std::sync::Arc;
@@ -373,64 +903,122 @@ impl RagSystem {
 
         // If explicitly depth 0 and not crawl-all, do single-page fetch without the crawler
         if matches!(max_depth, Some(0)) && !crawl_all {
-            eprintln!("\nIndexing single page (no crawl): {}", url);
-            let embedding_model = std::sync::Arc::new(
-                EmbeddingModel::new_with_config(self.config.embedding.clone()).await?,
-            );
+            progress_note!("\nIndexing single page (no crawl): {}", url);
+            let embedding_model = self.embedding_model().await?;
             let indexer = Indexer::new(&self.config)?;
             let chunks = indexer.index_single_url_no_crawl(url).await?;
             let total_stored =
                 store_chunks_with_model_config(&self.config, &chunks, &embedding_model).await?;
 
             let index_path = indexer.get_index_path();
-            eprintln!("\n==== Manx Index Summary ====");
-            eprintln!("Mode: Single page (no crawl)");
-            eprintln!("Chunks created: {}", chunks.len());
-            eprintln!("Chunks stored: {}", total_stored);
-            eprintln!("Index path: {}", index_path.display());
+            progress_note!("\n==== Manx Index Summary ====");
+            progress_note!("Mode: Single page (no crawl)");
+            progress_note!("Chunks created: {}", chunks.len());
+            progress_note!("Chunks stored: {}", total_stored);
+            let outcomes = note_chunk_outcomes(&embedding_model, total_stored);
+            progress_note!("Index path: {}", index_path.display());
+            progress::emit("done", done_event(total_stored, outcomes));
             return Ok(total_stored);
         }
 
-        // If depth is 1 (shallow), prefer our manual shallow crawler to avoid docrawl host-scope quirks
-        if matches!(max_depth, Some(1)) && !crawl_all {
-            eprintln!("\nShallow crawl (depth 1) for: {}", url);
-            let embedding_model = std::sync::Arc::new(
-                EmbeddingModel::new_with_config(self.config.embedding.clone()).await?,
-            );
+        // If depth is 1 (shallow), prefer our manual shallow crawler to avoid docrawl host-scope quirks;
+        // only docrawl keeps the state a resumed crawl needs
+        if matches!(max_depth, Some(1)) && !crawl_all && !resume {
+            progress_note!("\nShallow crawl (depth 1) for: {}", url);
+            let embedding_model = self.embedding_model().await?;
             let indexer = Indexer::new(&self.config)?;
             let chunks = indexer.index_shallow_url(url, crawl_max_pages).await?;
             let total_stored =
                 store_chunks_with_model_config(&self.config, &chunks, &embedding_model).await?;
 
             let index_path = indexer.get_index_path();
-            eprintln!("\n==== Manx Index Summary ====");
-            eprintln!("Mode: Shallow crawl (depth 1)");
-            eprintln!("Chunks created: {}", chunks.len());
-            eprintln!("Chunks stored: {}", total_stored);
-            eprintln!("Index path: {}", index_path.display());
+            progress_note!("\n==== Manx Index Summary ====");
+            progress_note!("Mode: Shallow crawl (depth 1)");
+            progress_note!("Chunks created: {}", chunks.len());
+            progress_note!("Chunks stored: {}", total_stored);
+            let outcomes = note_chunk_outcomes(&embedding_model, total_stored);
+            progress_note!("Index path: {}", index_path.display());
+            progress::emit("done", done_event(total_stored, outcomes));
             return Ok(total_stored);
         }
 
-        let temp_dir = std::env::temp_dir().join(format!("manx_crawl_{}", uuid::Uuid::new_v4()));
-        std::fs::create_dir_all(&temp_dir)?;
+        let index_path = Indexer::new(&self.config)?.get_index_path().clone();
+        let interrupted = if resume {
+            let interrupted = manifest::interrupted_crawl(&index_path, url);
+            if interrupted.is_none() {
+                progress_note!(
+                    "\nNo interrupted crawl of {} to resume; starting fresh",
+                    url
+                );
+            }
+            interrupted
+        } else {
+            None
+        };
+        let (temp_dir, resumed_from) = match interrupted {
+            Some((dir, started)) => (dir, Some(started)),
+            None => {
+                let dir = manifest::crawl_dir(&index_path, url);
+                manifest::start_crawl(&dir)?;
+                (dir, None)
+            }
+        };
+        // Pages stored before the interruption are skipped rather than embedded again
+        let already_stored = Arc::new(match resumed_from {
+            Some(started) => {
+                let records = manifest::IndexManifest::new(&index_path).load()?;
+                manifest::stored_since(&records, started)
+            }
+            None => HashSet::new(),
+        });
+        if let Some(started) = resumed_from {
+            progress_note!(
+                "\nResuming crawl started {} ({} pages already stored)",
+                started
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M"),
+                already_stored.len()
+            );
+        }
 
         // Show initial status
-        eprintln!("\nStarting document crawl for: {}", url);
-        eprintln!("   This will: 1) Crawl pages -> 2) Chunk content -> 3) Create embeddings");
+        progress_note!("\nStarting document crawl for: {}", url);
+        progress_note!("   This will: 1) Crawl pages -> 2) Chunk content -> 3) Create embeddings");
         log::debug!("Temp directory: {}", temp_dir.display());
-        eprintln!();
+        progress_note!();
 
         // Resolve potential redirects to get canonical host (e.g., kali.org -> www.kali.org)
-        let base_url = if let Ok(resp) = reqwest::Client::new().get(url).send().await {
-            resp.url().clone()
-        } else {
-            url::Url::parse(url)?
-        };
+        let base_url =
+            if let Ok(resp) = crate::http_client::builder().build()?.get(url).send().await {
+                resp.url().clone()
+            } else {
+                url::Url::parse(url)?
+            };
+        let robots = Robots::new(self.config.ignore_robots);
+        let crawl_delay = robots.crawl_delay(&base_url).await;
+        let (rate_limit_per_sec, concurrency) = robots::docrawl_pacing(
+            crawl_delay,
+            20,                                // Reasonable rate limit for documentation sites
+            std::cmp::max(8, num_cpus::get()), // Use more threads for faster crawling
+        );
+        if let Some(delay) = crawl_delay {
+            progress_note!(
+                "robots.txt asks for {:?} between requests; crawling at {} page(s)/s",
+                delay,
+                rate_limit_per_sec
+            );
+        }
+        if self.config.ignore_robots {
+            // docrawl applies Disallow rules itself; only the crawl delay can be lifted
+            progress_note!(
+                "Ignoring the robots.txt crawl delay (docrawl still skips disallowed pages)"
+            );
+        }
         // Configure docrawl secondary options (use defaults)
         let crawl_config = CrawlConfig {
             base_url,
             output_dir: temp_dir.clone(),
-            user_agent: "Manx/0.5.0 (Documentation Crawler)".to_string(),
+            user_agent: crate::http_client::user_agent(),
             max_depth: if let Some(d) = max_depth {
                 Some(d as usize)
             } else if crawl_all {
@@ -438,19 +1026,20 @@ impl RagSystem {
             } else {
                 Some(3)
             },
-            silence: true,          // Silence docrawl; Manx renders its own progress UI
-            rate_limit_per_sec: 20, // Reasonable rate limit for documentation sites
+            silence: true, // Silence docrawl; Manx renders its own progress UI
+            rate_limit_per_sec,
             follow_sitemaps: true,
-            concurrency: std::cmp::max(8, num_cpus::get()), // Use more threads for faster crawling
+            concurrency,
             timeout: Some(std::time::Duration::from_secs(30)),
-            resume: false,
-            // Additional crawler behavior configuration
-            config: DocrawlConfig::default(),
+            resume: resumed_from.is_some(),
+            // Stop the crawler itself at the page cap rather than only indexing that many
+            config: DocrawlConfig {
+                max_pages: crawl_max_pages,
+                ..DocrawlConfig::default()
+            },
         };
 
-        // Create embedding model once
-        let embedding_model =
-            Arc::new(EmbeddingModel::new_with_config(self.config.embedding.clone()).await?);
+        let embedding_model = self.embedding_model().await?;
 
         // Channel for discovered markdown files
         let (tx, rx) = mpsc::channel::<PathBuf>(200);
@@ -461,6 +1050,7 @@ impl RagSystem {
         let pages_counter = Arc::new(AtomicUsize::new(0));
         let processed_pages_counter = Arc::new(AtomicUsize::new(0));
         let chunks_counter = Arc::new(AtomicUsize::new(0));
+        let skipped_pages_counter = Arc::new(AtomicUsize::new(0));
 
         // Track when crawl is done
         let crawl_done = Arc::new(AtomicBool::new(false));
@@ -539,6 +1129,7 @@ impl RagSystem {
                         new_found,
                         seen.len()
                     );
+                    progress::emit("crawl", serde_json::json!({ "pages": seen.len() }));
                     idle_ticks = 0;
                     // Speed up scanning when we're finding files
                     if scan_interval_ms > 300 {
@@ -560,7 +1151,7 @@ impl RagSystem {
                 }
 
                 if seen.len() >= crawl_max_pages {
-                    eprintln!("Scanner: Reached max pages limit ({})", crawl_max_pages);
+                    progress_note!("Scanner: Reached max pages limit ({})", crawl_max_pages);
                     break;
                 }
 
@@ -600,6 +1191,8 @@ impl RagSystem {
             let url_clone = url_for_worker.clone();
             let chunks_counter = chunks_counter.clone();
             let processed_pages_counter = processed_pages_counter.clone();
+            let skipped_pages_counter = skipped_pages_counter.clone();
+            let already_stored = already_stored.clone();
             let join = tokio::spawn(async move {
                 let mut stored = 0usize;
                 let idx = match Indexer::new(&config_clone) {
@@ -609,6 +1202,11 @@ impl RagSystem {
                 loop {
                     let opt_path = { rx.lock().await.recv().await };
                     let Some(md_path) = opt_path else { break };
+                    if already_stored.contains(&idx.derive_page_url(&md_path, &url_clone)) {
+                        skipped_pages_counter.fetch_add(1, Ordering::Relaxed);
+                        processed_pages_counter.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
                     if let Ok(chunks) = idx.process_markdown_file(&md_path, &url_clone).await {
                         if let Ok(count) =
                             store_chunks_with_model_config(&config_clone, &chunks, &embedding_model)
@@ -629,20 +1227,20 @@ impl RagSystem {
         // Wait for crawl to complete first
         let crawl_result = crawl_handle.await;
         let crawled_pages = if let Ok(Ok(stats)) = &crawl_result {
-            eprintln!("\nCrawl completed: {} pages crawled", stats.pages);
+            progress_note!("\nCrawl completed: {} pages crawled", stats.pages);
             stats.pages
         } else if let Ok(Err(e)) = &crawl_result {
-            eprintln!("\nCrawl completed with error: {}", e);
+            progress_note!("\nCrawl completed with error: {}", e);
             0
         } else {
-            eprintln!("\nCrawl status unknown");
+            progress_note!("\nCrawl status unknown");
             0
         };
 
         // Monitor file discovery with a proper progress spinner
-        eprintln!("\nScanning for markdown files...");
+        progress_note!("\nScanning for markdown files...");
 
-        let pb = ProgressBar::new_spinner();
+        let pb = progress::attach(ProgressBar::new_spinner());
         pb.set_style(
             ProgressStyle::default_spinner()
                 .template("{spinner:.green} {msg}")
@@ -720,10 +1318,10 @@ impl RagSystem {
 
         // If we have pages to process, show chunking progress with a progress bar
         if total_pages_found > 0 {
-            eprintln!("\nProcessing {} markdown files...", total_pages_found);
+            progress_note!("\nProcessing {} markdown files...", total_pages_found);
 
             // Create a progress bar for chunking
-            let pb = ProgressBar::new(total_pages_found as u64);
+            let pb = progress::attach(ProgressBar::new(total_pages_found as u64));
             pb.set_style(
                 ProgressStyle::default_bar()
                     .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files ({percent}%) | {msg}")
@@ -741,6 +1339,14 @@ impl RagSystem {
                 let chunks_so_far = chunks_counter.load(Ordering::Relaxed);
 
                 if processed_so_far != last_processed {
+                    progress::emit(
+                        "chunk",
+                        serde_json::json!({
+                            "processed": processed_so_far,
+                            "total": total_pages_found,
+                            "chunks": chunks_so_far,
+                        }),
+                    );
                     pb.set_position(processed_so_far as u64);
                     pb.set_message(format!("{} chunks created", chunks_so_far));
                     last_processed = processed_so_far;
@@ -761,19 +1367,19 @@ impl RagSystem {
                 pb.abandon_with_message("Processing incomplete - some files may have failed");
             }
         } else {
-            eprintln!("\nNo markdown files found to process");
-            eprintln!(
+            progress_note!("\nNo markdown files found to process");
+            progress_note!(
                 "   The crawler processed {} pages but docrawl generated no markdown files.",
                 crawled_pages
             );
-            eprintln!("   This can happen when:");
-            eprintln!("   • The site uses JavaScript rendering that docrawl can't parse");
-            eprintln!("   • The pages contain mostly non-text content (images, PDFs, etc.)");
-            eprintln!("   • The site structure isn't compatible with the crawler");
+            progress_note!("   This can happen when:");
+            progress_note!("   • The site uses JavaScript rendering that docrawl can't parse");
+            progress_note!("   • The pages contain mostly non-text content (images, PDFs, etc.)");
+            progress_note!("   • The site structure isn't compatible with the crawler");
             // Extra newline intentionally removed to satisfy clippy
-            eprintln!("   Try:");
-            eprintln!("   • Using a different URL that points to documentation pages");
-            eprintln!("   • Indexing local files instead if you have them downloaded");
+            progress_note!("   Try:");
+            progress_note!("   • Using a different URL that points to documentation pages");
+            progress_note!("   • Indexing local files instead if you have them downloaded");
         }
 
         // Wait for all workers to complete
@@ -781,8 +1387,8 @@ impl RagSystem {
 
         if total_pages_found > 0 {
             // Only show spinner if we had files to process
-            eprintln!("\nWaiting for workers to finish...");
-            let pb_final = ProgressBar::new_spinner();
+            progress_note!("\nWaiting for workers to finish...");
+            let pb_final = progress::attach(ProgressBar::new_spinner());
             pb_final.set_style(
                 ProgressStyle::default_spinner()
                     .template("{spinner:.green} {msg}")
@@ -808,8 +1414,12 @@ impl RagSystem {
             }
         }
 
-        // Clean up temp directory (silently)
-        let _ = std::fs::remove_dir_all(&temp_dir);
+        // A finished crawl has nothing to resume; a failed one keeps its pages for --resume
+        if matches!(crawl_result, Ok(Ok(_))) {
+            let _ = std::fs::remove_dir_all(&temp_dir);
+        } else {
+            progress_note!("\nCrawl output kept; run the same command with --resume to continue");
+        }
 
         // Final summary (always show, even if no files found)
         let total_pages = pages_counter.load(Ordering::Relaxed);
@@ -818,22 +1428,194 @@ impl RagSystem {
         let indexer = Indexer::new(&self.config)?;
         let index_path = indexer.get_index_path();
 
-        eprintln!();
-        eprintln!("==== Manx Index Summary ====");
-        eprintln!("Markdown files found: {}", total_pages);
-        eprintln!("Files processed: {}", final_processed);
-        eprintln!("Chunks created: {}", final_chunks);
-        eprintln!("Chunks stored: {}", total_stored);
-        eprintln!("Index path: {}", index_path.display());
+        progress_note!();
+        progress_note!("==== Manx Index Summary ====");
+        progress_note!("Markdown files found: {}", total_pages);
+        progress_note!("Files processed: {}", final_processed);
+        let skipped_pages = skipped_pages_counter.load(Ordering::Relaxed);
+        if skipped_pages > 0 {
+            progress_note!("Already stored before resuming: {}", skipped_pages);
+        }
+        progress_note!("Chunks created: {}", final_chunks);
+        progress_note!("Chunks stored: {}", total_stored);
+        let outcomes = note_chunk_outcomes(&embedding_model, total_stored);
+        progress_note!("Index path: {}", index_path.display());
 
         if total_pages == 0 {
-            eprintln!();
-            eprintln!("No markdown files were found. Docrawl may not have generated any content.");
-            eprintln!("   This could mean the site structure is not compatible with crawling.");
+            progress_note!();
+            progress_note!(
+                "No markdown files were found. Docrawl may not have generated any content."
+            );
+            progress_note!(
+                "   This could mean the site structure is not compatible with crawling."
+            );
         } else if total_stored == 0 {
-            eprintln!();
-            eprintln!("No chunks were stored. The markdown files may have been empty.");
+            progress_note!();
+            progress_note!("No chunks were stored. The markdown files may have been empty.");
+        }
+
+        progress::emit("done", done_event(total_stored, outcomes));
+        Ok(total_stored)
+    }
+
+    /// Page URLs an index run would start from, fetching only the page or sitemap itself
+    ///
+    /// Sitemaps list every page selected by `sitemap_filters`; other URLs list the page and,
+    /// with `follow_links`, the same-host pages it links to. Capped at `max_pages`.
+    pub async fn discover_pages(
+        &self,
+        url: &str,
+        sitemap_filters: Option<&[String]>,
+        follow_links: bool,
+        max_pages: Option<usize>,
+    ) -> Result<Vec<String>> {
+        let indexer = Indexer::new(&self.config)?;
+        let mut pages = match sitemap_filters {
+            Some(filters) => {
+                let client = crate::http_client::builder()
+                    .timeout(std::time::Duration::from_secs(30))
+                    .build()?;
+                let listed = indexer.fetch_sitemap_urls(&client, url).await?;
+                let selected = indexer::filter_sitemap_urls(listed, filters)?;
+                indexer.robots_allowed(selected).await
+            }
+            None if follow_links => indexer.discover_links(url, max_pages).await?,
+            None => vec![url.to_string()],
+        };
+        pages.truncate(max_pages.unwrap_or(usize::MAX));
+        Ok(pages)
+    }
+
+    /// Index exactly the pages listed in a sitemap (and nested sitemap indexes), skipping link discovery
+    pub async fn index_sitemap(
+        &self,
+        sitemap_url: &str,
+        filters: &[String],
+        embed_concurrency: Option<usize>,
+    ) -> Result<usize> {
+        use std::sync::Arc;
+        use tokio::sync::Semaphore;
+        use tokio::task::JoinSet;
+
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("RAG system is disabled"));
+        }
+
+        let client = crate::http_client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+
+        let indexer = Arc::new(Indexer::new(&self.config)?);
+
+        progress_note!("\nReading sitemap: {}", sitemap_url);
+        let listed = indexer.fetch_sitemap_urls(&client, sitemap_url).await?;
+        let listed_count = listed.len();
+        let selected = indexer::filter_sitemap_urls(listed, filters)?;
+        let selected_count = selected.len();
+        let urls = indexer.robots_allowed(selected).await;
+        progress_note!(
+            "Sitemap lists {} pages ({} selected by filters)",
+            listed_count,
+            selected_count
+        );
+        if urls.len() < selected_count {
+            progress_note!(
+                "Skipping {} pages disallowed by robots.txt (--debug lists them)",
+                selected_count - urls.len()
+            );
+        }
+        progress::emit(
+            "sitemap",
+            serde_json::json!({ "listed": listed_count, "selected": urls.len() }),
+        );
+
+        if urls.is_empty() {
+            progress::emit("done", serde_json::json!({ "stored": 0 }));
+            return Ok(0);
+        }
+
+        let embedding_model = self.embedding_model().await?;
+
+        // Bounded concurrency: each permit covers one page fetch + chunk + embed
+        let workers = embed_concurrency.unwrap_or_else(|| std::cmp::max(4, num_cpus::get()));
+        let semaphore = Arc::new(Semaphore::new(workers.max(1)));
+        let mut set = JoinSet::new();
+        for url in urls.clone() {
+            let semaphore = semaphore.clone();
+            let client = client.clone();
+            let indexer = indexer.clone();
+            let embedding_model = embedding_model.clone();
+            let config = self.config.clone();
+            set.spawn(async move {
+                let _permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| PageFailure::Fetch)?;
+                let chunks = match indexer.index_sitemap_page(&client, &url).await {
+                    Ok(chunks) => chunks,
+                    Err(e) => {
+                        log::warn!("Failed to index sitemap page {}: {}", url, e);
+                        return Err(PageFailure::Fetch);
+                    }
+                };
+                store_chunks_with_model_config(&config, &chunks, &embedding_model)
+                    .await
+                    .map_err(|e| {
+                        log::warn!("Failed to store sitemap page {}: {}", url, e);
+                        PageFailure::Store
+                    })
+            });
+        }
+
+        let pb = progress::attach(ProgressBar::new(urls.len() as u64));
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} pages ({percent}%) | {msg}")
+                .unwrap()
+                .progress_chars("█▉▊▋▌▍▎▏  "),
+        );
+
+        let mut processed = 0usize;
+        let mut failed = 0usize;
+        let mut store_failed = 0usize;
+        let mut total_stored = 0usize;
+        while let Some(res) = set.join_next().await {
+            processed += 1;
+            match res {
+                Ok(Ok(count)) => total_stored += count,
+                Ok(Err(PageFailure::Store)) => {
+                    failed += 1;
+                    store_failed += 1;
+                }
+                _ => failed += 1,
+            }
+            pb.set_position(processed as u64);
+            pb.set_message(format!("{} chunks stored", total_stored));
+            progress::emit(
+                "chunk",
+                serde_json::json!({
+                    "processed": processed,
+                    "total": urls.len(),
+                    "chunks": total_stored,
+                }),
+            );
         }
+        pb.finish_with_message("Sitemap pages processed");
+
+        progress_note!("\n==== Manx Index Summary ====");
+        progress_note!("Mode: Sitemap");
+        progress_note!("Pages processed: {}", processed - failed);
+        progress_note!("Pages failed: {}", failed);
+        if store_failed > 0 {
+            progress_note!(
+                "  {} of them fetched but not stored (--debug shows why)",
+                store_failed
+            );
+        }
+        progress_note!("Chunks stored: {}", total_stored);
+        let outcomes = note_chunk_outcomes(&embedding_model, total_stored);
+        progress_note!("Index path: {}", indexer.get_index_path().display());
+        progress::emit("done", done_event(total_stored, outcomes));
 
         Ok(total_stored)
     }
@@ -842,6 +1624,20 @@ impl RagSystem {
         &self,
         query: &str,
         max_results: Option<usize>,
+    ) -> Result<Vec<RagSearchResult>> {
+        self.search_filtered(query, max_results, &SearchFilters::default())
+            .await
+    }
+
+    /// Search, keeping only chunks whose stored metadata passes `filters`
+    ///
+    /// Embeddings carry no language or tag information, so chunks are filtered on their
+    /// metadata before ranking.
+    pub async fn search_filtered(
+        &self,
+        query: &str,
+        max_results: Option<usize>,
+        filters: &SearchFilters,
     ) -> Result<Vec<RagSearchResult>> {
         if !self.config.enabled {
             return Err(anyhow::anyhow!("RAG system is disabled"));
@@ -850,11 +1646,23 @@ impl RagSystem {
         log::info!("Starting intelligent search for: '{}'", query);
 
         // Create smart search engine
-        let search_engine =
-            SmartSearchEngine::new(self.config.clone(), self.llm_client.clone()).await?;
-
-        // Perform intelligent search
-        let verified_results = search_engine.search(query, max_results).await?;
+        let search_engine = SmartSearchEngine::with_embedding_model(
+            self.config.clone(),
+            self.llm_client.clone(),
+            self.search_model().await?,
+        )?
+        .with_stored_chunks(self.index_chunks()?)
+        .with_filters(filters.clone());
+
+        // Reranking starts from twice as many candidates as requested
+        let limit = max_results.unwrap_or(self.config.max_results);
+        let candidates = if self.config.smart_search.llm_rerank {
+            limit.saturating_mul(2)
+        } else {
+            limit
+        };
+        let verified_results = search_engine.search(query, Some(candidates)).await?;
+        let verified_results = search_engine.rerank(query, verified_results, limit).await;
 
         // Convert VerifiedResult back to RagSearchResult for compatibility
         let results: Vec<RagSearchResult> = verified_results
@@ -869,6 +1677,7 @@ impl RagSystem {
                 score: verified.confidence_score, // Use the verified confidence score
                 chunk_index: verified.result.chunk_index,
                 metadata: verified.result.metadata,
+                score_breakdown: verified.result.score_breakdown,
             })
             .collect();
 
@@ -885,53 +1694,19 @@ impl RagSystem {
         }
 
         let indexer = Indexer::new(&self.config)?;
-        let index_path = indexer.get_index_path();
-        let embedding_dir = index_path.join("embeddings");
-
-        if !embedding_dir.exists() {
-            return Ok(RagStats {
-                total_documents: 0,
-                total_chunks: 0,
-                index_size_mb: 0.0,
-                last_updated: chrono::Utc::now(),
-                sources: vec![],
-            });
-        }
-
-        // Count chunks and calculate size
-        let mut total_chunks = 0;
-        let mut total_size = 0u64;
-        let mut sources = std::collections::HashSet::new();
-        let mut last_modified = std::time::UNIX_EPOCH;
-
-        let entries = std::fs::read_dir(&embedding_dir)?;
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.ends_with(".json") {
-                    total_chunks += 1;
-
-                    if let Ok(metadata) = entry.metadata() {
-                        total_size += metadata.len();
-
-                        // Track most recent modification
-                        if let Ok(modified) = metadata.modified() {
-                            if modified > last_modified {
-                                last_modified = modified;
-                            }
-                        }
-                    }
-
-                    // Try to extract source info from chunk data
-                    if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                        if let Ok(chunk_data) = serde_json::from_str::<StoredChunk>(&content) {
-                            if let Some(source_str) = chunk_data.source_path.to_str() {
-                                sources.insert(source_str.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let store = store::VectorStore::open(indexer.get_index_path())?;
+        let contents = store.load()?;
+
+        let total_chunks = contents.chunks.len();
+        let total_size = store.size_bytes();
+        let sources: std::collections::HashSet<String> = contents
+            .chunks
+            .iter()
+            .map(|(_, chunk)| chunk.source_path.to_string_lossy().to_string())
+            .collect();
+        let last_modified = std::fs::metadata(store.path())
+            .and_then(|m| m.modified())
+            .unwrap_or_else(|_| std::time::SystemTime::now());
 
         // Convert sources to unique document count estimate
         let total_documents = sources.len();
@@ -950,58 +1725,401 @@ impl RagSystem {
         })
     }
 
-    pub async fn clear_index(&self) -> Result<()> {
+    /// Find the chunks whose source equals, or starts with, `source` (path or URL)
+    pub fn select_source(&self, source: &str) -> Result<SourceSelection> {
+        let mut selection = SourceSelection::default();
+        for (key, chunk, matched) in self.matching_chunks(source)? {
+            if matched == manifest::SourceMatch::Prefix {
+                selection.partial = true;
+            }
+            selection.chunk_keys.push(key);
+            selection
+                .sources
+                .insert(chunk.source_path.to_string_lossy().to_string());
+        }
+        Ok(selection)
+    }
+
+    /// Chunk counts and sizes for each indexed source, largest first
+    pub fn source_stats(&self) -> Result<Vec<SourceStats>> {
         if !self.config.enabled {
             return Err(anyhow::anyhow!("RAG system is disabled"));
         }
 
-        log::info!("Clearing local vector storage");
-
-        // Get index path and embeddings directory
         let indexer = Indexer::new(&self.config)?;
-        let index_path = indexer.get_index_path();
-        let embedding_dir = index_path.join("embeddings");
-
-        if embedding_dir.exists() {
-            // Remove all embedding files
-            let entries = std::fs::read_dir(&embedding_dir)?;
-            let mut cleared_count = 0;
-
-            for entry in entries.flatten() {
-                if let Some(file_name) = entry.file_name().to_str() {
-                    if file_name.ends_with(".json") {
-                        if let Err(e) = std::fs::remove_file(entry.path()) {
-                            log::warn!("Failed to remove embedding file {:?}: {}", entry.path(), e);
-                        } else {
-                            cleared_count += 1;
-                        }
-                    }
-                }
-            }
-
-            log::info!(
-                "Successfully cleared {} embedding files from local vector storage",
-                cleared_count
-            );
-        } else {
-            log::info!("Local vector storage directory does not exist, nothing to clear");
+        let contents = store::VectorStore::open(indexer.get_index_path())?.load()?;
+
+        let mut by_source: std::collections::HashMap<String, SourceStats> =
+            std::collections::HashMap::new();
+        for (_, chunk) in &contents.chunks {
+            let source = chunk.source_path.to_string_lossy().to_string();
+            let stats = by_source
+                .entry(source.clone())
+                .or_insert_with(|| SourceStats {
+                    source,
+                    source_type: chunk.source_type.clone(),
+                    chunks: 0,
+                    content_bytes: 0,
+                    last_modified: chunk.metadata.modified,
+                });
+            stats.chunks += 1;
+            stats.content_bytes += chunk.content.len() as u64;
+            stats.last_modified = stats.last_modified.max(chunk.metadata.modified);
         }
 
-        Ok(())
+        let mut stats: Vec<SourceStats> = by_source.into_values().collect();
+        stats.sort_by(|a, b| {
+            (b.chunks, b.content_bytes)
+                .cmp(&(a.chunks, a.content_bytes))
+                .then_with(|| a.source.cmp(&b.source))
+        });
+        Ok(stats)
     }
 
-    pub async fn health_check(&self) -> Result<()> {
+    /// Every tag in the index with the number of chunks carrying it, most used first
+    pub fn tag_counts(&self) -> Result<Vec<(String, usize)>> {
         if !self.config.enabled {
             return Err(anyhow::anyhow!("RAG system is disabled"));
         }
 
-        log::info!("Running RAG system health check...");
-
-        // Check if embedding model can be loaded
-        let _embedding_model = EmbeddingModel::new_with_config(self.config.embedding.clone())
-            .await
-            .map_err(|e| anyhow::anyhow!("Embedding model unavailable: {}", e))?;
-        log::info!("Embedding model loaded successfully");
+        let indexer = Indexer::new(&self.config)?;
+        let contents = store::VectorStore::open(indexer.get_index_path())?.load()?;
+
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for (_, chunk) in &contents.chunks {
+            for tag in &chunk.metadata.tags {
+                *counts.entry(tag.to_lowercase()).or_default() += 1;
+            }
+        }
+
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+
+    /// Stored chunks of the sources matching `source`, ordered by source and chunk index
+    ///
+    /// Embeddings are left out; this is for inspecting how documents were chunked.
+    pub fn source_chunks(&self, source: &str) -> Result<Vec<StoredChunk>> {
+        let mut chunks: Vec<StoredChunk> = self
+            .matching_chunks(source)?
+            .into_iter()
+            .map(|(_, mut chunk, _)| {
+                chunk.embedding = Vec::new();
+                chunk
+            })
+            .collect();
+        chunks
+            .sort_by(|a, b| (&a.source_path, a.chunk_index).cmp(&(&b.source_path, b.chunk_index)));
+        Ok(chunks)
+    }
+
+    /// Stored chunks whose source equals, or starts with, `source`, with their store keys
+    fn matching_chunks(
+        &self,
+        source: &str,
+    ) -> Result<Vec<(String, StoredChunk, manifest::SourceMatch)>> {
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("RAG system is disabled"));
+        }
+
+        // Local paths are stored absolute, so resolve relative arguments the same way
+        let wanted = if source.starts_with("http://") || source.starts_with("https://") {
+            source.to_string()
+        } else {
+            let path = PathBuf::from(source);
+            path.canonicalize()
+                .or_else(|_| std::env::current_dir().map(|cwd| cwd.join(&path)))
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        };
+
+        let indexer = Indexer::new(&self.config)?;
+        let contents = store::VectorStore::open(indexer.get_index_path())?.load()?;
+
+        Ok(contents
+            .chunks
+            .into_iter()
+            .filter_map(|(key, chunk)| {
+                let stored = chunk.source_path.to_string_lossy().to_string();
+                let matched = match manifest::match_source(&stored, &wanted) {
+                    manifest::SourceMatch::None => manifest::match_source(&stored, source),
+                    matched => matched,
+                };
+                (matched != manifest::SourceMatch::None).then_some((key, chunk, matched))
+            })
+            .collect())
+    }
+
+    /// Delete the selected chunks and record the sources as removed; returns chunks removed
+    pub fn remove_source(&self, selection: &SourceSelection) -> Result<usize> {
+        let indexer = Indexer::new(&self.config)?;
+        let removed =
+            store::VectorStore::open(indexer.get_index_path())?.remove(&selection.chunk_keys)?;
+
+        let manifest = manifest::IndexManifest::new(indexer.get_index_path());
+        for source in &selection.sources {
+            manifest.append(&manifest::SourceRecord::tombstone(source))?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Export the whole index to a portable JSONL file; returns chunks exported
+    pub fn export_index(&self, path: &std::path::Path) -> Result<usize> {
+        let indexer = Indexer::new(&self.config)?;
+        store::VectorStore::open(indexer.get_index_path())?.export_jsonl(path)
+    }
+
+    /// Import chunks from a JSONL export; returns chunks imported
+    ///
+    /// Fails without touching the index when the export's embedding dimension differs
+    /// from `embedding.dimension` in the configuration. A source that is already indexed
+    /// is replaced by the imported chunks.
+    pub fn import_index(&self, path: &std::path::Path) -> Result<usize> {
+        let indexer = Indexer::new(&self.config)?;
+        let index_path = indexer.get_index_path();
+        let store = store::VectorStore::open(index_path)?;
+        let imported = store.import_jsonl(path, self.config.embedding.dimension)?;
+        if !imported.is_empty() {
+            store.write_meta(&store::IndexMeta {
+                dimension: self.config.embedding.dimension,
+                provider: self.config.embedding.provider.clone(),
+            })?;
+        }
+
+        // Record the imported sources so removal and re-indexing can find their chunks
+        let mut by_source: std::collections::BTreeMap<String, Vec<String>> =
+            std::collections::BTreeMap::new();
+        for (key, source) in &imported {
+            by_source
+                .entry(source.clone())
+                .or_default()
+                .push(key.clone());
+        }
+        let manifest = manifest::IndexManifest::new(index_path);
+        let records = manifest.load()?;
+        for (source, keys) in by_source {
+            // The import replaces an already indexed source, so drop its earlier chunks
+            if let Some(existing) = records.get(&source) {
+                let superseded: Vec<String> = existing
+                    .chunk_keys
+                    .iter()
+                    .filter(|key| !keys.contains(key))
+                    .cloned()
+                    .collect();
+                store.remove(&superseded)?;
+            }
+            manifest.append(&manifest::SourceRecord {
+                chunk_keys: keys,
+                ..manifest::SourceRecord::new(source)
+            })?;
+        }
+
+        Ok(imported.len())
+    }
+
+    /// Re-embed every stored chunk's content with the configured provider, in place
+    ///
+    /// The store is only rewritten once every chunk has been embedded, so a provider
+    /// failure leaves the existing index untouched. Chunks without content are dropped.
+    pub async fn reindex_embeddings<F: FnMut(usize, usize)>(
+        &self,
+        mut on_progress: F,
+    ) -> Result<ReindexSummary> {
+        let indexer = Indexer::new(&self.config)?;
+        let store = store::VectorStore::open(indexer.get_index_path())?;
+        let chunks = store.load()?.chunks;
+
+        let previous_dimension = chunks
+            .iter()
+            .map(|(_, chunk)| chunk.embedding.len())
+            .find(|len| *len > 0);
+
+        let embedding_model = self.embedding_model().await?;
+
+        let total = chunks.len();
+        let (chunks, empty): (Vec<_>, Vec<_>) = chunks
+            .into_iter()
+            .partition(|(_, chunk)| !chunk.content.trim().is_empty());
+        for (_, chunk) in &empty {
+            log::warn!(
+                "Dropping chunk {} from {:?}: no stored content to re-embed",
+                chunk.id,
+                chunk.source_path
+            );
+        }
+        let dropped = empty.len();
+        on_progress(dropped, total);
+
+        let batch_size = self.config.embedding.batch_size.max(1);
+        let mut reembedded = Vec::with_capacity(chunks.len());
+        let mut chunks = chunks.into_iter().peekable();
+        while chunks.peek().is_some() {
+            let mut batch: Vec<(String, StoredChunk)> = chunks.by_ref().take(batch_size).collect();
+            let texts: Vec<&str> = batch
+                .iter()
+                .map(|(_, chunk)| chunk.content.as_str())
+                .collect();
+            let embeddings = embedding_model.embed_batch(&texts).await.map_err(|e| {
+                let (_, first) = &batch[0];
+                anyhow::anyhow!(
+                    "Failed to re-embed {} chunks starting at {} from {:?}: {} (index left unchanged)",
+                    batch.len(),
+                    first.id,
+                    first.source_path,
+                    e
+                )
+            })?;
+            for ((_, chunk), embedding) in batch.iter_mut().zip(embeddings) {
+                chunk.embedding = embedding;
+            }
+            reembedded.append(&mut batch);
+            on_progress(dropped + reembedded.len(), total);
+        }
+
+        let dimension = match reembedded.first() {
+            Some((_, chunk)) => chunk.embedding.len(),
+            None => embedding_model.get_dimension().await?,
+        };
+        let summary = ReindexSummary {
+            reembedded: reembedded.len(),
+            dropped,
+            previous_dimension,
+            dimension,
+        };
+        if total > 0 {
+            store.replace_all(reembedded)?;
+        }
+        store.write_meta(&store::IndexMeta {
+            dimension,
+            provider: self.config.embedding.provider.clone(),
+        })?;
+
+        Ok(summary)
+    }
+
+    pub async fn clear_index(&self) -> Result<()> {
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("RAG system is disabled"));
+        }
+
+        log::info!("Clearing local vector storage");
+
+        let indexer = Indexer::new(&self.config)?;
+        let index_path = indexer.get_index_path();
+        store::VectorStore::clear(index_path)?;
+        log::info!("Successfully cleared local vector storage");
+
+        manifest::IndexManifest::new(index_path).clear()?;
+
+        Ok(())
+    }
+
+    /// Check every stored chunk for corruption, wrong embedding length and missing sources
+    ///
+    /// With `repair`, the store is rewritten with only the readable chunks of the right
+    /// dimension, the manifest stops listing the dropped ones, and unmigratable legacy files
+    /// are deleted. Chunks of deleted sources are only reported; `manx sources remove` drops
+    /// them.
+    pub fn verify_index(&self, repair: bool) -> Result<IndexVerification> {
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("RAG system is disabled"));
+        }
+
+        let indexer = Indexer::new(&self.config)?;
+        let store = store::VectorStore::open(indexer.get_index_path())?;
+        let check = store.check()?;
+        let dimension = self.config.embedding.dimension;
+
+        let mut report = IndexVerification {
+            chunks: check.contents.chunks.len(),
+            dead_records: check.contents.dead_records,
+            corrupt_records: check.corrupt_records,
+            truncated_bytes: check.truncated_bytes,
+            dimension,
+            legacy_leftovers: check.legacy_leftovers,
+            ..Default::default()
+        };
+
+        let mut mismatches: std::collections::BTreeMap<String, usize> =
+            std::collections::BTreeMap::new();
+        let mut missing: std::collections::BTreeMap<PathBuf, usize> =
+            std::collections::BTreeMap::new();
+        for (_, chunk) in &check.contents.chunks {
+            if chunk.embedding.len() != dimension {
+                *mismatches
+                    .entry(chunk.source_path.display().to_string())
+                    .or_default() += 1;
+            }
+            if matches!(chunk.source_type, SourceType::Local) && !chunk.source_path.exists() {
+                *missing.entry(chunk.source_path.clone()).or_default() += 1;
+            }
+        }
+        report.dimension_mismatches = mismatches.into_iter().collect();
+        report.missing_sources = missing.into_iter().collect();
+
+        if repair && report.repairable_problems() > 0 {
+            let keep: Vec<(String, StoredChunk)> = check
+                .contents
+                .chunks
+                .into_iter()
+                .filter(|(_, chunk)| chunk.embedding.len() == dimension)
+                .collect();
+            report.chunks = keep.len();
+            report.dead_records = 0;
+            let kept: std::collections::HashSet<String> =
+                keep.iter().map(|(key, _)| key.clone()).collect();
+            store.replace_all(keep)?;
+
+            // Sources that lost chunks list only the stored ones, and lose their fingerprint
+            // so the next `manx index` splits them again
+            let manifest = manifest::IndexManifest::new(indexer.get_index_path());
+            for (_, mut record) in manifest.load()? {
+                let before = record.chunk_keys.len();
+                record.chunk_keys.retain(|key| kept.contains(key));
+                if record.chunk_keys.len() == before {
+                    continue;
+                }
+                if record.chunk_keys.is_empty() {
+                    manifest.append(&manifest::SourceRecord::tombstone(&record.source))?;
+                } else {
+                    manifest.append(&manifest::SourceRecord {
+                        modified: None,
+                        sha256: None,
+                        ..record
+                    })?;
+                }
+            }
+            for path in &report.legacy_leftovers {
+                if path.is_dir() {
+                    std::fs::remove_dir_all(path)?;
+                } else {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            if let Some(legacy_dir) = report.legacy_leftovers.first().and_then(|p| p.parent()) {
+                let _ = std::fs::remove_dir(legacy_dir);
+            }
+            report.repaired = true;
+        }
+
+        Ok(report)
+    }
+
+    pub async fn health_check(&self) -> Result<()> {
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("RAG system is disabled"));
+        }
+
+        log::info!("Running RAG system health check...");
+
+        // Check if embedding model can be loaded
+        self.embedding_model()
+            .await
+            .map_err(|e| anyhow::anyhow!("Embedding model unavailable: {}", e))?;
+        log::info!("Embedding model loaded successfully");
 
         // Check if index directory exists and is accessible
         let indexer = Indexer::new(&self.config)?;
@@ -1010,23 +2128,31 @@ impl RagSystem {
         if index_path.exists() {
             log::info!("Local index directory exists: {:?}", index_path);
 
-            // Check embeddings directory
-            let embedding_dir = index_path.join("embeddings");
-            if embedding_dir.exists() {
-                // Count existing embeddings
-                match std::fs::read_dir(&embedding_dir) {
-                    Ok(entries) => {
-                        let count = entries.filter_map(|e| e.ok()).count();
+            // Check the vector store
+            let store = store::VectorStore::open(index_path)?;
+            if store.path().exists() {
+                match store.load() {
+                    Ok(contents) => {
                         log::info!(
-                            "Local vector storage accessible with {} embedding files",
-                            count
+                            "Local vector storage accessible with {} chunks ({} dead records)",
+                            contents.chunks.len(),
+                            contents.dead_records
                         );
+                        match manifest::IndexManifest::new(index_path).load() {
+                            Ok(records) => {
+                                let tracked: usize =
+                                    records.values().map(|r| r.chunk_keys.len()).sum();
+                                log::info!(
+                                    "Manifest tracks {} sources ({} chunks)",
+                                    records.len(),
+                                    tracked
+                                );
+                            }
+                            Err(e) => log::warn!("Index manifest unreadable: {}", e),
+                        }
                     }
                     Err(e) => {
-                        log::warn!(
-                            "Local vector storage directory exists but cannot read contents: {}",
-                            e
-                        );
+                        log::warn!("Local vector storage exists but cannot be read: {}", e);
                     }
                 }
             } else {
@@ -1060,42 +2186,211 @@ impl RagSystem {
     }
 
     /// Store document chunks in local file-based vector storage
-    async fn store_chunks_locally(&self, chunks: &[DocumentChunk]) -> Result<()> {
-        use uuid::Uuid;
-
+    ///
+    /// `embed_concurrency` defaults to `embedding.max_in_flight`. Sources with a record in
+    /// `previous` are added to rather than replaced.
+    async fn store_chunks_locally(
+        &self,
+        chunks: &[DocumentChunk],
+        embed_concurrency: Option<usize>,
+        previous: &std::collections::HashMap<String, manifest::SourceRecord>,
+    ) -> Result<ChunkOutcomes> {
         if chunks.is_empty() {
             log::info!("No chunks to store locally");
-            return Ok(());
+            return Ok(ChunkOutcomes::default());
         }
 
         log::info!("Storing {} chunks in local vector storage", chunks.len());
 
-        // Initialize embedding model
-        let embedding_model =
-            EmbeddingModel::new_with_config(self.config.embedding.clone()).await?;
+        let embedding_model = self.embedding_model().await?;
+
+        let concurrency = embed_concurrency.unwrap_or(self.config.embedding.max_in_flight);
+        let stored_count = store_chunks_incrementally(
+            &self.config,
+            chunks,
+            &embedding_model,
+            concurrency,
+            previous,
+            |done, total| {
+                if done % 100 == 0 || done == total {
+                    progress_note!("Processed {}/{} chunks...", done, total);
+                    progress::emit(
+                        "chunk",
+                        serde_json::json!({ "processed": done, "total": total }),
+                    );
+                }
+            },
+        )
+        .await?;
+
+        log::info!(
+            "Successfully stored {} chunks in local vector storage",
+            stored_count
+        );
+        Ok(note_chunk_outcomes(&embedding_model, stored_count))
+    }
+}
 
-        // Get index path and create embeddings directory
-        let indexer = Indexer::new(&self.config)?;
-        let index_path = indexer.get_index_path();
-        let embedding_dir = index_path.join("embeddings");
+/// Store chunks using a shared embedding model (config-based helper)
+///
+/// Embeds one batch at a time; callers already run one of these per crawl worker.
+pub async fn store_chunks_with_model_config(
+    config: &RagConfig,
+    chunks: &[DocumentChunk],
+    embedding_model: &Arc<EmbeddingModel>,
+) -> Result<usize> {
+    store_chunks_incrementally(
+        config,
+        chunks,
+        embedding_model,
+        1,
+        &Default::default(),
+        |_, _| {},
+    )
+    .await
+}
+
+/// Whether a source split again still yields the chunks `record` describes
+///
+/// The chunk count must match and every chunk that was stored must have kept its text.
+fn matches_stored_chunks(
+    source_chunks: &[DocumentChunk],
+    record: &manifest::SourceRecord,
+    stored: &std::collections::HashMap<String, StoredChunk>,
+) -> bool {
+    let stored_chunks: Vec<&StoredChunk> = record
+        .chunk_keys
+        .iter()
+        .filter_map(|key| stored.get(key))
+        .collect();
+    source_chunks.len() == stored_chunks.len() + record.failed_chunks.len()
+        && stored_chunks.iter().all(|stored| {
+            source_chunks
+                .iter()
+                .any(|chunk| chunk.id == stored.id && chunk.content == stored.content)
+        })
+}
 
-        // Create directories if they don't exist
-        std::fs::create_dir_all(&embedding_dir)?;
+/// Number of chunks written between fsync checkpoints
+const FSYNC_CHECKPOINT_CHUNKS: usize = 64;
+
+/// Refuse to add `dimension`-dim vectors to a store that already holds vectors of another size
+///
+/// The recorded dimension is trusted only while the store still has chunks, so an emptied
+/// index can be rebuilt with a new model.
+fn ensure_store_dimension(store: &store::VectorStore, dimension: usize) -> Result<()> {
+    if store
+        .meta()?
+        .is_some_and(|meta| meta.dimension == dimension)
+    {
+        return Ok(());
+    }
+    let stored_dimension = store
+        .load()?
+        .chunks
+        .iter()
+        .map(|(_, chunk)| chunk.embedding.len())
+        .find(|len| *len > 0);
+    search_engine::check_dimension(stored_dimension, dimension)
+}
 
-        // Process chunks and store with embeddings
-        let mut stored_count = 0;
+/// Embed and append chunks to the vector store, syncing every `FSYNC_CHECKPOINT_CHUNKS`
+/// chunks and appending a manifest record as soon as each source's chunks are durable.
+///
+/// Chunks of one source are expected to be contiguous (as produced by the indexer).
+/// Batches of `embedding.batch_size` chunks are embedded on Tokio tasks, at most
+/// `concurrency` at a time, and written in their original order. Chunks that fail to
+/// embed are listed in their source's manifest record. A source with a record in
+/// `previous` keeps its stored chunks and gains the new ones, as when retrying failures.
+async fn store_chunks_incrementally<F: FnMut(usize, usize)>(
+    config: &RagConfig,
+    chunks: &[DocumentChunk],
+    embedding_model: &Arc<EmbeddingModel>,
+    concurrency: usize,
+    previous: &std::collections::HashMap<String, manifest::SourceRecord>,
+    mut on_progress: F,
+) -> Result<usize> {
+    use std::collections::VecDeque;
+    use tokio::sync::Semaphore;
+    use uuid::Uuid;
 
-        for (i, chunk) in chunks.iter().enumerate() {
-            // Generate embedding for chunk content
-            let embedding = match embedding_model.embed_text(&chunk.content).await {
-                Ok(embedding) => embedding,
-                Err(e) => {
-                    log::warn!("Failed to generate embedding for chunk {}: {}", chunk.id, e);
-                    continue;
+    if chunks.is_empty() {
+        return Ok(0);
+    }
+
+    let chunking = config.chunk_params()?;
+    let indexer = Indexer::new(config)?;
+    let index_path = indexer.get_index_path();
+    let store = store::VectorStore::open(index_path)?;
+    let mut writer = store.writer()?;
+    let manifest = manifest::IndexManifest::new(index_path);
+
+    let mut meta_recorded = false;
+    let mut unsynced = 0usize;
+    let mut current: Option<manifest::SourceRecord> = None;
+    let mut stored_count = 0usize;
+
+    let batch_size = config.embedding.batch_size.max(1);
+    let concurrency = concurrency.max(1);
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut unspawned = chunks.chunks(batch_size);
+    let mut pending = VecDeque::new();
+    for (batch_index, batch) in chunks.chunks(batch_size).enumerate() {
+        // Queue more batches than may run so workers don't idle while this one is written
+        while pending.len() < concurrency * 2 {
+            let Some(next) = unspawned.next() else { break };
+            let next = next.to_vec();
+            let embedding_model = embedding_model.clone();
+            let semaphore = semaphore.clone();
+            pending.push_back(tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                embed_chunk_batch(&embedding_model, &next).await
+            }));
+        }
+        let embeddings = match pending.pop_front() {
+            Some(handle) => handle.await?,
+            None => break,
+        };
+
+        for (offset, (chunk, embedding)) in batch.iter().zip(embeddings).enumerate() {
+            let i = batch_index * batch_size + offset;
+            let source = chunk.source_path.to_string_lossy().to_string();
+            if current
+                .as_ref()
+                .is_some_and(|record| record.source != source)
+            {
+                if let Some(record) = current.take() {
+                    finish_source(&manifest, &mut writer, &record)?;
+                    unsynced = 0;
                 }
+            }
+            let record = current.get_or_insert_with(|| match previous.get(&source) {
+                Some(previous) => manifest::SourceRecord {
+                    indexed_at: chrono::Utc::now(),
+                    failed_chunks: Vec::new(),
+                    ..previous.clone()
+                },
+                None => manifest::SourceRecord {
+                    chunking: Some(chunking),
+                    ..manifest::SourceRecord::new(source)
+                },
+            });
+
+            let Some(embedding) = embedding else {
+                log::debug!("Chunk {} was not stored", chunk.id);
+                record.failed_chunks.push(chunk.id.clone());
+                on_progress(i + 1, chunks.len());
+                continue;
             };
+            if !meta_recorded {
+                ensure_store_dimension(&store, embedding.len())?;
+                store.write_meta(&store::IndexMeta {
+                    dimension: embedding.len(),
+                    provider: config.embedding.provider.clone(),
+                })?;
+                meta_recorded = true;
+            }
 
-            // Create stored chunk with embedding
             let stored_chunk = StoredChunk {
                 id: chunk.id.clone(),
                 content: chunk.content.clone(),
@@ -1108,69 +2403,801 @@ impl RagSystem {
                 embedding,
             };
 
-            // Save to JSON file
-            let file_id = Uuid::new_v4().to_string();
-            let file_path = embedding_dir.join(format!("{}.json", file_id));
-
-            let json_content = serde_json::to_string_pretty(&stored_chunk)?;
-            std::fs::write(&file_path, json_content)?;
+            let key = Uuid::new_v4().to_string();
+            writer.put(&key, stored_chunk)?;
+            log::debug!("Stored chunk {} as {}", chunk.id, key);
 
+            record.chunk_keys.push(key);
             stored_count += 1;
-            log::debug!("Stored chunk {} to {:?}", chunk.id, file_path);
-            if (i + 1) % 100 == 0 || i + 1 == chunks.len() {
-                println!("Stored {}/{} chunks...", i + 1, chunks.len());
+            unsynced += 1;
+
+            if unsynced >= FSYNC_CHECKPOINT_CHUNKS {
+                writer.checkpoint()?;
+                unsynced = 0;
             }
+            on_progress(i + 1, chunks.len());
         }
+    }
 
-        log::info!(
-            "Successfully stored {} chunks in local vector storage",
-            stored_count
-        );
-        Ok(())
+    if let Some(record) = current.take() {
+        finish_source(&manifest, &mut writer, &record)?;
     }
+
+    Ok(stored_count)
 }
 
-/// Store chunks using a shared embedding model (config-based helper)
-pub async fn store_chunks_with_model_config(
-    config: &RagConfig,
-    chunks: &[DocumentChunk],
+/// Embed a batch of chunks in one provider call
+///
+/// If the batch call fails, each chunk is retried on its own so one bad chunk only costs
+/// itself; chunks that still fail are logged and come back as `None`.
+async fn embed_chunk_batch(
     embedding_model: &EmbeddingModel,
-) -> Result<usize> {
-    use uuid::Uuid;
-    if chunks.is_empty() {
-        return Ok(0);
+    batch: &[DocumentChunk],
+) -> Vec<Option<Vec<f32>>> {
+    let texts: Vec<&str> = batch.iter().map(|chunk| chunk.content.as_str()).collect();
+    match embedding_model.embed_batch(&texts).await {
+        Ok(embeddings) => return embeddings.into_iter().map(Some).collect(),
+        Err(e) if batch.len() == 1 => {
+            log::warn!(
+                "Failed to generate embedding for chunk {}: {}",
+                batch[0].id,
+                e
+            );
+            embedding_model.record_chunk_outcomes(0, 1);
+            return vec![None];
+        }
+        Err(e) => log::warn!(
+            "Batch embedding of {} chunks failed, embedding one at a time: {}",
+            batch.len(),
+            e
+        ),
     }
 
-    let indexer = Indexer::new(config)?;
-    let index_path = indexer.get_index_path();
-    let embedding_dir = index_path.join("embeddings");
-    std::fs::create_dir_all(&embedding_dir)?;
+    let mut embeddings = Vec::with_capacity(batch.len());
+    for chunk in batch {
+        match embedding_model.embed_text(&chunk.content).await {
+            Ok(embedding) => embeddings.push(Some(embedding)),
+            Err(e) => {
+                log::warn!("Failed to generate embedding for chunk {}: {}", chunk.id, e);
+                embeddings.push(None);
+            }
+        }
+    }
+    let failed = embeddings.iter().filter(|e| e.is_none()).count();
+    embedding_model.record_chunk_outcomes(batch.len() - failed, failed);
+    embeddings
+}
 
-    let mut stored_count = 0usize;
-    for chunk in chunks {
-        let embedding = match embedding_model.embed_text(&chunk.content).await {
-            Ok(embedding) => embedding,
-            Err(_) => continue,
+/// Print how many chunks needed retries or were lost, for an index summary
+///
+/// The IDs of lost chunks are in the debug log and the index manifest.
+fn note_chunk_outcomes(embedding_model: &EmbeddingModel, stored: usize) -> ChunkOutcomes {
+    let outcomes = embedding_model.take_chunk_outcomes();
+    if outcomes.retried > 0 {
+        progress_note!(
+            "Chunks retried: {} (stored after a retry)",
+            outcomes.retried
+        );
+    }
+    if outcomes.failed > 0 {
+        progress_note!(
+            "Stored {}/{} chunks, {} failed — run with --debug for details",
+            stored,
+            stored + outcomes.failed,
+            outcomes.failed
+        );
+        progress_note!("Retry them with: manx index --retry-failed");
+    }
+    outcomes
+}
+
+/// Why a sitemap page was not indexed
+enum PageFailure {
+    Fetch,
+    Store,
+}
+
+/// Final `--json-progress` event of an index run
+fn done_event(stored: usize, outcomes: ChunkOutcomes) -> serde_json::Value {
+    serde_json::json!({
+        "stored": stored,
+        "retried": outcomes.retried,
+        "failed": outcomes.failed,
+    })
+}
+
+/// Make a source's chunks durable, then record the source in the manifest
+fn finish_source(
+    manifest: &manifest::IndexManifest,
+    writer: &mut store::StoreWriter,
+    record: &manifest::SourceRecord,
+) -> Result<()> {
+    writer.checkpoint()?;
+    if !record.chunk_keys.is_empty() || !record.failed_chunks.is_empty() {
+        manifest.append(record)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_store_keeps_chunk_order() {
+        let dir = std::env::temp_dir().join(format!("manx_store_order_{}", uuid::Uuid::new_v4()));
+        let mut config = RagConfig {
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        };
+        config.embedding.batch_size = 2;
+
+        let chunks: Vec<DocumentChunk> = (0..9)
+            .map(|i| DocumentChunk {
+                id: format!("chunk-{}", i),
+                content: format!("chunk number {} about connection pools", i),
+                source_path: PathBuf::from(if i < 5 { "/docs/a.md" } else { "/docs/b.md" }),
+                source_type: SourceType::Local,
+                title: None,
+                section: None,
+                chunk_index: i,
+                metadata: DocumentMetadata {
+                    file_type: "md".to_string(),
+                    size: 0,
+                    modified: chrono::Utc::now(),
+                    tags: vec![],
+                    language: None,
+                    line_range: None,
+                },
+            })
+            .collect();
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+
+        let stored =
+            store_chunks_incrementally(&config, &chunks, &model, 3, &Default::default(), |_, _| {})
+                .await
+                .unwrap();
+
+        assert_eq!(stored, 9);
+        let ids: Vec<String> = store::VectorStore::open(&dir)
+            .unwrap()
+            .load()
+            .unwrap()
+            .chunks
+            .into_iter()
+            .map(|(_, chunk)| chunk.id)
+            .collect();
+        let expected: Vec<String> = (0..9).map(|i| format!("chunk-{}", i)).collect();
+        assert_eq!(ids, expected);
+        let records = manifest::IndexManifest::new(&dir).load().unwrap();
+        assert_eq!(records["/docs/a.md"].chunk_keys.len(), 5);
+        assert_eq!(records["/docs/b.md"].chunk_keys.len(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_source_chunks_list_a_source_in_order() {
+        let dir = std::env::temp_dir().join(format!("manx_source_chunks_{}", uuid::Uuid::new_v4()));
+        let config = RagConfig {
+            index_path: dir.clone(),
+            ..RagConfig::default()
         };
+        let chunks: Vec<DocumentChunk> = (0..9)
+            .map(|i| DocumentChunk {
+                id: format!("chunk-{}", i),
+                content: format!("chunk number {} about connection pools", i),
+                source_path: PathBuf::from(if i < 5 { "/docs/a.md" } else { "/docs/b.md" }),
+                source_type: SourceType::Local,
+                title: None,
+                section: None,
+                chunk_index: i,
+                metadata: DocumentMetadata {
+                    file_type: "md".to_string(),
+                    size: 0,
+                    modified: chrono::Utc::now(),
+                    tags: vec![],
+                    language: None,
+                    line_range: None,
+                },
+            })
+            .collect();
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        store_chunks_incrementally(&config, &chunks, &model, 3, &Default::default(), |_, _| {})
+            .await
+            .unwrap();
+
+        let rag = RagSystem::new(config).await.unwrap();
+        let b_chunks = rag.source_chunks("/docs/b.md").unwrap();
+        let indexes: Vec<usize> = b_chunks.iter().map(|c| c.chunk_index).collect();
+        assert_eq!(indexes, vec![5, 6, 7, 8]);
+        assert!(b_chunks.iter().all(|c| c.embedding.is_empty()));
+        assert_eq!(rag.source_chunks("/docs").unwrap().len(), 9);
+
+        // `rag chunks --json` prints these directly and should not carry empty vectors
+        let json = serde_json::to_value(&b_chunks).unwrap();
+        assert!(json[0].get("embedding").is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 
-        let stored_chunk = StoredChunk {
+    #[tokio::test]
+    async fn test_import_replaces_an_indexed_source() {
+        let index = |name: &str, count: usize| {
+            let config = RagConfig {
+                index_path: std::env::temp_dir().join(format!(
+                    "manx_import_{}_{}",
+                    name,
+                    uuid::Uuid::new_v4()
+                )),
+                ..RagConfig::default()
+            };
+            let chunks: Vec<DocumentChunk> = (0..count)
+                .map(|i| DocumentChunk {
+                    id: format!("{}-{}", name, i),
+                    content: format!("{} chunk {} about connection pools", name, i),
+                    source_path: PathBuf::from("/docs/a.md"),
+                    source_type: SourceType::Local,
+                    title: None,
+                    section: None,
+                    chunk_index: i,
+                    metadata: DocumentMetadata {
+                        file_type: "md".to_string(),
+                        size: 0,
+                        modified: chrono::Utc::now(),
+                        tags: vec![],
+                        language: None,
+                        line_range: None,
+                    },
+                })
+                .collect();
+            (config, chunks)
+        };
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        let (local, local_chunks) = index("local", 3);
+        let (other, other_chunks) = index("other", 2);
+        for (config, chunks) in [(&local, &local_chunks), (&other, &other_chunks)] {
+            store_chunks_incrementally(config, chunks, &model, 1, &Default::default(), |_, _| {})
+                .await
+                .unwrap();
+        }
+        let export = other.index_path.join("export.jsonl");
+        RagSystem::new(other.clone())
+            .await
+            .unwrap()
+            .export_index(&export)
+            .unwrap();
+
+        let rag = RagSystem::new(local.clone()).await.unwrap();
+        assert_eq!(rag.import_index(&export).unwrap(), 2);
+
+        let stored = store::VectorStore::open(&local.index_path)
+            .unwrap()
+            .load()
+            .unwrap();
+        let ids: Vec<&str> = stored.chunks.iter().map(|(_, c)| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["other-0", "other-1"]);
+        let record = &manifest::IndexManifest::new(&local.index_path)
+            .load()
+            .unwrap()["/docs/a.md"];
+        let keys: Vec<&String> = stored.chunks.iter().map(|(key, _)| key).collect();
+        assert_eq!(record.chunk_keys.iter().collect::<Vec<_>>(), keys);
+
+        // Importing the same file again keeps the chunks it just imported
+        rag.import_index(&export).unwrap();
+        let stored = store::VectorStore::open(&local.index_path)
+            .unwrap()
+            .load()
+            .unwrap();
+        assert_eq!(stored.chunks.len(), 2);
+
+        std::fs::remove_dir_all(&local.index_path).unwrap();
+        std::fs::remove_dir_all(&other.index_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_source_stats_aggregate_per_source() {
+        let dir = std::env::temp_dir().join(format!("manx_source_stats_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = RagConfig {
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        };
+        let older = chrono::Utc::now() - chrono::TimeDelta::days(3);
+        let newer = chrono::Utc::now();
+        let chunk = |source: &str, source_type: SourceType, content: &str, modified| StoredChunk {
+            id: content.to_string(),
+            content: content.to_string(),
+            source_path: PathBuf::from(source),
+            source_type,
+            title: None,
+            section: None,
+            chunk_index: 0,
+            metadata: DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 0,
+                modified,
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+            embedding: vec![0.5; 4],
+        };
+        let store = store::VectorStore::open(&dir).unwrap();
+        let mut writer = store.writer().unwrap();
+        let web = "https://docs.example.com/guide";
+        writer
+            .put("w1", chunk(web, SourceType::Web, "four", older))
+            .unwrap();
+        writer
+            .put("w2", chunk(web, SourceType::Web, "sixsix", newer))
+            .unwrap();
+        writer
+            .put("l1", chunk("/docs/a.md", SourceType::Local, "ab", older))
+            .unwrap();
+        writer
+            .put("l2", chunk("/docs/z.md", SourceType::Local, "ab", older))
+            .unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let stats = RagSystem::new(config)
+            .await
+            .unwrap()
+            .source_stats()
+            .unwrap();
+        let order: Vec<&str> = stats.iter().map(|s| s.source.as_str()).collect();
+        // Most chunks first, ties broken by source name
+        assert_eq!(order, vec![web, "/docs/a.md", "/docs/z.md"]);
+        assert_eq!(stats[0].chunks, 2);
+        assert_eq!(stats[0].content_bytes, 10);
+        assert_eq!(stats[0].last_modified, newer);
+        assert!(matches!(stats[0].source_type, SourceType::Web));
+        assert!(matches!(stats[1].source_type, SourceType::Local));
+        assert_eq!(stats[1].chunks, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_chunks_are_reread_only_after_a_write() {
+        let dir = std::env::temp_dir().join(format!("manx_index_chunks_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rag = RagSystem::new(RagConfig {
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        })
+        .await
+        .unwrap();
+        let chunk = |id: &str| StoredChunk {
+            id: id.to_string(),
+            content: id.to_string(),
+            source_path: PathBuf::from("/docs/a.md"),
+            source_type: SourceType::Local,
+            title: None,
+            section: None,
+            chunk_index: 0,
+            metadata: DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 0,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+            embedding: vec![0.5; 4],
+        };
+        let store = store::VectorStore::open(&dir).unwrap();
+        let mut writer = store.writer().unwrap();
+        writer.put("a", chunk("a")).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let first = rag.index_chunks().unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(Arc::ptr_eq(&first, &rag.index_chunks().unwrap()));
+
+        let mut writer = store.writer().unwrap();
+        writer.put("b", chunk("b")).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+        assert_eq!(rag.index_chunks().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_repair_updates_the_manifest() {
+        let dir = std::env::temp_dir().join(format!("manx_verify_repair_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = RagConfig {
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        };
+        let dimension = config.embedding.dimension;
+        let chunk = |source: &str, dimension: usize| StoredChunk {
+            id: source.to_string(),
+            content: "text".to_string(),
+            source_path: PathBuf::from(source),
+            source_type: SourceType::Web,
+            title: None,
+            section: None,
+            chunk_index: 0,
+            metadata: DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 0,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+            embedding: vec![0.5; dimension],
+        };
+        let store = store::VectorStore::open(&dir).unwrap();
+        let mut writer = store.writer().unwrap();
+        writer.put("a1", chunk("https://a", dimension)).unwrap();
+        writer.put("a2", chunk("https://a", 8)).unwrap();
+        writer.put("b1", chunk("https://b", 8)).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let manifest = manifest::IndexManifest::new(&dir);
+        for (source, keys) in [("https://a", vec!["a1", "a2"]), ("https://b", vec!["b1"])] {
+            let mut record = manifest::SourceRecord::new(source.to_string());
+            record.chunk_keys = keys.into_iter().map(String::from).collect();
+            record.sha256 = Some("digest".to_string());
+            manifest.append(&record).unwrap();
+        }
+
+        let mut rag = RagSystem::new(config).await.unwrap();
+        let report = rag.verify_index(true).unwrap();
+        assert!(report.repaired);
+        assert_eq!(report.chunks, 1);
+
+        let records = manifest.load().unwrap();
+        assert_eq!(records.len(), 1);
+        let a = &records["https://a"];
+        assert_eq!(a.chunk_keys, vec!["a1".to_string()]);
+        // Cleared so the next index run splits the source again
+        assert!(a.sha256.is_none());
+
+        rag.config.enabled = false;
+        assert!(rag.verify_index(false).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_matches_language_filters_on_metadata() {
+        let metadata = |language: Option<&str>| DocumentMetadata {
+            file_type: "rs".to_string(),
+            size: 0,
+            modified: chrono::Utc::now(),
+            tags: vec![],
+            language: language.map(str::to_string),
+            line_range: None,
+        };
+        let wanted = vec!["rust".to_string(), "Python".to_string()];
+
+        assert!(matches_language(&metadata(Some("Rust")), &wanted));
+        assert!(matches_language(&metadata(Some("python")), &wanted));
+        assert!(!matches_language(&metadata(Some("Go")), &wanted));
+        assert!(!matches_language(&metadata(None), &wanted));
+        assert!(matches_language(&metadata(None), &[]));
+
+        let short = vec!["rs".to_string(), "CPP".to_string(), "env".to_string()];
+        assert!(matches_language(&metadata(Some("Rust")), &short));
+        assert!(matches_language(&metadata(Some("C++")), &short));
+        assert!(matches_language(&metadata(Some("dotenv")), &short));
+        assert!(!matches_language(&metadata(Some("en")), &short));
+    }
+
+    #[test]
+    fn test_search_filters_require_every_tag() {
+        let metadata = DocumentMetadata {
+            file_type: "md".to_string(),
+            size: 0,
+            modified: chrono::Utc::now(),
+            tags: vec!["database".to_string(), "tuning".to_string()],
+            language: Some("en".to_string()),
+            line_range: None,
+        };
+        let filters = |tags: &[&str]| SearchFilters {
+            languages: vec![],
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+
+        assert!(filters(&[]).matches(&metadata));
+        assert!(filters(&["Database"]).matches(&metadata));
+        assert!(filters(&["database", "tuning"]).matches(&metadata));
+        assert!(!filters(&["database", "security"]).matches(&metadata));
+        assert!(!SearchFilters {
+            languages: vec!["rust".to_string()],
+            ..filters(&["database"])
+        }
+        .matches(&metadata));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_model_loaded_once() {
+        let dir = std::env::temp_dir().join(format!("manx_model_once_{}", uuid::Uuid::new_v4()));
+        let config = RagConfig {
+            enabled: true,
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        };
+
+        let rag = RagSystem::new(config.clone()).await.unwrap();
+        let first = rag.embedding_model().await.unwrap();
+        assert!(Arc::ptr_eq(&first, &rag.embedding_model().await.unwrap()));
+
+        let shared = Arc::new(EmbeddingModel::new().await.unwrap());
+        let rag = RagSystem::new(config)
+            .await
+            .unwrap()
+            .with_shared_embeddings(shared.clone());
+        assert!(Arc::ptr_eq(&shared, &rag.embedding_model().await.unwrap()));
+        let search_model = rag.search_model().await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&shared, &search_model));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_indexing_refuses_a_different_dimension() {
+        let dir = std::env::temp_dir().join(format!("manx_mixed_dim_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = RagConfig {
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        };
+        let store = store::VectorStore::open(&dir).unwrap();
+        let mut writer = store.writer().unwrap();
+        writer
+            .put(
+                "old",
+                StoredChunk {
+                    id: "old".to_string(),
+                    content: "built with another model".to_string(),
+                    source_path: PathBuf::from("/docs/old.md"),
+                    source_type: SourceType::Local,
+                    title: None,
+                    section: None,
+                    chunk_index: 0,
+                    metadata: DocumentMetadata {
+                        file_type: "md".to_string(),
+                        size: 0,
+                        modified: chrono::Utc::now(),
+                        tags: vec![],
+                        language: None,
+                        line_range: None,
+                    },
+                    embedding: vec![0.5; 8],
+                },
+            )
+            .unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+        let meta = store::IndexMeta {
+            dimension: 8,
+            provider: EmbeddingProvider::Custom("http://localhost:9000".to_string()),
+        };
+        store.write_meta(&meta).unwrap();
+
+        let chunk = DocumentChunk {
+            id: "new".to_string(),
+            content: "embedded with the hash model".to_string(),
+            source_path: PathBuf::from("/docs/new.md"),
+            source_type: SourceType::Local,
+            title: None,
+            section: None,
+            chunk_index: 0,
+            metadata: DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 0,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+        };
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        let err = store_chunks_incrementally(
+            &config,
+            &[chunk],
+            &model,
+            1,
+            &Default::default(),
+            |_, _| {},
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("manx rag reindex"));
+        assert_eq!(store.meta().unwrap(), Some(meta));
+        assert_eq!(store.load().unwrap().chunks.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_chunks_uses_recorded_chunking() {
+        let dir = std::env::temp_dir().join(format!("manx_retry_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        let words: Vec<String> = (0..200).map(|i| format!("word{}", i)).collect();
+        std::fs::write(&file, words.join(" ")).unwrap();
+
+        let index_path = dir.join("index");
+        let mut config = RagConfig {
+            enabled: true,
+            index_path: index_path.clone(),
+            chunk_size: 40,
+            chunk_overlap: 0,
+            ..RagConfig::default()
+        };
+        let chunks = Indexer::new(&config)
+            .unwrap()
+            .index_document(file.clone())
+            .unwrap();
+        assert!(chunks.len() > 3);
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        store_chunks_incrementally(&config, &chunks, &model, 1, &Default::default(), |_, _| {})
+            .await
+            .unwrap();
+
+        // Turn the second chunk into a failure recorded by an earlier run
+        let failed = chunks[1].clone();
+        let store = store::VectorStore::open(&index_path).unwrap();
+        let source = file.to_string_lossy().to_string();
+        let manifest = manifest::IndexManifest::new(&index_path);
+        let mut record = manifest.load().unwrap().remove(&source).unwrap();
+        let key = record.chunk_keys.remove(1);
+        store.remove(std::slice::from_ref(&key)).unwrap();
+        record.failed_chunks = vec![failed.id.clone()];
+        manifest.append(&record).unwrap();
+
+        // The chunk size changed since; the retry must still split like the first run
+        config.chunk_size = 100;
+        let rag = RagSystem::new(config).await.unwrap();
+        assert_eq!(rag.retry_failed_chunks(None, None).await.unwrap(), 1);
+
+        let retried: Vec<StoredChunk> = store
+            .load()
+            .unwrap()
+            .chunks
+            .into_iter()
+            .map(|(_, chunk)| chunk)
+            .filter(|chunk| chunk.id == failed.id)
+            .collect();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].content, failed.content);
+        let record = &manifest.load().unwrap()[&source];
+        assert!(record.failed_chunks.is_empty());
+        assert_eq!(record.chunk_keys.len(), chunks.len());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_matches_stored_chunks_detects_changed_pages() {
+        let chunk = |i: usize, content: &str| DocumentChunk {
+            id: format!("page_{}", i),
+            content: content.to_string(),
+            source_path: PathBuf::from("https://docs.example.com/page"),
+            source_type: SourceType::Web,
+            title: None,
+            section: None,
+            chunk_index: i,
+            metadata: DocumentMetadata {
+                file_type: "html".to_string(),
+                size: 0,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+        };
+        let stored_chunk = |chunk: &DocumentChunk| StoredChunk {
             id: chunk.id.clone(),
             content: chunk.content.clone(),
             source_path: chunk.source_path.clone(),
             source_type: chunk.source_type.clone(),
-            title: chunk.title.clone(),
-            section: chunk.section.clone(),
+            title: None,
+            section: None,
             chunk_index: chunk.chunk_index,
             metadata: chunk.metadata.clone(),
-            embedding,
+            embedding: vec![1.0],
+        };
+        let page = vec![chunk(0, "intro"), chunk(1, "failed part")];
+        let stored = std::collections::HashMap::from([("k0".to_string(), stored_chunk(&page[0]))]);
+        let record = manifest::SourceRecord {
+            chunk_keys: vec!["k0".to_string()],
+            failed_chunks: vec!["page_1".to_string()],
+            ..manifest::SourceRecord::new("https://docs.example.com/page".to_string())
         };
 
-        let file_id = Uuid::new_v4().to_string();
-        let file_path = embedding_dir.join(format!("{}.json", file_id));
-        let json_content = serde_json::to_string_pretty(&stored_chunk)?;
-        std::fs::write(&file_path, json_content)?;
-        stored_count += 1;
+        assert!(matches_stored_chunks(&page, &record, &stored));
+        let edited = vec![chunk(0, "new intro"), chunk(1, "failed part")];
+        assert!(!matches_stored_chunks(&edited, &record, &stored));
+        let grown = vec![chunk(0, "intro"), chunk(1, "failed part"), chunk(2, "more")];
+        assert!(!matches_stored_chunks(&grown, &record, &stored));
     }
 
-    Ok(stored_count)
+    #[tokio::test]
+    async fn test_reindex_embeddings_keeps_chunks_and_updates_dimension() {
+        let dir = std::env::temp_dir().join(format!("manx_reindex_{}", uuid::Uuid::new_v4()));
+        let mut config = RagConfig {
+            enabled: true,
+            index_path: dir.clone(),
+            ..RagConfig::default()
+        };
+        config.embedding.batch_size = 2;
+
+        let chunks: Vec<DocumentChunk> = (0..5)
+            .map(|i| DocumentChunk {
+                id: format!("chunk-{}", i),
+                content: format!("chunk number {} about retry budgets", i),
+                source_path: PathBuf::from("/docs/retries.rs"),
+                source_type: SourceType::Local,
+                title: Some("Retries".to_string()),
+                section: None,
+                chunk_index: i,
+                metadata: DocumentMetadata {
+                    file_type: "rs".to_string(),
+                    size: 0,
+                    modified: chrono::Utc::now(),
+                    tags: vec!["networking".to_string()],
+                    language: Some("rust".to_string()),
+                    line_range: Some((i * 10 + 1, i * 10 + 10)),
+                },
+            })
+            .collect();
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        store_chunks_incrementally(&config, &chunks, &model, 1, &Default::default(), |_, _| {})
+            .await
+            .unwrap();
+
+        // Pretend the index was built by a provider with a smaller dimension
+        let store = store::VectorStore::open(&dir).unwrap();
+        let shrunk = store
+            .load()
+            .unwrap()
+            .chunks
+            .into_iter()
+            .map(|(key, mut chunk)| {
+                chunk.embedding.truncate(8);
+                (key, chunk)
+            })
+            .collect();
+        store.replace_all(shrunk).unwrap();
+
+        let rag = RagSystem::new(config).await.unwrap();
+        let mut progress = Vec::new();
+        let summary = rag
+            .reindex_embeddings(|done, total| progress.push((done, total)))
+            .await
+            .unwrap();
+
+        assert_eq!(summary.reembedded, 5);
+        assert_eq!(summary.dropped, 0);
+        assert_eq!(summary.previous_dimension, Some(8));
+        assert_eq!(summary.dimension, 384);
+        assert_eq!(progress.last(), Some(&(5, 5)));
+
+        let reloaded = store.load().unwrap().chunks;
+        assert_eq!(reloaded.len(), 5);
+        for (i, (_, chunk)) in reloaded.iter().enumerate() {
+            assert_eq!(chunk.id, format!("chunk-{}", i));
+            assert_eq!(chunk.chunk_index, i);
+            assert_eq!(chunk.title.as_deref(), Some("Retries"));
+            assert_eq!(chunk.metadata.tags, vec!["networking".to_string()]);
+            assert_eq!(chunk.metadata.language.as_deref(), Some("rust"));
+            assert_eq!(chunk.metadata.line_range, Some((i * 10 + 1, i * 10 + 10)));
+            assert_eq!(chunk.embedding.len(), 384);
+        }
+        assert_eq!(store.meta().unwrap().unwrap().dimension, 384);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
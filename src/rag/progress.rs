@@ -0,0 +1,49 @@
+//! Indexing progress reporting
+//!
+//! Indexing prints human-readable progress (notes, spinners, bars) to stderr by default.
+//! With `manx index --json-progress` the decorative output is replaced by
+//! newline-delimited JSON events on stderr so wrappers and GUIs can track status.
+
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Switch indexing progress output to newline-delimited JSON events
+pub fn set_json_progress(enabled: bool) {
+    JSON_PROGRESS.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether JSON progress events are enabled
+pub fn is_json() -> bool {
+    JSON_PROGRESS.load(Ordering::Relaxed)
+}
+
+/// Print a human-readable progress line to stderr (suppressed in JSON mode)
+pub fn note(args: std::fmt::Arguments) {
+    if !is_json() {
+        eprintln!("{}", args);
+    }
+}
+
+/// Emit a structured progress event to stderr (only in JSON mode)
+pub fn emit(event: &str, fields: Value) {
+    if !is_json() {
+        return;
+    }
+
+    let mut payload = json!({ "event": event });
+    if let (Some(obj), Value::Object(extra)) = (payload.as_object_mut(), fields) {
+        obj.extend(extra);
+    }
+    eprintln!("{}", payload);
+}
+
+/// Hide a progress bar or spinner when JSON events replace the decorative output
+pub fn attach(pb: ProgressBar) -> ProgressBar {
+    if is_json() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    pb
+}
@@ -3,76 +3,100 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+use super::rate_limit;
 use super::{EmbeddingProvider as ProviderTrait, ProviderInfo};
 
-/// Custom endpoint embedding provider
+/// Custom endpoint embedding provider for servers speaking the OpenAI `/v1/embeddings` API,
+/// such as text-embeddings-inference, vLLM, LocalAI or LM Studio
 pub struct CustomProvider {
     client: Client,
     endpoint_url: String,
     api_key: Option<String>,
-    dimension: Option<usize>, // Cached dimension
+    /// Model sent with each request; servers hosting a single model ignore it
+    model: Option<String>,
+    /// Dimension found by the first embedding the server returns
+    dimension: std::sync::OnceLock<usize>,
 }
 
 #[derive(Serialize)]
 struct CustomEmbeddingRequest {
-    text: String,
+    input: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     model: Option<String>,
+    encoding_format: &'static str,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CustomEmbeddingResponse {
+    /// OpenAI-compatible `{"data": [{"embedding": [...], "index": 0}]}`
+    OpenAi { data: Vec<EmbeddingData> },
+    /// Single `{"embedding": [...]}`, as older custom endpoints return
+    Single { embedding: Vec<f32> },
 }
 
 #[derive(Deserialize)]
-struct CustomEmbeddingResponse {
+struct EmbeddingData {
     embedding: Vec<f32>,
     #[serde(default)]
-    dimension: Option<usize>,
+    index: usize,
+}
+
+/// Embeddings URL for a configured endpoint: a base URL such as `http://localhost:8080` or
+/// `http://localhost:8080/v1` gets the OpenAI path appended, a full URL is used as given
+pub fn embeddings_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    let path = endpoint
+        .split_once("://")
+        .map_or(endpoint, |(_, rest)| rest)
+        .split_once('/')
+        .map_or("", |(_, path)| path);
+    if path.is_empty() {
+        format!("{}/v1/embeddings", endpoint)
+    } else if path == "v1" || path.ends_with("/v1") {
+        format!("{}/embeddings", endpoint)
+    } else {
+        endpoint.to_string()
+    }
 }
 
 impl CustomProvider {
     /// Create a new custom endpoint provider
-    pub fn new(endpoint_url: String, api_key: Option<String>) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
+    pub fn new(
+        endpoint_url: String,
+        api_key: Option<String>,
+        model: Option<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let client = crate::http_client::builder()
+            .timeout(timeout)
             .build()
             .unwrap();
 
         Self {
             client,
-            endpoint_url,
+            endpoint_url: embeddings_url(&endpoint_url),
             api_key,
-            dimension: None,
+            model,
+            dimension: std::sync::OnceLock::new(),
         }
     }
 
-    /// Detect dimension by making a test API call
-    #[allow(dead_code)]
-    pub async fn detect_dimension(&mut self) -> Result<usize> {
-        if let Some(dim) = self.dimension {
-            return Ok(dim);
-        }
-
-        log::info!(
-            "Detecting embedding dimension for custom endpoint: {}",
-            self.endpoint_url
-        );
-
-        let test_embedding = self.call_api("test").await?;
-        let dimension = test_embedding.len();
-
-        self.dimension = Some(dimension);
-        log::info!(
-            "Detected dimension: {} for endpoint {}",
-            dimension,
-            self.endpoint_url
-        );
-
-        Ok(dimension)
-    }
-
     /// Make API call to custom endpoint
     async fn call_api(&self, text: &str) -> Result<Vec<f32>> {
+        self.call_api_batch(vec![text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("No embeddings returned from custom endpoint"))
+    }
+
+    /// Embed several inputs in one request, returning embeddings in input order
+    async fn call_api_batch(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let input_count = inputs.len();
         let request = CustomEmbeddingRequest {
-            text: text.to_string(),
-            model: None,
+            input: inputs,
+            model: self.model.clone(),
+            encoding_format: "float",
         };
 
         let mut request_builder = self
@@ -87,61 +111,41 @@ impl CustomProvider {
                 request_builder.header("Authorization", format!("Bearer {}", api_key));
         }
 
-        let response = request_builder.send().await?;
+        let response = request_builder.send().await.map_err(|e| {
+            anyhow!(
+                "Failed to connect to custom endpoint {}: {}",
+                self.endpoint_url,
+                e
+            )
+        })?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "Custom endpoint error: HTTP {} - {}",
-                status,
-                error_text
-            ));
+        if !response.status().is_success() {
+            return Err(rate_limit::error_for_response("Custom endpoint", response).await);
         }
 
-        let embedding_response: CustomEmbeddingResponse = response.json().await?;
-
-        if embedding_response.embedding.is_empty() {
-            return Err(anyhow!("No embeddings returned from custom endpoint"));
-        }
-
-        // Cache dimension if provided in response
-        if let Some(dim) = embedding_response.dimension {
-            if self.dimension.is_none() {
-                // Note: This is a mutable operation but we're in an immutable context
-                // In practice, this would need to be handled differently
-                log::info!("Custom endpoint reported dimension: {}", dim);
+        let embeddings = match response.json::<CustomEmbeddingResponse>().await? {
+            CustomEmbeddingResponse::OpenAi { mut data } => {
+                data.sort_by_key(|data| data.index);
+                data.into_iter().map(|data| data.embedding).collect()
             }
-        }
-
-        Ok(embedding_response.embedding)
-    }
+            CustomEmbeddingResponse::Single { embedding } => vec![embedding],
+        };
 
-    /// Health check for custom endpoint
-    pub async fn check_endpoint(&self) -> Result<()> {
-        // Try a simple GET request first to see if endpoint is reachable
-        let response = self
-            .client
-            .get(&self.endpoint_url)
-            .send()
-            .await
-            .map_err(|e| {
-                anyhow!(
-                    "Failed to connect to custom endpoint {}: {}",
-                    self.endpoint_url,
-                    e
-                )
-            })?;
-
-        // Accept any non-server-error response for basic connectivity
-        if response.status().as_u16() >= 500 {
+        if embeddings.len() != input_count {
             return Err(anyhow!(
-                "Custom endpoint returned server error: HTTP {}",
-                response.status()
+                "Custom endpoint returned {} embeddings for {} inputs",
+                embeddings.len(),
+                input_count
             ));
         }
+        if let Some(embedding) = embeddings.first() {
+            if embedding.is_empty() {
+                return Err(anyhow!("Custom endpoint returned an empty embedding"));
+            }
+            self.dimension.get_or_init(|| embedding.len());
+        }
 
-        Ok(())
+        Ok(embeddings)
     }
 }
 
@@ -155,16 +159,31 @@ impl ProviderTrait for CustomProvider {
         self.call_api(text).await
     }
 
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        if texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(anyhow!("Cannot embed empty text"));
+        }
+
+        self.call_api_batch(texts.iter().map(|text| text.to_string()).collect())
+            .await
+    }
+
     async fn get_dimension(&self) -> Result<usize> {
-        if let Some(dim) = self.dimension {
-            Ok(dim)
-        } else {
-            Err(anyhow!("Dimension not known for custom endpoint {}. Use 'manx embedding test' to detect it.", self.endpoint_url))
+        if let Some(dim) = self.dimension.get() {
+            return Ok(*dim);
         }
+        // Probe the server, which caches the dimension of its answer
+        log::info!(
+            "Detecting embedding dimension for custom endpoint: {}",
+            self.endpoint_url
+        );
+        Ok(self.call_api("test").await?.len())
     }
 
     async fn health_check(&self) -> Result<()> {
-        self.check_endpoint().await?;
         self.call_api("test").await.map(|_| ())
     }
 
@@ -172,13 +191,69 @@ impl ProviderTrait for CustomProvider {
         ProviderInfo {
             name: "Custom Endpoint".to_string(),
             provider_type: "custom".to_string(),
-            model_name: None,
-            description: format!("Custom embedding endpoint: {}", self.endpoint_url),
+            model_name: self.model.clone(),
+            description: format!(
+                "OpenAI-compatible embedding endpoint: {}",
+                self.endpoint_url
+            ),
             max_input_length: None, // Unknown
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeddings_url() {
+        assert_eq!(
+            embeddings_url("http://localhost:8080"),
+            "http://localhost:8080/v1/embeddings"
+        );
+        assert_eq!(
+            embeddings_url("http://localhost:8080/v1/"),
+            "http://localhost:8080/v1/embeddings"
+        );
+        assert_eq!(
+            embeddings_url("https://gpu.internal/tei/embed"),
+            "https://gpu.internal/tei/embed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_endpoint_speaks_openai_format() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 8192];
+            let read = socket.read(&mut request).await.unwrap();
+            let body = r#"{"object":"list","data":[{"object":"embedding","embedding":[0.0,1.0,0.0],"index":1},{"object":"embedding","embedding":[1.0,0.0,0.0],"index":0}],"model":"bge-small"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            String::from_utf8_lossy(&request[..read]).to_string()
+        });
+
+        let provider = CustomProvider::new(
+            format!("http://{}", addr),
+            None,
+            Some("bge-small".to_string()),
+            std::time::Duration::from_secs(30),
+        );
+        let batch = provider.embed_batch(&["first", "second"]).await.unwrap();
+        assert_eq!(batch, vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]]);
+        assert_eq!(provider.get_dimension().await.unwrap(), 3);
 
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/embeddings "));
+        assert!(request.contains(r#""input":["first","second"]"#));
+        assert!(request.contains(r#""model":"bge-small""#));
     }
 }
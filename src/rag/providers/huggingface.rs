@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::Serialize;
 
+use super::rate_limit::{self, RateLimiter};
 use super::{EmbeddingProvider as ProviderTrait, ProviderInfo};
 
 /// HuggingFace Inference API embedding provider
@@ -11,11 +12,13 @@ pub struct HuggingFaceProvider {
     api_key: String,
     model: String,
     dimension: Option<usize>, // Cached dimension
+    rate_limiter: RateLimiter,
 }
 
 #[derive(Serialize)]
-struct HfEmbeddingRequest {
-    inputs: String,
+struct HfEmbeddingRequest<T: Serialize> {
+    /// A single string, or a list of strings for a batch
+    inputs: T,
     options: HfOptions,
 }
 
@@ -26,9 +29,9 @@ struct HfOptions {
 
 impl HuggingFaceProvider {
     /// Create a new HuggingFace provider
-    pub fn new(api_key: String, model: String) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(60)) // HF can be slower
+    pub fn new(api_key: String, model: String, timeout: std::time::Duration) -> Self {
+        let client = crate::http_client::builder()
+            .timeout(timeout)
             .build()
             .unwrap();
 
@@ -37,9 +40,20 @@ impl HuggingFaceProvider {
             api_key,
             model,
             dimension: None,
+            rate_limiter: RateLimiter::new(None, None),
         }
     }
 
+    /// Keep requests within a requests-per-minute and tokens-per-minute quota
+    pub fn with_rate_limit(
+        mut self,
+        requests_per_minute: Option<u32>,
+        tokens_per_minute: Option<u32>,
+    ) -> Self {
+        self.rate_limiter = RateLimiter::new(requests_per_minute, tokens_per_minute);
+        self
+    }
+
     /// Detect dimension by making a test API call
     #[allow(dead_code)]
     pub async fn detect_dimension(&mut self) -> Result<usize> {
@@ -63,8 +77,43 @@ impl HuggingFaceProvider {
 
     /// Make API call to HuggingFace Inference API
     async fn call_api(&self, text: &str) -> Result<Vec<f32>> {
+        // HuggingFace returns embeddings as a flat array
+        let embeddings: Vec<f32> = self
+            .post(text, rate_limit::estimate_tokens(&[text]))
+            .await?;
+
+        if embeddings.is_empty() {
+            return Err(anyhow!("No embeddings returned from HuggingFace API"));
+        }
+
+        Ok(embeddings)
+    }
+
+    /// Embed several inputs in one request; the API answers with one array per input
+    async fn call_api_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let input_count = texts.len();
+        let tokens = rate_limit::estimate_tokens(&texts);
+        let embeddings: Vec<Vec<f32>> = self.post(texts, tokens).await?;
+
+        if embeddings.len() != input_count {
+            return Err(anyhow!(
+                "HuggingFace API returned {} embeddings for {} inputs",
+                embeddings.len(),
+                input_count
+            ));
+        }
+
+        Ok(embeddings)
+    }
+
+    async fn post<T: Serialize, R: serde::de::DeserializeOwned>(
+        &self,
+        inputs: T,
+        tokens: u32,
+    ) -> Result<R> {
+        self.rate_limiter.acquire(tokens).await;
         let request = HfEmbeddingRequest {
-            inputs: text.to_string(),
+            inputs,
             options: HfOptions {
                 wait_for_model: true,
             },
@@ -81,24 +130,18 @@ impl HuggingFaceProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "HuggingFace API error: HTTP {} - {}",
-                status,
-                error_text
-            ));
+        if !response.status().is_success() {
+            return Err(rate_limit::error_for_response("HuggingFace", response).await);
         }
 
-        // HuggingFace returns embeddings as a flat array
-        let embeddings: Vec<f32> = response.json().await?;
-
-        if embeddings.is_empty() {
-            return Err(anyhow!("No embeddings returned from HuggingFace API"));
-        }
+        Ok(response.json().await?)
+    }
 
-        Ok(embeddings)
+    /// Cut text down to roughly the model's token limit
+    fn truncate<'a>(&self, text: &'a str) -> &'a str {
+        let (_, max_tokens) = Self::get_model_info(&self.model);
+        // Rough token approximation
+        super::truncate_chars(text, max_tokens * 4)
     }
 
     /// Get common HuggingFace model information (dimension, max_length)
@@ -122,16 +165,22 @@ impl ProviderTrait for HuggingFaceProvider {
             return Err(anyhow!("Cannot embed empty text"));
         }
 
-        // Truncate text if too long
-        let (_, max_chars) = Self::get_model_info(&self.model);
-        let truncated_text = if text.len() > max_chars * 4 {
-            // Rough token approximation
-            &text[..max_chars * 4]
-        } else {
-            text
-        };
+        self.call_api(self.truncate(text)).await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        if texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(anyhow!("Cannot embed empty text"));
+        }
 
-        self.call_api(truncated_text).await
+        let inputs = texts
+            .iter()
+            .map(|text| self.truncate(text).to_string())
+            .collect();
+        self.call_api_batch(inputs).await
     }
 
     async fn get_dimension(&self) -> Result<usize> {
@@ -167,8 +216,4 @@ impl ProviderTrait for HuggingFaceProvider {
             max_input_length: Some(max_length),
         }
     }
-
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
 }
@@ -6,6 +6,7 @@ pub mod huggingface;
 pub mod ollama;
 pub mod onnx;
 pub mod openai;
+pub mod rate_limit;
 
 /// Trait for embedding providers
 #[async_trait::async_trait]
@@ -13,6 +14,18 @@ pub trait EmbeddingProvider: std::any::Any {
     /// Generate embeddings for a single text
     async fn embed_text(&self, text: &str) -> Result<Vec<f32>>;
 
+    /// Generate embeddings for several texts, one per input in the same order
+    ///
+    /// The default embeds them one at a time; API providers override this to send a
+    /// single request for the whole batch.
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.embed_text(text).await?);
+        }
+        Ok(embeddings)
+    }
+
     /// Get the dimension of embeddings produced by this provider
     async fn get_dimension(&self) -> Result<usize>;
 
@@ -21,13 +34,10 @@ pub trait EmbeddingProvider: std::any::Any {
 
     /// Get provider-specific information
     fn get_info(&self) -> ProviderInfo;
-
-    /// Downcast support for accessing provider-specific methods
-    fn as_any(&self) -> &dyn std::any::Any;
 }
 
 /// Information about an embedding provider
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct ProviderInfo {
     pub name: String,
     pub provider_type: String,
@@ -35,3 +45,25 @@ pub struct ProviderInfo {
     pub description: String,
     pub max_input_length: Option<usize>,
 }
+
+/// The first `max_chars` characters of `text`, cut on a character boundary
+pub(crate) fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_chars_on_multibyte_text() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("連接池の設定", 3), "連接池");
+        assert_eq!(truncate_chars("ab🦀cd", 3), "ab🦀");
+        // Byte 3 falls inside the first character, which a byte slice would panic on
+        assert_eq!(truncate_chars("é🦀", 1), "é");
+    }
+}
@@ -44,9 +44,9 @@ pub struct ModelDetails {
 
 impl OllamaProvider {
     /// Create a new Ollama provider
-    pub fn new(model: String, base_url: Option<String>) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(60))
+    pub fn new(model: String, base_url: Option<String>, timeout: std::time::Duration) -> Self {
+        let client = crate::http_client::builder()
+            .timeout(timeout)
             .build()
             .unwrap();
 
@@ -234,8 +234,4 @@ impl ProviderTrait for OllamaProvider {
             max_input_length: Some(max_length),
         }
     }
-
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
 }
@@ -14,6 +14,20 @@ use ort::value::Value;
 #[cfg(feature = "onnx-embeddings")]
 use tokenizers::Tokenizer;
 
+/// SHA256 of files `download_model` fetches, as `(model, remote path, sha256)`
+///
+/// Downloads of a listed file must match; unlisted files are only checked against their
+/// `Content-Length`. Entries must be taken from the Hugging Face file page of the model.
+const PINNED_SHA256: &[(&str, &str, &str)] = &[];
+
+/// Pinned SHA256 of `remote_path` in `model_name`'s repository, if there is one
+fn pinned_sha256(model_name: &str, remote_path: &str) -> Option<&'static str> {
+    PINNED_SHA256
+        .iter()
+        .find(|(model, path, _)| *model == model_name && *path == remote_path)
+        .map(|(_, _, sha256)| *sha256)
+}
+
 /// ONNX-based embedding provider with real inference capabilities
 pub struct OnnxProvider {
     model_name: String,
@@ -134,9 +148,10 @@ impl OnnxProvider {
             ("config.json", "config.json"),
         ];
 
-        let client = reqwest::Client::new();
+        let client = crate::http_client::builder().build()?;
         let mut total_size = 0u64;
         let mut dimension = None;
+        let mut model_checksum = None;
 
         for (remote_path, local_filename) in files_to_download {
             let url = format!(
@@ -147,21 +162,18 @@ impl OnnxProvider {
 
             log::info!("Downloading: {} -> {:?}", url, local_path);
 
-            let response = client.get(&url).send().await?;
-
-            if !response.status().is_success() {
-                return Err(anyhow!(
-                    "Failed to download {}: HTTP {}",
-                    url,
-                    response.status()
-                ));
+            let expected_sha256 = pinned_sha256(model_name, remote_path);
+            if expected_sha256.is_none() {
+                log::debug!("No pinned SHA256 for {}, checking length only", url);
+            }
+            let (size, sha256) =
+                Self::download_file(&client, &url, &local_path, expected_sha256).await?;
+            total_size += size;
+            if local_filename == "model.onnx" {
+                model_checksum = Some(sha256);
             }
 
-            let bytes = response.bytes().await?;
-            std::fs::write(&local_path, &bytes)?;
-            total_size += bytes.len() as u64;
-
-            log::info!("Downloaded {} ({} bytes)", local_filename, bytes.len());
+            log::info!("Downloaded {} ({} bytes)", local_filename, size);
 
             // Try to extract dimension from config.json
             if local_filename == "config.json" {
@@ -196,7 +208,7 @@ impl OnnxProvider {
             api_endpoint: None,
             installed_date: chrono::Utc::now(),
             last_used: None,
-            checksum: Some(Self::calculate_model_checksum(&model_dir)?),
+            checksum: model_checksum,
             description: Some(format!("ONNX model: {}", model_name)),
             max_input_length: Some(512), // Common default
         };
@@ -262,58 +274,112 @@ impl OnnxProvider {
         }
     }
 
-    /// Calculate SHA256 checksum for model files to ensure integrity
-    fn calculate_model_checksum(model_dir: &Path) -> Result<String> {
-        use sha2::{Digest, Sha256};
-        use std::fs::File;
-        use std::io::Read;
-
-        let mut hasher = Sha256::new();
+    /// Download `url` to `dest`, returning its size and SHA256
+    ///
+    /// Data is streamed to a `.part` file next to `dest` and only renamed into place once the
+    /// length matches `Content-Length` and the hash matches `expected_sha256` (when given). The
+    /// partial file is deleted on any failure, so a previous copy of `dest` is left untouched.
+    async fn download_file(
+        client: &reqwest::Client,
+        url: &str,
+        dest: &Path,
+        expected_sha256: Option<&str>,
+    ) -> Result<(u64, String)> {
+        let mut partial = dest.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = std::path::PathBuf::from(partial);
+
+        match Self::stream_to_file(client, url, &partial, expected_sha256).await {
+            Ok(downloaded) => {
+                std::fs::rename(&partial, dest)?;
+                Ok(downloaded)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                Err(e)
+            }
+        }
+    }
 
-        // Hash main model files in deterministic order
-        let files_to_hash = ["model.onnx", "tokenizer.json", "config.json"];
+    async fn stream_to_file(
+        client: &reqwest::Client,
+        url: &str,
+        path: &Path,
+        expected_sha256: Option<&str>,
+    ) -> Result<(u64, String)> {
+        use indicatif::{ProgressBar, ProgressStyle};
+        use sha2::{Digest, Sha256};
+        use std::io::Write;
 
-        for filename in files_to_hash.iter() {
-            let file_path = model_dir.join(filename);
-            if file_path.exists() {
-                let mut file = File::open(&file_path)?;
-                let mut buffer = Vec::new();
-                file.read_to_end(&mut buffer)?;
+        let mut response = client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to download {}: HTTP {}",
+                url,
+                response.status()
+            ));
+        }
 
-                // Include filename in hash to ensure different files produce different hashes
-                hasher.update(filename.as_bytes());
-                hasher.update(&buffer);
+        let expected_len = response.content_length();
+        let pb = match expected_len {
+            Some(len) => ProgressBar::new(len).with_style(
+                ProgressStyle::default_bar()
+                    .template("{msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})")
+                    .unwrap()
+                    .progress_chars("=> "),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.cyan} {msg} {bytes}")
+                    .unwrap(),
+            ),
+        };
+        pb.set_message(
+            path.file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
 
-                log::debug!("Hashed {} ({} bytes)", filename, buffer.len());
-            } else {
-                log::warn!("Model file not found for checksum: {:?}", file_path);
+        let mut file = std::fs::File::create(path)?;
+        let mut hasher = Sha256::new();
+        let mut received = 0u64;
+        let streamed: Result<()> = async {
+            while let Some(chunk) = response.chunk().await? {
+                file.write_all(&chunk)?;
+                hasher.update(&chunk);
+                received += chunk.len() as u64;
+                pb.set_position(received);
             }
+            file.flush()?;
+            Ok(())
         }
+        .await;
+        pb.finish_and_clear();
+        streamed?;
 
-        let result = hasher.finalize();
-        let checksum = format!("{:x}", result);
-        log::info!("Calculated model checksum: {}", &checksum[..16]);
-
-        Ok(checksum)
-    }
-
-    /// Verify model integrity using stored checksum
-    #[allow(dead_code)] // Utility function for future use
-    pub fn verify_model_integrity(model_dir: &Path, expected_checksum: &str) -> Result<bool> {
-        let actual_checksum = Self::calculate_model_checksum(model_dir)?;
-        let is_valid = actual_checksum == expected_checksum;
-
-        if is_valid {
-            log::info!("Model integrity verified successfully");
-        } else {
-            log::error!(
-                "Model integrity check failed: expected {}, got {}",
-                &expected_checksum[..16],
-                &actual_checksum[..16]
-            );
+        if let Some(len) = expected_len {
+            if received != len {
+                return Err(anyhow!(
+                    "Download of {} was truncated: got {} of {} bytes",
+                    url,
+                    received,
+                    len
+                ));
+            }
         }
 
-        Ok(is_valid)
+        let sha256 = format!("{:x}", hasher.finalize());
+        if let Some(expected) = expected_sha256 {
+            if sha256 != expected {
+                return Err(anyhow!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    url,
+                    expected,
+                    sha256
+                ));
+            }
+        }
+        Ok((received, sha256))
     }
 
     /// List available models that can be downloaded
@@ -358,52 +424,6 @@ impl ProviderTrait for OnnxProvider {
             max_input_length: Some(self.max_length),
         }
     }
-
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-}
-
-impl OnnxProvider {
-    /// Batch embedding generation for improved performance
-    /// Processes multiple texts in a single ONNX inference pass
-    pub async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        if texts.is_empty() {
-            return Ok(vec![]);
-        }
-
-        // Filter out empty texts
-        let valid_texts: Vec<&str> = texts
-            .iter()
-            .filter(|t| !t.trim().is_empty())
-            .copied()
-            .collect();
-
-        if valid_texts.is_empty() {
-            return Err(anyhow!("Cannot embed batch with all empty texts"));
-        }
-
-        // For now, process sequentially (future: true batching in ONNX)
-        // This still provides benefits through shared session access pattern
-        let mut embeddings = Vec::with_capacity(valid_texts.len());
-
-        for text in valid_texts {
-            match self.embed_text_impl(text).await {
-                Ok(embedding) => embeddings.push(embedding),
-                Err(e) => {
-                    log::warn!("Failed to embed text in batch: {}", e);
-                    // Continue with other texts instead of failing entire batch
-                    continue;
-                }
-            }
-        }
-
-        if embeddings.is_empty() {
-            return Err(anyhow!("Batch embedding failed for all texts"));
-        }
-
-        Ok(embeddings)
-    }
 }
 
 impl OnnxProvider {
@@ -447,11 +467,8 @@ impl OnnxProvider {
         // Add token_type_ids only if the model requires it
         {
             let session = self.session.read().await;
-            let input_names: Vec<&str> = session
-                .inputs()
-                .iter()
-                .map(|input| input.name())
-                .collect();
+            let input_names: Vec<&str> =
+                session.inputs().iter().map(|input| input.name()).collect();
 
             if input_names.contains(&"token_type_ids") {
                 let token_type_ids: Vec<i64> = vec![0i64; self.max_length];
@@ -526,3 +543,81 @@ impl OnnxProvider {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve `body` to every request until the test ends
+    async fn serve(body: &'static [u8]) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+            }
+        });
+        format!("http://{}/model.onnx", addr)
+    }
+
+    #[test]
+    fn test_pinned_hashes_cover_offered_models() {
+        let models = OnnxProvider::list_available_models();
+        for (model, path, sha256) in PINNED_SHA256 {
+            assert!(models.contains(model), "{} is not offered", model);
+            assert!(
+                ["onnx/model.onnx", "tokenizer.json", "config.json"].contains(path),
+                "{} is not downloaded",
+                path
+            );
+            assert!(
+                sha256.len() == 64
+                    && sha256
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)),
+                "{} {} has a malformed hash",
+                model,
+                path
+            );
+        }
+        assert_eq!(pinned_sha256("unknown/model", "onnx/model.onnx"), None);
+    }
+
+    #[tokio::test]
+    async fn test_download_file_verifies_sha256() {
+        let body = b"onnx model bytes";
+        let url = serve(body).await;
+        let expected = format!("{:x}", Sha256::digest(body));
+        let dir = std::env::temp_dir().join(format!("manx_download_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("model.onnx");
+        let client = reqwest::Client::new();
+
+        let (size, sha256) = OnnxProvider::download_file(&client, &url, &dest, Some(&expected))
+            .await
+            .unwrap();
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(sha256, expected);
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+
+        let wrong = "0".repeat(64);
+        let err = OnnxProvider::download_file(&client, &url, &dest, Some(&wrong))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+        assert!(!dir.join("model.onnx.part").exists());
+        // The previously verified copy is kept
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,19 +3,26 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+use super::rate_limit::{self, RateLimiter};
 use super::{EmbeddingProvider as ProviderTrait, ProviderInfo};
+use crate::rag::azure::{self, AzureDeployment};
 
-/// OpenAI API embedding provider
+/// OpenAI API embedding provider, also serving Azure OpenAI deployments
 pub struct OpenAiProvider {
     client: Client,
     api_key: String,
     model: String,
     dimension: Option<usize>, // Cached dimension
+    /// Set when embedding through an Azure OpenAI deployment instead of api.openai.com
+    azure: Option<AzureDeployment>,
+    /// Dimension reported by an Azure deployment, whose model isn't known from its name
+    detected_dimension: std::sync::OnceLock<usize>,
+    rate_limiter: RateLimiter,
 }
 
 #[derive(Serialize)]
 struct EmbeddingRequest {
-    input: String,
+    input: Vec<String>,
     model: String,
     encoding_format: String,
 }
@@ -41,9 +48,9 @@ struct Usage {
 
 impl OpenAiProvider {
     /// Create a new OpenAI provider
-    pub fn new(api_key: String, model: String) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
+    pub fn new(api_key: String, model: String, timeout: std::time::Duration) -> Self {
+        let client = crate::http_client::builder()
+            .timeout(timeout)
             .build()
             .unwrap();
 
@@ -52,6 +59,31 @@ impl OpenAiProvider {
             api_key,
             model,
             dimension: None,
+            azure: None,
+            detected_dimension: std::sync::OnceLock::new(),
+            rate_limiter: RateLimiter::new(None, None),
+        }
+    }
+
+    /// Keep requests within a requests-per-minute and tokens-per-minute quota
+    pub fn with_rate_limit(
+        mut self,
+        requests_per_minute: Option<u32>,
+        tokens_per_minute: Option<u32>,
+    ) -> Self {
+        self.rate_limiter = RateLimiter::new(requests_per_minute, tokens_per_minute);
+        self
+    }
+
+    /// Create a provider for an Azure OpenAI embedding deployment
+    pub fn azure(
+        api_key: String,
+        deployment: AzureDeployment,
+        timeout: std::time::Duration,
+    ) -> Self {
+        Self {
+            azure: Some(deployment.clone()),
+            ..Self::new(api_key, deployment.deployment, timeout)
         }
     }
 
@@ -84,37 +116,63 @@ impl OpenAiProvider {
         None
     }
 
+    fn service(&self) -> &'static str {
+        if self.azure.is_some() {
+            "Azure OpenAI"
+        } else {
+            "OpenAI"
+        }
+    }
+
     /// Make API call to OpenAI embeddings endpoint
     async fn call_api(&self, text: &str) -> Result<Vec<f32>> {
+        self.call_api_batch(vec![text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("No embeddings returned from {} API", self.service()))
+    }
+
+    /// Embed several inputs in one request, returning embeddings in input order
+    async fn call_api_batch(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let input_count = inputs.len();
+        self.rate_limiter
+            .acquire(rate_limit::estimate_tokens(&inputs))
+            .await;
         let request = EmbeddingRequest {
-            input: text.to_string(),
+            input: inputs,
             model: self.model.clone(),
             encoding_format: "float".to_string(),
         };
 
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/embeddings")
-            .header("Authorization", format!("Bearer {}", self.api_key))
+        let http_request = match &self.azure {
+            Some(deployment) => self
+                .client
+                .post(deployment.url("embeddings"))
+                .header(azure::API_KEY_HEADER, &self.api_key),
+            None => self
+                .client
+                .post("https://api.openai.com/v1/embeddings")
+                .header("Authorization", format!("Bearer {}", self.api_key)),
+        };
+        let response = http_request
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
             .await?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow!(
-                "OpenAI API error: HTTP {} - {}",
-                status,
-                error_text
-            ));
+        if !response.status().is_success() {
+            return Err(rate_limit::error_for_response(self.service(), response).await);
         }
 
-        let embedding_response: EmbeddingResponse = response.json().await?;
+        let mut embedding_response: EmbeddingResponse = response.json().await?;
 
-        if embedding_response.data.is_empty() {
-            return Err(anyhow!("No embeddings returned from OpenAI API"));
+        if embedding_response.data.len() != input_count {
+            return Err(anyhow!(
+                "{} API returned {} embeddings for {} inputs",
+                self.service(),
+                embedding_response.data.len(),
+                input_count
+            ));
         }
 
         // Log usage statistics
@@ -124,14 +182,6 @@ impl OpenAiProvider {
             embedding_response.usage.total_tokens
         );
 
-        // Ensure we have the right embedding (index should match)
-        if embedding_response.data[0].index != 0 {
-            log::warn!(
-                "Unexpected embedding index: {}",
-                embedding_response.data[0].index
-            );
-        }
-
         // Verify model matches request
         if embedding_response.model != self.model {
             log::info!(
@@ -141,7 +191,19 @@ impl OpenAiProvider {
             );
         }
 
-        Ok(embedding_response.data[0].embedding.clone())
+        // The API tags each embedding with its input index; don't rely on response order
+        embedding_response.data.sort_by_key(|data| data.index);
+        Ok(embedding_response
+            .data
+            .into_iter()
+            .map(|data| data.embedding)
+            .collect())
+    }
+
+    /// Cut text down to the model's input limit, counted in characters
+    fn truncate<'a>(&self, text: &'a str) -> &'a str {
+        let (_, max_chars) = Self::get_model_info(&self.model);
+        super::truncate_chars(text, max_chars)
     }
 
     /// Get common OpenAI model information
@@ -163,19 +225,33 @@ impl ProviderTrait for OpenAiProvider {
         }
 
         // Truncate text if too long (OpenAI models have token limits)
-        let (_, max_chars) = Self::get_model_info(&self.model);
-        let truncated_text = if text.len() > max_chars {
-            &text[..max_chars]
-        } else {
-            text
-        };
+        self.call_api(self.truncate(text)).await
+    }
 
-        self.call_api(truncated_text).await
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        if texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(anyhow!("Cannot embed empty text"));
+        }
+
+        let inputs = texts
+            .iter()
+            .map(|text| self.truncate(text).to_string())
+            .collect();
+        self.call_api_batch(inputs).await
     }
 
     async fn get_dimension(&self) -> Result<usize> {
         if let Some(dim) = self.dimension {
             Ok(dim)
+        } else if self.azure.is_some() {
+            if let Some(dim) = self.detected_dimension.get() {
+                return Ok(*dim);
+            }
+            let dim = self.call_api("test").await?.len();
+            Ok(*self.detected_dimension.get_or_init(|| dim))
         } else {
             // Use known dimensions for common models
             let (dim, _) = Self::get_model_info(&self.model);
@@ -190,6 +266,20 @@ impl ProviderTrait for OpenAiProvider {
     fn get_info(&self) -> ProviderInfo {
         let (_, max_length) = Self::get_model_info(&self.model);
 
+        if let Some(deployment) = &self.azure {
+            return ProviderInfo {
+                name: "Azure OpenAI Embeddings".to_string(),
+                provider_type: "azure".to_string(),
+                model_name: Some(deployment.deployment.clone()),
+                description: format!(
+                    "Azure OpenAI deployment {} on {}",
+                    deployment.deployment,
+                    deployment.endpoint()
+                ),
+                max_input_length: Some(max_length),
+            };
+        }
+
         ProviderInfo {
             name: "OpenAI Embeddings".to_string(),
             provider_type: "openai".to_string(),
@@ -198,8 +288,23 @@ impl ProviderTrait for OpenAiProvider {
             max_input_length: Some(max_length),
         }
     }
+}
 
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_keeps_multibyte_characters_whole() {
+        let provider = OpenAiProvider::new(
+            "sk-test".to_string(),
+            "text-embedding-3-small".to_string(),
+            std::time::Duration::from_secs(30),
+        );
+        // Byte 8191, the old cut, falls inside the "é"
+        let text = format!("{}é{}", "a".repeat(8190), "b".repeat(10));
+        let truncated = provider.truncate(&text);
+        assert_eq!(truncated.chars().count(), 8191);
+        assert!(truncated.ends_with('é'));
     }
 }
@@ -0,0 +1,179 @@
+//! Client-side throttling for embedding APIs
+//!
+//! Indexing a large directory can send more requests than an API key's quota allows.
+//! [`RateLimiter`] holds requests back to a requests-per-minute and tokens-per-minute budget,
+//! and responses the API asks to have retried (429 and 5xx) come back as [`RetryableError`]
+//! carrying the server's `Retry-After`, so `EmbeddingModel` can wait exactly that long.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Window the per-minute budgets apply to
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Holds requests back so no 60-second window exceeds the configured budgets
+pub struct RateLimiter {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+    /// Start time and estimated tokens of every request sent within the last window
+    sent: Mutex<VecDeque<(Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// A limit of `None` (or 0) leaves that budget unchecked
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        Self {
+            requests_per_minute: requests_per_minute.filter(|&limit| limit > 0),
+            tokens_per_minute: tokens_per_minute.filter(|&limit| limit > 0),
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Wait until a request of about `tokens` tokens fits both budgets, then record it
+    pub async fn acquire(&self, tokens: u32) {
+        if self.requests_per_minute.is_none() && self.tokens_per_minute.is_none() {
+            return;
+        }
+        while let Some(wait) = self.try_acquire(Instant::now(), tokens) {
+            log::debug!("Embedding rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Record the request and return `None` if it fits, otherwise how long to wait
+    fn try_acquire(&self, now: Instant, tokens: u32) -> Option<Duration> {
+        let mut sent = self.sent.lock().unwrap();
+        while sent
+            .front()
+            .is_some_and(|&(at, _)| now.duration_since(at) >= WINDOW)
+        {
+            sent.pop_front();
+        }
+
+        let over_requests = self
+            .requests_per_minute
+            .is_some_and(|limit| sent.len() as u32 >= limit);
+        let used_tokens: u32 = sent.iter().map(|&(_, tokens)| tokens).sum();
+        // A request bigger than the whole budget still goes out once the window is empty
+        let over_tokens = self
+            .tokens_per_minute
+            .is_some_and(|limit| !sent.is_empty() && used_tokens + tokens > limit);
+        if !over_requests && !over_tokens {
+            sent.push_back((now, tokens));
+            return None;
+        }
+
+        let (oldest, _) = sent.front()?;
+        Some(WINDOW.saturating_sub(now.duration_since(*oldest)))
+    }
+}
+
+/// Rough token count of `texts` (about four characters per token)
+pub fn estimate_tokens<S: AsRef<str>>(texts: &[S]) -> u32 {
+    texts
+        .iter()
+        .map(|text| text.as_ref().len().div_ceil(4) as u32)
+        .sum()
+}
+
+/// An API response worth retrying: rate limited (429) or a server error (5xx)
+#[derive(Debug)]
+pub struct RetryableError {
+    pub service: &'static str,
+    pub status: reqwest::StatusCode,
+    /// How long the server asked us to wait, from its `Retry-After` header
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+impl fmt::Display for RetryableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} API error: HTTP {} - {}",
+            self.service, self.status, self.body
+        )
+    }
+}
+
+impl std::error::Error for RetryableError {}
+
+/// Whether a failed response should be retried rather than reported
+pub fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Turn an unsuccessful response into an error, retryable when its status is
+pub async fn error_for_response(
+    service: &'static str,
+    response: reqwest::Response,
+) -> anyhow::Error {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let body = response.text().await.unwrap_or_default();
+    if is_retryable_status(status) {
+        RetryableError {
+            service,
+            status,
+            retry_after,
+            body,
+        }
+        .into()
+    } else {
+        anyhow::anyhow!("{} API error: HTTP {} - {}", service, status, body)
+    }
+}
+
+/// `Retry-After` as either delay seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_budgets() {
+        let start = Instant::now();
+        let limiter = RateLimiter::new(Some(2), None);
+        assert_eq!(limiter.try_acquire(start, 10), None);
+        assert_eq!(limiter.try_acquire(start, 10), None);
+        assert_eq!(
+            limiter.try_acquire(start + Duration::from_secs(20), 10),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(limiter.try_acquire(start + WINDOW, 10), None);
+
+        let limiter = RateLimiter::new(None, Some(100));
+        assert_eq!(limiter.try_acquire(start, 80), None);
+        assert!(limiter.try_acquire(start, 30).is_some());
+        assert_eq!(limiter.try_acquire(start, 20), None);
+        // Oversized requests wait for an empty window instead of blocking forever
+        assert_eq!(limiter.try_acquire(start + WINDOW, 500), None);
+
+        assert_eq!(estimate_tokens(&["abcd", "abcde"]), 3);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
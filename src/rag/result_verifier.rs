@@ -13,6 +13,38 @@ use crate::rag::{
     RagSearchResult, SmartSearchConfig,
 };
 
+/// System prompt asking the LLM to score passages for `--rerank`
+const RERANK_PROMPT: &str = "You rank documentation search results for a developer. Rate how well each passage answers the query, from 0 (unrelated) to 10 (answers it directly). Reply with only a JSON array of numbers.";
+
+/// Scores from a rerank answer: the JSON array in it, if it has one score per passage
+fn parse_rerank_scores(response: &str, count: usize) -> Option<Vec<f32>> {
+    let start = response.find('[')?;
+    let end = response.rfind(']')?;
+    let scores: Vec<f32> = serde_json::from_str(response.get(start..=end)?).ok()?;
+    (scores.len() == count).then_some(scores)
+}
+
+/// Order results by their rerank scores, keeping similarity order between equal scores
+fn apply_rerank_scores(
+    results: Vec<VerifiedResult>,
+    scores: &[f32],
+    limit: usize,
+) -> Vec<VerifiedResult> {
+    let mut scored: Vec<(f32, VerifiedResult)> = scores.iter().copied().zip(results).collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(score, mut verified)| {
+            if let Some(breakdown) = &mut verified.result.score_breakdown {
+                breakdown.step("LLM rerank relevance (0-10)", score);
+            }
+            verified.verification_method = VerificationMethod::LlmBased;
+            verified
+        })
+        .collect()
+}
+
 /// Verified search result with confidence scoring
 #[derive(Debug, Clone)]
 pub struct VerifiedResult {
@@ -90,15 +122,79 @@ impl ResultVerifier {
         Ok(verified_results)
     }
 
+    /// Reorder results by asking the LLM how relevant each one is to the query
+    ///
+    /// The top `2 * limit` results are scored in a single prompt and the best `limit`
+    /// returned. Without an LLM, with reranking off, or when the call fails or its answer
+    /// can't be parsed, the results keep their similarity order.
+    pub async fn rerank_results(
+        &self,
+        query: &str,
+        mut results: Vec<VerifiedResult>,
+        limit: usize,
+    ) -> Vec<VerifiedResult> {
+        results.truncate(limit.saturating_mul(2));
+        let llm_client = match self.llm_client {
+            Some(ref llm_client)
+                if self.config.enable_result_verification
+                    && self.config.llm_rerank
+                    && results.len() > 1 =>
+            {
+                llm_client
+            }
+            _ => {
+                results.truncate(limit);
+                return results;
+            }
+        };
+
+        let mut user_message = format!("Query: \"{}\"\n\n", query);
+        for (i, verified) in results.iter().enumerate() {
+            user_message.push_str(&format!(
+                "[Passage {}] {}\n{}\n\n",
+                i + 1,
+                verified.result.title.as_deref().unwrap_or("Untitled"),
+                self.truncate_content(&verified.result.content, 600)
+            ));
+        }
+        user_message.push_str(&format!(
+            "Reply with a JSON array of {} scores, one per passage in the order given.",
+            results.len()
+        ));
+
+        let scores = match llm_client.complete(RERANK_PROMPT, &user_message).await {
+            Ok(response) => parse_rerank_scores(&response, results.len())
+                .ok_or_else(|| "the LLM's answer had no usable scores".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match scores {
+            Ok(scores) => apply_rerank_scores(results, &scores, limit),
+            Err(reason) => {
+                use colored::Colorize;
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Reranking skipped ({}); showing results by similarity",
+                        reason
+                    )
+                    .dimmed()
+                );
+                results.truncate(limit);
+                results
+            }
+        }
+    }
+
     /// Verify a single search result
     async fn verify_single_result(
         &self,
         query: &EnhancedQuery,
         result: RagSearchResult,
     ) -> Result<VerifiedResult> {
-        // Try LLM verification first if available
+        // Try LLM verification first if available; reranking asks the LLM once for the
+        // whole batch instead
         if let Some(ref llm_client) = self.llm_client {
-            if self.config.enable_result_verification {
+            if self.config.enable_result_verification && !self.config.llm_rerank {
                 match self.verify_with_llm(query, &result, llm_client).await {
                     Ok(verified) => return Ok(verified),
                     Err(e) => {
@@ -152,8 +248,13 @@ impl ResultVerifier {
             .as_str()
             .map(|s| s.to_string());
 
+        let mut result = result.clone();
+        if let Some(breakdown) = &mut result.score_breakdown {
+            breakdown.step("LLM verification confidence", confidence);
+        }
+
         Ok(VerifiedResult {
-            result: result.clone(),
+            result,
             confidence_score: confidence,
             relevance_explanation: explanation,
             extracted_context: key_context,
@@ -207,21 +308,39 @@ impl ResultVerifier {
     fn verify_with_fallback(
         &self,
         query: &EnhancedQuery,
-        result: RagSearchResult,
+        mut result: RagSearchResult,
     ) -> VerifiedResult {
         let mut confidence_score = result.score; // Start with embedding similarity
+        let mut steps = Vec::new();
 
         // Keyword matching boost
         let keyword_score = self.calculate_keyword_score(&query.original, &result.content);
         confidence_score = (confidence_score + keyword_score) / 2.0;
+        steps.push((
+            format!("averaged with query word match {:.3}", keyword_score),
+            confidence_score,
+        ));
 
         // Intent-specific scoring adjustments
-        confidence_score =
+        let adjusted =
             self.apply_intent_adjustments(confidence_score, &query.detected_intent, &result);
+        if adjusted != confidence_score {
+            confidence_score = adjusted;
+            steps.push(("query intent adjustment".to_string(), confidence_score));
+        }
 
         // Apply query variation matching
         let variation_score = self.calculate_variation_score(query, &result.content);
         confidence_score = (confidence_score * 0.7) + (variation_score * 0.3);
+        steps.push((
+            format!("30% query variation match {:.3}", variation_score),
+            confidence_score.clamp(0.0, 1.0),
+        ));
+        if let Some(breakdown) = &mut result.score_breakdown {
+            for (label, score) in steps {
+                breakdown.step(label, score);
+            }
+        }
 
         // Extract key context using simple heuristics
         let extracted_context = self.extract_key_context(&query.original, &result.content);
@@ -293,24 +412,22 @@ impl ResultVerifier {
                     }
                 }
             }
-            QueryIntent::Documentation => {
-                // Boost documentation-like files
+            // Boost documentation-like files
+            QueryIntent::Documentation
                 if result.source_path.to_string_lossy().contains("doc")
                     || result.source_path.to_string_lossy().contains("readme")
-                    || result.source_path.to_string_lossy().ends_with(".md")
-                {
-                    adjusted_score *= 1.1;
-                }
+                    || result.source_path.to_string_lossy().ends_with(".md") =>
+            {
+                adjusted_score *= 1.1;
             }
-            QueryIntent::Configuration => {
-                // Boost config-like files
+            // Boost config-like files
+            QueryIntent::Configuration
                 if result.source_path.to_string_lossy().contains("config")
                     || result.source_path.to_string_lossy().ends_with(".json")
                     || result.source_path.to_string_lossy().ends_with(".yaml")
-                    || result.source_path.to_string_lossy().ends_with(".toml")
-                {
-                    adjusted_score *= 1.2;
-                }
+                    || result.source_path.to_string_lossy().ends_with(".toml") =>
+            {
+                adjusted_score *= 1.2;
             }
             _ => {}
         }
@@ -401,7 +518,6 @@ mod tests {
     use crate::rag::{DocumentMetadata, SourceType};
     use std::path::PathBuf;
 
-    #[allow(dead_code)]
     fn create_test_result(content: &str, score: f32) -> RagSearchResult {
         RagSearchResult {
             id: "test".to_string(),
@@ -418,7 +534,9 @@ mod tests {
                 modified: chrono::Utc::now(),
                 tags: vec![],
                 language: Some("rust".to_string()),
+                line_range: None,
             },
+            score_breakdown: None,
         }
     }
 
@@ -434,6 +552,30 @@ mod tests {
         assert!(score > 0.5);
     }
 
+    #[test]
+    fn test_rerank_scores_reorder_results() {
+        let results: Vec<VerifiedResult> = ["intro", "exact answer", "related", "unrelated"]
+            .iter()
+            .map(|content| VerifiedResult {
+                result: create_test_result(content, 0.8),
+                confidence_score: 0.8,
+                relevance_explanation: None,
+                extracted_context: None,
+                verification_method: VerificationMethod::Statistical,
+            })
+            .collect();
+
+        // Thinking or prose around the array is tolerated; a wrong count is rejected
+        let response = "Here are the scores:\n[3, 9.5, 3, 0]";
+        assert!(parse_rerank_scores(response, 3).is_none());
+        let scores = parse_rerank_scores(response, 4).unwrap();
+
+        let reranked = apply_rerank_scores(results, &scores, 2);
+        let contents: Vec<&str> = reranked.iter().map(|r| r.result.content.as_str()).collect();
+        assert_eq!(contents, vec!["exact answer", "intro"]);
+        assert!(parse_rerank_scores("no scores", 4).is_none());
+    }
+
     #[tokio::test]
     async fn test_code_detection() {
         let verifier = ResultVerifier::new(None, SmartSearchConfig::default());
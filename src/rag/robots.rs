@@ -0,0 +1,336 @@
+//! robots.txt handling for URL indexing
+//!
+//! Crawls fetch each site's robots.txt once and skip the pages it disallows for our user
+//! agent (or for every agent), following the most specific matching rule. A `Crawl-delay` spaces out
+//! requests to that site. `index --ignore-robots` turns both off for sites you own.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use url::Url;
+
+/// Product token of a user agent (`manx` in `manx/0.5.0 (+https://...)`), lowercased as
+/// robots.txt `User-agent` lines are matched
+pub fn agent_token(user_agent: &str) -> String {
+    user_agent
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// The rules a robots.txt sets for one user agent
+#[derive(Debug, Default, PartialEq)]
+pub struct RobotsRules {
+    /// `Allow` (true) and `Disallow` (false) path patterns
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Rules of the group naming `agent` (a product token), or of the `*` group when none does
+    pub fn parse(body: &str, agent: &str) -> Self {
+        let mut specific = Self::default();
+        let mut wildcard = Self::default();
+        let mut found_specific = false;
+        // Agents of the group being read, and whether its rules have started
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_ascii_lowercase());
+                continue;
+            }
+            in_rules = true;
+
+            let names_us = agents.iter().any(|named| agent_token(named) == agent);
+            let target = if names_us {
+                found_specific = true;
+                &mut specific
+            } else if agents.iter().any(|agent| agent == "*") {
+                &mut wildcard
+            } else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" if !value.is_empty() => target.rules.push((true, value.to_string())),
+                "disallow" if !value.is_empty() => target.rules.push((false, value.to_string())),
+                "crawl-delay" => {
+                    target.crawl_delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                        .map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+
+        if found_specific {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    /// Whether `url` may be fetched: the longest matching rule decides, `Allow` winning ties
+    pub fn allows(&self, url: &Url) -> bool {
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.rules {
+            if !pattern_matches(pattern, &path) {
+                continue;
+            }
+            let candidate = (pattern.len(), *allow);
+            if best.is_none_or(|current| candidate > current) {
+                best = Some(candidate);
+            }
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Match a robots.txt path pattern, where `*` matches anything and a trailing `$` anchors
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or("")) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return !anchored || rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
+
+/// Request rate and concurrency for docrawl that keep to a site's crawl delay
+///
+/// docrawl cannot go slower than one request per second, so longer delays are
+/// approximated by that pace with a single worker.
+pub fn docrawl_pacing(
+    crawl_delay: Option<Duration>,
+    rate_per_sec: u32,
+    concurrency: usize,
+) -> (u32, usize) {
+    let Some(delay) = crawl_delay else {
+        return (rate_per_sec, concurrency);
+    };
+    let allowed = (1.0 / delay.as_secs_f64()).floor().max(1.0) as u32;
+    let rate = rate_per_sec.min(allowed);
+    if delay >= Duration::from_secs(1) {
+        (rate, 1)
+    } else {
+        (rate, concurrency)
+    }
+}
+
+struct SiteState {
+    rules: Arc<RobotsRules>,
+    /// Earliest time the next request to the site may start
+    next_request: Instant,
+}
+
+/// robots.txt rules per site for one index run, fetched on first use
+pub struct Robots {
+    ignore: bool,
+    /// Product token of this run's user agent
+    agent: String,
+    sites: Mutex<HashMap<String, SiteState>>,
+}
+
+impl Robots {
+    /// With `ignore`, every URL is allowed and no robots.txt is fetched
+    pub fn new(ignore: bool) -> Self {
+        Self {
+            ignore,
+            agent: agent_token(&crate::http_client::user_agent()),
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `url` may be fetched; disallowed URLs are logged at debug level
+    pub async fn allows(&self, url: &str) -> bool {
+        if self.ignore {
+            return true;
+        }
+        let Ok(parsed) = Url::parse(url) else {
+            return true;
+        };
+        let allowed = self.rules_for(&parsed).await.allows(&parsed);
+        if !allowed {
+            log::debug!("Skipping {} (disallowed by robots.txt)", url);
+        }
+        allowed
+    }
+
+    /// The site's `Crawl-delay`, if it sets one
+    pub async fn crawl_delay(&self, url: &Url) -> Option<Duration> {
+        if self.ignore {
+            return None;
+        }
+        self.rules_for(url).await.crawl_delay()
+    }
+
+    /// Wait until the site's crawl delay allows another request to `url`
+    pub async fn wait_turn(&self, url: &str) {
+        if self.ignore {
+            return;
+        }
+        let Ok(parsed) = Url::parse(url) else {
+            return;
+        };
+        self.rules_for(&parsed).await;
+        let start = {
+            let mut sites = self.sites.lock().await;
+            let Some(site) = sites.get_mut(&origin_key(&parsed)) else {
+                return;
+            };
+            let Some(delay) = site.rules.crawl_delay() else {
+                return;
+            };
+            let start = site.next_request.max(Instant::now());
+            site.next_request = start + delay;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    async fn rules_for(&self, url: &Url) -> Arc<RobotsRules> {
+        let key = origin_key(url);
+        // Held while fetching so concurrent crawl tasks read robots.txt only once
+        let mut sites = self.sites.lock().await;
+        if let Some(site) = sites.get(&key) {
+            return site.rules.clone();
+        }
+        let rules = Arc::new(fetch_rules(url, &self.agent).await);
+        sites.insert(
+            key,
+            SiteState {
+                rules: rules.clone(),
+                next_request: Instant::now(),
+            },
+        );
+        rules
+    }
+}
+
+fn origin_key(url: &Url) -> String {
+    url.origin().unicode_serialization()
+}
+
+/// A site's robots.txt rules; a missing or unreadable file allows everything
+async fn fetch_rules(url: &Url, agent: &str) -> RobotsRules {
+    let Ok(robots_url) = url.join("/robots.txt") else {
+        return RobotsRules::default();
+    };
+    let response = match crate::http_client::client(Duration::from_secs(10)) {
+        Ok(client) => client.get(robots_url.clone()).send().await,
+        Err(e) => {
+            log::debug!("Not reading {}: {}", robots_url, e);
+            return RobotsRules::default();
+        }
+    };
+    match response {
+        Ok(response) if response.status().is_success() => {
+            let rules = RobotsRules::parse(&response.text().await.unwrap_or_default(), agent);
+            log::debug!(
+                "Read {}: {} rules, crawl delay {:?}",
+                robots_url,
+                rules.rules.len(),
+                rules.crawl_delay
+            );
+            rules
+        }
+        Ok(response) => {
+            log::debug!("No robots.txt at {} ({})", robots_url, response.status());
+            RobotsRules::default()
+        }
+        Err(e) => {
+            log::debug!("Failed to fetch {}: {}", robots_url, e);
+            RobotsRules::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allows(rules: &RobotsRules, path: &str) -> bool {
+        rules.allows(&Url::parse(&format!("https://docs.example.com{}", path)).unwrap())
+    }
+
+    #[test]
+    fn test_robots_rules() {
+        let rules = RobotsRules::parse(
+            "User-agent: *\n\
+             Disallow: /\n\
+             \n\
+             User-agent: Googlebot\n\
+             User-agent: Manx/0.5\n\
+             Disallow: /private/\n\
+             Allow: /private/public-*.html$\n\
+             Disallow: /*?draft=  # previews\n\
+             Crawl-delay: 2\n",
+            &agent_token("manx/0.5.0 (+https://github.com/neur0map/manx)"),
+        );
+        assert!(allows(&rules, "/guide/intro"));
+        assert!(!allows(&rules, "/private/notes"));
+        assert!(allows(&rules, "/private/public-faq.html"));
+        assert!(!allows(&rules, "/private/public-faq.html.bak"));
+        assert!(!allows(&rules, "/guide?draft=1"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(2)));
+
+        // Without a group naming manx, the `*` group applies
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /search\nDisallow:\n", "manx");
+        assert!(!allows(&rules, "/search/results"));
+        assert!(allows(&rules, "/docs"));
+        assert!(allows(&RobotsRules::parse("", "manx"), "/anything"));
+    }
+
+    #[test]
+    fn test_docrawl_pacing() {
+        assert_eq!(docrawl_pacing(None, 20, 8), (20, 8));
+        assert_eq!(
+            docrawl_pacing(Some(Duration::from_millis(250)), 20, 8),
+            (4, 8)
+        );
+        assert_eq!(docrawl_pacing(Some(Duration::from_secs(5)), 20, 8), (1, 1));
+    }
+}
@@ -5,21 +5,83 @@
 
 use anyhow::Result;
 use std::collections::HashSet;
-use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 
+use crate::explain::ScoreBreakdown;
 use crate::rag::{
+    bm25::Bm25Index,
     embeddings::EmbeddingModel,
     indexer::Indexer,
     llm::LlmClient,
     query_enhancer::{EnhancedQuery, QueryEnhancer, SearchStrategy},
     result_verifier::{ResultVerifier, VerifiedResult},
-    EmbeddingProvider, RagConfig, RagSearchResult,
+    store::{IndexMeta, VectorStore},
+    EmbeddingProvider, RagConfig, RagSearchResult, SearchFilters, StoredChunk,
 };
 
 #[cfg(test)]
 use crate::rag::SmartSearchConfig;
 
+/// Word trigrams of `text` after lowercasing and dropping punctuation
+///
+/// Text under three words yields its single words, so short chunks still compare.
+fn shingles(text: &str) -> HashSet<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < 3 {
+        return words.into_iter().collect();
+    }
+    words.windows(3).map(|window| window.join(" ")).collect()
+}
+
+/// Jaccard similarity of two shingle sets
+fn shingle_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f32 / (a.len() + b.len() - shared) as f32
+}
+
+/// Remove results whose content is near-identical to a higher-ranked one
+///
+/// `results` must already be sorted best first. Similarity is measured on normalized
+/// word trigrams, so reformatting and punctuation changes don't hide a copy.
+fn remove_near_duplicates(results: Vec<VerifiedResult>, threshold: f32) -> Vec<VerifiedResult> {
+    if threshold > 1.0 {
+        return results;
+    }
+
+    let original_count = results.len();
+    let mut kept: Vec<(HashSet<String>, VerifiedResult)> = Vec::with_capacity(results.len());
+    for verified in results {
+        let candidate = shingles(&verified.result.content);
+        let duplicate_of = kept
+            .iter()
+            .find(|(existing, _)| shingle_similarity(existing, &candidate) >= threshold);
+        match duplicate_of {
+            Some((_, original)) => log::debug!(
+                "Dropping near-duplicate chunk {} (same text as {})",
+                verified.result.source_path.display(),
+                original.result.source_path.display()
+            ),
+            None => kept.push((candidate, verified)),
+        }
+    }
+
+    if kept.len() < original_count {
+        log::debug!(
+            "Removed {} near-duplicate results",
+            original_count - kept.len()
+        );
+    }
+    kept.into_iter().map(|(_, verified)| verified).collect()
+}
+
 /// Smart search engine that orchestrates intelligent search strategies
 pub struct SmartSearchEngine {
     config: RagConfig,
@@ -28,11 +90,23 @@ pub struct SmartSearchEngine {
     embedding_model: Option<Arc<EmbeddingModel>>,
     #[allow(dead_code)] // Used in public API methods
     llm_client: Option<Arc<LlmClient>>,
+    /// Every stored chunk, when the caller already read them
+    index: Option<Arc<Vec<StoredChunk>>>,
+    /// Stored chunks passing the filters, loaded once per engine and shared by every search stage
+    stored_chunks: OnceLock<Arc<Vec<StoredChunk>>>,
+    /// Keyword statistics of the stored chunks, built on the first hybrid search
+    keyword_index: OnceLock<Bm25Index>,
+    /// Metadata filters; chunks that fail them are never ranked
+    filters: SearchFilters,
 }
 
 impl SmartSearchEngine {
-    /// Create a new smart search engine
-    pub async fn new(config: RagConfig, llm_client: Option<LlmClient>) -> Result<Self> {
+    /// Create a smart search engine around an already-loaded (shared) embedding model
+    pub fn with_embedding_model(
+        config: RagConfig,
+        llm_client: Option<LlmClient>,
+        embedding_model: Option<Arc<EmbeddingModel>>,
+    ) -> Result<Self> {
         log::info!(
             "Initializing smart search engine with config: {:?}",
             config.smart_search
@@ -41,9 +115,6 @@ impl SmartSearchEngine {
         // Wrap LLM client in Arc for sharing
         let llm_client_arc = llm_client.map(Arc::new);
 
-        // Initialize embedding model based on smart search preferences
-        let embedding_model = Self::initialize_embedding_model(&config).await?;
-
         // Create query enhancer
         let query_enhancer =
             QueryEnhancer::new(llm_client_arc.clone(), config.smart_search.clone());
@@ -58,11 +129,32 @@ impl SmartSearchEngine {
             result_verifier,
             embedding_model,
             llm_client: llm_client_arc,
+            index: None,
+            stored_chunks: OnceLock::new(),
+            keyword_index: OnceLock::new(),
+            filters: SearchFilters::default(),
         })
     }
 
+    /// Search these chunks instead of reading the vector store
+    pub fn with_stored_chunks(mut self, chunks: Arc<Vec<StoredChunk>>) -> Self {
+        self.index = Some(chunks);
+        self
+    }
+
+    /// Only search chunks whose metadata passes `filters`
+    ///
+    /// Filtering happens before candidates are cut down, so chunks in a rare language or
+    /// with a rare tag are found however many other chunks rank above them.
+    pub fn with_filters(mut self, filters: SearchFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
     /// Initialize the best available embedding model (wrapped in Arc for sharing)
-    async fn initialize_embedding_model(config: &RagConfig) -> Result<Option<Arc<EmbeddingModel>>> {
+    pub async fn initialize_embedding_model(
+        config: &RagConfig,
+    ) -> Result<Option<Arc<EmbeddingModel>>> {
         if !config.smart_search.prefer_semantic {
             log::info!("Semantic embeddings disabled by config");
             return Ok(None);
@@ -112,6 +204,16 @@ impl SmartSearchEngine {
     ) -> Result<Vec<VerifiedResult>> {
         log::info!("Starting smart search for: '{}'", query);
 
+        if let Some(ref model) = self.embedding_model {
+            if !self.config.hide_hash_notice {
+                model.notify_if_hash();
+            }
+        }
+
+        if let Some(ref model) = self.embedding_model {
+            self.ensure_index_dimension(model, query).await?;
+        }
+
         // Stage 1: Query Enhancement
         let enhanced_query = self.query_enhancer.enhance_query(query).await?;
         log::debug!(
@@ -120,10 +222,13 @@ impl SmartSearchEngine {
         );
 
         // Stage 2: Multi-strategy search execution
+        let limit = max_results.unwrap_or(self.config.max_results);
         let mut all_results = if self.config.smart_search.enable_multi_stage {
-            self.execute_multi_stage_search(&enhanced_query).await?
+            self.execute_multi_stage_search(&enhanced_query, limit)
+                .await?
         } else {
-            self.execute_single_stage_search(&enhanced_query).await?
+            self.execute_single_stage_search(&enhanced_query, limit)
+                .await?
         };
 
         log::debug!(
@@ -156,13 +261,17 @@ impl SmartSearchEngine {
     async fn execute_multi_stage_search(
         &self,
         query: &EnhancedQuery,
+        limit: usize,
     ) -> Result<Vec<RagSearchResult>> {
         let mut all_results = Vec::new();
 
         // Stage 1: Direct semantic search with original query
         if let Some(ref embedding_model) = self.embedding_model {
             log::debug!("Stage 1: Semantic search with original query");
-            match self.semantic_search(&query.original, embedding_model).await {
+            match self
+                .semantic_search(&query.original, embedding_model, limit)
+                .await
+            {
                 Ok(mut results) => {
                     log::debug!("Semantic search found {} results", results.len());
                     all_results.append(&mut results);
@@ -180,7 +289,7 @@ impl SmartSearchEngine {
             let mut variation_results = match variation.strategy {
                 SearchStrategy::Semantic => {
                     if let Some(ref embedding_model) = self.embedding_model {
-                        self.semantic_search(&variation.query, embedding_model)
+                        self.semantic_search(&variation.query, embedding_model, limit)
                             .await
                             .unwrap_or_default()
                     } else {
@@ -198,7 +307,7 @@ impl SmartSearchEngine {
                     let mut mixed_results = Vec::new();
                     if let Some(ref embedding_model) = self.embedding_model {
                         if let Ok(mut semantic_results) = self
-                            .semantic_search(&variation.query, embedding_model)
+                            .semantic_search(&variation.query, embedding_model, limit)
                             .await
                         {
                             mixed_results.append(&mut semantic_results);
@@ -214,7 +323,13 @@ impl SmartSearchEngine {
 
             // Apply variation weight to scores
             for result in &mut variation_results {
-                result.score *= variation.weight;
+                result.rescore(
+                    format!(
+                        "query variation '{}' x{:.2}",
+                        variation.query, variation.weight
+                    ),
+                    result.score * variation.weight,
+                );
             }
 
             all_results.append(&mut variation_results);
@@ -228,7 +343,7 @@ impl SmartSearchEngine {
             .unwrap_or_default();
         // Boost keyword results slightly since they're exact matches
         for result in &mut keyword_results {
-            result.score *= 1.1;
+            result.rescore("exact keyword match x1.10", result.score * 1.1);
         }
         all_results.append(&mut keyword_results);
 
@@ -239,65 +354,55 @@ impl SmartSearchEngine {
     async fn execute_single_stage_search(
         &self,
         query: &EnhancedQuery,
+        limit: usize,
     ) -> Result<Vec<RagSearchResult>> {
         if let Some(ref embedding_model) = self.embedding_model {
-            self.semantic_search(&query.original, embedding_model).await
+            self.semantic_search(&query.original, embedding_model, limit)
+                .await
         } else {
             self.keyword_search(&query.original).await
         }
     }
 
-    /// Perform semantic search using embeddings
+    /// Perform semantic search using embeddings, blended with BM25 when `keyword_weight` is set
+    ///
+    /// Keeps enough candidates for `limit` results (or the configured `max_results`, if larger).
     async fn semantic_search(
         &self,
         query: &str,
         embedding_model: &EmbeddingModel,
+        limit: usize,
     ) -> Result<Vec<RagSearchResult>> {
         log::debug!("Performing semantic search for: '{}'", query);
 
         // Generate query embedding
         let query_embedding = embedding_model.embed_text(query).await?;
 
-        // Search through stored embeddings
-        let indexer = Indexer::new(&self.config)?;
-        let index_path = indexer.get_index_path();
-        let embedding_dir = index_path.join("embeddings");
-
-        if !embedding_dir.exists() {
-            log::debug!("No embeddings directory found");
-            return Ok(vec![]);
-        }
-
-        let mut results = Vec::new();
-        let entries = std::fs::read_dir(embedding_dir)?;
-
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.ends_with(".json") {
-                    match self
-                        .load_and_score_embedding(&entry.path(), &query_embedding, embedding_model)
-                        .await
-                    {
-                        Ok(Some(result)) => {
-                            if result.score >= self.config.similarity_threshold {
-                                results.push(result);
-                            }
-                        }
-                        Ok(None) => continue,
-                        Err(e) => {
-                            log::warn!(
-                                "Failed to process embedding file {:?}: {}",
-                                entry.path(),
-                                e
-                            );
-                        }
-                    }
-                }
-            }
-        }
-
-        // Sort by similarity score
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        let chunks = self.stored_chunks()?;
+        let threshold = self.config.similarity_threshold;
+        let top_k = self
+            .config
+            .max_results
+            .max(limit)
+            .max(1)
+            .saturating_mul(SEMANTIC_CANDIDATES_PER_RESULT);
+        let keyword_weight = self.config.smart_search.keyword_weight.clamp(0.0, 1.0);
+        let results = if keyword_weight > 0.0 {
+            let keyword_scores = self
+                .keyword_index
+                .get_or_init(|| Bm25Index::new(chunks.iter().map(|c| c.content.as_str())))
+                .scores(query);
+            rank_hybrid(
+                chunks,
+                &query_embedding,
+                &keyword_scores,
+                keyword_weight,
+                threshold,
+                top_k,
+            )
+        } else {
+            rank_by_similarity(chunks, &query_embedding, threshold, top_k)
+        };
 
         log::debug!("Semantic search found {} results", results.len());
         Ok(results)
@@ -307,14 +412,6 @@ impl SmartSearchEngine {
     async fn keyword_search(&self, query: &str) -> Result<Vec<RagSearchResult>> {
         log::debug!("Performing keyword search for: '{}'", query);
 
-        let indexer = Indexer::new(&self.config)?;
-        let index_path = indexer.get_index_path();
-        let embedding_dir = index_path.join("embeddings");
-
-        if !embedding_dir.exists() {
-            return Ok(vec![]);
-        }
-
         let query_words: Vec<String> = query
             .to_lowercase()
             .split_whitespace()
@@ -323,40 +420,21 @@ impl SmartSearchEngine {
             .collect();
 
         let mut results = Vec::new();
-        let entries = std::fs::read_dir(embedding_dir)?;
-
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.ends_with(".json") {
-                    if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                        if let Ok(stored_chunk) =
-                            serde_json::from_str::<crate::rag::StoredChunk>(&content)
-                        {
-                            let content_lower = stored_chunk.content.to_lowercase();
-
-                            let matches = query_words
-                                .iter()
-                                .filter(|word| content_lower.contains(*word))
-                                .count();
-
-                            if matches > 0 {
-                                let score = matches as f32 / query_words.len() as f32;
-
-                                results.push(RagSearchResult {
-                                    id: stored_chunk.id,
-                                    content: stored_chunk.content,
-                                    source_path: stored_chunk.source_path,
-                                    source_type: stored_chunk.source_type,
-                                    title: stored_chunk.title,
-                                    section: stored_chunk.section,
-                                    score,
-                                    chunk_index: stored_chunk.chunk_index,
-                                    metadata: stored_chunk.metadata,
-                                });
-                            }
-                        }
-                    }
-                }
+        for stored_chunk in self.stored_chunks()? {
+            let content_lower = stored_chunk.content.to_lowercase();
+
+            let matches = query_words
+                .iter()
+                .filter(|word| content_lower.contains(*word))
+                .count();
+
+            if matches > 0 {
+                let score = matches as f32 / query_words.len() as f32;
+                results.push(result_from_chunk(
+                    stored_chunk,
+                    score,
+                    ScoreBreakdown::new("word match", score),
+                ));
             }
         }
 
@@ -377,7 +455,7 @@ impl SmartSearchEngine {
         // Boost results that appear to be from code files
         for result in &mut results {
             if self.is_code_file(&result.source_path) {
-                result.score *= 1.3;
+                result.rescore("code file x1.30", result.score * 1.3);
             }
         }
 
@@ -398,30 +476,84 @@ impl SmartSearchEngine {
         }
     }
 
-    /// Load and score a single embedding file (adapted from mod.rs)
-    async fn load_and_score_embedding(
+    /// Refuse to rank the index with a model whose vectors it was not built with
+    ///
+    /// The model's dimension is read off the query embedding, which the semantic stage
+    /// then gets back from the model's cache, so providers aren't probed separately.
+    /// Indexes built before `index_meta.json` existed get one written on the first match.
+    async fn ensure_index_dimension(
         &self,
-        file_path: &PathBuf,
-        query_embedding: &[f32],
-        _embedding_model: &EmbeddingModel,
-    ) -> Result<Option<RagSearchResult>> {
-        let content = std::fs::read_to_string(file_path)?;
-        let chunk_data: crate::rag::StoredChunk = serde_json::from_str(&content)?;
-
-        // Calculate similarity score
-        let score = EmbeddingModel::cosine_similarity(query_embedding, &chunk_data.embedding);
-
-        Ok(Some(RagSearchResult {
-            id: chunk_data.id,
-            content: chunk_data.content,
-            source_path: chunk_data.source_path,
-            source_type: chunk_data.source_type,
-            title: chunk_data.title,
-            section: chunk_data.section,
-            score,
-            chunk_index: chunk_data.chunk_index,
-            metadata: chunk_data.metadata,
-        }))
+        embedding_model: &EmbeddingModel,
+        query: &str,
+    ) -> Result<()> {
+        let indexer = Indexer::new(&self.config)?;
+        let store = VectorStore::open(indexer.get_index_path())?;
+        let (index_dimension, recorded) = match store.meta()? {
+            Some(meta) => (Some(meta.dimension), true),
+            None => (
+                self.stored_chunks()?
+                    .iter()
+                    .map(|chunk| chunk.embedding.len())
+                    .find(|len| *len > 0),
+                false,
+            ),
+        };
+        let Some(index_dimension) = index_dimension else {
+            return Ok(());
+        };
+
+        // An unreachable provider still fails the semantic stage on its own; only give up
+        // here when the dimension can't be learned at all
+        let model_dimension = match embedding_model.embed_text(query).await {
+            Ok(embedding) => embedding.len(),
+            Err(e) => embedding_model.get_dimension().await.map_err(|_| e)?,
+        };
+        check_dimension(Some(index_dimension), model_dimension)?;
+
+        if !recorded {
+            let meta = IndexMeta {
+                dimension: index_dimension,
+                provider: embedding_model.get_config().provider.clone(),
+            };
+            if let Err(e) = store.write_meta(&meta) {
+                log::debug!("Could not record index dimension: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Chunks from the vector store that pass the filters, read on first use
+    fn stored_chunks(&self) -> Result<&[StoredChunk]> {
+        if let Some(chunks) = self.stored_chunks.get() {
+            return Ok(chunks);
+        }
+
+        let index = match &self.index {
+            Some(index) => Arc::clone(index),
+            None => {
+                let indexer = Indexer::new(&self.config)?;
+                let contents = VectorStore::open(indexer.get_index_path())?.load()?;
+                Arc::new(
+                    contents
+                        .chunks
+                        .into_iter()
+                        .map(|(_, chunk)| chunk)
+                        .collect(),
+                )
+            }
+        };
+        let chunks = if self.filters.is_empty() {
+            index
+        } else {
+            Arc::new(
+                index
+                    .iter()
+                    .filter(|chunk| self.filters.matches(&chunk.metadata))
+                    .cloned()
+                    .collect(),
+            )
+        };
+        Ok(self.stored_chunks.get_or_init(|| chunks))
     }
 
     /// Remove duplicate results based on content similarity
@@ -461,6 +593,10 @@ impl SmartSearchEngine {
         // Sort by confidence score (already done in verifier, but ensuring)
         results.sort_by(|a, b| b.confidence_score.partial_cmp(&a.confidence_score).unwrap());
 
+        // Drop copies of the same text indexed from different files, keeping the best-scoring one
+        let mut results =
+            remove_near_duplicates(results, self.config.smart_search.near_duplicate_threshold);
+
         // Apply limit
         let limit = max_results.unwrap_or(self.config.max_results);
         if results.len() > limit {
@@ -470,6 +606,20 @@ impl SmartSearchEngine {
         results
     }
 
+    /// Reorder ranked results with the LLM for `--rerank`, keeping the best `limit`
+    ///
+    /// Results keep their order when reranking is off or no LLM is available.
+    pub async fn rerank(
+        &self,
+        query: &str,
+        results: Vec<VerifiedResult>,
+        limit: usize,
+    ) -> Vec<VerifiedResult> {
+        self.result_verifier
+            .rerank_results(query, results, limit)
+            .await
+    }
+
     /// Check if the search engine is ready to perform intelligent search
     /// This is a public API method for external consumers
     #[allow(dead_code)] // Public API method - may be used by external code
@@ -503,10 +653,109 @@ pub struct SearchCapabilities {
     pub multi_stage_enabled: bool,
 }
 
+/// Semantic candidates kept per requested result, leaving room for verification and dedup
+const SEMANTIC_CANDIDATES_PER_RESULT: usize = 3;
+
+/// Error when the index's vectors and the query model's vectors have different sizes
+pub(crate) fn check_dimension(
+    index_dimension: Option<usize>,
+    model_dimension: usize,
+) -> Result<()> {
+    match index_dimension {
+        Some(index_dimension) if index_dimension != model_dimension => Err(anyhow::anyhow!(
+            "Index was built with {}-dim vectors, current model produces {}-dim; run `manx rag reindex`",
+            index_dimension,
+            model_dimension
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Brute-force cosine ranking of stored chunks against a query embedding
+///
+/// Chunks scoring below `threshold` are dropped; the best `top_k` are returned,
+/// highest score first. Only the returned chunks are cloned into results.
+pub fn rank_by_similarity(
+    chunks: &[StoredChunk],
+    query_embedding: &[f32],
+    threshold: f32,
+    top_k: usize,
+) -> Vec<RagSearchResult> {
+    rank_hybrid(chunks, query_embedding, &[], 0.0, threshold, top_k)
+}
+
+/// Rank stored chunks by cosine similarity blended with per-chunk keyword scores
+///
+/// Each score is `(1 - keyword_weight) * cosine + keyword_weight * keyword`, with missing
+/// keyword scores counting as 0. Chunks need at least `(1 - keyword_weight) * threshold`,
+/// so those without keyword hits face the usual threshold while exact term matches can
+/// get in below it.
+pub fn rank_hybrid(
+    chunks: &[StoredChunk],
+    query_embedding: &[f32],
+    keyword_scores: &[f32],
+    keyword_weight: f32,
+    threshold: f32,
+    top_k: usize,
+) -> Vec<RagSearchResult> {
+    let semantic_weight = 1.0 - keyword_weight;
+    let mut scored: Vec<(usize, f32, f32, f32)> = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let cosine = EmbeddingModel::cosine_similarity(query_embedding, &chunk.embedding);
+            let keyword = keyword_scores.get(i).copied().unwrap_or(0.0);
+            let score = semantic_weight * cosine + keyword_weight * keyword;
+            (i, score, cosine, keyword)
+        })
+        .filter(|(_, score, _, _)| *score >= semantic_weight * threshold)
+        .collect();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(top_k);
+
+    scored
+        .into_iter()
+        .map(|(i, score, cosine, keyword)| {
+            let mut breakdown = ScoreBreakdown::new("embedding", cosine);
+            if keyword_weight > 0.0 {
+                breakdown.keyword_score = Some(keyword);
+                breakdown.keyword_weight = keyword_weight;
+                breakdown.step(
+                    format!("hybrid blend, {:.0}% keyword", keyword_weight * 100.0),
+                    score,
+                );
+            }
+            result_from_chunk(&chunks[i], score, breakdown)
+        })
+        .collect()
+}
+
+/// Build a search result from a stored chunk
+fn result_from_chunk(
+    chunk: &StoredChunk,
+    score: f32,
+    breakdown: ScoreBreakdown,
+) -> RagSearchResult {
+    RagSearchResult {
+        id: chunk.id.clone(),
+        content: chunk.content.clone(),
+        source_path: chunk.source_path.clone(),
+        source_type: chunk.source_type.clone(),
+        title: chunk.title.clone(),
+        section: chunk.section.clone(),
+        score,
+        chunk_index: chunk.chunk_index,
+        metadata: chunk.metadata.clone(),
+        score_breakdown: Some(breakdown),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::rag::{CodeSecurityLevel, EmbeddingConfig, EmbeddingProvider};
+    use std::path::PathBuf;
 
     fn create_test_config() -> RagConfig {
         RagConfig {
@@ -525,17 +774,30 @@ mod tests {
                 model_path: None,
                 api_key: None,
                 endpoint: None,
+                model: None,
                 timeout_seconds: 30,
                 batch_size: 32,
+                max_in_flight: 4,
+                requests_per_minute: None,
+                tokens_per_minute: None,
             },
             smart_search: SmartSearchConfig::default(),
+            hide_hash_notice: false,
+            web_fallback_threshold: 0.5,
+            chunk_size: 500,
+            chunk_overlap: 50,
+            include_notebook_outputs: false,
+            ignore_robots: false,
         }
     }
 
     #[tokio::test]
     async fn test_search_engine_initialization() {
         let config = create_test_config();
-        let engine = SmartSearchEngine::new(config, None).await;
+        let embedding_model = SmartSearchEngine::initialize_embedding_model(&config)
+            .await
+            .unwrap();
+        let engine = SmartSearchEngine::with_embedding_model(config, None, embedding_model);
         assert!(engine.is_ok());
     }
 
@@ -547,4 +809,227 @@ mod tests {
         // This would be tested with the actual engine instance
         assert!(path.extension().and_then(|ext| ext.to_str()) == Some("rs"));
     }
+
+    fn stored_chunk(id: &str, embedding: Vec<f32>) -> StoredChunk {
+        StoredChunk {
+            id: id.to_string(),
+            content: format!("chunk {}", id),
+            source_path: PathBuf::from(format!("/docs/{}.md", id)),
+            source_type: crate::rag::SourceType::Local,
+            title: None,
+            section: None,
+            chunk_index: 0,
+            metadata: crate::rag::DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 0,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+            embedding,
+        }
+    }
+
+    #[test]
+    fn test_check_dimension() {
+        assert!(check_dimension(None, 768).is_ok());
+        assert!(check_dimension(Some(384), 384).is_ok());
+        let err = check_dimension(Some(384), 768).unwrap_err().to_string();
+        assert!(err.contains("384-dim") && err.contains("768-dim"));
+        assert!(err.contains("manx rag reindex"));
+    }
+
+    #[tokio::test]
+    async fn test_index_dimension_is_recorded_on_first_search() {
+        let dir = std::env::temp_dir().join(format!("manx_dimension_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = VectorStore::open(&dir).unwrap();
+        let mut writer = store.writer().unwrap();
+        writer.put("a", stored_chunk("a", vec![1.0; 384])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let config = RagConfig {
+            index_path: dir.clone(),
+            ..create_test_config()
+        };
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        let engine =
+            SmartSearchEngine::with_embedding_model(config.clone(), None, Some(model.clone()))
+                .unwrap();
+        assert!(store.meta().unwrap().is_none());
+        engine.search("chunk", Some(3)).await.unwrap();
+        assert_eq!(store.meta().unwrap().unwrap().dimension, 384);
+
+        // A recorded dimension that doesn't match the model is refused
+        store
+            .write_meta(&IndexMeta {
+                dimension: 768,
+                provider: EmbeddingProvider::Hash,
+            })
+            .unwrap();
+        let engine = SmartSearchEngine::with_embedding_model(config, None, Some(model)).unwrap();
+        let err = engine.search("chunk", Some(3)).await.unwrap_err();
+        assert!(err.to_string().contains("768-dim"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Index of ten chunks matching "connection pool" exactly and one Rust chunk ranked last
+    async fn candidate_window_index() -> (PathBuf, RagConfig, Arc<EmbeddingModel>) {
+        let dir = std::env::temp_dir().join(format!("manx_window_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = Arc::new(EmbeddingModel::new().await.unwrap());
+        let query = model.embed_text("connection pool").await.unwrap();
+
+        let store = VectorStore::open(&dir).unwrap();
+        let mut writer = store.writer().unwrap();
+        for i in 0..10 {
+            let id = format!("popular-{}", i);
+            let mut chunk = stored_chunk(&id, query.clone());
+            chunk.content = format!("topic{} connection pool guide{}", i, i);
+            writer.put(&id, chunk).unwrap();
+        }
+        let mut rare = stored_chunk("rare", query.clone());
+        rare.content = "tokio connection pool example".to_string();
+        rare.embedding.iter_mut().skip(192).for_each(|v| *v = 0.0);
+        rare.metadata.language = Some("rust".to_string());
+        writer.put("rare", rare).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let mut config = RagConfig {
+            index_path: dir.clone(),
+            max_results: 1,
+            similarity_threshold: 0.1,
+            ..create_test_config()
+        };
+        config.smart_search.enable_multi_stage = false;
+        (dir, config, model)
+    }
+
+    #[tokio::test]
+    async fn test_filters_apply_before_the_candidate_window() {
+        let (dir, config, model) = candidate_window_index().await;
+
+        let engine = SmartSearchEngine::with_embedding_model(config, None, Some(model))
+            .unwrap()
+            .with_filters(SearchFilters {
+                languages: vec!["rust".to_string()],
+                tags: vec![],
+            });
+        let results = engine.search("connection pool", Some(1)).await.unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.result.id.as_str()).collect();
+        assert_eq!(ids, vec!["rare"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requested_limit_widens_the_candidate_window() {
+        let (dir, config, model) = candidate_window_index().await;
+
+        // A second page of four (offset 4) asks for eight results, past max_results * 3
+        let engine = SmartSearchEngine::with_embedding_model(config, None, Some(model)).unwrap();
+        let results = engine.search("connection pool", Some(8)).await.unwrap();
+        assert_eq!(results.len(), 8);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rank_by_similarity_orders_and_filters() {
+        let chunks = vec![
+            stored_chunk("orthogonal", vec![0.0, 1.0, 0.0]),
+            stored_chunk("close", vec![0.9, 0.1, 0.0]),
+            stored_chunk("exact", vec![1.0, 0.0, 0.0]),
+            stored_chunk("opposite", vec![-1.0, 0.0, 0.0]),
+            stored_chunk("near", vec![0.7, 0.7, 0.0]),
+        ];
+        let query = [1.0, 0.0, 0.0];
+
+        let ranked = rank_by_similarity(&chunks, &query, 0.5, 10);
+        let ids: Vec<_> = ranked.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["exact", "close", "near"]);
+        assert!(ranked.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let top_two = rank_by_similarity(&chunks, &query, -1.0, 2);
+        let ids: Vec<_> = top_two.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["exact", "close"]);
+    }
+
+    #[test]
+    fn test_rank_hybrid_lifts_exact_keyword_matches() {
+        let mut identifier = stored_chunk("identifier", vec![0.3, 0.95, 0.0]);
+        identifier.content = "fn parse_args() -> Cli".to_string();
+        let chunks = vec![
+            stored_chunk("similar", vec![0.8, 0.6, 0.0]),
+            identifier,
+            stored_chunk("unrelated", vec![0.0, 0.0, 1.0]),
+        ];
+        let query = [1.0, 0.0, 0.0];
+        let keywords =
+            Bm25Index::new(chunks.iter().map(|c| c.content.as_str())).scores("parse_args");
+
+        let semantic = rank_by_similarity(&chunks, &query, 0.6, 10);
+        let ids: Vec<_> = semantic.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["similar"]);
+
+        let hybrid = rank_hybrid(&chunks, &query, &keywords, 0.5, 0.6, 10);
+        let ids: Vec<_> = hybrid.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["identifier", "similar"]);
+
+        // The breakdown keeps both parts of the blended score
+        let breakdown = hybrid[0].score_breakdown.as_ref().unwrap();
+        assert_eq!(breakdown.keyword_score, Some(1.0));
+        assert_eq!(breakdown.keyword_weight, 0.5);
+        assert!((0.5 * breakdown.similarity + 0.5 - hybrid[0].score).abs() < 1e-6);
+        assert_eq!(breakdown.steps.last().unwrap().score, hybrid[0].score);
+        assert_eq!(
+            semantic[0].score_breakdown.as_ref().unwrap().keyword_score,
+            None
+        );
+    }
+
+    #[test]
+    fn test_remove_near_duplicates_keeps_best_copy() {
+        let verified = |id: &str, content: &str, score: f32| {
+            let mut chunk = stored_chunk(id, vec![]);
+            chunk.content = content.to_string();
+            VerifiedResult {
+                result: result_from_chunk(&chunk, score, ScoreBreakdown::default()),
+                confidence_score: score,
+                relevance_explanation: None,
+                extracted_context: None,
+                verification_method: crate::rag::result_verifier::VerificationMethod::Statistical,
+            }
+        };
+        let paragraph = "Run `manx index ./docs` to add your notes, then search them with --rag.";
+        let results = vec![
+            verified("readme", paragraph, 0.9),
+            verified(
+                "site",
+                "Run manx index ./docs to add your notes; then search them with --rag",
+                0.8,
+            ),
+            verified(
+                "other",
+                "Configure the embedding provider with manx config.",
+                0.7,
+            ),
+        ];
+
+        let ids = |results: &[VerifiedResult]| -> Vec<String> {
+            results.iter().map(|r| r.result.id.clone()).collect()
+        };
+        assert_eq!(
+            ids(&remove_near_duplicates(results.clone(), 0.9)),
+            ["readme", "other"]
+        );
+        assert_eq!(
+            ids(&remove_near_duplicates(results, 1.1)),
+            ["readme", "site", "other"]
+        );
+    }
 }
@@ -0,0 +1,910 @@
+//! Binary vector store: every indexed chunk in a single append-only file
+//!
+//! Chunks used to be stored as one JSON file each under `embeddings/`, which made
+//! search and stats open and parse tens of thousands of files. They now live in
+//! `vectors.bin`, a log of length-prefixed records that is read with one sequential
+//! read. Removing chunks appends delete records; the log is compacted when dead
+//! records outnumber live ones.
+//!
+//! Record layout (all integers little-endian):
+//! - `put`: `1u8`, `u32` payload length, then key (`u32` length + UTF-8), chunk
+//!   metadata (`u32` length + JSON of [`StoredChunk`] without its embedding) and
+//!   the embedding (`u32` dimension + `f32` values)
+//! - `delete`: `2u8`, `u32` payload length, then the key as UTF-8
+//!
+//! A legacy `embeddings/` directory is migrated into the log on first open, keeping
+//! the file stems as keys so manifest records stay valid.
+//!
+//! `index_meta.json` next to the log records the embedding provider and dimension the
+//! chunks were embedded with, so searches with a different model can be refused. It
+//! also lists legacy files that failed to migrate, so they aren't retried on every open.
+//!
+//! Appends and rewrites take an advisory lock on `vectors.lock`, so separate `manx`
+//! processes indexing into the same store never interleave records or lose appends
+//! to a compaction.
+
+use crate::rag::{EmbeddingProvider, StoredChunk};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const STORE_FILE: &str = "vectors.bin";
+const LOCK_FILE: &str = "vectors.lock";
+const LEGACY_DIR: &str = "embeddings";
+const META_FILE: &str = "index_meta.json";
+const MAGIC: &[u8; 8] = b"MANXVEC1";
+
+const KIND_PUT: u8 = 1;
+const KIND_DELETE: u8 = 2;
+
+/// Embedding model the stored vectors were produced by
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct IndexMeta {
+    pub dimension: usize,
+    pub provider: EmbeddingProvider,
+}
+
+/// Contents of `index_meta.json`
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct MetaFile {
+    /// Absent until something is embedded (or for indexes built before it was recorded)
+    #[serde(flatten)]
+    model: Option<IndexMeta>,
+    /// Legacy chunk file names that couldn't be migrated; left for `manx rag verify --repair`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unmigrated_legacy_files: Vec<String>,
+}
+
+/// Exclusive advisory lock on a store's lock file, released on drop
+struct StoreLock<'a>(&'a File);
+
+impl<'a> StoreLock<'a> {
+    fn acquire(file: &'a File) -> Result<Self> {
+        fs2::FileExt::lock_exclusive(file)?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for StoreLock<'_> {
+    fn drop(&mut self) {
+        let _ = fs2::FileExt::unlock(self.0);
+    }
+}
+
+/// Handle to the vector store of one index directory
+pub struct VectorStore {
+    path: PathBuf,
+}
+
+/// Snapshot of the live chunks in the store, in insertion order
+#[derive(Default)]
+pub struct StoreContents {
+    /// `(key, chunk)` pairs
+    pub chunks: Vec<(String, StoredChunk)>,
+    /// Number of superseded or deleted records still occupying the log
+    pub dead_records: usize,
+}
+
+/// Every readable chunk plus what couldn't be read, for `manx rag verify`
+#[derive(Default)]
+pub struct StoreCheck {
+    pub contents: StoreContents,
+    /// Records that don't decode (bad chunk JSON, bad lengths or an unknown record type)
+    pub corrupt_records: usize,
+    /// Bytes of a record torn off at the end of the log by a crash mid-append
+    pub truncated_bytes: usize,
+    /// Files left in a legacy `embeddings/` directory because they couldn't be migrated
+    pub legacy_leftovers: Vec<PathBuf>,
+}
+
+impl VectorStore {
+    /// Open the store for an index directory, migrating a legacy JSON index if present
+    pub fn open(index_path: &Path) -> Result<Self> {
+        let store = Self {
+            path: index_path.join(STORE_FILE),
+        };
+        let legacy_dir = index_path.join(LEGACY_DIR);
+        if legacy_dir.is_dir() {
+            store.migrate_json_dir(&legacy_dir)?;
+        }
+        Ok(store)
+    }
+
+    /// Path of the store file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the store file in bytes
+    pub fn size_bytes(&self) -> u64 {
+        std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0)
+    }
+
+    /// Length and modification time of the store file, which change with every write
+    pub fn version(&self) -> Option<(u64, std::time::SystemTime)> {
+        let metadata = std::fs::metadata(&self.path).ok()?;
+        Some((metadata.len(), metadata.modified().ok()?))
+    }
+
+    /// Embedding model recorded for this index (`None` for indexes built before it was recorded)
+    pub fn meta(&self) -> Result<Option<IndexMeta>> {
+        Ok(self.meta_file()?.model)
+    }
+
+    /// Record the embedding model the stored vectors were produced by
+    pub fn write_meta(&self, meta: &IndexMeta) -> Result<()> {
+        let mut file = self.meta_file().unwrap_or_default();
+        if file.model.as_ref() == Some(meta) {
+            return Ok(());
+        }
+        file.model = Some(meta.clone());
+        self.write_meta_file(&file)
+    }
+
+    fn meta_file(&self) -> Result<MetaFile> {
+        match std::fs::read_to_string(self.meta_path()) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MetaFile::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_meta_file(&self, file: &MetaFile) -> Result<()> {
+        std::fs::write(self.meta_path(), serde_json::to_string_pretty(file)?)?;
+        Ok(())
+    }
+
+    fn meta_path(&self) -> PathBuf {
+        self.path.with_file_name(META_FILE)
+    }
+
+    fn open_lock(&self) -> Result<File> {
+        Ok(OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_file_name(LOCK_FILE))?)
+    }
+
+    /// Open an appender for writing chunks
+    pub fn writer(&self) -> Result<StoreWriter> {
+        let lock = self.open_lock()?;
+        let file = {
+            let _lock = StoreLock::acquire(&lock)?;
+            open_for_append(&self.path)?
+        };
+        Ok(StoreWriter {
+            file,
+            path: self.path.clone(),
+            lock,
+        })
+    }
+
+    /// Load every live chunk with one sequential read
+    pub fn load(&self) -> Result<StoreContents> {
+        Ok(self.scan(false)?.contents)
+    }
+
+    /// Read the whole log, skipping records that don't decode instead of failing
+    ///
+    /// Corrupt records are framed by their length prefix, so the records after them
+    /// are still read. Leftover legacy chunk files are listed too.
+    pub fn check(&self) -> Result<StoreCheck> {
+        let mut check = self.scan(true)?;
+        let legacy_dir = self.path.with_file_name(LEGACY_DIR);
+        if legacy_dir.is_dir() {
+            check.legacy_leftovers = std::fs::read_dir(&legacy_dir)?
+                .flatten()
+                .map(|entry| entry.path())
+                .collect();
+        }
+        Ok(check)
+    }
+
+    /// Parse the log; with `tolerant`, count corrupt records rather than failing on them
+    fn scan(&self, tolerant: bool) -> Result<StoreCheck> {
+        let bytes = match std::fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(StoreCheck::default());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !bytes.starts_with(MAGIC) {
+            return Err(anyhow!(
+                "{:?} is not a manx vector store (bad header)",
+                self.path
+            ));
+        }
+
+        let mut slots: Vec<Option<(String, StoredChunk)>> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut dead_records = 0;
+        let mut check = StoreCheck::default();
+
+        let mut reader = Reader::new(&bytes[MAGIC.len()..]);
+        while !reader.is_empty() {
+            // A torn record at the tail (crash mid-append) ends the readable log
+            let remaining = reader.bytes.len();
+            let Some((kind, payload)) = reader.record() else {
+                log::warn!("Ignoring truncated record at the end of {:?}", self.path);
+                check.truncated_bytes = remaining;
+                break;
+            };
+            match kind {
+                KIND_PUT => {
+                    let Some((key, chunk)) = decode_put(payload) else {
+                        if tolerant {
+                            check.corrupt_records += 1;
+                            continue;
+                        }
+                        return Err(anyhow!("Corrupt chunk record in {:?}", self.path));
+                    };
+                    if let Some(old) = positions.insert(key.clone(), slots.len()) {
+                        slots[old] = None;
+                        dead_records += 1;
+                    }
+                    slots.push(Some((key, chunk)));
+                }
+                KIND_DELETE => {
+                    let key = String::from_utf8_lossy(payload);
+                    if let Some(old) = positions.remove(key.as_ref()) {
+                        slots[old] = None;
+                        dead_records += 1;
+                    }
+                    dead_records += 1;
+                }
+                _ if tolerant => check.corrupt_records += 1,
+                other => return Err(anyhow!("Unknown record type {} in {:?}", other, self.path)),
+            }
+        }
+
+        check.contents = StoreContents {
+            chunks: slots.into_iter().flatten().collect(),
+            dead_records,
+        };
+        Ok(check)
+    }
+
+    /// Remove chunks by key; returns how many delete records were written
+    pub fn remove(&self, keys: &[String]) -> Result<usize> {
+        if keys.is_empty() || !self.path.exists() {
+            return Ok(0);
+        }
+
+        let mut writer = self.writer()?;
+        for key in keys {
+            writer.write_record(KIND_DELETE, key.as_bytes())?;
+        }
+        writer.checkpoint()?;
+        drop(writer);
+
+        self.compact_if_needed()?;
+        Ok(keys.len())
+    }
+
+    /// Rewrite the log without dead records once they outnumber live chunks
+    pub fn compact_if_needed(&self) -> Result<()> {
+        // Held from read to rename so no append lands in the log being discarded
+        let lock = self.open_lock()?;
+        let _lock = StoreLock::acquire(&lock)?;
+        let contents = self.load()?;
+        if contents.dead_records <= contents.chunks.len() {
+            return Ok(());
+        }
+
+        log::info!(
+            "Compacting vector store: {} live chunks, {} dead records",
+            contents.chunks.len(),
+            contents.dead_records
+        );
+        self.write_replacement(contents.chunks)
+    }
+
+    /// Atomically replace the whole log with `chunks`
+    pub fn replace_all(&self, chunks: Vec<(String, StoredChunk)>) -> Result<()> {
+        let lock = self.open_lock()?;
+        let _lock = StoreLock::acquire(&lock)?;
+        self.write_replacement(chunks)
+    }
+
+    /// Write `chunks` to a new log and rename it over the old one; the caller holds the lock
+    fn write_replacement(&self, chunks: Vec<(String, StoredChunk)>) -> Result<()> {
+        let tmp = self.path.with_extension("bin.tmp");
+        {
+            let mut file = File::create(&tmp)?;
+            file.write_all(MAGIC)?;
+            let mut out = std::io::BufWriter::new(&file);
+            for (key, chunk) in chunks {
+                out.write_all(&encode_record(KIND_PUT, &encode_put(&key, chunk)?))?;
+            }
+            out.flush()?;
+            drop(out);
+            file.sync_data()?;
+        }
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Delete the store of an index directory, including any unmigrated legacy chunks
+    pub fn clear(index_path: &Path) -> Result<()> {
+        let path = index_path.join(STORE_FILE);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let meta = index_path.join(META_FILE);
+        if meta.exists() {
+            std::fs::remove_file(&meta)?;
+        }
+        let legacy_dir = index_path.join(LEGACY_DIR);
+        if legacy_dir.is_dir() {
+            std::fs::remove_dir_all(&legacy_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Write every live chunk (with its embedding) as one JSON line; returns chunks written
+    pub fn export_jsonl(&self, path: &Path) -> Result<usize> {
+        let contents = self.load()?;
+        let mut out = std::io::BufWriter::new(File::create(path)?);
+        for (key, chunk) in &contents.chunks {
+            serde_json::to_writer(&mut out, &PortableChunkRef { key, chunk })?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+        Ok(contents.chunks.len())
+    }
+
+    /// Append chunks from a JSONL export, refusing files whose embeddings have the wrong dimension
+    ///
+    /// The whole file is validated before anything is written. Chunks keep their exported
+    /// keys, so importing the same file twice replaces rather than duplicates them.
+    /// Returns the imported `(key, source)` pairs.
+    pub fn import_jsonl(
+        &self,
+        path: &Path,
+        expected_dimension: usize,
+    ) -> Result<Vec<(String, String)>> {
+        use std::io::BufRead;
+
+        let reader = std::io::BufReader::new(File::open(path)?);
+        let mut chunks = Vec::new();
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let portable: PortableChunk = serde_json::from_str(&line)
+                .map_err(|e| anyhow!("{:?} line {}: invalid chunk: {}", path, line_no + 1, e))?;
+            let dimension = portable.chunk.embedding.len();
+            if dimension != expected_dimension {
+                return Err(anyhow!(
+                    "{:?} line {}: embedding has {} dimensions but the configured model uses {}. \
+                     Switch to the embedding provider the export was built with, or re-index instead.",
+                    path,
+                    line_no + 1,
+                    dimension,
+                    expected_dimension
+                ));
+            }
+            chunks.push(portable);
+        }
+
+        let mut writer = self.writer()?;
+        let mut imported = Vec::with_capacity(chunks.len());
+        for PortableChunk { key, chunk } in chunks {
+            let source = chunk.source_path.to_string_lossy().to_string();
+            writer.put(&key, chunk)?;
+            imported.push((key, source));
+        }
+        writer.checkpoint()?;
+        Ok(imported)
+    }
+
+    /// Convert a legacy one-JSON-file-per-chunk directory into the log
+    ///
+    /// Files that fail to migrate are listed in the index meta and skipped by later opens.
+    fn migrate_json_dir(&self, legacy_dir: &Path) -> Result<()> {
+        let mut meta = self.meta_file().unwrap_or_default();
+        let files: Vec<PathBuf> = std::fs::read_dir(legacy_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+            .filter(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                !meta.unmigrated_legacy_files.iter().any(|n| *n == name)
+            })
+            .collect();
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        progress_note!(
+            "Migrating {} indexed chunks to the binary vector store...",
+            files.len()
+        );
+
+        let mut writer = self.writer()?;
+        let mut migrated = Vec::new();
+        let mut failed = Vec::new();
+        for path in files {
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()).map(String::from) else {
+                failed.push(path);
+                continue;
+            };
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<StoredChunk>(&content)?));
+            match parsed {
+                Ok(chunk) => {
+                    writer.put(&key, chunk)?;
+                    migrated.push(path);
+                }
+                Err(e) => {
+                    log::warn!("Skipping unreadable chunk file {:?}: {}", path, e);
+                    failed.push(path);
+                }
+            }
+        }
+        writer.checkpoint()?;
+
+        // Only drop the JSON files once their records are durable
+        for path in &migrated {
+            let _ = std::fs::remove_file(path);
+        }
+        if std::fs::remove_dir(legacy_dir).is_err() {
+            log::warn!(
+                "Left unreadable chunk files in {:?}; they are not searchable",
+                legacy_dir
+            );
+        }
+        if !failed.is_empty() {
+            meta.unmigrated_legacy_files.extend(
+                failed
+                    .iter()
+                    .filter_map(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().to_string()),
+            );
+            self.write_meta_file(&meta)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line of a portable JSONL export
+#[derive(serde::Deserialize)]
+struct PortableChunk {
+    key: String,
+    #[serde(flatten)]
+    chunk: StoredChunk,
+}
+
+#[derive(serde::Serialize)]
+struct PortableChunkRef<'a> {
+    key: &'a str,
+    #[serde(flatten)]
+    chunk: &'a StoredChunk,
+}
+
+/// Appends records to the store; call [`StoreWriter::checkpoint`] to make them durable
+pub struct StoreWriter {
+    file: File,
+    path: PathBuf,
+    lock: File,
+}
+
+impl StoreWriter {
+    /// Append a chunk under `key` (a later put with the same key replaces it)
+    pub fn put(&mut self, key: &str, chunk: StoredChunk) -> Result<()> {
+        let payload = encode_put(key, chunk)?;
+        self.write_record(KIND_PUT, &payload)
+    }
+
+    /// Flush appended records to disk
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    fn write_record(&mut self, kind: u8, payload: &[u8]) -> Result<()> {
+        // One write per record, under the lock, so concurrent appenders never interleave
+        let record = encode_record(kind, payload);
+        let _lock = StoreLock::acquire(&self.lock)?;
+        // A compaction since the last append renamed a new log into place
+        if !is_same_file(&self.file.metadata()?, std::fs::metadata(&self.path).ok()) {
+            self.file.sync_data()?;
+            self.file = open_for_append(&self.path)?;
+        }
+        self.file.write_all(&record)?;
+        Ok(())
+    }
+}
+
+/// Open the log for appending, writing the format header if it is new; the caller holds the lock
+///
+/// A record torn off by a crash mid-append is cut away first, otherwise its length prefix
+/// would swallow the records appended after it.
+fn open_for_append(path: &Path) -> Result<File> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    let len = file.metadata()?.len();
+    if len == 0 {
+        file.write_all(MAGIC)?;
+    } else if len > MAGIC.len() as u64 {
+        let end = complete_length(&mut file, len)?;
+        if end < len {
+            log::warn!(
+                "Dropping {} bytes of a torn record at the end of {:?}",
+                len - end,
+                path
+            );
+            file.set_len(end)?;
+        }
+    }
+    Ok(file)
+}
+
+/// Length of the log up to the end of its last complete record, read from the record framing
+fn complete_length(file: &mut File, len: u64) -> Result<u64> {
+    use std::io::{BufReader, Read, Seek, SeekFrom};
+
+    let mut reader = BufReader::new(file);
+    let mut end = MAGIC.len() as u64;
+    reader.seek(SeekFrom::Start(end))?;
+    let mut header = [0u8; 5];
+    while end + header.len() as u64 <= len {
+        reader.read_exact(&mut header)?;
+        let payload = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as u64;
+        let record_end = end + header.len() as u64 + payload;
+        if record_end > len {
+            break;
+        }
+        reader.seek_relative(payload as i64)?;
+        end = record_end;
+    }
+    Ok(end)
+}
+
+#[cfg(unix)]
+fn is_same_file(open: &std::fs::Metadata, current: Option<std::fs::Metadata>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    current.is_some_and(|current| open.dev() == current.dev() && open.ino() == current.ino())
+}
+
+/// Windows refuses to rename over a log that is open, so a missing file is the only change
+#[cfg(not(unix))]
+fn is_same_file(_open: &std::fs::Metadata, current: Option<std::fs::Metadata>) -> bool {
+    current.is_some()
+}
+
+fn encode_put(key: &str, mut chunk: StoredChunk) -> Result<Vec<u8>> {
+    let embedding = std::mem::take(&mut chunk.embedding);
+    let meta = serde_json::to_vec(&chunk)?;
+
+    let mut payload = Vec::with_capacity(12 + key.len() + meta.len() + embedding.len() * 4);
+    write_bytes(&mut payload, key.as_bytes());
+    write_bytes(&mut payload, &meta);
+    payload.extend_from_slice(&(embedding.len() as u32).to_le_bytes());
+    for value in &embedding {
+        payload.extend_from_slice(&value.to_le_bytes());
+    }
+    Ok(payload)
+}
+
+fn encode_record(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(5 + payload.len());
+    record.push(kind);
+    write_bytes(&mut record, payload);
+    record
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn decode_put(payload: &[u8]) -> Option<(String, StoredChunk)> {
+    let mut reader = Reader::new(payload);
+    let key = String::from_utf8(reader.bytes()?.to_vec()).ok()?;
+    let mut chunk: StoredChunk = serde_json::from_slice(reader.bytes()?).ok()?;
+    let dimension = reader.u32()? as usize;
+    let raw = reader.take(dimension.checked_mul(4)?)?;
+    chunk.embedding = raw
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Some((key, chunk))
+}
+
+/// Bounds-checked cursor over the store bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Some(head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let b = self.take(4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn record(&mut self) -> Option<(u8, &'a [u8])> {
+        let kind = *self.take(1)?.first()?;
+        Some((kind, self.bytes()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::{DocumentMetadata, SourceType};
+
+    fn chunk(id: &str, embedding: Vec<f32>) -> StoredChunk {
+        StoredChunk {
+            id: id.to_string(),
+            content: format!("content of {}", id),
+            source_path: PathBuf::from("/docs/guide.md"),
+            source_type: SourceType::Local,
+            title: Some("Guide".to_string()),
+            section: None,
+            chunk_index: 0,
+            metadata: DocumentMetadata {
+                file_type: "md".to_string(),
+                size: 10,
+                modified: chrono::Utc::now(),
+                tags: vec![],
+                language: None,
+                line_range: None,
+            },
+            embedding,
+        }
+    }
+
+    #[test]
+    fn test_store_put_remove_and_migrate() {
+        let dir = std::env::temp_dir().join(format!("manx_store_{}", uuid::Uuid::new_v4()));
+        let legacy = dir.join(LEGACY_DIR);
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(
+            legacy.join("legacy-key.json"),
+            serde_json::to_string(&chunk("old", vec![0.5, -1.0])).unwrap(),
+        )
+        .unwrap();
+
+        let store = VectorStore::open(&dir).unwrap();
+        assert!(!legacy.exists());
+
+        let mut writer = store.writer().unwrap();
+        writer.put("a", chunk("a", vec![1.0, 2.0])).unwrap();
+        writer.put("b", chunk("b", vec![3.0, 4.0])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let contents = store.load().unwrap();
+        let keys: Vec<_> = contents.chunks.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["legacy-key", "a", "b"]);
+        assert_eq!(contents.chunks[0].1.embedding, vec![0.5, -1.0]);
+        assert_eq!(contents.chunks[2].1.embedding, vec![3.0, 4.0]);
+
+        store
+            .remove(&["a".to_string(), "legacy-key".to_string()])
+            .unwrap();
+        let contents = store.load().unwrap();
+        assert_eq!(contents.chunks.len(), 1);
+        assert_eq!(contents.chunks[0].1.id, "b");
+        // Four dead records against one live chunk triggers compaction
+        assert_eq!(contents.dead_records, 0);
+
+        // A torn tail record is ignored
+        let mut file = OpenOptions::new().append(true).open(store.path()).unwrap();
+        file.write_all(&[KIND_PUT, 200, 0]).unwrap();
+        assert_eq!(store.load().unwrap().chunks.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unmigratable_legacy_files_are_recorded_once() {
+        let dir = std::env::temp_dir().join(format!("manx_legacy_{}", uuid::Uuid::new_v4()));
+        let legacy = dir.join(LEGACY_DIR);
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join("broken.json"), "{not json").unwrap();
+        std::fs::write(
+            legacy.join("good.json"),
+            serde_json::to_string(&chunk("good", vec![1.0])).unwrap(),
+        )
+        .unwrap();
+
+        let store = VectorStore::open(&dir).unwrap();
+        assert!(legacy.join("broken.json").exists());
+        assert!(store.meta().unwrap().is_none());
+        let meta = store.meta_file().unwrap();
+        assert_eq!(meta.unmigrated_legacy_files, vec!["broken.json"]);
+
+        // Later opens leave the recorded file alone but still pick up new ones
+        let size = store.size_bytes();
+        VectorStore::open(&dir).unwrap();
+        assert_eq!(store.size_bytes(), size);
+        std::fs::write(
+            legacy.join("late.json"),
+            serde_json::to_string(&chunk("late", vec![2.0])).unwrap(),
+        )
+        .unwrap();
+        VectorStore::open(&dir).unwrap();
+        let keys: Vec<_> = store
+            .load()
+            .unwrap()
+            .chunks
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec!["good", "late"]);
+
+        // Recording the model keeps the list
+        let model = IndexMeta {
+            dimension: 1,
+            provider: EmbeddingProvider::Hash,
+        };
+        store.write_meta(&model).unwrap();
+        assert_eq!(store.meta().unwrap(), Some(model));
+        assert_eq!(store.meta_file().unwrap().unmigrated_legacy_files.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_append_after_compaction_lands_in_new_log() {
+        let dir = std::env::temp_dir().join(format!("manx_compact_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = VectorStore::open(&dir).unwrap();
+
+        let mut writer = store.writer().unwrap();
+        writer.put("a", chunk("a", vec![1.0])).unwrap();
+        writer.checkpoint().unwrap();
+
+        // Another handle (as another process would) rewrites the log under the writer
+        let other = VectorStore::open(&dir).unwrap();
+        let chunks = other.load().unwrap().chunks;
+        other.replace_all(chunks).unwrap();
+
+        writer.put("b", chunk("b", vec![2.0])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let keys: Vec<_> = store
+            .load()
+            .unwrap()
+            .chunks
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec!["a", "b"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_append_after_torn_record_drops_it() {
+        let dir = std::env::temp_dir().join(format!("manx_torn_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = VectorStore::open(&dir).unwrap();
+
+        let mut writer = store.writer().unwrap();
+        writer.put("a", chunk("a", vec![1.0])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+        let intact = store.size_bytes();
+        // A crash mid-append leaves a length prefix with only part of its payload
+        let mut file = OpenOptions::new().append(true).open(store.path()).unwrap();
+        file.write_all(&[KIND_PUT, 200, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+
+        let mut writer = store.writer().unwrap();
+        assert_eq!(store.size_bytes(), intact);
+        writer.put("b", chunk("b", vec![2.0])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let keys: Vec<_> = store
+            .load()
+            .unwrap()
+            .chunks
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec!["a", "b"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_skips_corrupt_records() {
+        let dir = std::env::temp_dir().join(format!("manx_check_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = VectorStore::open(&dir).unwrap();
+
+        let mut writer = store.writer().unwrap();
+        writer.put("a", chunk("a", vec![1.0, 2.0])).unwrap();
+        // A well-framed record whose payload isn't a chunk, then an unknown record type
+        writer.write_record(KIND_PUT, b"garbage").unwrap();
+        writer.write_record(9, b"x").unwrap();
+        writer.put("b", chunk("b", vec![3.0])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+        let mut file = OpenOptions::new().append(true).open(store.path()).unwrap();
+        file.write_all(&[KIND_PUT, 200, 0]).unwrap();
+
+        assert!(store.load().is_err());
+        let check = store.check().unwrap();
+        let keys: Vec<_> = check
+            .contents
+            .chunks
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect();
+        assert_eq!(keys, vec!["a", "b"]);
+        assert_eq!(check.corrupt_records, 2);
+        assert_eq!(check.truncated_bytes, 3);
+        assert!(check.legacy_leftovers.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_import_roundtrip_checks_dimension() {
+        let dir = std::env::temp_dir().join(format!("manx_export_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("dst")).unwrap();
+
+        let source = VectorStore::open(&dir.join("src")).unwrap();
+        let mut writer = source.writer().unwrap();
+        writer.put("a", chunk("a", vec![1.0, 0.0, 0.5])).unwrap();
+        writer.put("b", chunk("b", vec![0.0, 1.0, 0.5])).unwrap();
+        writer.checkpoint().unwrap();
+        drop(writer);
+
+        let export = dir.join("index.jsonl");
+        assert_eq!(source.export_jsonl(&export).unwrap(), 2);
+
+        let target = VectorStore::open(&dir.join("dst")).unwrap();
+        let err = target.import_jsonl(&export, 384).unwrap_err();
+        assert!(err.to_string().contains("3 dimensions"));
+        assert!(target.load().unwrap().chunks.is_empty());
+
+        // Importing twice keeps keys, so chunks are replaced rather than duplicated
+        target.import_jsonl(&export, 3).unwrap();
+        let imported = target.import_jsonl(&export, 3).unwrap();
+        assert_eq!(imported[0], ("a".to_string(), "/docs/guide.md".to_string()));
+        let contents = target.load().unwrap();
+        assert_eq!(contents.chunks.len(), 2);
+        assert_eq!(contents.chunks[1].1.embedding, vec![0.0, 1.0, 0.5]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
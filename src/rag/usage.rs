@@ -0,0 +1,133 @@
+//! Token usage and cost estimates for LLM synthesis (`--show-usage`)
+//!
+//! Providers that report usage supply prompt and completion token counts; for those
+//! that don't, counts are estimated at roughly four characters per token. Costs come
+//! from a small table of published per-million-token prices and are only a guide.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::rag::llm::LlmProvider;
+
+static SHOW_USAGE: AtomicBool = AtomicBool::new(false);
+
+/// Print token usage after each synthesis for this run (`--show-usage` or `--debug`)
+pub fn set_show_usage(enabled: bool) {
+    SHOW_USAGE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn show_usage() -> bool {
+    SHOW_USAGE.load(Ordering::Relaxed)
+}
+
+/// Prompt and completion tokens for one synthesis call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Counted from characters rather than reported by the provider
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    /// Approximate usage from prompt and answer lengths (about four characters per token)
+    pub fn from_chars(input_chars: usize, output_chars: usize) -> Self {
+        Self {
+            input_tokens: input_chars.div_ceil(4) as u32,
+            output_tokens: output_chars.div_ceil(4) as u32,
+            estimated: true,
+        }
+    }
+
+    /// Usage from an OpenAI-style (`prompt_tokens`/`completion_tokens`) or
+    /// Anthropic-style (`input_tokens`/`output_tokens`) `usage` object
+    pub fn from_json(usage: &serde_json::Value) -> Option<Self> {
+        let count = |keys: [&str; 2]| {
+            keys.iter()
+                .find_map(|key| usage.get(*key).and_then(|v| v.as_u64()))
+        };
+        let input = count(["prompt_tokens", "input_tokens"]);
+        let output = count(["completion_tokens", "output_tokens"]);
+        if input.is_none() && output.is_none() {
+            return None;
+        }
+        Some(Self {
+            input_tokens: input.unwrap_or(0) as u32,
+            output_tokens: output.unwrap_or(0) as u32,
+            estimated: false,
+        })
+    }
+
+    pub fn total(&self) -> u32 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// USD per million input and output tokens, matched by model name prefix
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-3-opus", 15.00, 75.00),
+    ("claude-opus-4", 15.00, 75.00),
+    ("llama-3.1-8b-instant", 0.05, 0.08),
+    ("llama-3.3-70b-versatile", 0.59, 0.79),
+];
+
+/// Estimated cost in USD, or `None` when the model isn't in the price table
+///
+/// Local Ollama models are always free. OpenRouter-style `vendor/model` names are
+/// looked up by the part after the slash.
+pub fn estimate_cost(provider: &LlmProvider, model: &str, usage: &TokenUsage) -> Option<f64> {
+    if matches!(provider, LlmProvider::Ollama) {
+        return Some(0.0);
+    }
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    let (_, input, output) = PRICES
+        .iter()
+        .filter(|(prefix, _, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())?;
+    Some((usage.input_tokens as f64 * input + usage.output_tokens as f64 * output) / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_parsing_and_cost_lookup() {
+        let openai = serde_json::json!({"prompt_tokens": 1200, "completion_tokens": 300, "total_tokens": 1500});
+        let usage = TokenUsage::from_json(&openai).unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (1200, 300));
+        assert!(!usage.estimated);
+
+        let anthropic = serde_json::json!({"input_tokens": 10, "output_tokens": 5});
+        assert_eq!(TokenUsage::from_json(&anthropic).unwrap().total(), 15);
+        assert!(TokenUsage::from_json(&serde_json::Value::Null).is_none());
+
+        let estimate = TokenUsage::from_chars(9, 4);
+        assert_eq!((estimate.input_tokens, estimate.output_tokens), (3, 1));
+        assert!(estimate.estimated);
+
+        // Longest prefix wins: gpt-4o-mini is not priced as gpt-4o
+        let mini = estimate_cost(&LlmProvider::OpenAI, "gpt-4o-mini", &usage).unwrap();
+        assert!((mini - (1200.0 * 0.15 + 300.0 * 0.60) / 1e6).abs() < 1e-12);
+        let routed = estimate_cost(&LlmProvider::OpenRouter, "openai/gpt-4o-mini", &usage);
+        assert_eq!(routed, Some(mini));
+        assert_eq!(
+            estimate_cost(&LlmProvider::Ollama, "llama3.1", &usage),
+            Some(0.0)
+        );
+        assert!(estimate_cost(&LlmProvider::Custom, "my-model", &usage).is_none());
+    }
+}
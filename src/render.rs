@@ -1,12 +1,227 @@
 use crate::client::{CodeExample, DocSection, Documentation, SearchResult};
 use crate::config::Config;
+use crate::rag::RagSearchResult;
+use crate::web_search::result_processor::significant_terms;
+use crate::web_search::ProcessedSearchResult;
 use anyhow::Result;
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::io;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Machine-readable output selected with `--format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Text,
+    Markdown,
+}
+
+/// A result that `--format text` and `--format markdown` can lay out
+///
+/// JSON and YAML serialize the record itself, so those formats carry every field.
+pub trait OutputRecord: Serialize {
+    fn heading(&self) -> String;
+    /// Short metadata lines (location, score) shown under the heading
+    fn details(&self) -> Vec<String>;
+    fn body(&self) -> &str;
+}
+
+impl OutputRecord for SearchResult {
+    fn heading(&self) -> String {
+        self.title.clone()
+    }
+
+    fn details(&self) -> Vec<String> {
+        let mut details = vec![
+            format!("ID: {}", self.id),
+            format!("Library: {}", self.library),
+        ];
+        if let Some(url) = &self.url {
+            details.push(format!("URL: {}", url));
+        }
+        details.push(format!("Score: {:.3}", self.relevance_score));
+        details
+    }
+
+    fn body(&self) -> &str {
+        &self.excerpt
+    }
+}
+
+impl OutputRecord for ProcessedSearchResult {
+    fn heading(&self) -> String {
+        self.title.clone()
+    }
+
+    fn details(&self) -> Vec<String> {
+        let source = if self.is_official {
+            "Official Documentation"
+        } else {
+            "Community Source"
+        };
+        vec![
+            format!("URL: {}", self.url),
+            format!("Source: {}", source),
+            format!("Score: {:.3}", self.similarity_score),
+        ]
+    }
+
+    fn body(&self) -> &str {
+        &self.snippet
+    }
+}
+
+impl OutputRecord for RagSearchResult {
+    fn heading(&self) -> String {
+        self.title.clone().unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    fn details(&self) -> Vec<String> {
+        vec![
+            format!("Source: {}", self.source_path.display()),
+            format!("Score: {:.3}", self.score),
+        ]
+    }
+
+    fn body(&self) -> &str {
+        &self.content
+    }
+}
+
+/// One section of Context7 documentation, as written by `manx doc --format`
+#[derive(Debug, Clone, Serialize)]
+pub struct DocSectionRecord {
+    pub library: String,
+    pub title: String,
+    pub content: String,
+}
+
+impl OutputRecord for DocSectionRecord {
+    fn heading(&self) -> String {
+        self.title.clone()
+    }
+
+    fn details(&self) -> Vec<String> {
+        vec![format!("Library: {}", self.library)]
+    }
+
+    fn body(&self) -> &str {
+        &self.content
+    }
+}
+
+/// Lay out results in the given format; the returned text always ends with a newline
+pub fn format_records<T: OutputRecord>(format: OutputFormat, records: &[T]) -> Result<String> {
+    let mut output = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(records)?,
+        OutputFormat::Yaml => serde_yaml::to_string(records)?,
+        OutputFormat::Text => records
+            .iter()
+            .enumerate()
+            .map(|(i, record)| {
+                let mut block = format!("{}. {}\n", i + 1, record.heading());
+                for line in record.details() {
+                    block.push_str(&format!("   {}\n", line));
+                }
+                for line in record.body().lines() {
+                    if !line.trim().is_empty() {
+                        block.push_str("   ");
+                        block.push_str(line);
+                    }
+                    block.push('\n');
+                }
+                block
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Markdown => records
+            .iter()
+            .enumerate()
+            .map(|(i, record)| {
+                let mut block = format!("## {}. {}\n\n", i + 1, record.heading());
+                for line in record.details() {
+                    block.push_str(&format!("- {}\n", line));
+                }
+                block.push_str(&format!("\n{}\n", record.body().trim_end()));
+                block
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Byte ranges of `text` where any of `terms` occurs, ignoring ASCII case
+///
+/// Ranges are sorted and overlapping matches merged. Punctuation around a term is
+/// ignored and single-character terms are skipped.
+fn term_ranges(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    // ASCII lowercasing keeps byte offsets identical to `text`
+    let lower = text.to_ascii_lowercase();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        let term = term
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_ascii_lowercase();
+        if term.len() < 2 {
+            continue;
+        }
+        let mut from = 0;
+        while let Some(pos) = lower[from..].find(&term) {
+            ranges.push((from + pos, from + pos + term.len()));
+            from += pos + term.len();
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Style `text` with `base`, picking out literal matches of `terms` in bold yellow
+///
+/// Semantic matches that share no words with the query come back in the base style.
+/// Colors follow `colored`'s global override, so `NO_COLOR` output stays plain.
+fn highlight_terms(text: &str, terms: &[String], base: impl Fn(&str) -> ColoredString) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (start, end) in term_ranges(text, terms) {
+        if start > cursor {
+            out.push_str(&base(&text[cursor..start]).to_string());
+        }
+        out.push_str(&text[start..end].yellow().bold().to_string());
+        cursor = end;
+    }
+    if cursor < text.len() {
+        out.push_str(&base(&text[cursor..]).to_string());
+    }
+    out
+}
+
+/// Keep the first `limit` results (0 means unlimited)
+pub fn apply_limit<T>(records: &[T], limit: usize) -> &[T] {
+    if limit == 0 {
+        records
+    } else {
+        &records[..limit.min(records.len())]
+    }
+}
 
 pub struct Renderer {
     quiet_mode: bool,
+    format: Option<OutputFormat>,
+    raw: bool,
     terminal_width: usize,
     config: Option<Config>,
 }
@@ -18,20 +233,108 @@ impl Renderer {
 
         Self {
             quiet_mode: quiet,
+            format: None,
+            raw: false,
             terminal_width,
             config,
         }
     }
 
-    pub fn render_search_results(&self, results: &[SearchResult]) -> io::Result<()> {
-        self.render_search_results_with_library(results, None, None)
+    /// Write results in a machine-readable format; also silences spinners and status lines
+    pub fn with_format(mut self, format: Option<OutputFormat>) -> Self {
+        if format.is_some() {
+            self.quiet_mode = true;
+        }
+        self.format = format;
+        self
+    }
+
+    /// Print only the AI answer, as plain text, when one is synthesized
+    pub fn with_raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    /// Whether `--raw` was requested
+    pub fn raw_mode(&self) -> bool {
+        self.raw
+    }
+
+    /// Format requested with `--format`, if any
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.format
+    }
+
+    /// Write results to stdout in the requested format
+    pub fn print_formatted<T: OutputRecord>(
+        &self,
+        format: OutputFormat,
+        records: &[T],
+    ) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(format_records(format, records)?.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Split Context7 documentation into one record per titled section
+    pub fn doc_section_records(&self, library: &str, content: &str) -> Vec<DocSectionRecord> {
+        let sections = self.extract_doc_sections(content);
+        if sections.is_empty() && !content.trim().is_empty() {
+            return vec![DocSectionRecord {
+                library: library.to_string(),
+                title: library.to_string(),
+                content: content.trim().to_string(),
+            }];
+        }
+
+        sections
+            .into_iter()
+            .map(|section| {
+                let (first, rest) = section.split_once('\n').unwrap_or((&section, ""));
+                DocSectionRecord {
+                    library: library.to_string(),
+                    title: first.trim_start_matches("TITLE: ").to_string(),
+                    content: rest.trim().to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Section `number` of Context7 documentation, counting from 1 like the `[n]` labels
+    ///
+    /// Documentation without titled sections counts as a single section, matching
+    /// [`Renderer::doc_section_records`].
+    pub fn doc_section(&self, library: &str, content: &str, number: usize) -> Result<String> {
+        let mut sections = self.extract_doc_sections(content);
+        if sections.is_empty() && !content.trim().is_empty() {
+            sections.push(content.trim().to_string());
+        }
+        number
+            .checked_sub(1)
+            .and_then(|i| sections.get(i).cloned())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Section {} not found: {} documentation has {} section{}",
+                    number,
+                    library,
+                    sections.len(),
+                    if sections.len() == 1 { "" } else { "s" }
+                )
+            })
+    }
+
+    pub fn render_search_results(&self, results: &[SearchResult], query: &str) -> io::Result<()> {
+        self.render_search_results_with_library(results, None, None, query)
     }
 
+    /// List search results, highlighting the query's significant terms in each excerpt
     pub fn render_search_results_with_library(
         &self,
         results: &[SearchResult],
         library_info: Option<(&str, &str)>,
         limit: Option<usize>,
+        query: &str,
     ) -> io::Result<()> {
         if self.quiet_mode {
             // JSON output for scripting
@@ -75,8 +378,9 @@ impl Renderer {
             results.iter().take(display_limit)
         };
 
+        let terms = significant_terms(query);
         for (idx, result) in results_to_show.enumerate() {
-            self.render_search_result(idx + 1, result)?;
+            self.render_search_result(idx + 1, result, &terms)?;
         }
 
         if display_limit > 0 && total_results > display_limit {
@@ -97,7 +401,12 @@ impl Renderer {
         Ok(())
     }
 
-    fn render_search_result(&self, num: usize, result: &SearchResult) -> io::Result<()> {
+    fn render_search_result(
+        &self,
+        num: usize,
+        result: &SearchResult,
+        terms: &[String],
+    ) -> io::Result<()> {
         let separator = "─".repeat(self.terminal_width.min(70));
 
         println!(
@@ -121,19 +430,19 @@ impl Renderer {
 
         // Parse and display Context7 content in a more readable format
         if result.excerpt.contains("CODE SNIPPETS") {
-            self.render_context7_excerpt(&result.excerpt)?;
+            self.render_context7_excerpt(&result.excerpt, terms)?;
         } else {
             // Show more of the excerpt with higher contrast
             let max_width = self.terminal_width.saturating_sub(4).max(60);
             let text = self.truncate_text(&result.excerpt, max_width);
-            println!("  {}", text.white());
+            println!("  {}", highlight_terms(&text, terms, |s| s.white()));
         }
 
         println!("{}\n", separator.white().dimmed());
         Ok(())
     }
 
-    fn render_context7_excerpt(&self, content: &str) -> io::Result<()> {
+    fn render_context7_excerpt(&self, content: &str, terms: &[String]) -> io::Result<()> {
         // Find the first meaningful content after CODE SNIPPETS header
         let lines: Vec<&str> = content.lines().collect();
         let mut found_title = false;
@@ -147,7 +456,7 @@ impl Renderer {
             } else if line.starts_with("DESCRIPTION: ") && found_title {
                 let desc = &line[13..];
                 let truncated = self.truncate_text(desc, self.terminal_width - 4);
-                println!("  {}", truncated.dimmed());
+                println!("  {}", highlight_terms(&truncated, terms, |s| s.dimmed()));
                 break;
             }
         }
@@ -159,6 +468,7 @@ impl Renderer {
         Ok(())
     }
 
+    #[allow(dead_code)] // Public API method - may be used by external code
     pub fn render_documentation(&self, doc: &Documentation) -> io::Result<()> {
         if self.quiet_mode {
             println!("{}", serde_json::to_string_pretty(doc)?);
@@ -190,6 +500,7 @@ impl Renderer {
         Ok(())
     }
 
+    #[allow(dead_code)]
     fn render_doc_section(&self, section: &DocSection) -> io::Result<()> {
         println!("\n{}", section.title.bright_green().bold());
 
@@ -207,6 +518,7 @@ impl Renderer {
         Ok(())
     }
 
+    #[allow(dead_code)]
     fn render_code_example(&self, example: &CodeExample) -> io::Result<()> {
         println!(
             "\n{} {}:",
@@ -323,9 +635,15 @@ impl Renderer {
         }
     }
 
+    pub fn print_warning(&self, message: &str) {
+        if !self.quiet_mode {
+            eprintln!("{} {}", "WARNING:".yellow().bold(), message.yellow());
+        }
+    }
+
     pub fn print_success(&self, message: &str) {
         if !self.quiet_mode {
-            println!("{} {}", "OK".green().bold(), message.green());
+            eprintln!("{} {}", "OK".green().bold(), message.green());
         }
     }
 
@@ -664,10 +982,14 @@ impl Renderer {
                     let sections_clone = sections.to_vec();
 
                     tokio::spawn(async move {
+                        let mut items = Vec::new();
                         for (idx, section) in sections_clone.iter().enumerate() {
-                            let cache_key = format!("{}_doc-{}", library_clean, idx + 1);
+                            let id = format!("doc-{}", idx + 1);
+                            let cache_key = format!("{}_{}", library_clean, id);
                             let _ = cache_manager.set("doc_sections", &cache_key, section).await;
+                            items.push(crate::cache::ItemRef::new(&library_clean, &id, &cache_key));
                         }
+                        let _ = cache_manager.record_items("doc_sections", &items);
                     });
                 }
             }
@@ -675,3 +997,88 @@ impl Renderer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(title: &str, excerpt: &str) -> SearchResult {
+        SearchResult {
+            id: "doc-1".to_string(),
+            library: "react".to_string(),
+            title: title.to_string(),
+            excerpt: excerpt.to_string(),
+            url: None,
+            relevance_score: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_format_records() {
+        let results = vec![result("Hooks", "useEffect(() => {})\n\ncleanup")];
+
+        let json = format_records(OutputFormat::Json, &results).unwrap();
+        let parsed: Vec<SearchResult> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0].title, "Hooks");
+
+        let yaml = format_records(OutputFormat::Yaml, &results).unwrap();
+        assert!(yaml.contains("title: Hooks"));
+
+        let text = format_records(OutputFormat::Text, &results).unwrap();
+        assert_eq!(
+            text,
+            "1. Hooks\n   ID: doc-1\n   Library: react\n   Score: 0.500\n   useEffect(() => {})\n\n   cleanup\n"
+        );
+        assert!(!text.contains('\u{1b}'));
+
+        let markdown = format_records(OutputFormat::Markdown, &results).unwrap();
+        assert!(markdown.starts_with("## 1. Hooks\n\n- ID: doc-1\n"));
+    }
+
+    #[test]
+    fn test_doc_section_selects_by_number() {
+        let renderer = Renderer::new(true);
+        let content = "TITLE: Hooks\nDESCRIPTION: State in functions\n\nTITLE: Effects\nDESCRIPTION: Side effects\n";
+
+        let section = renderer.doc_section("react", content, 2).unwrap();
+        assert!(section.starts_with("TITLE: Effects"));
+        assert!(!section.contains("Hooks"));
+
+        let err = renderer.doc_section("react", content, 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Section 3 not found: react documentation has 2 sections"
+        );
+        assert!(renderer.doc_section("react", content, 0).is_err());
+
+        let untitled = renderer.doc_section("react", "plain text", 1).unwrap();
+        assert_eq!(untitled, "plain text");
+    }
+
+    #[test]
+    fn test_term_ranges() {
+        let terms = significant_terms("how to use React hooks?");
+        let text = "Hooks let you use state. useState is a React Hook.";
+        let found: Vec<&str> = term_ranges(text, &terms)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(found, ["Hooks", "use", "use", "React"]);
+
+        // Overlapping terms merge into one range
+        let overlap = vec!["tokio".to_string(), "okio::spawn".to_string()];
+        assert_eq!(term_ranges("tokio::spawn", &overlap), [(0, 12)]);
+
+        // Non-ASCII text keeps valid boundaries; no literal match leaves text as is
+        assert_eq!(term_ranges("Größe über alles", &terms), []);
+        assert_eq!(term_ranges("Größe über hooks", &terms), [(14, 19)]);
+    }
+
+    #[test]
+    fn test_apply_limit() {
+        let items = [1, 2, 3];
+        assert_eq!(apply_limit(&items, 2), &[1, 2]);
+        assert_eq!(apply_limit(&items, 0), &[1, 2, 3]);
+        assert_eq!(apply_limit(&items, 10), &[1, 2, 3]);
+    }
+}
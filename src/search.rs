@@ -3,6 +3,7 @@ use crate::rag::embeddings::EmbeddingModel;
 use anyhow::Result;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -53,11 +54,19 @@ impl SearchEngine {
         query: &str,
         limit: Option<usize>,
     ) -> Result<(Vec<SearchResult>, String, String)> {
+        if let Some(ref model) = self.embedding_model {
+            model.notify_if_hash();
+        }
+
         // Parse library@version format
-        let (lib_name, _version) = parse_library_spec(library);
+        let (lib_name, version) = parse_library_spec(library);
 
-        // Step 1: Resolve library to Context7 ID
-        let (library_id, library_title) = self.client.resolve_library(lib_name).await?;
+        // Step 1: Resolve library to Context7 ID (pinned when a version was requested)
+        let (library_id, library_title) = self.client.resolve_library(lib_name, version).await?;
+        let library_title = match version {
+            Some(v) => format!("{} ({})", library_title, v),
+            None => library_title,
+        };
 
         // Step 2: Parse the query to extract phrases and terms
         let parsed_query = self.parse_search_query(query);
@@ -76,13 +85,20 @@ impl SearchEngine {
 
         // Step 4: Cache individual snippets for later retrieval via snippet command
         if let Ok(cache_manager) = crate::cache::CacheManager::new() {
+            let mut items = Vec::new();
             for result in &results {
                 let snippet_cache_key = format!("{}_{}", library, &result.id);
                 // Cache the complete excerpt content
                 let _ = cache_manager
                     .set("snippets", &snippet_cache_key, &result.excerpt)
                     .await;
+                items.push(crate::cache::ItemRef::new(
+                    library,
+                    &result.id,
+                    &snippet_cache_key,
+                ));
             }
+            let _ = cache_manager.record_items("snippets", &items);
         }
 
         Ok((results, library_title, library_id))
@@ -866,37 +882,199 @@ impl SearchEngine {
     }
 
     pub async fn get_documentation(&self, library: &str, query: Option<&str>) -> Result<String> {
-        let (lib_name, _version) = parse_library_spec(library);
+        let (lib_name, version) = parse_library_spec(library);
 
         // Step 1: Resolve library to Context7 ID
-        let (library_id, _library_title) = self.client.resolve_library(lib_name).await?;
+        let (library_id, _library_title) = self.client.resolve_library(lib_name, version).await?;
 
         // Step 2: Get documentation
         self.client.get_documentation(&library_id, query).await
     }
 }
 
+/// Detect the version of `library` declared by the nearest project manifest
+///
+/// Walks up from `start_dir` looking at `package.json`, `Cargo.toml` and
+/// `requirements.txt`. Returns the version (range operators stripped) and the
+/// manifest it came from.
+pub fn detect_installed_version(library: &str, start_dir: &Path) -> Option<(String, PathBuf)> {
+    let mut dir = Some(start_dir);
+    while let Some(current) = dir {
+        for manifest in ["package.json", "Cargo.toml", "requirements.txt"] {
+            let path = current.join(manifest);
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let version = match manifest {
+                "package.json" => version_from_package_json(library, &content),
+                "Cargo.toml" => version_from_cargo_toml(library, &content),
+                _ => version_from_requirements(library, &content),
+            };
+            if let Some(version) = version {
+                return Some((version, path));
+            }
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+fn version_from_package_json(library: &str, content: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(content).ok()?;
+    ["dependencies", "devDependencies", "peerDependencies"]
+        .iter()
+        .find_map(|section| json.get(section)?.get(library)?.as_str())
+        .map(clean_version_requirement)
+        .filter(|v| !v.is_empty())
+}
+
+fn version_from_cargo_toml(library: &str, content: &str) -> Option<String> {
+    let name = regex::escape(library);
+    let simple = regex::Regex::new(&format!(r#"(?m)^\s*{}\s*=\s*"([^"]+)""#, name)).ok()?;
+    let table = regex::Regex::new(&format!(
+        r#"(?m)^\s*{}\s*=\s*\{{[^}}]*version\s*=\s*"([^"]+)""#,
+        name
+    ))
+    .ok()?;
+    simple
+        .captures(content)
+        .or_else(|| table.captures(content))
+        .map(|caps| clean_version_requirement(&caps[1]))
+        .filter(|v| !v.is_empty())
+}
+
+fn version_from_requirements(library: &str, content: &str) -> Option<String> {
+    let re = regex::Regex::new(&format!(
+        r"(?mi)^\s*{}\s*(?:\[[^\]]*\])?\s*(?:==|~=|>=)\s*([0-9][\w.\-]*)",
+        regex::escape(library)
+    ))
+    .ok()?;
+    re.captures(content).map(|caps| caps[1].to_string())
+}
+
+/// Strip semver range operators (`^1.2.3`, `~1.2`, `>=1.0`) down to the version
+fn clean_version_requirement(requirement: &str) -> String {
+    requirement
+        .trim()
+        .trim_start_matches(['^', '~', '>', '<', '=', ' '])
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Split `name@version`; a leading `@` belongs to a scoped package name (`@vue/cli@5`)
 fn parse_library_spec(spec: &str) -> (&str, Option<&str>) {
-    if let Some(at_pos) = spec.find('@') {
-        let (lib, ver) = spec.split_at(at_pos);
-        (lib, Some(&ver[1..]))
-    } else {
-        (spec, None)
+    let spec = spec.trim();
+    match spec.get(1..).and_then(|rest| rest.find('@')) {
+        Some(at_pos) => {
+            let (lib, ver) = spec.split_at(at_pos + 1);
+            let ver = &ver[1..];
+            (lib, (!ver.is_empty()).then_some(ver))
+        }
+        None => (spec, None),
     }
 }
 
-pub fn fuzzy_find_libraries(query: &str, libraries: &[String]) -> Vec<(String, i64)> {
+/// Library names close to a misspelled `query`, closest first (at most three)
+///
+/// Candidates are ranked by Levenshtein similarity; those that are neither similar nor a
+/// fuzzy subsequence match are dropped.
+pub fn suggest_libraries(query: &str, libraries: &[String]) -> Vec<String> {
     let matcher = SkimMatcherV2::default();
-    let mut matches: Vec<(String, i64)> = libraries
+    let query = query.trim().to_lowercase();
+    let mut matches: Vec<(&String, f64)> = libraries
         .iter()
         .filter_map(|lib| {
-            matcher
-                .fuzzy_match(lib, query)
-                .map(|score| (lib.clone(), score))
+            let similarity = strsim::normalized_levenshtein(&query, &lib.to_lowercase());
+            (similarity >= 0.5 || matcher.fuzzy_match(lib, &query).is_some())
+                .then_some((lib, similarity))
         })
         .collect();
 
-    matches.sort_by_key(|(_, score)| -score);
-    matches.truncate(5);
-    matches
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut suggestions: Vec<String> = Vec::new();
+    for (lib, _) in matches {
+        if !suggestions.iter().any(|s| s.eq_ignore_ascii_case(lib)) {
+            suggestions.push(lib.clone());
+        }
+    }
+    suggestions.truncate(3);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_resolves_library_name_not_query() {
+        use crate::cli::{Cli, Commands};
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["manx", "snippet", "react", "hooks"]).unwrap();
+        let Some(Commands::Snippet { library, query, .. }) = cli.command else {
+            panic!("expected snippet command");
+        };
+        assert_eq!(query.as_deref(), Some("hooks"));
+
+        let (lib_name, version) = parse_library_spec(&library);
+        let arguments = crate::client::resolve_arguments(lib_name).unwrap();
+        assert_eq!(arguments["query"], "react");
+        assert_eq!(arguments["libraryName"], "react");
+        assert_eq!(version, None);
+
+        assert_eq!(parse_library_spec("react@18"), ("react", Some("18")));
+        assert_eq!(parse_library_spec("@vue/cli@5"), ("@vue/cli", Some("5")));
+        assert_eq!(parse_library_spec("@vue/cli"), ("@vue/cli", None));
+        assert_eq!(parse_library_spec("react@"), ("react", None));
+        assert!(crate::client::resolve_arguments("").is_err());
+    }
+
+    #[test]
+    fn test_suggest_libraries_ranks_by_edit_distance() {
+        let candidates: Vec<String> = ["Vue", "React DOM", "Preact", "React", "react"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            suggest_libraries("reactt", &candidates),
+            ["React", "Preact", "React DOM"]
+        );
+        assert!(suggest_libraries("django", &candidates).is_empty());
+    }
+
+    #[test]
+    fn test_detect_installed_version_walks_up_manifests() {
+        let root = std::env::temp_dir().join(format!("manx_version_{}", uuid::Uuid::new_v4()));
+        let nested = root.join("src").join("components");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        std::fs::write(
+            root.join("package.json"),
+            r#"{"dependencies": {"react": "^18.2.0"}, "devDependencies": {"vite": "~5.1"}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[dependencies]\nserde = \"1.0\"\ntokio = { version = \"1.35\", features = [\"full\"] }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("requirements.txt"),
+            "fastapi==0.110.0\nuvicorn[standard]>=0.27\n",
+        )
+        .unwrap();
+
+        let detect = |lib| detect_installed_version(lib, &nested).map(|(v, _)| v);
+        assert_eq!(detect("react"), Some("18.2.0".to_string()));
+        assert_eq!(detect("vite"), Some("5.1".to_string()));
+        assert_eq!(detect("tokio"), Some("1.35".to_string()));
+        assert_eq!(detect("serde"), Some("1.0".to_string()));
+        assert_eq!(detect("fastapi"), Some("0.110.0".to_string()));
+        assert_eq!(detect("uvicorn"), Some("0.27".to_string()));
+        assert_eq!(detect("django"), None);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
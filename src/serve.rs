@@ -0,0 +1,443 @@
+//! `manx serve`: a local HTTP/JSON API over the search commands
+//!
+//! Each endpoint takes a JSON body and answers with the same records `--format json` prints.
+//! The Context7 search engine, RAG index, embedding model and LLM client are created once at
+//! startup and reused, so requests skip the model loading a fresh `manx` process pays for.
+
+use anyhow::{anyhow, Context, Result};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Body, Bytes};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{header, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use hyper_util::server::graceful::GracefulShutdown;
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use crate::config::Config;
+use crate::rag::embeddings::EmbeddingModel;
+use crate::rag::llm::LlmClient;
+use crate::rag::RagSystem;
+use crate::render::{self, OutputFormat, Renderer};
+use crate::search::SearchEngine;
+use crate::web_search;
+
+/// How long shutdown waits for in-flight requests before giving up on them
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest request body read; larger ones are answered with 413
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// Request body shared by every endpoint; each one reads the fields it needs
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ApiRequest {
+    query: String,
+    library: String,
+    limit: Option<usize>,
+    offset: usize,
+    /// `/rag/search` only: restrict results to these languages
+    lang: Vec<String>,
+    /// `/rag/search` only: restrict results to chunks carrying all of these tags
+    tag: Vec<String>,
+    min_score: Option<f32>,
+    /// `/search` only
+    prefer_community: bool,
+    /// `/rag/search` only: supplement weak local matches with web results (default on)
+    web_fallback: Option<bool>,
+}
+
+/// A failed request: the status to answer with and the error for the JSON body
+struct ApiError(StatusCode, anyhow::Error);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e)
+    }
+}
+
+fn bad_request(message: &str) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, anyhow!("{}", message))
+}
+
+/// Everything kept warm between requests
+struct ServerState {
+    config: Config,
+    renderer: Renderer,
+    search_engine: SearchEngine,
+    rag_system: Option<RagSystem>,
+    embedding_model: Option<Arc<EmbeddingModel>>,
+    llm_client: Option<Arc<LlmClient>>,
+}
+
+impl ServerState {
+    async fn new(config: Config) -> Result<Self> {
+        // Results only: no spinners or status lines, just like `--format json`
+        let renderer = Renderer::new(true).with_format(Some(OutputFormat::Json));
+        let client = crate::context7_client(&config, false)?;
+        let search_engine =
+            crate::create_search_engine_with_pooling(client, &renderer, "", "").await?;
+        let embedding_model = crate::shared_rag_embedding_model(&config).await;
+        // One model serves web search and the local index
+        let rag_system =
+            crate::local_rag_system(&config)
+                .await
+                .map(|rag_system| match &embedding_model {
+                    Some(model) => rag_system.with_shared_embeddings(Arc::clone(model)),
+                    None => rag_system,
+                });
+        let llm_client = if config.should_use_llm(false) {
+            match crate::llm_client(&config) {
+                Ok(client) => Some(Arc::new(client)),
+                Err(e) => {
+                    log::warn!("LLM client unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            config,
+            renderer,
+            search_engine,
+            rag_system,
+            embedding_model,
+            llm_client,
+        })
+    }
+
+    async fn handle<B>(&self, request: Request<B>) -> Response<Full<Bytes>>
+    where
+        B: Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let path = request.uri().path().to_string();
+        log::info!("{} {}", request.method(), path);
+
+        let result = if request.method() != Method::POST {
+            Err(ApiError(
+                StatusCode::METHOD_NOT_ALLOWED,
+                anyhow!("Use POST with a JSON body"),
+            ))
+        } else {
+            match read_request(request).await {
+                Ok(body) => self.route(&path, body).await,
+                Err(e) => Err(e),
+            }
+        };
+
+        match result {
+            Ok(json) => json_response(StatusCode::OK, json),
+            Err(ApiError(status, e)) => {
+                if status.is_server_error() {
+                    log::warn!("{} failed: {:#}", path, e);
+                }
+                json_response(status, format!("{}\n", crate::error_json(&e)))
+            }
+        }
+    }
+
+    async fn route(&self, path: &str, body: ApiRequest) -> Result<String, ApiError> {
+        let mut config = self.config.clone();
+        if body.min_score.is_some() {
+            config.set_min_score(body.min_score);
+        }
+
+        match path {
+            "/search" => self.web_search(&body, &config).await,
+            "/snippet" => self.snippet(&body, &config).await,
+            "/doc" => self.doc(&body, &config).await,
+            "/rag/search" => self.rag_search(&body, &config).await,
+            _ => Err(ApiError(
+                StatusCode::NOT_FOUND,
+                anyhow!(
+                    "Unknown endpoint {}; try /search, /snippet, /doc or /rag/search",
+                    path
+                ),
+            )),
+        }
+    }
+
+    /// `POST /search`: web documentation search, like `manx search`
+    async fn web_search(&self, body: &ApiRequest, config: &Config) -> Result<String, ApiError> {
+        if body.query.trim().is_empty() {
+            return Err(bad_request("\"query\" is required"));
+        }
+
+        let (web_config, page_size) =
+            crate::web_search_page_config(config, body.limit, body.offset, body.prefer_community);
+        let mut search_system = web_search::DocumentationSearchSystem::with_shared_clients(
+            web_config,
+            self.llm_client.clone(),
+            self.embedding_model.clone(),
+        )?;
+        let mut response = search_system.search(&body.query).await?;
+        crate::filter_min_score(&mut response.results, config, &self.renderer, |r| {
+            r.similarity_score
+        });
+
+        let page = response.results.get(body.offset..).unwrap_or_default();
+        json_records(render::apply_limit(page, page_size))
+    }
+
+    /// `POST /snippet`: Context7 snippets plus local matches, like `manx snippet`
+    async fn snippet(&self, body: &ApiRequest, config: &Config) -> Result<String, ApiError> {
+        if body.library.trim().is_empty() {
+            return Err(bad_request("\"library\" is required"));
+        }
+
+        let (mut results, _, _) = crate::fetch_snippets(
+            &self.search_engine,
+            self.rag_system.as_ref(),
+            &body.library,
+            &body.query,
+            config,
+        )
+        .await?;
+        crate::filter_min_score(&mut results, config, &self.renderer, |r| r.relevance_score);
+
+        json_records(render::apply_limit(&results, body.limit.unwrap_or(10)))
+    }
+
+    /// `POST /doc`: Context7 documentation split into sections, like `manx doc`
+    async fn doc(&self, body: &ApiRequest, config: &Config) -> Result<String, ApiError> {
+        if body.library.trim().is_empty() {
+            return Err(bad_request("\"library\" is required"));
+        }
+
+        let doc_text =
+            crate::fetch_documentation(&self.search_engine, &body.library, &body.query, config)
+                .await?;
+        let sections = self.renderer.doc_section_records(&body.library, &doc_text);
+
+        json_records(render::apply_limit(&sections, body.limit.unwrap_or(10)))
+    }
+
+    /// `POST /rag/search`: search the local index, like `manx search --rag`
+    async fn rag_search(&self, body: &ApiRequest, config: &Config) -> Result<String, ApiError> {
+        if body.query.trim().is_empty() {
+            return Err(bad_request("\"query\" is required"));
+        }
+        let Some(rag_system) = &self.rag_system else {
+            return Err(bad_request(
+                "RAG (local document search) is not enabled. Enable with: manx config --rag-enabled",
+            ));
+        };
+
+        let max_results = body.offset + body.limit.unwrap_or(10);
+        let mut results = crate::search_local_index(
+            rag_system,
+            &body.query,
+            max_results,
+            body.web_fallback.unwrap_or(true),
+            &crate::rag::SearchFilters {
+                languages: body.lang.clone(),
+                tags: body.tag.clone(),
+            },
+            config,
+            &self.renderer,
+            &indicatif::ProgressBar::hidden(),
+        )
+        .await?;
+        crate::filter_min_score(&mut results, config, &self.renderer, |r| r.score);
+        results.drain(..body.offset.min(results.len()));
+
+        json_records(&results)
+    }
+}
+
+async fn read_request<B>(request: Request<B>) -> Result<ApiRequest, ApiError>
+where
+    B: Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let bytes = match Limited::new(request.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) if e.is::<LengthLimitError>() => {
+            return Err(ApiError(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                anyhow!("Request body is larger than {} KB", MAX_BODY_BYTES / 1024),
+            ))
+        }
+        Err(e) => {
+            return Err(ApiError(
+                StatusCode::BAD_REQUEST,
+                anyhow!("Failed to read request body: {}", e),
+            ))
+        }
+    };
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return Ok(ApiRequest::default());
+    }
+    serde_json::from_slice(&bytes)
+        .context("Request body is not valid JSON for this endpoint")
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))
+}
+
+fn json_records<T: render::OutputRecord>(records: &[T]) -> Result<String, ApiError> {
+    Ok(render::format_records(OutputFormat::Json, records)?)
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Serve the API on `host:port` until Ctrl+C, then let open requests finish
+pub async fn run(config: Config, host: &str, port: u16) -> Result<()> {
+    let listener = TcpListener::bind((host, port))
+        .await
+        .with_context(|| format!("Failed to listen on {}:{}", host, port))?;
+    let address = listener.local_addr()?;
+
+    eprintln!("Loading models...");
+    let state = Arc::new(ServerState::new(config).await?);
+    eprintln!(
+        "Serving on http://{} (POST /search, /snippet, /doc, /rag/search)",
+        address
+    );
+    eprintln!("Press Ctrl+C to stop");
+
+    let graceful = GracefulShutdown::new();
+    let mut shutdown = std::pin::pin!(tokio::signal::ctrl_c());
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        log::warn!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                let state = Arc::clone(&state);
+                let service = service_fn(move |request| {
+                    let state = Arc::clone(&state);
+                    async move { Ok::<_, Infallible>(state.handle(request).await) }
+                });
+                let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+                let connection = graceful.watch(connection);
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        log::debug!("Connection closed with error: {}", e);
+                    }
+                });
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    eprintln!("Shutting down, waiting for open requests...");
+    drop(listener);
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        log::warn!(
+            "Some requests were still running after {:?}",
+            SHUTDOWN_TIMEOUT
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_request_defaults() {
+        let request: ApiRequest =
+            serde_json::from_str(r#"{"library": "react", "lang": ["rust"]}"#).unwrap();
+        assert_eq!(request.library, "react");
+        assert_eq!(request.query, "");
+        assert_eq!(request.lang, ["rust"]);
+        assert_eq!(request.limit, None);
+        assert_eq!(request.offset, 0);
+        assert_eq!(request.web_fallback, None);
+    }
+
+    fn request(method: Method, path: &str, body: impl Into<Bytes>) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Full::new(body.into()))
+            .unwrap()
+    }
+
+    async fn error_of(response: Response<Full<Bytes>>) -> (StatusCode, String) {
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        (
+            status,
+            json["error"].as_str().unwrap_or_default().to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_validated_before_searching() {
+        let config = Config {
+            rag: crate::rag::RagConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let state = ServerState::new(config).await.unwrap();
+
+        let (status, _) = error_of(state.handle(request(Method::GET, "/search", "")).await).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+
+        let unknown = request(Method::POST, "/nope", r#"{"query": "hooks"}"#);
+        let (status, error) = error_of(state.handle(unknown).await).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(error.contains("/rag/search"));
+
+        let invalid = request(Method::POST, "/search", r#"{"query": "#);
+        let (status, _) = error_of(state.handle(invalid).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // An empty body reads as all defaults, so the missing query is reported
+        for body in ["", "  \n", r#"{"limit": 3}"#] {
+            let (status, error) =
+                error_of(state.handle(request(Method::POST, "/search", body)).await).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error, "\"query\" is required");
+        }
+
+        let rag = request(Method::POST, "/rag/search", r#"{"query": "hooks"}"#);
+        let (status, error) = error_of(state.handle(rag).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.contains("not enabled"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let body = format!(r#"{{"query": "{}"}}"#, "a".repeat(MAX_BODY_BYTES));
+        let result = read_request(request(Method::POST, "/search", body)).await;
+        assert!(matches!(
+            result,
+            Err(ApiError(StatusCode::PAYLOAD_TOO_LARGE, _))
+        ));
+        assert!(
+            read_request(request(Method::POST, "/search", r#"{"query": "hooks"}"#))
+                .await
+                .is_ok()
+        );
+    }
+}
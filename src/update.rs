@@ -1,7 +1,10 @@
+use crate::config::UpdateChannel;
 use crate::render::Renderer;
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -10,8 +13,30 @@ const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 struct CrateData {
     #[serde(default)]
     newest_version: String,
+    /// Highest version, including prereleases
     #[serde(default)]
     max_version: String,
+    /// Highest version that isn't a prerelease
+    #[serde(default)]
+    max_stable_version: Option<String>,
+}
+
+impl CrateData {
+    /// Newest version published on `channel`
+    fn latest_for(&self, channel: UpdateChannel) -> Option<String> {
+        let candidates: [Option<&str>; 2] = match channel {
+            UpdateChannel::Stable => [
+                self.max_stable_version.as_deref(),
+                Some(&self.newest_version),
+            ],
+            UpdateChannel::Prerelease => [Some(&self.max_version), Some(&self.newest_version)],
+        };
+        candidates
+            .into_iter()
+            .flatten()
+            .find(|v| !v.is_empty() && (channel == UpdateChannel::Prerelease || !is_prerelease(v)))
+            .map(str::to_string)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,6 +49,7 @@ pub struct UpdateInfo {
     pub current_version: String,
     pub latest_version: String,
     pub update_available: bool,
+    pub channel: UpdateChannel,
 }
 
 pub struct SelfUpdater {
@@ -33,15 +59,14 @@ pub struct SelfUpdater {
 
 impl SelfUpdater {
     pub fn new(renderer: Renderer) -> Result<Self> {
-        let client = Client::builder()
-            .user_agent(format!("manx/{}", CURRENT_VERSION))
+        let client = crate::http_client::builder()
             .build()
             .context("Failed to create HTTP client")?;
 
         Ok(Self { client, renderer })
     }
 
-    async fn check_cratesio_version(&self) -> Result<String> {
+    async fn check_cratesio_version(&self, channel: UpdateChannel) -> Result<String> {
         let url = "https://crates.io/api/v1/crates/manx-cli";
         let response = self
             .client
@@ -59,21 +84,18 @@ impl SelfUpdater {
             .await
             .context("Failed to parse crates.io information")?;
 
-        let latest_version = if !crates_info.crate_data.newest_version.is_empty() {
-            crates_info.crate_data.newest_version
-        } else if !crates_info.crate_data.max_version.is_empty() {
-            crates_info.crate_data.max_version
-        } else {
-            anyhow::bail!("Could not determine latest version from crates.io");
-        };
-
-        Ok(latest_version)
+        crates_info.crate_data.latest_for(channel).with_context(|| {
+            format!(
+                "Could not determine the latest {} version from crates.io",
+                channel
+            )
+        })
     }
 
-    pub async fn check_for_updates(&self) -> Result<UpdateInfo> {
+    pub async fn check_for_updates(&self, channel: UpdateChannel) -> Result<UpdateInfo> {
         let pb = self.renderer.show_progress("Checking for updates...");
 
-        let latest_version = self.check_cratesio_version().await?;
+        let latest_version = self.check_cratesio_version(channel).await?;
 
         pb.finish_and_clear();
 
@@ -84,11 +106,12 @@ impl SelfUpdater {
             current_version: current_version.to_string(),
             latest_version,
             update_available,
+            channel,
         })
     }
 
-    pub async fn perform_update(&self, force: bool) -> Result<()> {
-        let update_info = self.check_for_updates().await?;
+    pub async fn perform_update(&self, force: bool, channel: UpdateChannel) -> Result<()> {
+        let update_info = self.check_for_updates(channel).await?;
 
         if !update_info.update_available && !force {
             self.renderer
@@ -97,22 +120,70 @@ impl SelfUpdater {
         }
 
         self.renderer.print_success(&format!(
-            "Updating from v{} to v{}...",
-            update_info.current_version, update_info.latest_version
+            "Updating from v{} to v{} ({} channel)...",
+            update_info.current_version, update_info.latest_version, update_info.channel
         ));
 
-        self.update_via_cargo().await
+        // Stash the running binary first so `manx update --rollback` can restore it
+        let exe = current_exe()?;
+        let backup = backup_path(&exe);
+        let staged = backup.with_extension("bak.tmp");
+        fs::copy(&exe, &staged)
+            .map_err(|e| file_error(e, &staged, "back up the current binary"))?;
+
+        match self.update_via_cargo(&update_info.latest_version).await {
+            Ok(()) => {
+                fs::rename(&staged, &backup)
+                    .map_err(|e| file_error(e, &backup, "save the backup binary"))?;
+                println!(
+                    "Previous version saved to {}; run 'manx update --rollback' to restore it.",
+                    backup.display()
+                );
+                Ok(())
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&staged);
+                Err(e)
+            }
+        }
     }
 
-    async fn update_via_cargo(&self) -> Result<()> {
+    /// Swap the running binary with the one saved by the last update
+    ///
+    /// Running it again swaps back, so a rollback can itself be undone.
+    pub fn rollback(&self) -> Result<()> {
+        let exe = current_exe()?;
+        let backup = backup_path(&exe);
+        if !backup.exists() {
+            anyhow::bail!(
+                "No previous version to restore: {} does not exist. A backup is saved each time 'manx update' installs a new version.",
+                backup.display()
+            );
+        }
+
+        let restored_version = binary_version(&backup);
+        swap_with_backup(&exe, &backup)?;
+        self.renderer.print_success(&format!(
+            "Restored manx {} (replaced v{})",
+            restored_version
+                .map(|v| format!("v{}", v))
+                .unwrap_or_else(|| "(unknown version)".to_string()),
+            CURRENT_VERSION
+        ));
+        println!("Run 'manx update --rollback' again to switch back.");
+        Ok(())
+    }
+
+    async fn update_via_cargo(&self, version: &str) -> Result<()> {
         println!("Using cargo to update manx...");
 
         let pb = self
             .renderer
-            .show_progress("Running cargo install manx-cli...");
+            .show_progress(&format!("Running cargo install manx-cli@{}...", version));
 
+        // Pin the version: cargo install skips prereleases unless asked for one
         let output = Command::new("cargo")
-            .args(["install", "manx-cli", "--force"])
+            .args(["install", "manx-cli", "--version", version, "--force"])
             .output()
             .context("Failed to run cargo install. Make sure cargo is installed.")?;
 
@@ -131,16 +202,138 @@ impl SelfUpdater {
     }
 }
 
+fn current_exe() -> Result<PathBuf> {
+    std::env::current_exe().context("Failed to locate the manx binary")
+}
+
+/// Backup kept next to the executable, e.g. `~/.cargo/bin/manx.bak`
+fn backup_path(exe: &Path) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    exe.with_file_name(name)
+}
+
+/// Exchange `exe` and `backup` by renaming, which also works while `exe` is running
+fn swap_with_backup(exe: &Path, backup: &Path) -> Result<()> {
+    let parked = backup.with_extension("bak.swap");
+    fs::rename(exe, &parked).map_err(|e| file_error(e, exe, "move the current binary"))?;
+    if let Err(e) = fs::rename(backup, exe) {
+        // Put the original back so manx keeps working
+        let _ = fs::rename(&parked, exe);
+        return Err(file_error(e, exe, "restore the backup binary"));
+    }
+    fs::rename(&parked, backup).map_err(|e| file_error(e, backup, "keep the replaced binary"))
+}
+
+/// Version printed by another manx binary's `--version`, if it can be run
+fn binary_version(binary: &Path) -> Option<String> {
+    let output = Command::new(binary).arg("--version").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let version = regex::Regex::new(r"\bv?(\d+\.\d+\.\d+)\b").ok()?;
+    version.captures(&text).map(|c| c[1].to_string())
+}
+
+/// Describe a failed file operation, pointing permission errors at the likely fix
+fn file_error(e: std::io::Error, path: &Path, action: &str) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::PermissionDenied {
+        anyhow::anyhow!(
+            "Permission denied trying to {} at {}. Re-run with permission to write to {}.",
+            action,
+            path.display(),
+            path.parent().unwrap_or(path).display()
+        )
+    } else {
+        anyhow::anyhow!("Failed to {} at {}: {}", action, path.display(), e)
+    }
+}
+
+fn is_prerelease(version: &str) -> bool {
+    version.contains('-')
+}
+
+/// Whether `latest` is newer than `current`, following semver precedence
+///
+/// `0.6.0-beta.1` is older than `0.6.0` but newer than `0.5.9`; prerelease
+/// identifiers compare numerically when both are numbers.
 fn version_compare(latest: &str, current: &str) -> Result<bool> {
-    let latest_parts: Vec<u32> = latest.split('.').map(|s| s.parse().unwrap_or(0)).collect();
-    let current_parts: Vec<u32> = current.split('.').map(|s| s.parse().unwrap_or(0)).collect();
+    let parse = |version: &str| {
+        let version = version.split('+').next().unwrap_or(version);
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (version, None),
+        };
+        let mut parts: Vec<u32> = core.split('.').map(|s| s.parse().unwrap_or(0)).collect();
+        parts.resize(3, 0);
+        (parts, pre)
+    };
+    let (latest_core, latest_pre) = parse(latest);
+    let (current_core, current_pre) = parse(current);
+
+    if latest_core != current_core {
+        return Ok(latest_core > current_core);
+    }
+    Ok(match (latest_pre, current_pre) {
+        (None, Some(_)) => true,
+        (Some(latest), Some(current)) => {
+            let ids = |pre: &str| -> Vec<(u64, String)> {
+                pre.split('.')
+                    .map(|id| match id.parse::<u64>() {
+                        // Numeric identifiers sort before alphanumeric ones
+                        Ok(n) => (n, String::new()),
+                        Err(_) => (u64::MAX, id.to_string()),
+                    })
+                    .collect()
+            };
+            ids(&latest) > ids(&current)
+        }
+        _ => false,
+    })
+}
 
-    let max_len = latest_parts.len().max(current_parts.len());
-    let mut latest_padded = latest_parts;
-    let mut current_padded = current_parts;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    latest_padded.resize(max_len, 0);
-    current_padded.resize(max_len, 0);
+    #[test]
+    fn test_channel_selection_and_prerelease_ordering() {
+        let data = CrateData {
+            newest_version: "0.6.0-beta.2".to_string(),
+            max_version: "0.6.0-beta.2".to_string(),
+            max_stable_version: Some("0.5.9".to_string()),
+        };
+        assert_eq!(data.latest_for(UpdateChannel::Stable).unwrap(), "0.5.9");
+        assert_eq!(
+            data.latest_for(UpdateChannel::Prerelease).unwrap(),
+            "0.6.0-beta.2"
+        );
+
+        assert!(version_compare("0.6.0-beta.1", "0.5.9").unwrap());
+        assert!(version_compare("0.6.0", "0.6.0-beta.1").unwrap());
+        assert!(!version_compare("0.6.0-beta.1", "0.6.0").unwrap());
+        assert!(version_compare("0.6.0-beta.10", "0.6.0-beta.2").unwrap());
+        assert!(!version_compare("0.5.7", "0.5.7").unwrap());
+        assert!(version_compare("0.5.10", "0.5.9").unwrap());
+    }
 
-    Ok(latest_padded > current_padded)
+    #[test]
+    fn test_swap_with_backup_exchanges_binaries() {
+        let dir = std::env::temp_dir().join(format!("manx_update_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("manx");
+        fs::write(&exe, "new").unwrap();
+        let backup = backup_path(&exe);
+        assert_eq!(backup, dir.join("manx.bak"));
+        fs::write(&backup, "old").unwrap();
+
+        swap_with_backup(&exe, &backup).unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "new");
+
+        // A missing backup leaves the binary where it was
+        fs::remove_file(&backup).unwrap();
+        assert!(swap_with_backup(&exe, &backup).is_err());
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -83,6 +83,7 @@ mod tests {
             similarity_score: similarity,
             final_score: similarity,
             timestamp: Some(Utc::now()),
+            score_breakdown: None,
         }
     }
 
@@ -1,4 +1,4 @@
-//! Intelligent documentation search with DuckDuckGo + semantic embeddings
+//! Intelligent documentation search with DuckDuckGo (or SearXNG/Brave) + semantic embeddings
 //!
 //! This module provides official-first documentation search that:
 //! - Prioritizes official documentation sites by default
@@ -24,9 +24,19 @@ pub struct WebSearchConfig {
     pub enabled: bool,
     pub max_results: usize,
     pub similarity_threshold: f32,
+    /// Time budget shared by the official and fallback queries of one search
     pub search_timeout_seconds: u64,
     pub user_agent: String,
     pub min_official_results: usize, // Minimum official results before fallback
+    /// Score multipliers per source tier
+    #[serde(default)]
+    pub source_weights: official_sources::SourceWeights,
+    /// Backend queried for raw results
+    #[serde(default)]
+    pub provider: search_engine::SearchProviderConfig,
+    /// Drop results published before this (`--since`); undated results are kept
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
 }
 
 impl Default for WebSearchConfig {
@@ -36,14 +46,16 @@ impl Default for WebSearchConfig {
             max_results: 8,
             similarity_threshold: 0.6,
             search_timeout_seconds: 10,
-            user_agent: "Manx/0.3.5 Documentation Finder (+https://github.com/neur0map/manx)"
-                .to_string(),
+            user_agent: crate::http_client::user_agent(),
             min_official_results: 3,
+            source_weights: official_sources::SourceWeights::default(),
+            provider: search_engine::SearchProviderConfig::default(),
+            since: None,
         }
     }
 }
 
-/// Raw search result from DuckDuckGo
+/// Raw search result from the configured search provider
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawSearchResult {
     pub title: String,
@@ -65,6 +77,9 @@ pub struct ProcessedSearchResult {
     pub similarity_score: f32,
     pub final_score: f32, // Combined similarity + official boost
     pub timestamp: Option<DateTime<Utc>>,
+    /// How `final_score` was reached, for `manx explain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<crate::explain::ScoreBreakdown>,
 }
 
 /// Final documentation search response
@@ -75,6 +90,9 @@ pub struct DocumentationSearchResponse {
     pub results: Vec<ProcessedSearchResult>,
     pub official_results_count: usize,
     pub used_fallback: bool,
+    /// The time budget ran out, so `results` may be incomplete
+    #[serde(default)]
+    pub timed_out: bool,
     pub total_found: usize,
     pub search_time_ms: u64,
     pub sources: Vec<String>,
@@ -94,6 +112,7 @@ pub struct VerificationResult {
 /// Documentation search system
 pub struct DocumentationSearchSystem {
     config: WebSearchConfig,
+    provider: Box<dyn search_engine::SearchProvider>,
     embedding_model: Option<Arc<crate::rag::embeddings::EmbeddingModel>>,
     llm_client: Option<Arc<crate::rag::llm::LlmClient>>,
     official_sources: official_sources::OfficialSourceManager,
@@ -129,6 +148,19 @@ impl DocumentationSearchSystem {
             }
         };
 
+        Self::with_shared_embeddings(config, llm_config, embedding_model)
+    }
+
+    /// Create documentation search system around an already-loaded (shared) embedding model
+    pub fn with_shared_embeddings(
+        config: WebSearchConfig,
+        llm_config: Option<crate::rag::llm::LlmConfig>,
+        embedding_model: Option<Arc<crate::rag::embeddings::EmbeddingModel>>,
+    ) -> Result<Self> {
+        if !config.enabled {
+            return Err(anyhow!("Documentation search is disabled"));
+        }
+
         // Initialize LLM client if configured (with Arc for sharing)
         let llm_client = if let Some(llm_cfg) = llm_config {
             match crate::rag::llm::LlmClient::new(llm_cfg) {
@@ -145,11 +177,27 @@ impl DocumentationSearchSystem {
             None
         };
 
-        let official_sources = official_sources::OfficialSourceManager::new();
+        Self::with_shared_clients(config, llm_client, embedding_model)
+    }
+
+    /// Create documentation search system around an already-created LLM client and embedding model
+    pub fn with_shared_clients(
+        config: WebSearchConfig,
+        llm_client: Option<Arc<crate::rag::llm::LlmClient>>,
+        embedding_model: Option<Arc<crate::rag::embeddings::EmbeddingModel>>,
+    ) -> Result<Self> {
+        if !config.enabled {
+            return Err(anyhow!("Documentation search is disabled"));
+        }
+
+        let official_sources = official_sources::OfficialSourceManager::new()
+            .with_weights(config.source_weights.clone());
         let query_analyzer = query_analyzer::QueryAnalyzer::new();
+        let provider = search_engine::create_provider(&config.provider, &config.user_agent)?;
 
         Ok(Self {
             config,
+            provider,
             embedding_model,
             llm_client,
             official_sources,
@@ -240,13 +288,19 @@ impl DocumentationSearchSystem {
             log::debug!("Using standard search (no LLM configured)");
             self.official_sources.build_official_query(&phrase_query)
         };
-        let mut all_results = search_engine::search_duckduckgo(
-            &official_query,
-            self.config.max_results,
-            &self.config.user_agent,
-            self.config.search_timeout_seconds,
-        )
-        .await?;
+        // One budget covers the official query and the fallback, so a slow network
+        // returns partial results instead of waiting twice
+        let deadline = tokio::time::Instant::now()
+            + std::time::Duration::from_secs(self.config.search_timeout_seconds);
+        let mut all_results = self
+            .provider
+            .search(&official_query, self.config.max_results, deadline)
+            .await?;
+        log::info!(
+            "{} returned {} results",
+            self.provider.name(),
+            all_results.len()
+        );
 
         let mut used_fallback = false;
 
@@ -257,7 +311,9 @@ impl DocumentationSearchSystem {
             .count();
 
         // Step 3: Fallback to general search if insufficient official results
-        if official_results_count < self.config.min_official_results {
+        if official_results_count < self.config.min_official_results
+            && tokio::time::Instant::now() < deadline
+        {
             log::info!(
                 "⚠️ Only {} official results found, expanding search...",
                 official_results_count
@@ -265,21 +321,33 @@ impl DocumentationSearchSystem {
             used_fallback = true;
 
             // Search without site restrictions
-            let fallback_results = search_engine::search_duckduckgo(
-                &phrase_query,
-                self.config.max_results,
-                &self.config.user_agent,
-                self.config.search_timeout_seconds,
-            )
-            .await?;
-
-            // Merge results, avoiding duplicates
-            for result in fallback_results {
-                if !all_results.iter().any(|r| r.url == result.url) {
-                    all_results.push(result);
+            match self
+                .provider
+                .search(&phrase_query, self.config.max_results, deadline)
+                .await
+            {
+                Ok(fallback_results) => {
+                    // Merge results, avoiding duplicates
+                    for result in fallback_results {
+                        if !all_results.iter().any(|r| r.url == result.url) {
+                            all_results.push(result);
+                        }
+                    }
+                }
+                Err(e) if !all_results.is_empty() => {
+                    log::warn!("Fallback search failed, keeping official results: {}", e)
                 }
+                Err(e) => return Err(e),
             }
         }
+        let timed_out = tokio::time::Instant::now() >= deadline;
+        if timed_out {
+            log::warn!(
+                "Search budget of {}s used up with {} results",
+                self.config.search_timeout_seconds,
+                all_results.len()
+            );
+        }
 
         if all_results.is_empty() {
             return Ok(DocumentationSearchResponse {
@@ -288,6 +356,7 @@ impl DocumentationSearchSystem {
                 results: vec![],
                 official_results_count: 0,
                 used_fallback: false,
+                timed_out,
                 total_found: 0,
                 search_time_ms: start_time.elapsed().as_millis() as u64,
                 sources: vec![],
@@ -298,6 +367,7 @@ impl DocumentationSearchSystem {
 
         // Step 4: Process results with enhanced semantic filtering and query analysis
         let mut processed_results = if let Some(ref embedding_model) = self.embedding_model {
+            embedding_model.notify_if_hash();
             result_processor::process_with_embeddings_and_analysis(
                 &query_analysis,
                 &all_results,
@@ -366,6 +436,11 @@ impl DocumentationSearchSystem {
             processed_results = result_processor::deduplicate_results(soft_results);
         }
 
+        // Step 4e: Drop results published before --since
+        if let Some(since) = self.config.since {
+            result_processor::filter_since(&mut processed_results, since);
+        }
+
         // Step 5: LLM verification if available
         let verification_result = if let Some(ref llm_client) = self.llm_client {
             if llm_client.is_available() {
@@ -405,6 +480,7 @@ impl DocumentationSearchSystem {
             results: processed_results,
             official_results_count: final_official_count,
             used_fallback,
+            timed_out,
             total_found: all_results.len(),
             search_time_ms: search_time,
             sources,
@@ -467,7 +543,9 @@ impl DocumentationSearchSystem {
                             modified: r.timestamp.unwrap_or_else(chrono::Utc::now),
                             tags: vec!["documentation".to_string()],
                             language: Some("en".to_string()),
+                            line_range: None,
                         },
+                        score_breakdown: None,
                     })
                     .collect();
 
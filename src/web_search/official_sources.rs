@@ -3,10 +3,19 @@
 //! This module manages the whitelist of official documentation sites
 //! and provides utilities for source classification and query building.
 
-use std::collections::HashMap;
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+/// File in the config directory holding user-added domains and their tiers
+const CUSTOM_DOMAINS_FILE: &str = "official_domains.json";
 
 /// Tier levels for documentation sources
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
 pub enum SourceTier {
     OfficialDocs = 1,     // docs.python.org, reactjs.org
     OfficialRepos = 2,    // github.com/official-orgs
@@ -14,11 +23,61 @@ pub enum SourceTier {
     General = 4,          // Other sources (fallback only)
 }
 
+/// Score multipliers applied to web results by source tier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceWeights {
+    /// Scales every tier's boost: 0 ranks purely by relevance, 2 doubles the preference
+    pub official_boost: f32,
+    pub official_docs: f32,
+    pub official_repos: f32,
+    pub trusted_community: f32,
+    pub general: f32,
+    /// Extra multiplier for results on a detected framework's own site
+    pub framework_match: f32,
+}
+
+impl Default for SourceWeights {
+    fn default() -> Self {
+        Self {
+            official_boost: 1.0,
+            official_docs: 10.0,
+            official_repos: 5.0,
+            trusted_community: 2.0,
+            general: 1.0,
+            framework_match: 1.5,
+        }
+    }
+}
+
+impl SourceWeights {
+    /// Tier weight scaled by `official_boost`; 1.0 means no boost
+    pub fn tier_boost(&self, tier: &SourceTier) -> f32 {
+        let weight = match tier {
+            SourceTier::OfficialDocs => self.official_docs,
+            SourceTier::OfficialRepos => self.official_repos,
+            SourceTier::TrustedCommunity => self.trusted_community,
+            SourceTier::General => self.general,
+        };
+        Self::scaled(weight, self.official_boost)
+    }
+
+    /// Framework site multiplier scaled by `official_boost`
+    pub fn framework_boost(&self) -> f32 {
+        Self::scaled(self.framework_match, self.official_boost)
+    }
+
+    fn scaled(weight: f32, factor: f32) -> f32 {
+        (1.0 + (weight - 1.0) * factor).max(0.0)
+    }
+}
+
 /// Official documentation source manager
 #[derive(Debug)]
 pub struct OfficialSourceManager {
     official_domains: HashMap<String, SourceTier>,
     official_github_orgs: Vec<String>,
+    weights: SourceWeights,
 }
 
 impl Default for OfficialSourceManager {
@@ -28,8 +87,22 @@ impl Default for OfficialSourceManager {
 }
 
 impl OfficialSourceManager {
-    /// Create new official source manager with predefined official sources
+    /// Predefined official sources plus the domains saved in the custom domains file
     pub fn new() -> Self {
+        let mut manager = Self::builtin();
+        match CustomDomains::load() {
+            Ok(custom) => {
+                for (domain, tier) in custom.domains {
+                    manager.add_official_domain(domain, tier);
+                }
+            }
+            Err(e) => log::warn!("Failed to load custom official domains: {}", e),
+        }
+        manager
+    }
+
+    /// Create new official source manager with predefined official sources only
+    pub fn builtin() -> Self {
         let mut official_domains = HashMap::new();
 
         // Language Documentation (Tier 1)
@@ -117,9 +190,16 @@ impl OfficialSourceManager {
         Self {
             official_domains,
             official_github_orgs,
+            weights: SourceWeights::default(),
         }
     }
 
+    /// Score results with these tier weights instead of the defaults
+    pub fn with_weights(mut self, weights: SourceWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
     /// Check if a domain is official documentation
     pub fn is_official_domain(&self, domain: &str) -> bool {
         // Direct domain match
@@ -206,14 +286,14 @@ impl OfficialSourceManager {
         }
     }
 
-    /// Calculate score boost based on source tier
+    /// Calculate score boost based on source tier (10x/5x/2x/1x with default weights)
     pub fn get_score_boost(&self, tier: &SourceTier) -> f32 {
-        match tier {
-            SourceTier::OfficialDocs => 10.0,    // 10x boost for official docs
-            SourceTier::OfficialRepos => 5.0,    // 5x boost for official repos
-            SourceTier::TrustedCommunity => 2.0, // 2x boost for trusted community
-            SourceTier::General => 1.0,          // No boost for general sources
-        }
+        self.weights.tier_boost(tier)
+    }
+
+    /// Extra boost for results on a detected framework's official site
+    pub fn get_framework_boost(&self) -> f32 {
+        self.weights.framework_boost()
     }
 
     /// Get human-readable tier description
@@ -237,6 +317,74 @@ impl OfficialSourceManager {
     }
 }
 
+/// User-added official domains, persisted as JSON in the config directory
+#[derive(Debug)]
+pub struct CustomDomains {
+    path: PathBuf,
+    domains: BTreeMap<String, SourceTier>,
+}
+
+impl CustomDomains {
+    /// Domains saved in the config directory; empty if none were added yet
+    pub fn load() -> Result<Self> {
+        let dir = ProjectDirs::from("", "", "manx")
+            .context("Failed to determine config directory")?
+            .config_dir()
+            .to_path_buf();
+        Self::load_from(dir.join(CUSTOM_DOMAINS_FILE))
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<Self> {
+        let domains = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).context("Failed to read custom official domains"),
+        };
+        Ok(Self { path, domains })
+    }
+
+    /// Add or re-tier a domain; accepts a bare host or a URL. Returns the stored domain.
+    pub fn add(&mut self, domain: &str, tier: SourceTier) -> Result<String> {
+        let domain = normalize_domain(domain)
+            .with_context(|| format!("'{}' is not a valid domain", domain))?;
+        self.domains.insert(domain.clone(), tier);
+        Ok(domain)
+    }
+
+    /// Remove a domain, returning its tier if it was present
+    pub fn remove(&mut self, domain: &str) -> Option<SourceTier> {
+        normalize_domain(domain).and_then(|domain| self.domains.remove(&domain))
+    }
+
+    pub fn domains(&self) -> &BTreeMap<String, SourceTier> {
+        &self.domains
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.domains)?)
+            .context("Failed to write custom official domains")?;
+        Ok(())
+    }
+}
+
+/// Lowercased host of `docs.example.com` or `https://docs.example.com/path`
+fn normalize_domain(input: &str) -> Option<String> {
+    let input = input.trim();
+    let rest = input.split_once("://").map_or(input, |(_, rest)| rest);
+    let host = rest
+        .split(['/', '?', '#', ':'])
+        .next()?
+        .trim_end_matches('.');
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(host.to_lowercase())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -325,4 +473,57 @@ mod tests {
         assert!(query.len() > "python logging".len());
         assert!(query.contains(" OR ") || query.contains("site:github.com"));
     }
+
+    #[test]
+    fn test_source_weights_scale_boosts() {
+        let flat = OfficialSourceManager::builtin().with_weights(SourceWeights {
+            official_boost: 0.0,
+            ..Default::default()
+        });
+        assert_eq!(flat.get_score_boost(&SourceTier::OfficialDocs), 1.0);
+        assert_eq!(flat.get_score_boost(&SourceTier::TrustedCommunity), 1.0);
+        assert_eq!(flat.get_framework_boost(), 1.0);
+
+        let doubled = OfficialSourceManager::builtin().with_weights(SourceWeights {
+            official_boost: 2.0,
+            ..Default::default()
+        });
+        assert_eq!(doubled.get_score_boost(&SourceTier::OfficialDocs), 19.0);
+        assert_eq!(doubled.get_score_boost(&SourceTier::General), 1.0);
+    }
+
+    #[test]
+    fn test_custom_domains_persist() {
+        let dir = std::env::temp_dir().join(format!("manx_domains_{}", uuid::Uuid::new_v4()));
+        let path = dir.join(CUSTOM_DOMAINS_FILE);
+
+        let mut custom = CustomDomains::load_from(path.clone()).unwrap();
+        assert!(custom.domains().is_empty());
+        let added = custom
+            .add(
+                "https://Docs.Internal.Example:8443/start",
+                SourceTier::OfficialDocs,
+            )
+            .unwrap();
+        assert_eq!(added, "docs.internal.example");
+        custom
+            .add("wiki.example.com", SourceTier::TrustedCommunity)
+            .unwrap();
+        assert!(custom.add("  ", SourceTier::OfficialDocs).is_err());
+        custom.save().unwrap();
+
+        let mut reloaded = CustomDomains::load_from(path.clone()).unwrap();
+        assert_eq!(
+            reloaded.domains().get("docs.internal.example"),
+            Some(&SourceTier::OfficialDocs)
+        );
+        assert_eq!(
+            reloaded.remove("wiki.example.com"),
+            Some(SourceTier::TrustedCommunity)
+        );
+        assert_eq!(reloaded.remove("wiki.example.com"), None);
+        assert_eq!(reloaded.domains().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
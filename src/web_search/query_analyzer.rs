@@ -437,7 +437,9 @@ If the current enhanced query is already good, return it as-is."#,
                 modified: chrono::Utc::now(),
                 tags: vec!["query".to_string()],
                 language: Some("en".to_string()),
+                line_range: None,
             },
+            score_breakdown: None,
         }];
 
         match llm_client
@@ -5,11 +5,29 @@
 //! - Applying official source priority boosts
 //! - Ranking results by combined relevance + authority scores
 
+use crate::explain::ScoreBreakdown;
 use crate::rag::embeddings::EmbeddingModel;
 use crate::web_search::official_sources::{OfficialSourceManager, SourceTier};
 use crate::web_search::{query_analyzer, ProcessedSearchResult, RawSearchResult};
 use anyhow::Result;
-use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+
+/// Common words ignored when matching query terms against result text
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "the", "in", "on", "of", "to", "for", "how", "do", "i", "with", "using",
+    "is", "are", "be", "this", "that", "it", "from", "by", "into", "as",
+];
+
+/// Lowercased query words worth matching, with stopwords and repeats removed
+pub fn significant_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.to_lowercase().split_whitespace() {
+        if !STOPWORDS.contains(&word) && !terms.iter().any(|term| term == word) {
+            terms.push(word.to_string());
+        }
+    }
+    terms
+}
 
 fn extract_key_phrase(query: &str) -> Option<String> {
     let q = query.to_lowercase();
@@ -88,19 +106,27 @@ pub async fn process_with_embeddings_and_analysis(
         // Calculate semantic similarity
         let mut similarity_score =
             EmbeddingModel::cosine_similarity(&query_embedding, &result_embedding);
+        let mut breakdown = ScoreBreakdown::new("embedding", similarity_score);
 
         // Phrase priority: boost if key phrase is present, penalize if absent for example-type queries
         if let Some(ref phrase) = key_phrase {
             let haystack = combined_text.to_lowercase();
             if haystack.contains(phrase) {
-                similarity_score *= 1.2; // modest boost for phrase match
+                similarity_score = (similarity_score * 1.2).min(1.0); // modest boost for phrase match
+                breakdown.step(
+                    format!("key phrase '{}' found x1.2", phrase),
+                    similarity_score,
+                );
             } else if matches!(
                 query_analysis.query_type,
                 query_analyzer::QueryType::Example | query_analyzer::QueryType::HowTo
             ) {
                 similarity_score *= 0.85; // mild penalty if example-ish query lacks phrase
+                breakdown.step(
+                    format!("key phrase '{}' missing x0.85", phrase),
+                    similarity_score,
+                );
             }
-            similarity_score = similarity_score.min(1.0);
         }
 
         // Apply framework-specific similarity threshold adjustment
@@ -139,7 +165,7 @@ pub async fn process_with_embeddings_and_analysis(
                 .iter()
                 .any(|site| result.source_domain.contains(site))
             {
-                source_boost *= 1.5; // Extra framework match boost
+                source_boost *= official_sources.get_framework_boost(); // Extra framework match boost
                 log::debug!("Applied framework domain boost for {}", framework.name);
             }
         }
@@ -154,6 +180,17 @@ pub async fn process_with_embeddings_and_analysis(
 
         // Calculate final score with all boosts
         let final_score = similarity_score * source_boost * type_boost;
+        breakdown.source_boost = Some(source_boost);
+        breakdown.step(
+            format!("{:?} source boost x{:.2}", source_tier, source_boost),
+            similarity_score * source_boost,
+        );
+        if type_boost != 1.0 {
+            breakdown.step(
+                format!("{:?} query x{:.2}", query_analysis.query_type, type_boost),
+                final_score,
+            );
+        }
 
         processed_results.push(ProcessedSearchResult {
             title: result.title.clone(),
@@ -165,6 +202,7 @@ pub async fn process_with_embeddings_and_analysis(
             similarity_score,
             final_score,
             timestamp: result.timestamp,
+            score_breakdown: Some(breakdown),
         });
 
         log::debug!(
@@ -265,20 +303,8 @@ pub fn process_without_embeddings(
         raw_results.len()
     );
 
-    let query_lower = query.to_lowercase();
     // Basic stopword filtering to avoid inflating scores with common words
-    let stopwords: HashSet<&str> = [
-        "a", "an", "and", "the", "in", "on", "of", "to", "for", "how", "do", "i", "with", "using",
-        "is", "are", "be", "this", "that", "it", "from", "by", "into", "as",
-    ]
-    .into_iter()
-    .collect();
-
-    let mut query_words: Vec<&str> = query_lower
-        .split_whitespace()
-        .filter(|w| !stopwords.contains(*w))
-        .collect();
-    query_words.dedup();
+    let query_words = significant_terms(query);
     let key_phrase = extract_key_phrase(query);
     let mut processed_results = Vec::new();
 
@@ -298,13 +324,22 @@ pub fn process_without_embeddings(
         } else {
             word_matches as f32 / query_words.len() as f32
         };
+        let mut breakdown = ScoreBreakdown::new("word match", similarity_score);
 
         // Phrase priority/penalty
         if let Some(ref phrase) = key_phrase {
             if combined_text.contains(phrase) {
                 similarity_score = (similarity_score + 0.2).min(1.0);
+                breakdown.step(
+                    format!("key phrase '{}' found +0.2", phrase),
+                    similarity_score,
+                );
             } else {
                 similarity_score = (similarity_score - 0.1).max(0.0);
+                breakdown.step(
+                    format!("key phrase '{}' missing -0.1", phrase),
+                    similarity_score,
+                );
             }
         }
 
@@ -325,6 +360,11 @@ pub fn process_without_embeddings(
 
         // Calculate final score
         let final_score = similarity_score * source_boost;
+        breakdown.source_boost = Some(source_boost);
+        breakdown.step(
+            format!("{:?} source boost x{:.2}", source_tier, source_boost),
+            final_score,
+        );
 
         processed_results.push(ProcessedSearchResult {
             title: result.title.clone(),
@@ -336,6 +376,7 @@ pub fn process_without_embeddings(
             similarity_score,
             final_score,
             timestamp: result.timestamp,
+            score_breakdown: Some(breakdown),
         });
     }
 
@@ -404,6 +445,28 @@ pub fn filter_quality_results(
         .collect()
 }
 
+/// Drop results published before `since`, then rank dated results above undated ones
+///
+/// Results without a timestamp are kept since most providers don't report dates. Within
+/// each group results stay ordered by score, with newer results first on equal scores.
+pub fn filter_since(results: &mut Vec<ProcessedSearchResult>, since: DateTime<Utc>) {
+    let before = results.len();
+    results.retain(|result| result.timestamp.is_none_or(|published| published >= since));
+    log::info!(
+        "Dropped {} results published before {}",
+        before - results.len(),
+        since.format("%Y-%m-%d")
+    );
+
+    results.sort_by(|a, b| {
+        b.timestamp
+            .is_some()
+            .cmp(&a.timestamp.is_some())
+            .then_with(|| b.final_score.total_cmp(&a.final_score))
+            .then_with(|| b.timestamp.cmp(&a.timestamp))
+    });
+}
+
 /// Deduplicate results based on content similarity
 pub fn deduplicate_results(
     mut processed_results: Vec<ProcessedSearchResult>,
@@ -510,6 +573,33 @@ mod tests {
         assert!(results[0].final_score > results[1].final_score);
     }
 
+    #[test]
+    fn test_filter_since() {
+        let result = |title: &str, score: f32, days_old: Option<i64>| ProcessedSearchResult {
+            title: title.to_string(),
+            url: format!("https://example.com/{}", title),
+            snippet: String::new(),
+            source_domain: "example.com".to_string(),
+            is_official: false,
+            source_tier: 4,
+            similarity_score: score,
+            final_score: score,
+            timestamp: days_old.map(|days| Utc::now() - chrono::Duration::days(days)),
+            score_breakdown: None,
+        };
+        let mut results = vec![
+            result("undated", 0.9, None),
+            result("stale", 0.9, Some(800)),
+            result("older", 0.7, Some(200)),
+            result("newer", 0.7, Some(10)),
+            result("best", 0.8, Some(100)),
+        ];
+
+        filter_since(&mut results, Utc::now() - chrono::Duration::days(365));
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, ["best", "newer", "older", "undated"]);
+    }
+
     #[test]
     fn test_filter_quality_results() {
         let results = vec![
@@ -523,6 +613,7 @@ mod tests {
                 similarity_score: 0.8,
                 final_score: 0.8,
                 timestamp: Some(Utc::now()),
+                score_breakdown: None,
             },
             ProcessedSearchResult {
                 title: "Short Result".to_string(),
@@ -534,6 +625,7 @@ mod tests {
                 similarity_score: 0.5,
                 final_score: 0.5,
                 timestamp: Some(Utc::now()),
+                score_breakdown: None,
             },
         ];
 
@@ -1,13 +1,315 @@
-//! DuckDuckGo search engine integration
+//! Web search backends
 //!
-//! This module provides anonymous, privacy-focused web search using DuckDuckGo's API.
-//! No user data is logged or transmitted beyond the search query itself.
+//! DuckDuckGo is the default: anonymous, privacy-focused web search with no user data
+//! logged or transmitted beyond the search query itself. A self-hosted SearXNG instance
+//! or the Brave Search API can be configured instead through `SearchProvider`.
 
 use crate::web_search::RawSearchResult;
-use anyhow::{anyhow, Result};
-use chrono::Utc;
-use serde::Deserialize;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use tokio::time::Instant;
+
+/// Upper bound on establishing a connection, independent of the search deadline
+const CONNECT_TIMEOUT_SECS: u64 = 10;
+const BRAVE_API_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+/// Most results the Brave API returns per request
+const BRAVE_MAX_COUNT: usize = 20;
+
+/// A web search backend returning raw results for the scoring pipeline
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    /// Results gathered before `deadline`; a request still running then is abandoned and
+    /// whatever arrived so far is returned instead of an error
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        deadline: Instant,
+    ) -> Result<Vec<RawSearchResult>>;
+
+    /// Name shown in logs and search statistics
+    fn name(&self) -> &'static str;
+}
+
+/// Which backend `manx search` queries, stored in the config file
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SearchProviderConfig {
+    #[default]
+    DuckDuckGo,
+    /// SearXNG instance with the JSON output format enabled
+    Searxng { endpoint: String },
+    /// Brave Search API; requires a subscription token
+    Brave { api_key: Option<String> },
+}
+
+impl std::str::FromStr for SearchProviderConfig {
+    type Err = anyhow::Error;
+
+    /// Parse `duckduckgo`, `searxng:<url>` or `brave`
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+        match kind.to_lowercase().as_str() {
+            "duckduckgo" | "ddg" => Ok(Self::DuckDuckGo),
+            "searxng" if !value.is_empty() => Ok(Self::Searxng {
+                endpoint: value.trim_end_matches('/').to_string(),
+            }),
+            "searxng" => Err(anyhow!(
+                "SearXNG needs the instance URL, e.g. searxng:https://search.example.com"
+            )),
+            "brave" => Ok(Self::Brave { api_key: None }),
+            _ => Err(anyhow!(
+                "Unknown search provider '{}'. Use duckduckgo, searxng:<url> or brave",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for SearchProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuckDuckGo => write!(f, "duckduckgo"),
+            Self::Searxng { endpoint } => write!(f, "searxng:{}", endpoint),
+            Self::Brave { .. } => write!(f, "brave"),
+        }
+    }
+}
+
+// Debug output goes through Display so the Brave API key never ends up in logs
+impl std::fmt::Debug for SearchProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// Build the configured backend with a shared HTTP client
+///
+/// Time limits come from the deadline passed to each search rather than the client.
+pub fn create_provider(
+    config: &SearchProviderConfig,
+    user_agent: &str,
+) -> Result<Box<dyn SearchProvider>> {
+    let client = crate::http_client::builder()
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .user_agent(user_agent)
+        .build()?;
+
+    Ok(match config {
+        SearchProviderConfig::DuckDuckGo => Box::new(DuckDuckGo { client }),
+        SearchProviderConfig::Searxng { endpoint } => Box::new(Searxng {
+            endpoint: endpoint.clone(),
+            client,
+        }),
+        SearchProviderConfig::Brave { api_key } => Box::new(Brave {
+            api_key: api_key
+                .clone()
+                .context("Brave Search needs an API key: manx config --search-api-key <KEY>")?,
+            client,
+        }),
+    })
+}
+
+pub struct DuckDuckGo {
+    client: reqwest::Client,
+}
+
+/// Send a request and read its body, giving up at `deadline`
+///
+/// `Ok(None)` means the deadline passed first.
+async fn fetch_before(
+    request: reqwest::RequestBuilder,
+    deadline: Instant,
+) -> Result<Option<(reqwest::StatusCode, String)>> {
+    let fetch = async {
+        let response = request.send().await?;
+        let status = response.status();
+        Ok::<_, reqwest::Error>((status, response.text().await?))
+    };
+    match tokio::time::timeout_at(deadline, fetch).await {
+        Ok(Ok(fetched)) => Ok(Some(fetched)),
+        Ok(Err(e)) if e.is_timeout() => Ok(None),
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => Ok(None),
+    }
+}
+
+#[async_trait]
+impl SearchProvider for DuckDuckGo {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        deadline: Instant,
+    ) -> Result<Vec<RawSearchResult>> {
+        search_duckduckgo(query, max_results, &self.client, deadline).await
+    }
+
+    fn name(&self) -> &'static str {
+        "DuckDuckGo"
+    }
+}
+
+pub struct Searxng {
+    endpoint: String,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResult {
+    url: String,
+    title: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, rename = "publishedDate")]
+    published_date: Option<String>,
+}
+
+#[async_trait]
+impl SearchProvider for Searxng {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        deadline: Instant,
+    ) -> Result<Vec<RawSearchResult>> {
+        if query.trim().is_empty() {
+            return Err(anyhow!("Search query cannot be empty"));
+        }
+        log::info!(
+            "Searching SearXNG at {} with query: {}",
+            self.endpoint,
+            query
+        );
+
+        let request = self
+            .client
+            .get(format!("{}/search", self.endpoint))
+            .query(&[("q", query), ("format", "json")]);
+        let Some((status, body)) = fetch_before(request, deadline)
+            .await
+            .context("Failed to reach SearXNG")?
+        else {
+            log::warn!("SearXNG search timed out");
+            return Ok(Vec::new());
+        };
+        if !status.is_success() {
+            return Err(anyhow!(
+                "SearXNG search failed with status: {} (is the json format enabled?)",
+                status
+            ));
+        }
+        parse_searxng(&body, max_results)
+    }
+
+    fn name(&self) -> &'static str {
+        "SearXNG"
+    }
+}
+
+/// Results from a SearXNG `format=json` response, skipping entries with unusable URLs
+fn parse_searxng(body: &str, max_results: usize) -> Result<Vec<RawSearchResult>> {
+    let response: SearxngResponse =
+        serde_json::from_str(body).context("Invalid SearXNG response")?;
+    Ok(response
+        .results
+        .into_iter()
+        .filter_map(|r| {
+            let mut result = create_raw_result(&r.title, &r.url, &r.content).ok()?;
+            result.timestamp = r.published_date.as_deref().and_then(parse_published);
+            Some(result)
+        })
+        .take(max_results)
+        .collect())
+}
+
+pub struct Brave {
+    api_key: String,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveResponse {
+    web: Option<BraveWebResults>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveWebResults {
+    results: Vec<BraveResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BraveResult {
+    url: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    page_age: Option<String>,
+}
+
+#[async_trait]
+impl SearchProvider for Brave {
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        deadline: Instant,
+    ) -> Result<Vec<RawSearchResult>> {
+        if query.trim().is_empty() {
+            return Err(anyhow!("Search query cannot be empty"));
+        }
+        log::info!("Searching Brave with query: {}", query);
+
+        let count = max_results.clamp(1, BRAVE_MAX_COUNT).to_string();
+        let request = self
+            .client
+            .get(BRAVE_API_URL)
+            .query(&[("q", query), ("count", count.as_str())])
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key);
+        let Some((status, body)) = fetch_before(request, deadline)
+            .await
+            .context("Failed to reach Brave Search")?
+        else {
+            log::warn!("Brave search timed out");
+            return Ok(Vec::new());
+        };
+        if !status.is_success() {
+            return Err(anyhow!("Brave search failed with status: {}", status));
+        }
+        parse_brave(&body, max_results)
+    }
+
+    fn name(&self) -> &'static str {
+        "Brave"
+    }
+}
+
+/// Web results from a Brave Search API response
+fn parse_brave(body: &str, max_results: usize) -> Result<Vec<RawSearchResult>> {
+    let response: BraveResponse = serde_json::from_str(body).context("Invalid Brave response")?;
+    Ok(response
+        .web
+        .map(|web| web.results)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|r| {
+            let mut result = create_raw_result(&r.title, &r.url, &r.description).ok()?;
+            result.timestamp = r.page_age.as_deref().and_then(parse_published);
+            Some(result)
+        })
+        .take(max_results)
+        .collect())
+}
 
 /// DuckDuckGo search response structure
 #[derive(Debug, Deserialize)]
@@ -35,11 +337,11 @@ struct RelatedTopic {
 }
 
 /// Search DuckDuckGo for documentation results
-pub async fn search_duckduckgo(
+async fn search_duckduckgo(
     query: &str,
     max_results: usize,
-    user_agent: &str,
-    timeout_seconds: u64,
+    client: &reqwest::Client,
+    deadline: Instant,
 ) -> Result<Vec<RawSearchResult>> {
     if query.trim().is_empty() {
         return Err(anyhow!("Search query cannot be empty"));
@@ -48,24 +350,19 @@ pub async fn search_duckduckgo(
     log::info!("Searching DuckDuckGo with query: {}", query);
 
     // Use DuckDuckGo's Instant Answer API for initial results
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(timeout_seconds))
-        .user_agent(user_agent)
-        .build()?;
-
     // DuckDuckGo Instant Answer API
     let instant_api_url = format!(
         "https://api.duckduckgo.com/?q={}&format=json&no_html=1&skip_disambig=1",
         urlencoding::encode(query)
     );
 
-    let instant_response = client.get(&instant_api_url).send().await;
+    let instant_response = fetch_before(client.get(&instant_api_url), deadline).await;
     let mut results = Vec::new();
 
     // Try instant answers first
-    if let Ok(response) = instant_response {
-        if response.status().is_success() {
-            if let Ok(ddg_response) = response.json::<DuckDuckGoResponse>().await {
+    if let Ok(Some((status, body))) = instant_response {
+        if status.is_success() {
+            if let Ok(ddg_response) = serde_json::from_str::<DuckDuckGoResponse>(&body) {
                 // Process direct results
                 for result in ddg_response.results.into_iter().take(max_results / 2) {
                     if !result.text.is_empty() && !result.first_url.is_empty() {
@@ -96,9 +393,12 @@ pub async fn search_duckduckgo(
     // If we don't have enough results from instant API, try HTML search
     if results.len() < max_results {
         log::info!("Expanding search with HTML scraping");
-        let html_results =
-            search_duckduckgo_html(query, max_results - results.len(), &client).await?;
-        results.extend(html_results);
+        match search_duckduckgo_html(query, max_results - results.len(), client, deadline).await {
+            Ok(html_results) => results.extend(html_results),
+            // Keep the instant answers rather than failing the whole search
+            Err(e) if !results.is_empty() => log::warn!("DuckDuckGo HTML search failed: {}", e),
+            Err(e) => return Err(e),
+        }
     }
 
     // Remove duplicates based on URL
@@ -117,6 +417,7 @@ async fn search_duckduckgo_html(
     query: &str,
     max_results: usize,
     client: &reqwest::Client,
+    deadline: Instant,
 ) -> Result<Vec<RawSearchResult>> {
     // DuckDuckGo HTML search URL
     let search_url = format!(
@@ -124,16 +425,15 @@ async fn search_duckduckgo_html(
         urlencoding::encode(query)
     );
 
-    let response = client.get(&search_url).send().await?;
+    let Some((status, html)) = fetch_before(client.get(&search_url), deadline).await? else {
+        log::warn!("DuckDuckGo HTML search timed out");
+        return Ok(Vec::new());
+    };
 
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "DuckDuckGo search failed with status: {}",
-            response.status()
-        ));
+    if !status.is_success() {
+        return Err(anyhow!("DuckDuckGo search failed with status: {}", status));
     }
 
-    let html = response.text().await?;
     parse_duckduckgo_html(&html, max_results)
 }
 
@@ -197,7 +497,27 @@ fn parse_duckduckgo_html(html: &str, max_results: usize) -> Result<Vec<RawSearch
     Ok(results)
 }
 
+/// A publication date as search APIs report it: RFC 3339, a bare datetime or a date
+fn parse_published(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(date.and_utc());
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        return Some(date.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
 /// Create a RawSearchResult with proper URL validation and domain extraction
+///
+/// The timestamp is left empty; only providers that report publication dates fill it in.
 fn create_raw_result(title: &str, url: &str, snippet: &str) -> Result<RawSearchResult> {
     // Clean and validate URL
     let cleaned_url = clean_url(url)?;
@@ -212,7 +532,7 @@ fn create_raw_result(title: &str, url: &str, snippet: &str) -> Result<RawSearchR
         url: cleaned_url,
         snippet: clean_snippet,
         source_domain: domain,
-        timestamp: Some(Utc::now()),
+        timestamp: None,
     })
 }
 
@@ -306,6 +626,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_search_provider_config_parsing() {
+        assert_eq!(
+            "duckduckgo".parse::<SearchProviderConfig>().unwrap(),
+            SearchProviderConfig::DuckDuckGo
+        );
+        assert_eq!(
+            "searxng:https://search.example.com/"
+                .parse::<SearchProviderConfig>()
+                .unwrap(),
+            SearchProviderConfig::Searxng {
+                endpoint: "https://search.example.com".to_string()
+            }
+        );
+        assert!("searxng".parse::<SearchProviderConfig>().is_err());
+        assert!("bing".parse::<SearchProviderConfig>().is_err());
+        assert!(create_provider(&SearchProviderConfig::Brave { api_key: None }, "manx").is_err());
+    }
+
+    #[test]
+    fn test_parse_provider_responses() {
+        let searxng = r#"{"results": [
+            {"url": "https://docs.rs/tokio", "title": "tokio - Rust", "content": "An async runtime", "publishedDate": "2024-03-01T10:00:00"},
+            {"url": "not a url at all", "title": "Broken"},
+            {"url": "https://tokio.rs/tokio/tutorial", "title": "Tutorial", "content": ""}
+        ]}"#;
+        let results = parse_searxng(searxng, 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].source_domain, "docs.rs");
+        assert_eq!(results[0].snippet, "An async runtime");
+        assert_eq!(
+            results[0].timestamp.unwrap().to_rfc3339(),
+            "2024-03-01T10:00:00+00:00"
+        );
+        assert_eq!(results[1].timestamp, None);
+        assert_eq!(parse_searxng(searxng, 1).unwrap().len(), 1);
+
+        let brave = r#"{"web": {"results": [
+            {"url": "https://doc.rust-lang.org/book/", "title": "The <strong>Rust</strong> Book", "description": "Learn Rust", "page_age": "2023-11-20"}
+        ]}}"#;
+        let results = parse_brave(brave, 10).unwrap();
+        assert_eq!(results[0].title, "The Rust Book");
+        assert!(results[0].timestamp.is_some());
+        assert!(parse_published("2024-03-01T10:00:00Z").is_some());
+        assert!(parse_published("last week").is_none());
+        assert!(parse_brave(r#"{"query": {}}"#, 10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_gives_up_at_deadline() {
+        // A server that accepts the connection but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+
+        let client = reqwest::Client::new();
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let fetched = fetch_before(client.get(format!("http://{}/", addr)), deadline)
+            .await
+            .unwrap();
+        assert!(fetched.is_none());
+        assert!(Instant::now() < deadline + Duration::from_secs(2));
+    }
+
     #[test]
     fn test_clean_html_text() {
         assert_eq!(
@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Answers for `manx init --non-interactive`, read from `--from <file>` and the answer flags
+///
+/// A missing answer keeps the current setting, as "Keep current setting" does in the wizard.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetupAnswers {
+    /// Context7 API key; an empty string removes the saved one
+    pub context7_api_key: Option<String>,
+    /// Provider spec as for `manx embedding set`: `hash`, `onnx:<model>`, `openai:<model>`, ...
+    pub embedding_provider: Option<String>,
+    /// API key for a hosted embedding provider
+    pub embedding_api_key: Option<String>,
+    /// AI provider: `openai`, `anthropic`, `groq` or `zai`
+    pub llm_provider: Option<String>,
+    pub llm_api_key: Option<String>,
+    /// Model for the AI provider; defaults to the one the wizard recommends
+    pub llm_model: Option<String>,
+}
+
+impl SetupAnswers {
+    /// Read answers from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read setup answers from {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid setup answers in {}", path.display()))
+    }
+
+    /// These answers with any set in `overrides` replacing them
+    pub fn merge(self, overrides: SetupAnswers) -> Self {
+        Self {
+            context7_api_key: overrides.context7_api_key.or(self.context7_api_key),
+            embedding_provider: overrides.embedding_provider.or(self.embedding_provider),
+            embedding_api_key: overrides.embedding_api_key.or(self.embedding_api_key),
+            llm_provider: overrides.llm_provider.or(self.llm_provider),
+            llm_api_key: overrides.llm_api_key.or(self.llm_api_key),
+            llm_model: overrides.llm_model.or(self.llm_model),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_answers_load_and_merge() {
+        let path = std::env::temp_dir().join(format!("manx_answers_{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"{"embedding_provider": "hash", "llm_provider": "groq", "llm_api_key": "gsk_from_file"}"#,
+        )
+        .unwrap();
+        let answers = SetupAnswers::load(&path).unwrap().merge(SetupAnswers {
+            llm_api_key: Some("gsk_from_flag".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(answers.embedding_provider.as_deref(), Some("hash"));
+        assert_eq!(answers.llm_provider.as_deref(), Some("groq"));
+        assert_eq!(answers.llm_api_key.as_deref(), Some("gsk_from_flag"));
+        assert!(answers.context7_api_key.is_none());
+
+        // Misspelled answers are rejected instead of silently ignored
+        std::fs::write(&path, r#"{"llm_key": "gsk_typo"}"#).unwrap();
+        assert!(SetupAnswers::load(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
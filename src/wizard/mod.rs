@@ -2,6 +2,7 @@ use anyhow::Result;
 use console::{style, Term};
 use dialoguer::theme::ColorfulTheme;
 
+mod answers;
 mod navigation;
 mod prompts;
 mod steps;
@@ -10,10 +11,14 @@ mod validators;
 
 use crate::config::Config;
 
+pub use answers::SetupAnswers;
+
 pub struct SetupWizard {
     term: Term,
     theme: ColorfulTheme,
     config: Config,
+    /// Config as saved before this run, to show what the wizard changes
+    saved: Config,
 }
 
 impl SetupWizard {
@@ -25,6 +30,7 @@ impl SetupWizard {
         Ok(Self {
             term,
             theme,
+            saved: config.clone(),
             config,
         })
     }
@@ -76,7 +82,9 @@ impl SetupWizard {
                 }
                 WizardStep::Summary => {
                     navigation::show_step_header(&current_step);
-                    let action = steps::summary::show_and_test(&self.config, &self.theme).await?;
+                    let action =
+                        steps::summary::show_and_test(&self.config, &self.saved, &self.theme)
+                            .await?;
                     current_step = self.handle_navigation_action(action, &current_step)?;
                     if current_step == WizardStep::Complete {
                         break;
@@ -97,6 +105,37 @@ impl SetupWizard {
         Ok(())
     }
 
+    /// Configure from `answers` without prompts, for containers and other machines without a TTY
+    ///
+    /// Each step validates its answers as the interactive wizard does; the first failure is
+    /// returned naming its step, and nothing is saved.
+    pub async fn run_non_interactive(&mut self, answers: &SetupAnswers) -> Result<()> {
+        self.config = self.apply_answers(answers).await?;
+        self.config.save()?;
+        println!(
+            "{} Configuration saved to ~/.config/manx/config.json",
+            style("Setup complete!").green().bold()
+        );
+        Ok(())
+    }
+
+    /// The current config with every step's answers applied
+    async fn apply_answers(&self, answers: &SetupAnswers) -> Result<Config> {
+        use anyhow::Context;
+
+        let mut config = self.config.clone();
+        steps::context7::apply(&mut config, answers)
+            .await
+            .context("Setup failed at the Context7 step")?;
+        steps::embeddings::apply(&mut config, answers)
+            .await
+            .context("Setup failed at the search engine step")?;
+        steps::llm::apply(&mut config, answers)
+            .await
+            .context("Setup failed at the AI features step")?;
+        Ok(config)
+    }
+
     fn handle_navigation_action(
         &self,
         action: navigation::WizardAction,
@@ -196,3 +235,36 @@ impl SetupWizard {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failed_step_is_named_and_config_kept() {
+        let mut config = Config::default();
+        config.rag.embedding.dimension = 768;
+        let mut wizard = SetupWizard {
+            term: Term::stdout(),
+            theme: themes::create_theme(),
+            saved: config.clone(),
+            config,
+        };
+        let before = serde_json::to_value(&wizard.config).unwrap();
+        let answers = SetupAnswers {
+            embedding_provider: Some("hash".to_string()),
+            llm_provider: Some("zai".to_string()),
+            llm_api_key: Some("short".to_string()),
+            ..Default::default()
+        };
+
+        let error = wizard.run_non_interactive(&answers).await.unwrap_err();
+        assert_eq!(error.to_string(), "Setup failed at the AI features step");
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Invalid Z.AI API key format"
+        );
+        // The search engine step set a 384 dimension, but a failed run leaves the config as it was
+        assert_eq!(serde_json::to_value(&wizard.config).unwrap(), before);
+    }
+}
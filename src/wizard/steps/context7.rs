@@ -3,7 +3,22 @@ use console::style;
 use dialoguer::theme::ColorfulTheme;
 
 use crate::config::Config;
-use crate::wizard::{navigation::WizardAction, prompts, validators};
+use crate::wizard::{answers::SetupAnswers, navigation::WizardAction, prompts, validators};
+
+/// Apply the Context7 answer without prompting, validating the key as `setup` does
+pub async fn apply(config: &mut Config, answers: &SetupAnswers) -> Result<()> {
+    match answers.context7_api_key.as_deref() {
+        None => {}
+        Some("") => config.api_key = None,
+        Some(api_key) => {
+            if !validators::test_context7_api(api_key).await? {
+                anyhow::bail!("Invalid Context7 API key format");
+            }
+            config.api_key = Some(api_key.to_string());
+        }
+    }
+    Ok(())
+}
 
 pub async fn setup(config: &mut Config, theme: &ColorfulTheme) -> Result<WizardAction> {
     loop {
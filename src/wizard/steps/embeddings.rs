@@ -1,11 +1,51 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use console::style;
 use dialoguer::theme::ColorfulTheme;
-use spinoff::{spinners, Color, Spinner};
 
 use crate::config::Config;
 use crate::rag::EmbeddingProvider;
-use crate::wizard::navigation::WizardAction;
+use crate::wizard::{answers::SetupAnswers, navigation::WizardAction};
+
+/// Apply the search engine answer without prompting, downloading an ONNX model if needed
+/// and checking the provider answers before it is kept
+pub async fn apply(config: &mut Config, answers: &SetupAnswers) -> Result<()> {
+    if let Some(api_key) = &answers.embedding_api_key {
+        config.rag.embedding.api_key = Some(api_key.clone());
+    }
+    let Some(spec) = answers.embedding_provider.as_deref() else {
+        return Ok(());
+    };
+    let provider = Config::parse_embedding_provider(spec)?;
+
+    if provider == EmbeddingProvider::Hash {
+        config.rag.embedding.provider = EmbeddingProvider::Hash;
+        config.rag.embedding.dimension = 384;
+        return Ok(());
+    }
+    if let EmbeddingProvider::Onnx(model_name) = &provider {
+        use crate::rag::model_metadata::ModelMetadataManager;
+        use crate::rag::providers::onnx::OnnxProvider;
+
+        let installed = ModelMetadataManager::new()?.get_model(model_name).is_some();
+        if !installed {
+            println!("Downloading {}...", model_name);
+            OnnxProvider::download_model(model_name, false)
+                .await
+                .with_context(|| format!("Failed to download {}", model_name))?;
+        }
+        config.rag.embedding.model_path = ModelMetadataManager::new()?
+            .get_model(model_name)
+            .and_then(|metadata| metadata.model_path.clone());
+    }
+
+    config.rag.embedding.provider = provider;
+    config
+        .rag
+        .embedding
+        .detect_and_update_dimension()
+        .await
+        .with_context(|| format!("Search engine test failed for {}", spec))
+}
 
 pub async fn setup(config: &mut Config, theme: &ColorfulTheme) -> Result<WizardAction> {
     println!();
@@ -239,21 +279,15 @@ async fn download_and_configure_model(config: &mut Config, model_name: &str) ->
     println!("  {} config.json (model configuration)", style("3.").dim());
     println!();
 
-    let mut spinner = Spinner::new(
-        spinners::Dots12,
-        format!("Downloading {} {}...", model_name, size_desc),
-        Color::Cyan,
-    );
-
-    // Use the ONNX provider to download
+    // The provider draws a progress bar per file and verifies each download
     use crate::rag::providers::onnx::OnnxProvider;
 
     match OnnxProvider::download_model(model_name, false).await {
         Ok(()) => {
-            spinner.success(&format!(
+            println!(
                 "{} Neural search model installed successfully!",
                 style("OK").green().bold()
-            ));
+            );
 
             // Configure the provider
             config.rag.embedding.provider = EmbeddingProvider::Onnx(model_name.to_string());
@@ -311,11 +345,7 @@ async fn download_and_configure_model(config: &mut Config, model_name: &str) ->
             );
         }
         Err(e) => {
-            spinner.fail(&format!(
-                "{} Download failed: {}",
-                style("Error").red().bold(),
-                e
-            ));
+            println!("{} Download failed: {}", style("Error").red().bold(), e);
 
             println!();
             println!(
@@ -4,7 +4,52 @@ use dialoguer::theme::ColorfulTheme;
 
 use crate::config::Config;
 use crate::rag::llm::LlmProvider;
-use crate::wizard::{navigation::WizardAction, validators};
+use crate::wizard::{answers::SetupAnswers, navigation::WizardAction, validators};
+
+/// Apply the AI provider answers without prompting, validating the key as `setup` does
+pub async fn apply(config: &mut Config, answers: &SetupAnswers) -> Result<()> {
+    let Some(provider) = answers.llm_provider.as_deref() else {
+        if answers.llm_api_key.is_some() || answers.llm_model.is_some() {
+            anyhow::bail!("llm_provider is required with llm_api_key or llm_model");
+        }
+        return Ok(());
+    };
+    let Some(choice) = PROVIDERS
+        .iter()
+        .find(|choice| choice.key == provider.to_lowercase())
+    else {
+        anyhow::bail!(
+            "Unknown AI provider '{}' (expected openai, anthropic, groq or zai)",
+            provider
+        );
+    };
+    let name = choice.name;
+    let Some(api_key) = answers.llm_api_key.clone() else {
+        anyhow::bail!("llm_api_key is required for {}", name);
+    };
+    let valid = match choice.provider {
+        LlmProvider::Zai => valid_zai_key(&api_key),
+        _ => validators::test_llm_api(name, &api_key).await?,
+    };
+    if !valid {
+        anyhow::bail!("Invalid {} API key format", name);
+    }
+
+    match choice.provider {
+        LlmProvider::OpenAI => config.llm.openai_api_key = Some(api_key),
+        LlmProvider::Anthropic => config.llm.anthropic_api_key = Some(api_key),
+        LlmProvider::Groq => config.llm.groq_api_key = Some(api_key),
+        _ => config.llm.zai_api_key = Some(api_key),
+    }
+    config.llm.preferred_provider = choice.provider.clone();
+    config.llm.model_name = Some(
+        answers
+            .llm_model
+            .clone()
+            .unwrap_or_else(|| choice.models[choice.recommended].0.to_string()),
+    );
+    Ok(())
+}
 
 pub async fn setup(config: &mut Config, theme: &ColorfulTheme) -> Result<WizardAction> {
     println!();
@@ -142,7 +187,7 @@ fn setup_openai(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     }
 
     // Select OpenAI model
-    let model = select_openai_model(theme)?;
+    let model = select_model(theme, &LlmProvider::OpenAI)?;
 
     config.llm.openai_api_key = Some(api_key);
     config.llm.preferred_provider = LlmProvider::OpenAI;
@@ -184,7 +229,7 @@ fn setup_anthropic(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     }
 
     // Select Anthropic model
-    let model = select_anthropic_model(theme)?;
+    let model = select_model(theme, &LlmProvider::Anthropic)?;
 
     config.llm.anthropic_api_key = Some(api_key);
     config.llm.preferred_provider = LlmProvider::Anthropic;
@@ -226,7 +271,7 @@ fn setup_groq(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     }
 
     // Select Groq model
-    let model = select_groq_model(theme)?;
+    let model = select_model(theme, &LlmProvider::Groq)?;
 
     config.llm.groq_api_key = Some(api_key);
     config.llm.preferred_provider = LlmProvider::Groq;
@@ -239,116 +284,6 @@ fn setup_groq(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     Ok(true)
 }
 
-fn select_openai_model(theme: &ColorfulTheme) -> Result<String> {
-    println!();
-    println!("{}", style("Choose OpenAI Model").cyan().bold());
-    println!();
-
-    let models = [
-        ("gpt-4o", "Most capable - Best for complex tasks"),
-        (
-            "gpt-4o-mini",
-            "Recommended - Fast, cost-effective, great quality",
-        ),
-        ("gpt-4-turbo", "Previous generation - Still very capable"),
-        ("gpt-3.5-turbo", "Budget option - Fast and cheap"),
-    ];
-
-    let choices: Vec<String> = models
-        .iter()
-        .map(|(model, desc)| format!("{} - {}", model, desc))
-        .collect();
-
-    let selection = dialoguer::Select::with_theme(theme)
-        .with_prompt("Select OpenAI model")
-        .items(&choices)
-        .default(1) // Default to gpt-4o-mini (recommended)
-        .interact()?;
-
-    Ok(models[selection].0.to_string())
-}
-
-fn select_anthropic_model(theme: &ColorfulTheme) -> Result<String> {
-    println!();
-    println!("{}", style("Choose Anthropic Model").cyan().bold());
-    println!();
-
-    let models = [
-        (
-            "claude-3-5-sonnet-20241022",
-            "Most capable - Best reasoning and code",
-        ),
-        (
-            "claude-3-haiku-20240307",
-            "Recommended - Fast, cost-effective",
-        ),
-        (
-            "claude-3-sonnet-20240229",
-            "Balanced - Good quality and speed",
-        ),
-        (
-            "claude-3-opus-20240229",
-            "Premium - Highest quality (expensive)",
-        ),
-    ];
-
-    let choices: Vec<String> = models
-        .iter()
-        .map(|(model, desc)| format!("{} - {}", model, desc))
-        .collect();
-
-    let selection = dialoguer::Select::with_theme(theme)
-        .with_prompt("Select Anthropic model")
-        .items(&choices)
-        .default(1) // Default to haiku (recommended)
-        .interact()?;
-
-    Ok(models[selection].0.to_string())
-}
-
-fn select_groq_model(theme: &ColorfulTheme) -> Result<String> {
-    println!();
-    println!("{}", style("Choose Groq Model").cyan().bold());
-    println!();
-
-    let models = [
-        (
-            "llama-3.1-8b-instant",
-            "Recommended - Lightning fast, good quality",
-        ),
-        (
-            "llama-3.1-70b-versatile",
-            "More capable - Slower but better reasoning",
-        ),
-        (
-            "llama-3.2-11b-vision-preview",
-            "Vision capable - Can analyze images",
-        ),
-        ("mixtral-8x7b-32768", "Mixtral - Good for longer contexts"),
-        (
-            "qwen/qwen-2.5-72b-instruct",
-            "Qwen - Excellent for coding tasks",
-        ),
-        (
-            "qwen/qwen-2.5-32b-instruct",
-            "Qwen 32B - Balanced performance",
-        ),
-    ];
-
-    let choices: Vec<String> = models
-        .iter()
-        .map(|(model, desc)| format!("{} - {}", model, desc))
-        .collect();
-
-    let selection = dialoguer::Select::with_theme(theme)
-        .with_prompt("Select Groq model")
-        .items(&choices)
-        .default(0) // Default to llama-3.1-8b-instant (recommended)
-        .interact()?;
-
-    Ok(models[selection].0.to_string())
-}
-
 fn setup_zai(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     println!();
     println!("{}", style("Setting up Z.AI GLM Coding Plan...").cyan());
@@ -364,7 +299,7 @@ fn setup_zai(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
         .validate_with(|input: &String| {
             if input.is_empty() {
                 Ok(())
-            } else if input.len() < 10 {
+            } else if !valid_zai_key(input) {
                 Err("API key too short - please enter a valid key")
             } else {
                 Ok(())
@@ -378,7 +313,7 @@ fn setup_zai(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     }
 
     // Select Zai model
-    let model = select_zai_model(theme)?;
+    let model = select_model(theme, &LlmProvider::Zai)?;
 
     config.llm.zai_api_key = Some(api_key);
     config.llm.preferred_provider = LlmProvider::Zai;
@@ -391,33 +326,135 @@ fn setup_zai(config: &mut Config, theme: &ColorfulTheme) -> Result<bool> {
     Ok(true)
 }
 
-fn select_zai_model(theme: &ColorfulTheme) -> Result<String> {
+/// An AI provider the wizard offers
+struct ProviderChoice {
+    /// Name accepted as the `llm_provider` answer
+    key: &'static str,
+    name: &'static str,
+    provider: LlmProvider,
+    /// Models to choose from, with a short description
+    models: &'static [(&'static str, &'static str)],
+    /// Index of the model used when none is chosen
+    recommended: usize,
+}
+
+static PROVIDERS: [ProviderChoice; 4] = [
+    ProviderChoice {
+        key: "openai",
+        name: "OpenAI",
+        provider: LlmProvider::OpenAI,
+        models: &[
+            ("gpt-4o", "Most capable - Best for complex tasks"),
+            (
+                "gpt-4o-mini",
+                "Recommended - Fast, cost-effective, great quality",
+            ),
+            ("gpt-4-turbo", "Previous generation - Still very capable"),
+            ("gpt-3.5-turbo", "Budget option - Fast and cheap"),
+        ],
+        recommended: 1,
+    },
+    ProviderChoice {
+        key: "anthropic",
+        name: "Anthropic",
+        provider: LlmProvider::Anthropic,
+        models: &[
+            (
+                "claude-3-5-sonnet-20241022",
+                "Most capable - Best reasoning and code",
+            ),
+            (
+                "claude-3-haiku-20240307",
+                "Recommended - Fast, cost-effective",
+            ),
+            (
+                "claude-3-sonnet-20240229",
+                "Balanced - Good quality and speed",
+            ),
+            (
+                "claude-3-opus-20240229",
+                "Premium - Highest quality (expensive)",
+            ),
+        ],
+        recommended: 1,
+    },
+    ProviderChoice {
+        key: "groq",
+        name: "Groq",
+        provider: LlmProvider::Groq,
+        models: &[
+            (
+                "llama-3.1-8b-instant",
+                "Recommended - Lightning fast, good quality",
+            ),
+            (
+                "llama-3.1-70b-versatile",
+                "More capable - Slower but better reasoning",
+            ),
+            (
+                "llama-3.2-11b-vision-preview",
+                "Vision capable - Can analyze images",
+            ),
+            ("mixtral-8x7b-32768", "Mixtral - Good for longer contexts"),
+            (
+                "qwen/qwen-2.5-72b-instruct",
+                "Qwen - Excellent for coding tasks",
+            ),
+            (
+                "qwen/qwen-2.5-32b-instruct",
+                "Qwen 32B - Balanced performance",
+            ),
+        ],
+        recommended: 0,
+    },
+    ProviderChoice {
+        key: "zai",
+        name: "Z.AI",
+        provider: LlmProvider::Zai,
+        models: &[
+            ("glm-4.7", "Recommended - Best quality, optimized for code"),
+            (
+                "glm-4.5-air",
+                "Lightweight - Faster response, good for quick tasks",
+            ),
+            (
+                "glm-4-flash",
+                "Ultra-fast - Minimal latency for simple queries",
+            ),
+        ],
+        recommended: 0,
+    },
+];
+
+/// Z.AI keys have no fixed prefix, so only their length is checked
+fn valid_zai_key(api_key: &str) -> bool {
+    api_key.len() >= 10
+}
+
+fn select_model(theme: &ColorfulTheme, provider: &LlmProvider) -> Result<String> {
+    let choice = PROVIDERS
+        .iter()
+        .find(|choice| choice.provider == *provider)
+        .expect("the wizard only offers providers from PROVIDERS");
+
     println!();
-    println!("{}", style("Choose Z.AI Model").cyan().bold());
+    println!(
+        "{}",
+        style(format!("Choose {} Model", choice.name)).cyan().bold()
+    );
     println!();
 
-    let models = [
-        ("glm-4.7", "Recommended - Best quality, optimized for code"),
-        (
-            "glm-4.5-air",
-            "Lightweight - Faster response, good for quick tasks",
-        ),
-        (
-            "glm-4-flash",
-            "Ultra-fast - Minimal latency for simple queries",
-        ),
-    ];
-
-    let choices: Vec<String> = models
+    let choices: Vec<String> = choice
+        .models
         .iter()
         .map(|(model, desc)| format!("{} - {}", model, desc))
         .collect();
 
     let selection = dialoguer::Select::with_theme(theme)
-        .with_prompt("Select Z.AI model")
+        .with_prompt(format!("Select {} model", choice.name))
         .items(&choices)
-        .default(0) // Default to glm-4.7 (recommended)
+        .default(choice.recommended)
         .interact()?;
 
-    Ok(models[selection].0.to_string())
+    Ok(choice.models[selection].0.to_string())
 }
@@ -1,6 +1,7 @@
 use anyhow::Result;
-use console::style;
+use console::{measure_text_width, pad_str, style, Alignment};
 use dialoguer::theme::ColorfulTheme;
+use serde_json::Value;
 
 use crate::config::Config;
 use crate::wizard::{
@@ -8,7 +9,11 @@ use crate::wizard::{
     prompts,
 };
 
-pub async fn show_and_test(config: &Config, theme: &ColorfulTheme) -> Result<WizardAction> {
+pub async fn show_and_test(
+    config: &Config,
+    saved: &Config,
+    theme: &ColorfulTheme,
+) -> Result<WizardAction> {
     println!();
     println!("{}", style("Your manx is configured!").green().bold());
     println!();
@@ -67,8 +72,150 @@ pub async fn show_and_test(config: &Config, theme: &ColorfulTheme) -> Result<Wiz
     // Show next steps
     show_next_steps(config);
 
-    // Use the navigation function for final navigation
-    crate::wizard::navigation::show_navigation_options(theme, &WizardStep::Summary, false)
+    let changes = config_changes(saved, config);
+    if changes.is_empty() {
+        println!();
+        println!("{}", style("No changes to your saved config.").dim());
+        // Use the navigation function for final navigation
+        return crate::wizard::navigation::show_navigation_options(
+            theme,
+            &WizardStep::Summary,
+            false,
+        );
+    }
+
+    // Let the user review what will be overwritten before anything is saved
+    show_config_changes(&changes);
+    println!();
+    let selection = dialoguer::Select::with_theme(theme)
+        .with_prompt("Save these changes?")
+        .items(&["Save and finish", "Back", "Cancel without saving"])
+        .default(0)
+        .interact()?;
+    match selection {
+        0 => Ok(WizardAction::Next),
+        1 => Ok(WizardAction::Back),
+        _ => {
+            println!();
+            println!(
+                "{}",
+                style("Setup cancelled; your config was not changed.").dim()
+            );
+            Ok(WizardAction::Quit)
+        }
+    }
+}
+
+/// A setting whose value the wizard changed, with both values ready for display
+#[derive(Debug, PartialEq)]
+struct ConfigChange {
+    setting: String,
+    saved: String,
+    new: String,
+}
+
+/// Settings that differ between the saved config and the wizard's, by dotted path
+fn config_changes(saved: &Config, new: &Config) -> Vec<ConfigChange> {
+    let mut saved_values = Vec::new();
+    let mut new_values = Vec::new();
+    flatten_settings(
+        "",
+        &serde_json::to_value(saved).unwrap_or_default(),
+        &mut saved_values,
+    );
+    flatten_settings(
+        "",
+        &serde_json::to_value(new).unwrap_or_default(),
+        &mut new_values,
+    );
+
+    let saved_values: std::collections::BTreeMap<_, _> = saved_values.into_iter().collect();
+    let new_values: std::collections::BTreeMap<_, _> = new_values.into_iter().collect();
+    let settings: std::collections::BTreeSet<&String> =
+        saved_values.keys().chain(new_values.keys()).collect();
+
+    settings
+        .into_iter()
+        .filter(|setting| saved_values.get(*setting) != new_values.get(*setting))
+        .map(|setting| ConfigChange {
+            setting: setting.clone(),
+            saved: display_setting(setting, saved_values.get(setting)),
+            new: display_setting(setting, new_values.get(setting)),
+        })
+        .collect()
+}
+
+/// Leaf values of a JSON config by dotted path, such as `llm.openai_api_key`
+fn flatten_settings(prefix: &str, value: &Value, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, field) in fields {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_settings(&path, field, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), value.clone())),
+    }
+}
+
+/// A setting's value for display, with API keys and other secrets masked
+fn display_setting(setting: &str, value: Option<&Value>) -> String {
+    let text = match value {
+        None | Some(Value::Null) => return "(not set)".to_string(),
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+    };
+    let name = setting.rsplit('.').next().unwrap_or(setting);
+    let secret = ["key", "token", "secret", "password"]
+        .iter()
+        .any(|marker| name.contains(marker));
+    if !secret {
+        return text;
+    }
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() > 8 {
+        format!(
+            "****{}",
+            chars[chars.len() - 4..].iter().collect::<String>()
+        )
+    } else {
+        "****".to_string()
+    }
+}
+
+fn show_config_changes(changes: &[ConfigChange]) {
+    let width = |column: fn(&ConfigChange) -> &str, header: &str| {
+        changes
+            .iter()
+            .map(|change| measure_text_width(column(change)))
+            .chain([measure_text_width(header)])
+            .max()
+            .unwrap_or(0)
+    };
+    let setting_width = width(|change| &change.setting, "Setting");
+    let saved_width = width(|change| &change.saved, "Saved");
+
+    println!();
+    println!("{}", style("Changes to your saved config:").bold());
+    println!();
+    println!(
+        "  {}  {}  {}",
+        style(pad_str("Setting", setting_width, Alignment::Left, None)).dim(),
+        style(pad_str("Saved", saved_width, Alignment::Left, None)).dim(),
+        style("New").dim()
+    );
+    for change in changes {
+        println!(
+            "  {}  {}  {}",
+            pad_str(&change.setting, setting_width, Alignment::Left, None),
+            style(pad_str(&change.saved, saved_width, Alignment::Left, None)).red(),
+            style(&change.new).green()
+        );
+    }
 }
 
 fn show_config_summary(config: &Config) {
@@ -153,6 +300,12 @@ fn show_next_steps(config: &Config) {
         );
     }
 
+    println!(
+        "  {} config test   {}",
+        style("manx").bold().blue(),
+        style("# check API keys with live requests").dim()
+    );
+
     println!();
     println!(
         "{}",
@@ -163,11 +316,13 @@ fn show_next_steps(config: &Config) {
 fn get_llm_provider_name(config: &Config) -> &'static str {
     match config.llm.preferred_provider {
         crate::rag::llm::LlmProvider::OpenAI => "OpenAI",
+        crate::rag::llm::LlmProvider::AzureOpenAI => "Azure OpenAI",
         crate::rag::llm::LlmProvider::Anthropic => "Anthropic",
         crate::rag::llm::LlmProvider::Groq => "Groq",
         crate::rag::llm::LlmProvider::OpenRouter => "OpenRouter",
         crate::rag::llm::LlmProvider::HuggingFace => "HuggingFace",
         crate::rag::llm::LlmProvider::Zai => "Z.AI",
+        crate::rag::llm::LlmProvider::Ollama => "Ollama",
         crate::rag::llm::LlmProvider::Custom => "Custom",
         crate::rag::llm::LlmProvider::Auto => "Auto",
     }
@@ -176,6 +331,7 @@ fn get_llm_provider_name(config: &Config) -> &'static str {
 fn get_llm_api_key(config: &Config) -> Option<&str> {
     match config.llm.preferred_provider {
         crate::rag::llm::LlmProvider::OpenAI => config.llm.openai_api_key.as_deref(),
+        crate::rag::llm::LlmProvider::AzureOpenAI => config.llm.azure_api_key.as_deref(),
         crate::rag::llm::LlmProvider::Anthropic => config.llm.anthropic_api_key.as_deref(),
         crate::rag::llm::LlmProvider::Groq => config.llm.groq_api_key.as_deref(),
         crate::rag::llm::LlmProvider::OpenRouter => config.llm.openrouter_api_key.as_deref(),
@@ -184,3 +340,36 @@ fn get_llm_api_key(config: &Config) -> Option<&str> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_changes_lists_changed_settings_with_secrets_masked() {
+        let saved = Config::default();
+        let mut new = Config {
+            default_limit: saved.default_limit + 5,
+            ..Config::default()
+        };
+        new.llm.openai_api_key = Some("sk-test-1234567890abcd".to_string());
+
+        let changes = config_changes(&saved, &new);
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange {
+                    setting: "default_limit".to_string(),
+                    saved: saved.default_limit.to_string(),
+                    new: new.default_limit.to_string(),
+                },
+                ConfigChange {
+                    setting: "llm.openai_api_key".to_string(),
+                    saved: "(not set)".to_string(),
+                    new: "****abcd".to_string(),
+                },
+            ]
+        );
+        assert!(config_changes(&saved, &saved).is_empty());
+    }
+}
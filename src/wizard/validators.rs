@@ -31,8 +31,12 @@ pub async fn test_embedding_setup(provider: &crate::rag::EmbeddingProvider) -> R
         model_path: None,
         api_key: None,
         endpoint: None,
+        model: None,
         timeout_seconds: 30,
         batch_size: 32,
+        max_in_flight: 4,
+        requests_per_minute: None,
+        tokens_per_minute: None,
     };
 
     // Try to create embedding model and test it